@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE permission_modifications;
+DROP FUNCTION audit_permission_modification() CASCADE;
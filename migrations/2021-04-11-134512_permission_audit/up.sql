@@ -0,0 +1,20 @@
+-- Dedicated audit log for permission changes. The generic user_modifications table only ever stores the
+-- old permission bitmask, which makes it needlessly awkward to reconstruct who gained/lost which
+-- permission when. Here we store both the old and new bitmask of every change.
+CREATE TABLE permission_modifications (
+    id INTEGER NOT NULL, -- REFERENCES members(member_id)
+    old_permissions BIT(16) NOT NULL,
+    new_permissions BIT(16) NOT NULL
+) INHERITS (audit_log2);
+
+CREATE FUNCTION audit_permission_modification() RETURNS trigger AS $permission_modification_trigger$
+    BEGIN
+        INSERT INTO permission_modifications (userid, id, old_permissions, new_permissions)
+            (SELECT id, NEW.member_id, OLD.permissions, NEW.permissions FROM active_user LIMIT 1);
+
+        RETURN NEW;
+    END;
+$permission_modification_trigger$ LANGUAGE plpgsql;
+
+CREATE TRIGGER permission_modification_trigger AFTER UPDATE OF permissions ON members FOR EACH ROW
+    WHEN (OLD.permissions IS DISTINCT FROM NEW.permissions) EXECUTE PROCEDURE audit_permission_modification();
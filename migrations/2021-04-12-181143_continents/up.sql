@@ -0,0 +1,102 @@
+-- Your SQL goes here
+
+CREATE TYPE CONTINENT AS ENUM ('Africa', 'Antarctica', 'Asia', 'Europe', 'North America', 'Oceania', 'South America');
+
+-- Nullable, since not every nationality added in the future will necessarily be assigned a continent
+ALTER TABLE nationalities ADD COLUMN continent CONTINENT NULL;
+
+UPDATE nationalities SET continent = 'Africa' WHERE iso_country_code IN (
+    'AO', 'BF', 'BI', 'BJ', 'BW', 'CD', 'CF', 'CG', 'CI', 'CM', 'CV', 'DJ', 'DZ', 'EG', 'EH', 'ER',
+    'ET', 'GA', 'GH', 'GM', 'GN', 'GQ', 'GW', 'IO', 'KE', 'KM', 'LR', 'LS', 'LY', 'MA', 'MG', 'ML',
+    'MR', 'MU', 'MW', 'MZ', 'NA', 'NE', 'NG', 'RE', 'RW', 'SC', 'SD', 'SH', 'SL', 'SN', 'SO', 'SS',
+    'ST', 'SZ', 'TD', 'TF', 'TG', 'TN', 'TZ', 'UG', 'YT', 'ZA', 'ZM', 'ZW'
+);
+
+UPDATE nationalities SET continent = 'Antarctica' WHERE iso_country_code IN (
+    'AQ', 'BV', 'GS', 'HM'
+);
+
+UPDATE nationalities SET continent = 'Asia' WHERE iso_country_code IN (
+    'AE', 'AF', 'AM', 'AZ', 'BD', 'BH', 'BN', 'BT', 'CN', 'CY', 'GE', 'HK', 'ID', 'IL', 'IN', 'IQ',
+    'IR', 'JO', 'JP', 'KG', 'KH', 'KP', 'KR', 'KW', 'KZ', 'LA', 'LB', 'LK', 'MM', 'MN', 'MO', 'MV',
+    'MY', 'NP', 'OM', 'PH', 'PK', 'PS', 'QA', 'SA', 'SG', 'SY', 'TH', 'TJ', 'TL', 'TM', 'TR', 'TW',
+    'UZ', 'VN', 'YE'
+);
+
+UPDATE nationalities SET continent = 'Europe' WHERE iso_country_code IN (
+    'AD', 'AL', 'AT', 'AX', 'BA', 'BE', 'BG', 'BY', 'CH', 'CZ', 'DE', 'DK', 'EE', 'ES', 'FI', 'FO',
+    'FR', 'GB', 'GG', 'GI', 'GR', 'HR', 'HU', 'IE', 'IM', 'IS', 'IT', 'JE', 'LI', 'LT', 'LU', 'LV',
+    'MC', 'MD', 'ME', 'MK', 'MT', 'NL', 'NO', 'PL', 'PT', 'RO', 'RS', 'RU', 'SE', 'SI', 'SJ', 'SK',
+    'SM', 'UA', 'VA'
+);
+
+UPDATE nationalities SET continent = 'North America' WHERE iso_country_code IN (
+    'AG', 'AI', 'AW', 'BB', 'BL', 'BM', 'BQ', 'BS', 'BZ', 'CA', 'CR', 'CU', 'CW', 'DM', 'DO', 'GD',
+    'GL', 'GP', 'GT', 'HN', 'HT', 'JM', 'KN', 'KY', 'LC', 'MF', 'MQ', 'MS', 'MX', 'NI', 'PA', 'PM',
+    'PR', 'SV', 'SX', 'TC', 'TT', 'US', 'VC', 'VG', 'VI'
+);
+
+UPDATE nationalities SET continent = 'Oceania' WHERE iso_country_code IN (
+    'AS', 'AU', 'CC', 'CK', 'CX', 'FJ', 'FM', 'GU', 'KI', 'MH', 'MP', 'NC', 'NF', 'NR', 'NU', 'NZ',
+    'PF', 'PG', 'PN', 'PW', 'SB', 'TK', 'TO', 'TV', 'UM', 'VU', 'WF', 'WS'
+);
+
+UPDATE nationalities SET continent = 'South America' WHERE iso_country_code IN (
+    'AR', 'BO', 'BR', 'CL', 'CO', 'EC', 'FK', 'GF', 'GY', 'PE', 'PY', 'SR', 'UY', 'VE'
+);
+
+-- Can only append columns with CREATE OR REPLACE
+CREATE OR REPLACE VIEW players_with_score AS
+SELECT players.id,
+       players.name,
+       RANK() OVER(ORDER BY scores.total_score DESC) AS rank,
+       CASE WHEN scores.total_score IS NULL THEN 0.0::FLOAT ELSE scores.total_score END AS score,
+       ROW_NUMBER() OVER(ORDER BY scores.total_score DESC) AS index,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent
+FROM
+    (
+        SELECT pseudo_records.player,
+               SUM(record_score(pseudo_records.progress::FLOAT, pseudo_records.position::FLOAT, 100::FLOAT, pseudo_records.requirement)) as total_score
+        FROM (
+                 SELECT player,
+                        progress,
+                        position,
+                        CASE WHEN demons.position > 75 THEN 100 ELSE requirement END AS requirement
+                 FROM records
+                          INNER JOIN demons
+                                     ON demons.id = demon
+                 WHERE demons.position <= 150 AND status_ = 'APPROVED'
+
+                 UNION
+
+                 SELECT verifier as player,
+                        CASE WHEN demons.position > 150 THEN 0.0::FLOAT ELSE 100.0::FLOAT END as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT publisher as player,
+                        0.0::FLOAT as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT creator as player,
+                        0.0::FLOAT as progress,
+                        1.0::FLOAT as position, -- doesn't matter
+                        100.0::FLOAT
+                 FROM creators
+             ) AS pseudo_records
+        GROUP BY player
+    ) scores
+        INNER JOIN players
+                   ON scores.player = players.id
+        LEFT OUTER JOIN nationalities
+                        ON players.nationality = nationalities.iso_country_code
+WHERE NOT players.banned AND players.id != 1534;
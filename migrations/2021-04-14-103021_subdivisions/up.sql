@@ -0,0 +1,172 @@
+-- Your SQL goes here
+
+CREATE TABLE subdivisions (
+    iso_code VARCHAR(3) NOT NULL,
+    name CITEXT NOT NULL,
+    nation VARCHAR(2) NOT NULL REFERENCES nationalities(iso_country_code),
+    PRIMARY KEY (iso_code, nation)
+);
+
+-- Only the part of the ISO 3166-2 code after the hyphen is stored, the country part is given by `nation`
+INSERT INTO subdivisions (iso_code, name, nation) VALUES
+    ('AL', 'Alabama', 'US'),
+    ('AK', 'Alaska', 'US'),
+    ('AZ', 'Arizona', 'US'),
+    ('AR', 'Arkansas', 'US'),
+    ('CA', 'California', 'US'),
+    ('CO', 'Colorado', 'US'),
+    ('CT', 'Connecticut', 'US'),
+    ('DE', 'Delaware', 'US'),
+    ('DC', 'District of Columbia', 'US'),
+    ('FL', 'Florida', 'US'),
+    ('GA', 'Georgia', 'US'),
+    ('HI', 'Hawaii', 'US'),
+    ('ID', 'Idaho', 'US'),
+    ('IL', 'Illinois', 'US'),
+    ('IN', 'Indiana', 'US'),
+    ('IA', 'Iowa', 'US'),
+    ('KS', 'Kansas', 'US'),
+    ('KY', 'Kentucky', 'US'),
+    ('LA', 'Louisiana', 'US'),
+    ('ME', 'Maine', 'US'),
+    ('MD', 'Maryland', 'US'),
+    ('MA', 'Massachusetts', 'US'),
+    ('MI', 'Michigan', 'US'),
+    ('MN', 'Minnesota', 'US'),
+    ('MS', 'Mississippi', 'US'),
+    ('MO', 'Missouri', 'US'),
+    ('MT', 'Montana', 'US'),
+    ('NE', 'Nebraska', 'US'),
+    ('NV', 'Nevada', 'US'),
+    ('NH', 'New Hampshire', 'US'),
+    ('NJ', 'New Jersey', 'US'),
+    ('NM', 'New Mexico', 'US'),
+    ('NY', 'New York', 'US'),
+    ('NC', 'North Carolina', 'US'),
+    ('ND', 'North Dakota', 'US'),
+    ('OH', 'Ohio', 'US'),
+    ('OK', 'Oklahoma', 'US'),
+    ('OR', 'Oregon', 'US'),
+    ('PA', 'Pennsylvania', 'US'),
+    ('RI', 'Rhode Island', 'US'),
+    ('SC', 'South Carolina', 'US'),
+    ('SD', 'South Dakota', 'US'),
+    ('TN', 'Tennessee', 'US'),
+    ('TX', 'Texas', 'US'),
+    ('UT', 'Utah', 'US'),
+    ('VT', 'Vermont', 'US'),
+    ('VA', 'Virginia', 'US'),
+    ('WA', 'Washington', 'US'),
+    ('WV', 'West Virginia', 'US'),
+    ('WI', 'Wisconsin', 'US'),
+    ('WY', 'Wyoming', 'US'),
+    ('AB', 'Alberta', 'CA'),
+    ('BC', 'British Columbia', 'CA'),
+    ('MB', 'Manitoba', 'CA'),
+    ('NB', 'New Brunswick', 'CA'),
+    ('NL', 'Newfoundland and Labrador', 'CA'),
+    ('NS', 'Nova Scotia', 'CA'),
+    ('NT', 'Northwest Territories', 'CA'),
+    ('NU', 'Nunavut', 'CA'),
+    ('ON', 'Ontario', 'CA'),
+    ('PE', 'Prince Edward Island', 'CA'),
+    ('QC', 'Quebec', 'CA'),
+    ('SK', 'Saskatchewan', 'CA'),
+    ('YT', 'Yukon', 'CA'),
+    ('AC', 'Acre', 'BR'),
+    ('AL', 'Alagoas', 'BR'),
+    ('AP', 'Amapá', 'BR'),
+    ('AM', 'Amazonas', 'BR'),
+    ('BA', 'Bahia', 'BR'),
+    ('CE', 'Ceará', 'BR'),
+    ('DF', 'Distrito Federal', 'BR'),
+    ('ES', 'Espírito Santo', 'BR'),
+    ('GO', 'Goiás', 'BR'),
+    ('MA', 'Maranhão', 'BR'),
+    ('MT', 'Mato Grosso', 'BR'),
+    ('MS', 'Mato Grosso do Sul', 'BR'),
+    ('MG', 'Minas Gerais', 'BR'),
+    ('PA', 'Pará', 'BR'),
+    ('PB', 'Paraíba', 'BR'),
+    ('PR', 'Paraná', 'BR'),
+    ('PE', 'Pernambuco', 'BR'),
+    ('PI', 'Piauí', 'BR'),
+    ('RJ', 'Rio de Janeiro', 'BR'),
+    ('RN', 'Rio Grande do Norte', 'BR'),
+    ('RS', 'Rio Grande do Sul', 'BR'),
+    ('RO', 'Rondônia', 'BR'),
+    ('RR', 'Roraima', 'BR'),
+    ('SC', 'Santa Catarina', 'BR'),
+    ('SP', 'São Paulo', 'BR'),
+    ('SE', 'Sergipe', 'BR'),
+    ('TO', 'Tocantins', 'BR'),
+    ('ACT', 'Australian Capital Territory', 'AU'),
+    ('NSW', 'New South Wales', 'AU'),
+    ('NT', 'Northern Territory', 'AU'),
+    ('QLD', 'Queensland', 'AU'),
+    ('SA', 'South Australia', 'AU'),
+    ('TAS', 'Tasmania', 'AU'),
+    ('VIC', 'Victoria', 'AU'),
+    ('WA', 'Western Australia', 'AU');
+
+ALTER TABLE players ADD COLUMN subdivision VARCHAR(3) NULL DEFAULT NULL;
+ALTER TABLE players ADD CONSTRAINT players_subdivision_fkey FOREIGN KEY (nationality, subdivision) REFERENCES subdivisions(nation, iso_code);
+
+CREATE OR REPLACE VIEW players_with_score AS
+SELECT players.id,
+       players.name,
+       RANK() OVER(ORDER BY scores.total_score DESC) AS rank,
+       CASE WHEN scores.total_score IS NULL THEN 0.0::FLOAT ELSE scores.total_score END AS score,
+       ROW_NUMBER() OVER(ORDER BY scores.total_score DESC) AS index,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent,
+       subdivisions.iso_code AS subdivision_code,
+       subdivisions.name AS subdivision_name
+FROM
+    (
+        SELECT pseudo_records.player,
+               SUM(record_score(pseudo_records.progress::FLOAT, pseudo_records.position::FLOAT, 100::FLOAT, pseudo_records.requirement)) as total_score
+        FROM (
+                 SELECT player,
+                        progress,
+                        position,
+                        CASE WHEN demons.position > 75 THEN 100 ELSE requirement END AS requirement
+                 FROM records
+                          INNER JOIN demons
+                                     ON demons.id = demon
+                 WHERE demons.position <= 150 AND status_ = 'APPROVED'
+
+                 UNION
+
+                 SELECT verifier as player,
+                        CASE WHEN demons.position > 150 THEN 0.0::FLOAT ELSE 100.0::FLOAT END as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT publisher as player,
+                        0.0::FLOAT as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT creator as player,
+                        0.0::FLOAT as progress,
+                        1.0::FLOAT as position, -- doesn't matter
+                        100.0::FLOAT
+                 FROM creators
+             ) AS pseudo_records
+        GROUP BY player
+    ) scores
+        INNER JOIN players
+                   ON scores.player = players.id
+        LEFT OUTER JOIN nationalities
+                        ON players.nationality = nationalities.iso_country_code
+        LEFT OUTER JOIN subdivisions
+                        ON players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code
+WHERE NOT players.banned AND players.id != 1534;
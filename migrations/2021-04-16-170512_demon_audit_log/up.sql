@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Field-level audit log for demons. Unlike demon_modifications, which is populated by a trigger and only ever
+-- stores the old value of each changed column, entries in here are written by the application and contain both
+-- the old and the new value of every field that changed.
+CREATE TABLE demon_audit_log (
+    id INTEGER NOT NULL, -- REFERENCES demons(id)
+    action TEXT NOT NULL CHECK (action IN ('addition', 'modification')),
+    changes JSONB NOT NULL
+) INHERITS (audit_log2);
+
+CREATE INDEX demon_audit_log_id_idx ON demon_audit_log(id);
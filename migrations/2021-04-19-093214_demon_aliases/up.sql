@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- Alternative names (e.g. common nicknames) under which a demon can be looked up. Aliases are case-insensitive and globally unique,
+-- and are not allowed to coincide with the name of an existing demon (the latter is enforced by the application).
+CREATE TABLE demon_aliases (
+    alias CITEXT PRIMARY KEY,
+    demon INTEGER NOT NULL REFERENCES demons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX demon_aliases_demon_idx ON demon_aliases(demon);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX demon_aliases_alias_trgm_idx;
+DROP INDEX players_name_trgm_idx;
+DROP INDEX demons_name_trgm_idx;
+
+DROP EXTENSION pg_trgm;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Trigram indices speeding up the substring matches of the demon search endpoint
+CREATE INDEX demons_name_trgm_idx ON demons USING GIN (LOWER(name::TEXT) gin_trgm_ops);
+CREATE INDEX players_name_trgm_idx ON players USING GIN (LOWER(name::TEXT) gin_trgm_ops);
+CREATE INDEX demon_aliases_alias_trgm_idx ON demon_aliases USING GIN (LOWER(alias::TEXT) gin_trgm_ops);
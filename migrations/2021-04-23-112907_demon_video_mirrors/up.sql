@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+-- Fallback videos for a demon, in case its primary video (demons.video) gets taken down. Mirrors are ordered by their position,
+-- starting at 1.
+CREATE TABLE demon_video_mirrors (
+    demon INTEGER NOT NULL REFERENCES demons(id) ON DELETE CASCADE,
+    video VARCHAR(200) NOT NULL,
+    position SMALLINT NOT NULL CHECK (position > 0),
+    PRIMARY KEY (demon, position),
+    UNIQUE (demon, video)
+);
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+-- For every demon currently positioned below the given threshold (meaning it is on the legacy list), computes the most recent
+-- point in time at which it moved from a position at or above the threshold to a position below it.
+--
+-- Like list_at, this is derived from demon_modifications, which stores the position a demon had _before_ each change. The
+-- position after a change is thus the one logged by the next modification (or the demon's current position, for the last one).
+-- The addition of a demon is treated as a move from position 0, so that demons added directly to the legacy list are
+-- considered legacy since their addition.
+CREATE FUNCTION demon_legacy_since(SMALLINT)
+RETURNS TABLE (
+    id INTEGER,
+    since TIMESTAMP WITHOUT TIME ZONE
+)
+AS $$
+    SELECT DISTINCT ON (transitions.id) transitions.id, transitions.time
+    FROM (
+        SELECT history.id, history.time, history.audit_id, history.position AS old_position,
+               COALESCE(LEAD(history.position) OVER (PARTITION BY history.id ORDER BY history.time, history.audit_id), demons.position) AS new_position
+        FROM (
+            SELECT id, time, audit_id, 0::SMALLINT AS position FROM demon_additions
+            UNION ALL
+            SELECT id, time, audit_id, position FROM demon_modifications WHERE position > 0
+        ) history
+        INNER JOIN demons ON demons.id = history.id
+        WHERE demons.position > $1
+    ) transitions
+    WHERE transitions.old_position <= $1 AND transitions.new_position > $1
+    ORDER BY transitions.id, transitions.time DESC, transitions.audit_id DESC
+$$
+LANGUAGE SQL
+STABLE;
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER mirrors_last_modified_trigger ON demon_video_mirrors;
+DROP TRIGGER players_last_modified_trigger ON players;
+DROP TRIGGER records_last_modified_trigger ON records;
+DROP TRIGGER demons_last_modified_trigger ON demons;
+DROP FUNCTION touch_list_last_modified();
+DROP TABLE list_last_modified;
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+
+-- Single-row table holding the point in time at which anything shown on the demonlist overview was last changed. Used to
+-- answer conditional requests for the overview without having to render it.
+CREATE TABLE list_last_modified (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    time TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (now() AT TIME ZONE 'utc')
+);
+
+INSERT INTO list_last_modified DEFAULT VALUES;
+
+-- We use clock_timestamp() instead of now(), as the latter is the time the current transaction started, meaning writes made
+-- by long-running transactions could appear older than reads done during them.
+CREATE FUNCTION touch_list_last_modified() RETURNS trigger AS $touch_list_last_modified$
+BEGIN
+    UPDATE list_last_modified SET time = clock_timestamp() AT TIME ZONE 'utc';
+
+    RETURN NULL;
+END;
+$touch_list_last_modified$ LANGUAGE plpgsql;
+
+CREATE TRIGGER demons_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demons FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER records_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON records FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER players_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON players FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER mirrors_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demon_video_mirrors FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
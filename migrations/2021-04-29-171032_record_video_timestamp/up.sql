@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- The point (in seconds) in the record's video at which the actual completion starts, for videos that are longer stream VODs
+ALTER TABLE records ADD COLUMN video_timestamp INTEGER CHECK (video_timestamp >= 0);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+DROP FUNCTION demon_movements(TIMESTAMP WITHOUT TIME ZONE, TIMESTAMP WITHOUT TIME ZONE);
@@ -0,0 +1,41 @@
+-- Your SQL goes here
+
+-- Lists all changes to the placement of demons that happened in the given time range, in chronological order.
+--
+-- Like demon_legacy_since, this treats the addition of a demon as a move from position 0, and derives the position after each change
+-- from the next logged modification (or the demon's current position, for the last one). Explicitly moving a demon temporarily
+-- moves it to position -1 (to avoid conflicts while shifting the other demons around), which we use to tell apart demons that
+-- were moved by a list moderator (`moved` is true) from demons that merely got shifted by one because of another addition or move.
+CREATE FUNCTION demon_movements(TIMESTAMP WITHOUT TIME ZONE, TIMESTAMP WITHOUT TIME ZONE)
+RETURNS TABLE (
+    id INTEGER,
+    name CITEXT,
+    time_ TIMESTAMP WITHOUT TIME ZONE,
+    audit_id INTEGER,
+    old_position SMALLINT,
+    new_position SMALLINT,
+    moved BOOLEAN
+)
+AS $$
+    SELECT transitions.id, demons.name, transitions.time, transitions.audit_id, transitions.old_position,
+           COALESCE(transitions.new_position, demons.position), transitions.moved
+    FROM (
+        SELECT history.id, history.time, history.audit_id, history.position AS old_position,
+               LEAD(history.position) OVER (PARTITION BY history.id ORDER BY history.time, history.audit_id) AS new_position,
+               history.position = 0 OR COALESCE(history.next_position = -1, FALSE) AS moved
+        FROM (
+            SELECT raw.*, LEAD(raw.position) OVER (PARTITION BY raw.id ORDER BY raw.time, raw.audit_id) AS next_position
+            FROM (
+                SELECT id, time, audit_id, 0::SMALLINT AS position FROM demon_additions
+                UNION ALL
+                SELECT id, time, audit_id, position FROM demon_modifications WHERE position IS NOT NULL
+            ) raw
+        ) history
+        WHERE history.position != -1
+    ) transitions
+    INNER JOIN demons ON demons.id = transitions.id
+    WHERE transitions.time >= $1 AND transitions.time < $2
+    ORDER BY transitions.time, transitions.audit_id
+$$
+LANGUAGE SQL
+STABLE;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- Free-form, lowercase tags communities use to categorize demons beyond their position (e.g. "memory" or "wave-heavy")
+CREATE TABLE demon_tags (
+    demon INTEGER NOT NULL REFERENCES demons(id) ON DELETE CASCADE,
+    tag VARCHAR(32) NOT NULL CHECK (tag = LOWER(tag) AND tag <> ''),
+    PRIMARY KEY (demon, tag)
+);
+
+CREATE INDEX demon_tags_tag_idx ON demon_tags(tag);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+-- Names players were previously known under, either because they were renamed, or because another player object was merged into
+-- them. Used to keep players findable under their old names.
+CREATE TABLE player_name_history (
+    player INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    name CITEXT NOT NULL,
+    time TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
+
+CREATE INDEX player_name_history_player_idx ON player_name_history(player);
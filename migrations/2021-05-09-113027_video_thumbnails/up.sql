@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+-- Thumbnails of videos whose thumbnail cannot be derived from the video URL alone (currently only
+-- twitch VODs), so that we only need to ask the host once per video
+CREATE TABLE video_thumbnails (
+    video VARCHAR(200) PRIMARY KEY,
+    thumbnail TEXT NOT NULL,
+    resolved_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+DROP FUNCTION recompute_record_counts();
+DROP TRIGGER record_count_trigger ON records;
+DROP FUNCTION update_record_count();
+
+DROP TRIGGER demon_modification_trigger ON demons;
+CREATE TRIGGER demon_modification_trigger AFTER UPDATE ON demons FOR EACH ROW EXECUTE PROCEDURE audit_demon_modification();
+
+ALTER TABLE demons DROP COLUMN record_count;
@@ -0,0 +1,45 @@
+-- Your SQL goes here
+
+-- Number of approved records on each demon. Since approved records are unique per (player, demon) pair, this is also the number
+-- of players that have a record on the demon. Maintained by the trigger below, so that the overview doesn't have to aggregate
+-- the records table on every render.
+ALTER TABLE demons ADD COLUMN record_count INTEGER NOT NULL DEFAULT 0;
+
+-- Trigger-maintained changes to the record count are not modifications a list moderator made, and should not show up in the
+-- audit log. No UPDATE issued by pointercrate changes the record count together with any other column.
+DROP TRIGGER demon_modification_trigger ON demons;
+CREATE TRIGGER demon_modification_trigger AFTER UPDATE ON demons FOR EACH ROW WHEN (OLD.record_count = NEW.record_count) EXECUTE PROCEDURE audit_demon_modification();
+
+CREATE FUNCTION update_record_count() RETURNS trigger AS $update_record_count$
+BEGIN
+    IF TG_OP = 'DELETE' OR TG_OP = 'UPDATE' THEN
+        IF OLD.status_ = 'APPROVED' THEN
+            UPDATE demons SET record_count = record_count - 1 WHERE id = OLD.demon;
+        END IF;
+    END IF;
+
+    IF TG_OP = 'INSERT' OR TG_OP = 'UPDATE' THEN
+        IF NEW.status_ = 'APPROVED' THEN
+            UPDATE demons SET record_count = record_count + 1 WHERE id = NEW.demon;
+        END IF;
+    END IF;
+
+    RETURN NULL;
+END;
+$update_record_count$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_count_trigger AFTER INSERT OR UPDATE OF status_, demon OR DELETE ON records FOR EACH ROW EXECUTE PROCEDURE update_record_count();
+
+-- Recomputes the record counts of all demons from scratch, in case they ever get out of sync. Only touches demons whose count
+-- actually changes, as updates that leave the count as-is would end up in the audit log.
+CREATE FUNCTION recompute_record_counts() RETURNS VOID AS $$
+    UPDATE demons SET record_count = counts.record_count
+    FROM (
+        SELECT demons.id, COUNT(records.id) AS record_count
+        FROM demons LEFT OUTER JOIN records ON records.demon = demons.id AND records.status_ = 'APPROVED'
+        GROUP BY demons.id
+    ) AS counts
+    WHERE demons.id = counts.id AND demons.record_count <> counts.record_count
+$$ LANGUAGE SQL;
+
+SELECT recompute_record_counts();
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP FUNCTION player_scores_at(TIMESTAMP WITHOUT TIME ZONE);
+DROP FUNCTION records_at(TIMESTAMP WITHOUT TIME ZONE);
@@ -0,0 +1,70 @@
+-- Your SQL goes here
+
+-- The state of all records at the given point in time, reconstructed from record_modifications, which stores the values a record
+-- had _before_ each change. Records added after the given time are excluded. Records that have since been deleted cannot be
+-- reconstructed, as record_deletions only stores their IDs.
+CREATE FUNCTION records_at(TIMESTAMP WITHOUT TIME ZONE)
+RETURNS TABLE (
+    id INTEGER,
+    progress SMALLINT,
+    status_ RECORD_STATUS,
+    player INTEGER,
+    demon INTEGER
+)
+AS $$
+    SELECT records.id,
+           COALESCE(
+               (SELECT progress FROM record_modifications m WHERE m.id = records.id AND time >= $1 AND m.progress IS NOT NULL ORDER BY time, audit_id LIMIT 1),
+               records.progress
+           ),
+           COALESCE(
+               (SELECT status_ FROM record_modifications m WHERE m.id = records.id AND time >= $1 AND m.status_ IS NOT NULL ORDER BY time, audit_id LIMIT 1),
+               records.status_
+           ),
+           COALESCE(
+               (SELECT player FROM record_modifications m WHERE m.id = records.id AND time >= $1 AND m.player IS NOT NULL ORDER BY time, audit_id LIMIT 1),
+               records.player
+           ),
+           COALESCE(
+               (SELECT demon FROM record_modifications m WHERE m.id = records.id AND time >= $1 AND m.demon IS NOT NULL ORDER BY time, audit_id LIMIT 1),
+               records.demon
+           )
+    FROM records
+    WHERE NOT EXISTS (SELECT 1 FROM record_additions WHERE record_additions.id = records.id AND time >= $1)
+$$
+LANGUAGE SQL
+STABLE;
+
+-- The score of every player at the given point in time, computed the same way as in players_with_score, but based on list_at and
+-- records_at. Publisher and creator pseudo-records are left out, as they do not award any points. Note that demon requirements are
+-- not tracked historically, so the current requirements are used.
+CREATE FUNCTION player_scores_at(TIMESTAMP WITHOUT TIME ZONE)
+RETURNS TABLE (
+    player INTEGER,
+    score FLOAT
+)
+AS $$
+    SELECT pseudo_records.player,
+           SUM(record_score(pseudo_records.progress::FLOAT, pseudo_records.position::FLOAT, 100::FLOAT, pseudo_records.requirement))
+    FROM (
+             SELECT records.player,
+                    records.progress,
+                    demons.position_ AS position,
+                    CASE WHEN demons.position_ > 75 THEN 100 ELSE demons.requirement END AS requirement
+             FROM records_at($1) AS records
+                      INNER JOIN list_at($1) AS demons
+                                 ON demons.id = records.demon
+             WHERE demons.position_ <= 150 AND records.status_ = 'APPROVED'
+
+             UNION
+
+             SELECT verifier AS player,
+                    CASE WHEN position_ > 150 THEN 0.0::FLOAT ELSE 100.0::FLOAT END AS progress,
+                    position_,
+                    100.0::FLOAT
+             FROM list_at($1)
+         ) AS pseudo_records
+    GROUP BY pseudo_records.player
+$$
+LANGUAGE SQL
+STABLE;
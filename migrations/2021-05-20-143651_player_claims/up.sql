@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TYPE claim_status AS ENUM ('PENDING', 'APPROVED', 'REJECTED');
+
+-- Claims of users to be a specific player. Once a member of the list team approved a claim, the
+-- user can manage some of the player's details (e.g. their nationality) themselves
+CREATE TABLE player_claims (
+    id SERIAL PRIMARY KEY,
+    member_id INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE,
+    player_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    status claim_status NOT NULL DEFAULT 'PENDING',
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
+
+-- A player can only be claimed by a single user. Rejected claims do not count, so that a player can
+-- be claimed again after a bogus claim was rejected
+CREATE UNIQUE INDEX player_claims_player_idx ON player_claims (player_id) WHERE status <> 'REJECTED';
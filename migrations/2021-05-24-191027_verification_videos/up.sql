@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- The video of the demon's verification, as opposed to demons.video, which is a showcase of the level used for thumbnails
+ALTER TABLE demons ADD COLUMN verification_video VARCHAR(200);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE members DROP COLUMN api_quota_tier;
+DROP TYPE api_quota_tier;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+CREATE TYPE api_quota_tier AS ENUM ('STANDARD', 'ELEVATED', 'UNLIMITED');
+
+-- The tier determining how many requests can be made to the API using this member's access tokens
+ALTER TABLE members ADD COLUMN api_quota_tier api_quota_tier NOT NULL DEFAULT 'STANDARD';
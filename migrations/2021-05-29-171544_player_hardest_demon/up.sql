@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-- The ID of the hardest demon the given player has completed (either via an approved 100% record, or by verifying it), or NULL
+-- if they have not completed any demon.
+--
+-- The hardest demon is the one at the highest position (that is, the lowest position number). Legacy demons are placed below
+-- the main and extended list, so they are only picked if the player has not completed anything else. Should multiple demons
+-- share a position (which the unique constraint on positions only allows while it is deferred), the one with the lowest ID
+-- wins.
+CREATE FUNCTION player_hardest_demon(player_id INTEGER) RETURNS INTEGER AS $$
+    SELECT demons.id
+    FROM demons
+    WHERE demons.verifier = player_id
+       OR demons.id IN (SELECT demon FROM records WHERE player = player_id AND status_ = 'APPROVED' AND progress = 100)
+    ORDER BY demons.position, demons.id
+    LIMIT 1
+$$ LANGUAGE SQL STABLE;
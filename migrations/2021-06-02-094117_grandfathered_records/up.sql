@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+-- Non-100% records on demons outside the main list are only valid if they were made before the demon left the main list.
+ALTER TABLE records ADD COLUMN grandfathered BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- All existing non-100% records predate this rule
+UPDATE records SET grandfathered = TRUE WHERE progress < 100;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP FUNCTION demon_first_victor(INTEGER);
+DROP FUNCTION record_approved_at(INTEGER);
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+-- The time the given record was approved, reconstructed from the audit log: either the time of its last status change (as
+-- record_modifications stores the status a record had _before_ the change), or, if its status never changed, the time it was
+-- added. NULL for records predating the audit log.
+CREATE FUNCTION record_approved_at(record_id INTEGER) RETURNS TIMESTAMP WITHOUT TIME ZONE AS $$
+    SELECT COALESCE(
+        (SELECT time FROM record_modifications WHERE id = record_id AND status_ IS NOT NULL ORDER BY time DESC, audit_id DESC LIMIT 1),
+        (SELECT time FROM record_additions WHERE id = record_id ORDER BY time LIMIT 1)
+    )
+$$ LANGUAGE SQL STABLE;
+
+-- The ID of the earliest approved 100% record on the given demon that was not made by its verifier, or NULL if there is no such
+-- record.
+--
+-- Records predating the audit log are considered older than all others. Records approved at the same time are ordered by ID.
+CREATE FUNCTION demon_first_victor(demon_id INTEGER) RETURNS INTEGER AS $$
+    SELECT id
+    FROM (
+        SELECT records.id, ROW_NUMBER() OVER (ORDER BY record_approved_at(records.id) NULLS FIRST, records.id) AS victory
+        FROM records
+        INNER JOIN demons ON demons.id = records.demon
+        WHERE records.demon = demon_id AND records.status_ = 'APPROVED' AND records.progress = 100 AND records.player != demons.verifier
+    ) AS victories
+    WHERE victory = 1
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,98 @@
+-- Your SQL goes here
+
+-- The time the most recent approved record of the given player was approved (see record_approved_at). NULL if the player has no
+-- approved records, or if all of them predate the audit log.
+CREATE FUNCTION player_last_record_at(player_id INTEGER) RETURNS TIMESTAMP WITHOUT TIME ZONE AS $$
+    SELECT MAX(record_approved_at(id)) FROM records WHERE player = player_id AND status_ = 'APPROVED'
+$$ LANGUAGE SQL STABLE;
+
+-- Memoized value of player_last_record_at, maintained by the trigger below so that the ranking doesn't have to go through the audit
+-- log of every record on every request.
+ALTER TABLE players ADD COLUMN last_record_at TIMESTAMP WITHOUT TIME ZONE NULL DEFAULT NULL;
+
+-- Like the demon record counts, trigger-maintained changes to this column are not modifications made by a user and should not show
+-- up in the audit log. No UPDATE issued by pointercrate changes it together with any other column.
+DROP TRIGGER player_modification_trigger ON players;
+CREATE TRIGGER player_modification_trigger AFTER UPDATE ON players FOR EACH ROW WHEN (OLD.last_record_at IS NOT DISTINCT FROM NEW.last_record_at) EXECUTE PROCEDURE audit_player_modification();
+
+CREATE FUNCTION update_last_record_at() RETURNS trigger AS $update_last_record_at$
+BEGIN
+    IF TG_OP = 'DELETE' OR TG_OP = 'UPDATE' THEN
+        UPDATE players SET last_record_at = player_last_record_at(id)
+        WHERE id = OLD.player AND last_record_at IS DISTINCT FROM player_last_record_at(id);
+    END IF;
+
+    IF TG_OP = 'INSERT' OR TG_OP = 'UPDATE' THEN
+        UPDATE players SET last_record_at = player_last_record_at(id)
+        WHERE id = NEW.player AND last_record_at IS DISTINCT FROM player_last_record_at(id);
+    END IF;
+
+    RETURN NULL;
+END;
+$update_last_record_at$ LANGUAGE plpgsql;
+
+-- Triggers of the same kind fire in alphabetical order, so naming this one record_player_* guarantees that the audit log entries
+-- record_approved_at relies on have already been written by the time it runs
+CREATE TRIGGER record_player_activity_trigger AFTER INSERT OR UPDATE OF status_, player OR DELETE ON records FOR EACH ROW EXECUTE PROCEDURE update_last_record_at();
+
+UPDATE players SET last_record_at = player_last_record_at(id) WHERE player_last_record_at(id) IS NOT NULL;
+
+CREATE OR REPLACE VIEW players_with_score AS
+SELECT players.id,
+       players.name,
+       RANK() OVER(ORDER BY scores.total_score DESC) AS rank,
+       CASE WHEN scores.total_score IS NULL THEN 0.0::FLOAT ELSE scores.total_score END AS score,
+       ROW_NUMBER() OVER(ORDER BY scores.total_score DESC) AS index,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent,
+       subdivisions.iso_code AS subdivision_code,
+       subdivisions.name AS subdivision_name,
+       players.last_record_at
+FROM
+    (
+        SELECT pseudo_records.player,
+               SUM(record_score(pseudo_records.progress::FLOAT, pseudo_records.position::FLOAT, 100::FLOAT, pseudo_records.requirement)) as total_score
+        FROM (
+                 SELECT player,
+                        progress,
+                        position,
+                        CASE WHEN demons.position > 75 THEN 100 ELSE requirement END AS requirement
+                 FROM records
+                          INNER JOIN demons
+                                     ON demons.id = demon
+                 WHERE demons.position <= 150 AND status_ = 'APPROVED'
+
+                 UNION
+
+                 SELECT verifier as player,
+                        CASE WHEN demons.position > 150 THEN 0.0::FLOAT ELSE 100.0::FLOAT END as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT publisher as player,
+                        0.0::FLOAT as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT creator as player,
+                        0.0::FLOAT as progress,
+                        1.0::FLOAT as position, -- doesn't matter
+                        100.0::FLOAT
+                 FROM creators
+             ) AS pseudo_records
+        GROUP BY player
+    ) scores
+        INNER JOIN players
+                   ON scores.player = players.id
+        LEFT OUTER JOIN nationalities
+                        ON players.nationality = nationalities.iso_country_code
+        LEFT OUTER JOIN subdivisions
+                        ON players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code
+WHERE NOT players.banned AND players.id != 1534;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER record_modification_trigger ON records;
+CREATE TRIGGER record_modification_trigger AFTER UPDATE ON records FOR EACH ROW EXECUTE PROCEDURE audit_record_modification();
+
+ALTER TABLE records DROP COLUMN video_dead;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- Set by the video recheck if a record's video is no longer available. Records are never rejected because of it, the flag is only a
+-- hint to list moderators.
+ALTER TABLE records ADD COLUMN video_dead BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Flagging a video as dead is not a modification made by a user and should not show up in the audit log. Changing the video (which
+-- resets the flag) still does.
+DROP TRIGGER record_modification_trigger ON records;
+CREATE TRIGGER record_modification_trigger AFTER UPDATE ON records FOR EACH ROW WHEN (OLD.video_dead IS NOT DISTINCT FROM NEW.video_dead OR OLD.video IS DISTINCT FROM NEW.video) EXECUTE PROCEDURE audit_record_modification();
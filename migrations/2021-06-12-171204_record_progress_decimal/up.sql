@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- Tenths of a percent of a record's progress, for lists tracking progress to one decimal place. `progress` continues to hold the
+-- whole percentage, so nothing that compares it to requirements or 100 has to change
+ALTER TABLE records ADD COLUMN progress_decimal SMALLINT NOT NULL DEFAULT 0 CHECK (progress_decimal >= 0 AND progress_decimal < 10);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE demons DROP COLUMN verified_on;
+DROP TYPE verification_platform;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+CREATE TYPE verification_platform AS ENUM ('PC', 'MOBILE', 'UNKNOWN');
+
+-- The platform the demon was verified on. Mobile verifications are contentious enough that
+-- communities want to flag them
+ALTER TABLE demons ADD COLUMN verified_on verification_platform NOT NULL DEFAULT 'UNKNOWN';
@@ -0,0 +1,37 @@
+-- Your SQL goes here
+
+-- For every demon, computes the number of (full) days it spent on the main list (positions up to the first argument) and on the
+-- list in general (positions up to the second argument, meaning not on the legacy list), as well as the best position it ever held.
+--
+-- Like demon_legacy_since, this is derived from demon_modifications, which stores the position a demon had _before_ each change. The
+-- position a demon held between two changes is thus the one logged by the later change (or the demon's current position, for the
+-- period since the last one). The addition of a demon is treated as a move from position 0. Since only periods spent at a qualifying
+-- position are counted, the counters of demons currently on the legacy list stay frozen at the time they fell to legacy.
+CREATE FUNCTION demon_longevity(SMALLINT, SMALLINT)
+RETURNS TABLE (
+    id INTEGER,
+    days_on_main_list INTEGER,
+    days_on_list INTEGER,
+    peak_position SMALLINT
+)
+AS $$
+    SELECT periods.id,
+           COALESCE(FLOOR(EXTRACT(EPOCH FROM SUM(periods.until - periods.since) FILTER (WHERE periods.position BETWEEN 1 AND $1)) / 86400), 0)::INTEGER,
+           COALESCE(FLOOR(EXTRACT(EPOCH FROM SUM(periods.until - periods.since) FILTER (WHERE periods.position BETWEEN 1 AND $2)) / 86400), 0)::INTEGER,
+           MIN(periods.position) FILTER (WHERE periods.position > 0)
+    FROM (
+        SELECT history.id, history.time AS since,
+               COALESCE(LEAD(history.time) OVER successor, NOW() AT TIME ZONE 'utc') AS until,
+               COALESCE(LEAD(history.position) OVER successor, demons.position) AS position
+        FROM (
+            SELECT id, time, audit_id, 0::SMALLINT AS position FROM demon_additions
+            UNION ALL
+            SELECT id, time, audit_id, position FROM demon_modifications WHERE position > 0
+        ) history
+        INNER JOIN demons ON demons.id = history.id
+        WINDOW successor AS (PARTITION BY history.id ORDER BY history.time, history.audit_id)
+    ) periods
+    GROUP BY periods.id
+$$
+LANGUAGE SQL
+STABLE;
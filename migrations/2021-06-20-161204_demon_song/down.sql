@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE demons DROP COLUMN song_artist;
+ALTER TABLE demons DROP COLUMN song_name;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- The song used by the demon. Purely informational, but players like to search the list by song
+ALTER TABLE demons ADD COLUMN song_name TEXT NULL;
+ALTER TABLE demons ADD COLUMN song_artist TEXT NULL;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- The number of attempts the player needed for the record, if they chose to log it
+ALTER TABLE records ADD COLUMN attempts INTEGER CHECK (attempts >= 0);
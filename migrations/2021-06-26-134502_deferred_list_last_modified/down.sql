@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER mirrors_last_modified_trigger ON demon_video_mirrors;
+DROP TRIGGER players_last_modified_trigger ON players;
+DROP TRIGGER records_last_modified_trigger ON records;
+DROP TRIGGER demons_last_modified_trigger ON demons;
+
+CREATE TRIGGER demons_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demons FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER records_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON records FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER players_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON players FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
+CREATE TRIGGER mirrors_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demon_video_mirrors FOR EACH STATEMENT EXECUTE PROCEDURE touch_list_last_modified();
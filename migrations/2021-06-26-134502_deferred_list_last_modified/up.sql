@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+-- Touching list_last_modified from statement triggers meant that every transaction writing to the list held the lock on
+-- its only row until it committed, serializing all of them. Deferred constraint triggers only run (and take the lock)
+-- while the transaction commits. Constraint triggers cannot be statement-level, so the row gets touched once per
+-- modified row, but all of these updates happen in a single burst right before the commit.
+DROP TRIGGER mirrors_last_modified_trigger ON demon_video_mirrors;
+DROP TRIGGER players_last_modified_trigger ON players;
+DROP TRIGGER records_last_modified_trigger ON records;
+DROP TRIGGER demons_last_modified_trigger ON demons;
+
+CREATE CONSTRAINT TRIGGER demons_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demons DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE touch_list_last_modified();
+CREATE CONSTRAINT TRIGGER records_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON records DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE touch_list_last_modified();
+CREATE CONSTRAINT TRIGGER players_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON players DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE touch_list_last_modified();
+CREATE CONSTRAINT TRIGGER mirrors_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON demon_video_mirrors DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE touch_list_last_modified();
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM video_thumbnails WHERE thumbnail IS NULL;
+
+ALTER TABLE video_thumbnails ALTER COLUMN thumbnail SET NOT NULL;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- A NULL thumbnail means that resolving the thumbnail failed at resolved_at. We remember these failures for a while, so
+-- that videos whose thumbnail cannot be resolved do not cause a request to the host every time they are displayed.
+ALTER TABLE video_thumbnails ALTER COLUMN thumbnail DROP NOT NULL;
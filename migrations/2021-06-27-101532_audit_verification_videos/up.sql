@@ -0,0 +1,55 @@
+-- Your SQL goes here
+
+ALTER TABLE demon_modifications ADD COLUMN verification_video VARCHAR(200) NULL;
+
+CREATE OR REPLACE FUNCTION audit_demon_modification() RETURNS trigger AS $demon_modification_trigger$
+    DECLARE
+        name_change CITEXT;
+        position_change SMALLINT;
+        requirement_change SMALLINT;
+        video_change VARCHAR(200);
+        verification_video_change VARCHAR(200);
+        verifier_change INT;
+        publisher_change InT;
+        published_change BOOLEAN;
+    BEGIN
+        IF (OLD.name <> NEW.name) THEN
+            name_change = OLD.name;
+        END IF;
+
+        IF (OLD.position <> NEW.position) THEN
+            position_change = OLD.position;
+        END IF;
+
+        IF (OLD.requirement <> NEW.requirement) THEN
+            requirement_change = OLD.requirement;
+        END IF;
+
+        IF (OLD.video <> NEW.video) THEN
+            video_change = OLD.video;
+        END IF;
+
+        IF (OLD.verification_video <> NEW.verification_video) THEN
+            verification_video_change = OLD.verification_video;
+        END IF;
+
+        IF (OLD.verifier <> NEW.verifier) THEN
+            verifier_change = OLD.verifier;
+        END IF;
+
+        IF (OLD.publisher <> NEW.publisher) THEN
+            publisher_change = OLD.publisher;
+        END IF;
+
+        IF (OLD.published <> NEW.published) THEN
+            published_change = OLD.published;
+        END IF;
+
+        INSERT INTO demon_modifications (userid, name, position, requirement, video, verification_video, verifier, publisher, published, id)
+            (SELECT id, name_change, position_change, requirement_change, video_change, verification_video_change, verifier_change, publisher_change,
+            published_change, NEW.id
+            FROM active_user LIMIT 1);
+
+        RETURN NEW;
+    END;
+$demon_modification_trigger$ LANGUAGE plpgsql;
@@ -0,0 +1,141 @@
+-- This file should undo anything in `up.sql`
+
+CREATE OR REPLACE FUNCTION player_hardest_demon(player_id INTEGER) RETURNS INTEGER AS $$
+    SELECT demons.id
+    FROM demons
+    WHERE demons.verifier = player_id
+       OR demons.id IN (SELECT demon FROM records WHERE player = player_id AND status_ = 'APPROVED' AND progress = 100)
+    ORDER BY demons.position, demons.id
+    LIMIT 1
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION demon_movements(TIMESTAMP WITHOUT TIME ZONE, TIMESTAMP WITHOUT TIME ZONE)
+RETURNS TABLE (
+    id INTEGER,
+    name CITEXT,
+    time_ TIMESTAMP WITHOUT TIME ZONE,
+    audit_id INTEGER,
+    old_position SMALLINT,
+    new_position SMALLINT,
+    moved BOOLEAN
+)
+AS $$
+    SELECT transitions.id, demons.name, transitions.time, transitions.audit_id, transitions.old_position,
+           COALESCE(transitions.new_position, demons.position), transitions.moved
+    FROM (
+        SELECT history.id, history.time, history.audit_id, history.position AS old_position,
+               LEAD(history.position) OVER (PARTITION BY history.id ORDER BY history.time, history.audit_id) AS new_position,
+               history.position = 0 OR COALESCE(history.next_position = -1, FALSE) AS moved
+        FROM (
+            SELECT raw.*, LEAD(raw.position) OVER (PARTITION BY raw.id ORDER BY raw.time, raw.audit_id) AS next_position
+            FROM (
+                SELECT id, time, audit_id, 0::SMALLINT AS position FROM demon_additions
+                UNION ALL
+                SELECT id, time, audit_id, position FROM demon_modifications WHERE position IS NOT NULL
+            ) raw
+        ) history
+        WHERE history.position != -1
+    ) transitions
+    INNER JOIN demons ON demons.id = transitions.id
+    WHERE transitions.time >= $1 AND transitions.time < $2
+    ORDER BY transitions.time, transitions.audit_id
+$$
+LANGUAGE SQL
+STABLE;
+
+CREATE OR REPLACE FUNCTION list_at(TIMESTAMP WITHOUT TIME ZONE)
+RETURNS TABLE (
+    name CITEXT,
+    position_ SMALLINT,
+    requirement SMALLINT,
+    video VARCHAR(200),
+    verifier INTEGER,
+    publisher INTEGER,
+    id INTEGER,
+    level_id BIGINT,
+    current_position SMALLINT
+)
+AS $$
+    SELECT name, CASE WHEN t.position IS NULL THEN demons.position ELSE t.position END, requirement, video, verifier, publisher, demons.id, level_id, demons.position AS current_position
+    FROM demons
+    LEFT OUTER JOIN (
+            SELECT DISTINCT ON (id) id, position
+            FROM demon_modifications
+            WHERE time >= $1 AND position != -1
+            ORDER BY id, time
+        ) t
+    ON demons.id = t.id
+    LEFT OUTER JOIN (
+            SELECT DISTINCT ON (id) id, published
+            FROM demon_modifications
+            WHERE time >= $1 AND published IS NOT NULL
+            ORDER BY id, time
+        ) p
+    ON demons.id = p.id
+    WHERE NOT EXISTS (SELECT 1 FROM demon_additions WHERE demon_additions.id = demons.id AND time >= $1)
+      AND COALESCE(p.published, demons.published)
+$$
+LANGUAGE SQL
+STABLE;
+
+CREATE OR REPLACE VIEW players_with_score AS
+SELECT players.id,
+       players.name,
+       RANK() OVER(ORDER BY scores.total_score DESC) AS rank,
+       CASE WHEN scores.total_score IS NULL THEN 0.0::FLOAT ELSE scores.total_score END AS score,
+       ROW_NUMBER() OVER(ORDER BY scores.total_score DESC) AS index,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent,
+       subdivisions.iso_code AS subdivision_code,
+       subdivisions.name AS subdivision_name,
+       players.last_record_at
+FROM
+    (
+        SELECT pseudo_records.player,
+               SUM(record_score(pseudo_records.progress::FLOAT, pseudo_records.position::FLOAT, 100::FLOAT, pseudo_records.requirement)) as total_score
+        FROM (
+                 SELECT player,
+                        progress,
+                        position,
+                        CASE WHEN demons.position > 75 THEN 100 ELSE requirement END AS requirement
+                 FROM records
+                          INNER JOIN demons
+                                     ON demons.id = demon
+                 WHERE demons.position <= 150 AND status_ = 'APPROVED'
+
+                 UNION
+
+                 SELECT verifier as player,
+                        CASE WHEN demons.position > 150 THEN 0.0::FLOAT ELSE 100.0::FLOAT END as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT publisher as player,
+                        0.0::FLOAT as progress,
+                        position,
+                        100.0::FLOAT
+                 FROM demons
+
+                 UNION
+
+                 SELECT creator as player,
+                        0.0::FLOAT as progress,
+                        1.0::FLOAT as position, -- doesn't matter
+                        100.0::FLOAT
+                 FROM creators
+             ) AS pseudo_records
+        GROUP BY player
+    ) scores
+        INNER JOIN players
+                   ON scores.player = players.id
+        LEFT OUTER JOIN nationalities
+                        ON players.nationality = nationalities.iso_country_code
+        LEFT OUTER JOIN subdivisions
+                        ON players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code
+WHERE NOT players.banned AND players.id != 1534;
+
+DROP VIEW published_positions;
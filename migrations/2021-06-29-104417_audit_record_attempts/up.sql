@@ -0,0 +1,48 @@
+-- Your SQL goes here
+
+-- Like all other columns, this is the attempt count before the modification. Records without logged attempts have none, so
+-- changes from there are indistinguishable from modifications leaving the attempts untouched
+ALTER TABLE record_modifications ADD COLUMN attempts INTEGER NULL;
+
+CREATE OR REPLACE FUNCTION audit_record_modification() RETURNS trigger AS $record_modification_trigger$
+    DECLARE
+        progress_change SMALLINT;
+        progress_decimal_change SMALLINT;
+        attempts_change INTEGER;
+        video_change VARCHAR(200);
+        status_change RECORD_STATUS;
+        player_change INT;
+        demon_change INTEGER;
+    BEGIN
+        IF (OLD.progress <> NEW.progress OR OLD.progress_decimal <> NEW.progress_decimal) THEN
+            progress_change = OLD.progress;
+            progress_decimal_change = OLD.progress_decimal;
+        END IF;
+
+        IF (OLD.attempts IS DISTINCT FROM NEW.attempts) THEN
+            attempts_change = OLD.attempts;
+        END IF;
+
+        IF (OLD.video <> NEW.video) THEN
+            video_change = OLD.video;
+        END IF;
+
+        IF (OLD.status_ <> NEW.status_) THEN
+            status_change = OLD.status_;
+        END IF;
+
+        IF (OLD.player <> NEW.player) THEN
+            player_change = OLD.player;
+        END IF;
+
+        IF (OLD.demon <> NEW.demon) THEN
+            demon_change = OLD.demon;
+        END IF;
+
+        INSERT INTO record_modifications (userid, id, progress, progress_decimal, attempts, video, status_, player, demon)
+            (SELECT id, NEW.id, progress_change, progress_decimal_change, attempts_change, video_change, status_change, player_change,
+            demon_change FROM active_user LIMIT 1);
+
+        RETURN NEW;
+    END;
+$record_modification_trigger$ LANGUAGE plpgsql;
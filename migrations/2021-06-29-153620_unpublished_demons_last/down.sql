@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+-- The positions unpublished demons had before being moved to the end of the list are not retained, so there is nothing to undo
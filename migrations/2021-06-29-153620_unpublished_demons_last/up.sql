@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Unpublished demons are now always positioned after all published ones, so that published demons have the same positions for
+-- the public as for the list team. Existing unpublished demons are moved to the end of the list, keeping their relative order.
+UPDATE demons
+SET position = reordered.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (ORDER BY NOT published, position)::SMALLINT AS position
+    FROM demons
+    WHERE position IS NOT NULL
+) AS reordered
+WHERE demons.id = reordered.id AND demons.position <> reordered.position;
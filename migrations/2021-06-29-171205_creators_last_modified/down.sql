@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER creators_last_modified_trigger ON creators;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- players_with_score includes the creators of published demons (even if creating a demon awards no points), so changes
+-- to them have to invalidate the ranking's ETag just like changes to demons and records do.
+CREATE CONSTRAINT TRIGGER creators_last_modified_trigger AFTER INSERT OR UPDATE OR DELETE ON creators DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE touch_list_last_modified();
@@ -0,0 +1,7 @@
+SELECT COUNT(*)
+FROM players_with_score
+WHERE (STRPOS(name, $1::CITEXT) > 0 OR $1 is NULL)
+  AND (nation = $2 OR iso_country_code = $2 OR (nation IS NULL AND $3) OR ($2 IS NULL AND NOT $3))
+  AND (continent = CAST($4::TEXT AS CONTINENT) OR $4 IS NULL)
+  AND (subdivision_code = $5 OR iso_country_code || '-' || subdivision_code = $5 OR $5 IS NULL)
+  AND (last_record_at >= $6 OR $6 IS NULL)
\ No newline at end of file
@@ -0,0 +1,8 @@
+SELECT COUNT(*)
+FROM players
+LEFT OUTER JOIN nationalities ON nationality = iso_country_code
+WHERE (players.name = $1::CITEXT OR $1 is NULL)
+  AND (STRPOS(players.name, $2::CITEXT) > 0 OR $2 is NULL
+       OR EXISTS (SELECT FROM player_name_history WHERE player = players.id AND STRPOS(player_name_history.name, $2::CITEXT) > 0))
+  AND (banned = $3 OR $3 IS NULL)
+  AND (nationality = $4 OR iso_country_code = $4 OR (nationality IS NULL AND $5) OR ($4 IS NULL AND NOT $5))
\ No newline at end of file
@@ -0,0 +1,17 @@
+WITH completions AS (
+    SELECT player, demon FROM records WHERE status_ = 'APPROVED' AND progress = 100
+    UNION
+    SELECT verifier AS player, id AS demon FROM demons WHERE published
+)
+SELECT rank, players_with_score.name::TEXT, nation::TEXT, score, COUNT(demons.id) AS completed,
+       (SELECT name::TEXT FROM demons WHERE id = player_hardest_demon(players_with_score.id)) AS hardest
+FROM players_with_score
+LEFT OUTER JOIN completions ON completions.player = players_with_score.id
+LEFT OUTER JOIN demons ON demons.id = completions.demon AND demons.published
+WHERE (STRPOS(players_with_score.name, $1::CITEXT) > 0 OR $1 is NULL)
+  AND (nation = $2 OR iso_country_code = $2 OR (nation IS NULL AND $3) OR ($2 IS NULL AND NOT $3))
+  AND (continent = CAST($4::TEXT AS CONTINENT) OR $4 IS NULL)
+  AND (subdivision_code = $5 OR iso_country_code || '-' || subdivision_code = $5 OR $5 IS NULL)
+  AND (last_record_at >= $6 OR $6 IS NULL)
+GROUP BY players_with_score.id, rank, players_with_score.name, nation, score, index
+ORDER BY index
@@ -1,4 +1,5 @@
 SELECT demons.id AS demon_id, demons.name::text AS demon_name, demons.position, demons.requirement, demons.level_id, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END,
+       CASE WHEN verifiers.link_banned THEN NULL ELSE demons.verification_video::text END AS verification_video, demons.verified_on::text AS verified_on, demons.published, demons.song_name, demons.song_artist,
        verifiers.id AS verifier_id, verifiers.name::text AS verifier_name, verifiers.banned AS verifier_banned,
        publishers.id AS publisher_id, publishers.name::text AS publisher_name, publishers.banned AS publisher_banned
 FROM demons
@@ -15,5 +16,6 @@ WHERE (demons.id < $1 OR $1 IS NULL)
   AND (publishers.id = $9 OR $9 IS NULL)
   AND (publishers.name::CITEXT = $10 OR $10 IS NULL)
   AND (STRPOS(demons.name, $11::CITEXT) > 0 OR $11 is NULL)
+  AND (demons.published = $12 OR $12 IS NULL)
 ORDER BY demons.id {}
-LIMIT $12
\ No newline at end of file
+LIMIT $13
\ No newline at end of file
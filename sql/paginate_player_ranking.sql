@@ -1,8 +1,17 @@
-SELECT id, name::TEXT, rank, score, index, nation::TEXT, iso_country_code::TEXT
-FROM players_with_score
+SELECT id, name::TEXT, rank, score, index, nation::TEXT, iso_country_code::TEXT, continent::TEXT, subdivision_code, subdivision_name::TEXT, last_record_at, total_attempts
+FROM (
+    SELECT players_with_score.id, name, rank, score, nation, iso_country_code, continent, subdivision_code, subdivision_name, last_record_at,
+           total_attempts, ROW_NUMBER() OVER (ORDER BY {}) AS index
+    FROM players_with_score
+    LEFT OUTER JOIN (SELECT player, COUNT(*) AS record_count, SUM(attempts) AS total_attempts FROM records WHERE status_ = 'APPROVED' GROUP BY player) AS record_counts
+                    ON record_counts.player = players_with_score.id
+) AS ranking
 WHERE (index < $1 OR $1 IS NULL)
   AND (index > $2 OR $2 IS NULL)
   AND (STRPOS(name, $3::CITEXT) > 0 OR $3 is NULL)
   AND (nation = $4 OR iso_country_code = $4 OR (nation IS NULL AND $5) OR ($4 IS NULL AND NOT $5))
-ORDER BY rank {}
+  AND (continent = CAST($7::TEXT AS CONTINENT) OR $7 IS NULL)
+  AND (subdivision_code = $8 OR iso_country_code || '-' || subdivision_code = $8 OR $8 IS NULL)
+  AND (last_record_at >= $9 OR $9 IS NULL)
+ORDER BY index {}
 LIMIT $6
\ No newline at end of file
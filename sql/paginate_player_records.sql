@@ -0,0 +1,16 @@
+SELECT id, progress, progress_decimal, video, status, demon_id, demon_name, position, index
+FROM (
+    SELECT records.id, progress, progress_decimal, CASE WHEN players.link_banned THEN NULL ELSE records.video::TEXT END AS video, status_::TEXT AS status,
+           demons.id AS demon_id, demons.name::TEXT AS demon_name, demons.position,
+           ROW_NUMBER() OVER (ORDER BY demons.position, records.id) AS index
+    FROM records
+    INNER JOIN players ON records.player = players.id
+    INNER JOIN demons ON records.demon = demons.id
+    WHERE records.player = $1
+      AND (status_ = CAST($2::TEXT AS record_status) OR $2 IS NULL)
+      AND (demons.published = $3 OR $3 IS NULL)
+) AS player_records
+WHERE (index < $4 OR $4 IS NULL)
+  AND (index > $5 OR $5 IS NULL)
+ORDER BY index {}
+LIMIT $6
\ No newline at end of file
@@ -1,10 +1,13 @@
-SELECT id, name::TEXT, banned, nation::TEXT, iso_country_code::TEXT
+SELECT id, players.name::TEXT, banned, nationalities.nation::TEXT, iso_country_code::TEXT, continent::TEXT, subdivisions.iso_code AS subdivision_code,
+       subdivisions.name::TEXT AS subdivision_name
 FROM players
 LEFT OUTER JOIN nationalities ON nationality = iso_country_code
+LEFT OUTER JOIN subdivisions ON nationality = subdivisions.nation AND subdivision = subdivisions.iso_code
 WHERE (id < $1 OR $1 IS NULL)
   AND (id > $2 OR $2 IS NULL)
-  AND (name = $3::CITEXT OR $3 is NULL)
-  AND (STRPOS(name, $4::CITEXT) > 0 OR $4 is NULL)
+  AND (players.name = $3::CITEXT OR $3 is NULL)
+  AND (STRPOS(players.name, $4::CITEXT) > 0 OR $4 is NULL
+       OR EXISTS (SELECT FROM player_name_history WHERE player = players.id AND STRPOS(player_name_history.name, $4::CITEXT) > 0))
   AND (banned = $5 OR $5 IS NULL)
   AND (nationality = $6 OR iso_country_code = $6 OR (nationality IS NULL AND $7) OR ($6 IS NULL AND NOT $7))
 ORDER BY id {}
@@ -1,4 +1,4 @@
-SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, status_::text AS status,
+SELECT records.id, progress, progress_decimal, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, status_::text AS status,
        players.id AS player_id, players.name::text AS player_name, players.banned AS player_banned,
        demons.id AS demon_id, demons.name::text AS demon_name, demons.position
 FROM records
@@ -18,5 +18,7 @@ WHERE (records.id < $1 OR $1 IS NULL)
   AND (records.video = $12 OR (records.video IS NULL AND $13) OR ($12 IS NULL AND NOT $13))
   AND (players.id = $14 OR $14 IS NULL)
   AND (records.submitter = $15 OR $15 IS NULL)
+  AND (records.video_dead = $16 OR $16 IS NULL)
+  AND (demons.published = $17 OR $17 IS NULL)
 ORDER BY id {}
-LIMIT $16
\ No newline at end of file
+LIMIT $18
\ No newline at end of file
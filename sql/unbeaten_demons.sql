@@ -0,0 +1,11 @@
+SELECT demons.id AS demon_id, demons.name AS "demon_name: String", demons.position, demons.requirement, demons.level_id, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END,
+       CASE WHEN verifiers.link_banned THEN NULL ELSE demons.verification_video::text END AS verification_video, demons.verified_on::text AS "verified_on!", demons.published, demons.song_name, demons.song_artist,
+       verifiers.id AS verifier_id, verifiers.name AS "verifier_name: String", verifiers.banned AS verifier_banned,
+       publishers.id AS publisher_id, publishers.name AS "publisher_name: String", publishers.banned AS publisher_banned
+FROM demons
+INNER JOIN players AS verifiers ON verifiers.id=demons.verifier
+INNER JOIN players AS publishers ON publishers.id=demons.publisher
+WHERE NOT EXISTS (SELECT FROM records WHERE records.demon = demons.id AND records.player <> demons.verifier AND records.status_ = 'APPROVED' AND records.progress = 100)
+  AND ($1 OR demons.position <= $2)
+  AND demons.published
+ORDER BY demons.position
\ No newline at end of file
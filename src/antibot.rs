@@ -0,0 +1,161 @@
+//! Cheap checks weeding out naive bots posting the record submission forms
+//!
+//! Both submission forms contain a honeypot field hidden from humans, and we additionally determine
+//! how long they were open before being submitted. Submissions with the honeypot filled in, or
+//! submitted implausibly fast, are silently discarded: the bot is told the submission succeeded, so
+//! that it has no reason to try harder.
+//!
+//! As the page containing the forms is cached, the time cannot be embedded into the form itself.
+//! The JavaScript form measures it in the browser. Without JavaScript, the form loads an (empty)
+//! image, whose response sets a cookie containing a signed timestamp, which is sent along when the
+//! form is posted. Submissions without either (e.g. from API clients) are never discarded for their
+//! timing, this is no replacement for rate limiting.
+
+use crate::config;
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name of the honeypot field in the submission forms
+pub const HONEYPOT_FIELD: &str = "homepage";
+
+/// The name of the cookie holding the [signed time](form_opened_token) at which the submission form
+/// without JavaScript was loaded
+pub const FORM_OPENED_COOKIE: &str = "submission_form_opened";
+
+/// How long a [`form_opened_token`] stays valid for
+const FORM_OPENED_LIFETIME: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+struct FormOpenedClaims {
+    iat: u64,
+    exp: u64,
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("time went backwards")
+}
+
+fn form_opened_token_at(opened_at: Duration, application_secret: &[u8]) -> String {
+    let claims = FormOpenedClaims {
+        iat: opened_at.as_secs(),
+        exp: (opened_at + FORM_OPENED_LIFETIME).as_secs(),
+    };
+
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &EncodingKey::from_secret(application_secret),
+    )
+    .unwrap()
+}
+
+/// Generates a token recording that a submission form was loaded just now, signed with the
+/// application secret so that it cannot be backdated
+pub fn form_opened_token(application_secret: &[u8]) -> String {
+    form_opened_token_at(since_epoch(), application_secret)
+}
+
+/// The number of seconds that passed since the given [`form_opened_token`] was generated, or `None`
+/// if the token is invalid or expired
+pub fn fill_time_since(token: &str, application_secret: &[u8]) -> Option<i64> {
+    let claims = jsonwebtoken::decode::<FormOpenedClaims>(
+        token,
+        &DecodingKey::from_secret(application_secret),
+        &jsonwebtoken::Validation::default(),
+    )
+    .ok()?
+    .claims;
+
+    Some(since_epoch().as_secs() as i64 - claims.iat as i64)
+}
+
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
+pub struct BotCheck {
+    /// The value of the honeypot field, which humans never get to see
+    #[serde(default)]
+    pub homepage: Option<String>,
+
+    /// The number of seconds the submission form was open for before being submitted, as measured
+    /// by the browser, or [derived from](fill_time_since) the [`FORM_OPENED_COOKIE`]
+    #[serde(default)]
+    pub fill_time: Option<i64>,
+}
+
+impl BotCheck {
+    /// Whether the submission was made by a bot
+    pub fn is_bot(&self) -> bool {
+        let honeypot_filled = self.homepage.as_ref().map(|value| !value.trim().is_empty()).unwrap_or(false);
+
+        // negative times mean the field has been tampered with, which is just as suspicious
+        let too_fast = self
+            .fill_time
+            .map(|fill_time| fill_time < config::submission_min_fill_time())
+            .unwrap_or(false);
+
+        honeypot_filled || too_fast
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_time_since, form_opened_token, form_opened_token_at, since_epoch, BotCheck};
+    use std::time::Duration;
+
+    #[test]
+    fn test_bot_check() {
+        // API clients do not know about any of this
+        assert!(!BotCheck::default().is_bot());
+
+        assert!(!BotCheck {
+            homepage: Some("   ".to_string()),
+            fill_time: Some(60),
+        }
+        .is_bot());
+        assert!(BotCheck {
+            homepage: Some("https://cheap-pills.example".to_string()),
+            fill_time: Some(60),
+        }
+        .is_bot());
+        assert!(BotCheck {
+            homepage: None,
+            fill_time: Some(1),
+        }
+        .is_bot());
+        assert!(BotCheck {
+            homepage: None,
+            fill_time: Some(-3600),
+        }
+        .is_bot());
+    }
+
+    #[test]
+    fn test_form_opened_token() {
+        let secret = b"secret";
+
+        let fill_time = fill_time_since(&form_opened_token(secret), secret).unwrap();
+
+        assert!(BotCheck {
+            homepage: None,
+            fill_time: Some(fill_time),
+        }
+        .is_bot());
+
+        let token = form_opened_token_at(since_epoch() - Duration::from_secs(60), secret);
+        let fill_time = fill_time_since(&token, secret).unwrap();
+
+        assert!((60..65).contains(&fill_time));
+        assert!(!BotCheck {
+            homepage: None,
+            fill_time: Some(fill_time),
+        }
+        .is_bot());
+
+        // tokens cannot be forged, and expire eventually
+        assert_eq!(fill_time_since(&token, b"guessed secret"), None);
+        assert_eq!(fill_time_since("garbage", secret), None);
+        let expired = form_opened_token_at(since_epoch() - Duration::from_secs(48 * 3600), secret);
+
+        assert_eq!(fill_time_since(&expired, secret), None);
+    }
+}
@@ -1,13 +1,55 @@
 use crate::{
+    config,
+    error::PointercrateError,
     extractor::auth::TokenAuth,
-    model::demonlist::demon::{FullDemon, PostDemon},
+    model::demonlist::demon::{audit, longevity_ranking, Demon, DemonSearch, FullDemon, MinimalDemon, PostDemon},
+    notify,
     permissions::Permissions,
     state::PointercrateState,
     util::HttpResponseBuilderExt,
     ApiResult,
 };
-use actix_web::{web::Json, HttpResponse};
-use actix_web_codegen::post;
+use actix_web::{
+    web::{Json, Path, Query},
+    HttpResponse,
+};
+use actix_web_codegen::{get, post};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Whether the given user may see unpublished demons, which is reserved to list moderators
+fn sees_unpublished(user: &ApiResult<TokenAuth>) -> bool {
+    match user {
+        Ok(TokenAuth(user)) => user.inner().has_permission(Permissions::ListModerator),
+        Err(_) => false,
+    }
+}
+
+/// Pretends unpublished demons do not exist to anyone but list moderators
+fn require_visible(demon: &FullDemon, identified_by: String, user: &ApiResult<TokenAuth>) -> ApiResult<()> {
+    if !demon.demon.published && !sees_unpublished(user) {
+        return Err(PointercrateError::ModelNotFound {
+            model: "Demon",
+            identified_by,
+        }
+        .into())
+    }
+
+    Ok(())
+}
+
+/// Whether patching a demon that was at the given position and in the given publication state
+/// changed its placement on the public list. Moving an unpublished demon around does not change
+/// the public list, but publishing or unpublishing a demon does.
+fn public_placement_changed(demon: &FullDemon, previous_position: i16, previously_published: bool) -> bool {
+    demon.demon.published != previously_published || (demon.demon.published && demon.demon.base.position != previous_position)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnbeatenQuery {
+    #[serde(default)]
+    include_legacy: bool,
+}
 
 #[post("/")]
 pub async fn post(TokenAuth(user): TokenAuth, state: PointercrateState, data: Json<PostDemon>) -> ApiResult<HttpResponse> {
@@ -15,13 +57,122 @@ pub async fn post(TokenAuth(user): TokenAuth, state: PointercrateState, data: Js
 
     let mut connection = state.audited_transaction(&user).await?;
 
+    let previous_top = Demon::top_id(&mut connection).await?;
     let demon = FullDemon::create_from(data.into_inner(), &mut connection).await?;
+    let new_top = Demon::new_top(previous_top, &mut connection).await?;
 
     connection.commit().await?;
 
+    state.overview_cache.invalidate();
+    state.hardest_demons.invalidate();
+
+    if let Some(ref new_top) = new_top {
+        notify::new_top_demon(&state, new_top);
+    }
+
+    // Unpublished demons do not show up in the changelog
+    if demon.demon.published {
+        notify::placement_changed(&state);
+    }
+
     Ok(HttpResponse::Created().json_with_etag(&demon))
 }
 
+/// Creates all given demons inside a single transaction, which is only committed if every demon
+/// could be created. The response contains the outcome of each demon either way.
+#[post("/batch/")]
+pub async fn post_batch(TokenAuth(user): TokenAuth, state: PointercrateState, data: Json<Vec<PostDemon>>) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+    let mut connection = state.audited_transaction(&user).await?;
+
+    let previous_top = Demon::top_id(&mut connection).await?;
+    let results = FullDemon::create_batch(data.into_inner(), &mut connection).await?;
+    let committed = results.iter().all(Result::is_ok);
+    let created = results.iter().filter_map(|result| result.as_ref().ok()).collect::<Vec<_>>();
+
+    let results = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            match result {
+                Ok(demon) => json!({"index": index, "demon": demon}),
+                Err(error) =>
+                    json!({
+                        "index": index,
+                        "error": {
+                            "code": error.error_code(),
+                            "message": error.to_string(),
+                            "data": error
+                        }
+                    }),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !committed {
+        // dropping the transaction rolls back every demon in the batch
+        return Ok(HttpResponse::UnprocessableEntity().json(json!({"committed": false, "results": results})))
+    }
+
+    let new_top = Demon::new_top(previous_top, &mut connection).await?;
+
+    connection.commit().await?;
+
+    state.overview_cache.invalidate();
+    state.hardest_demons.invalidate();
+
+    if let Some(ref new_top) = new_top {
+        notify::new_top_demon(&state, new_top);
+    }
+
+    if created.iter().any(|demon| demon.demon.published) {
+        notify::placement_changed(&state);
+    }
+
+    Ok(HttpResponse::Created().json(json!({"committed": true, "results": results})))
+}
+
+#[get("/{demon_id}/audit/")]
+pub async fn audit_log(TokenAuth(user): TokenAuth, state: PointercrateState, demon_id: Path<i32>) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListModerator)?;
+
+    let mut connection = state.connection().await?;
+
+    // Demons added before the audit log existed have no entries, so an empty log does not mean the demon doesn't exist
+    let demon = MinimalDemon::by_id(demon_id.into_inner(), &mut connection).await?;
+    let log = audit::entries_for_demon(demon.id, &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(log))
+}
+
+#[get("/search/")]
+pub async fn search(state: PointercrateState, search: Query<DemonSearch>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let results = search.search(&mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+#[get("/unbeaten/")]
+pub async fn unbeaten(state: PointercrateState, query: Query<UnbeatenQuery>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let demons = Demon::unbeaten(query.include_legacy, config::extended_list_size(), &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(demons))
+}
+
+#[get("/longevity/")]
+pub async fn longevity(state: PointercrateState) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let ranking = longevity_ranking(&mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(ranking))
+}
+
 pub mod v1 {
     use crate::{
         extractor::{auth::TokenAuth, if_match::IfMatch},
@@ -30,6 +181,7 @@ pub mod v1 {
             demon::{Demon, DemonPositionPagination, FullDemon, PatchDemon},
             player::DatabasePlayer,
         },
+        notify,
         permissions::Permissions,
         state::PointercrateState,
         util::HttpResponseBuilderExt,
@@ -42,9 +194,15 @@ pub mod v1 {
     use actix_web_codegen::{delete, get, patch, post};
 
     #[get("/")]
-    pub async fn paginate(state: PointercrateState, mut pagination: Query<DemonPositionPagination>) -> ApiResult<HttpResponse> {
+    pub async fn paginate(
+        user: ApiResult<TokenAuth>, state: PointercrateState, mut pagination: Query<DemonPositionPagination>,
+    ) -> ApiResult<HttpResponse> {
         let mut connection = state.connection().await?;
 
+        if !super::sees_unpublished(&user) {
+            pagination.published = Some(true);
+        }
+
         let mut demons = pagination.page(&mut connection).await?;
         let max_position = Demon::max_position(&mut connection).await?;
 
@@ -61,10 +219,13 @@ pub mod v1 {
     }
 
     #[get("/{position}/")]
-    pub async fn get(state: PointercrateState, position: Path<i16>) -> ApiResult<HttpResponse> {
+    pub async fn get(user: ApiResult<TokenAuth>, state: PointercrateState, position: Path<i16>) -> ApiResult<HttpResponse> {
         let mut connection = state.connection().await?;
 
-        let demon = FullDemon::by_position(position.into_inner(), &mut connection).await?;
+        let position = position.into_inner();
+        let demon = FullDemon::by_position(position, &mut connection).await?;
+
+        super::require_visible(&demon, position.to_string(), &user)?;
 
         Ok(HttpResponse::Ok().json_with_etag(&demon))
     }
@@ -82,10 +243,25 @@ pub mod v1 {
 
         if_match.require_etag_match(&demon)?;
 
+        let previous_top = Demon::top_id(&mut connection).await?;
+        let previous_position = demon.demon.base.position;
+        let previously_published = demon.demon.published;
         let demon = demon.apply_patch(patch.into_inner(), &mut connection).await?;
+        let new_top = Demon::new_top(previous_top, &mut connection).await?;
 
         connection.commit().await?;
 
+        state.overview_cache.invalidate();
+        state.hardest_demons.invalidate();
+
+        if let Some(ref new_top) = new_top {
+            notify::new_top_demon(&state, new_top);
+        }
+
+        if super::public_placement_changed(&demon, previous_position, previously_published) {
+            notify::placement_changed(&state);
+        }
+
         Ok(HttpResponse::Ok().json_with_etag(&demon))
     }
 
@@ -132,12 +308,18 @@ pub mod v1 {
 
 pub mod v2 {
     use crate::{
+        cistring::CiStr,
+        error::PointercrateError,
         extractor::{auth::TokenAuth, if_match::IfMatch},
         model::demonlist::{
             creator::{Creator, PostCreator},
-            demon::{Demon, DemonIdPagination, DemonPositionPagination, FullDemon, PatchDemon},
+            demon::{
+                append_mirror, set_mirrors, Demon, DemonAlias, DemonIdPagination, DemonPositionPagination, FullDemon, PatchDemon, PostAlias,
+                PostMirror,
+            },
             player::DatabasePlayer,
         },
+        notify,
         permissions::Permissions,
         state::PointercrateState,
         util::HttpResponseBuilderExt,
@@ -147,12 +329,18 @@ pub mod v2 {
         web::{Json, Path, Query},
         HttpResponse,
     };
-    use actix_web_codegen::{delete, get, patch, post};
+    use actix_web_codegen::{delete, get, patch, post, put};
 
     #[get("/")]
-    pub async fn paginate(state: PointercrateState, mut pagination: Query<DemonIdPagination>) -> ApiResult<HttpResponse> {
+    pub async fn paginate(
+        user: ApiResult<TokenAuth>, state: PointercrateState, mut pagination: Query<DemonIdPagination>,
+    ) -> ApiResult<HttpResponse> {
         let mut connection = state.connection().await?;
 
+        if !super::sees_unpublished(&user) {
+            pagination.published = Some(true);
+        }
+
         let mut demons = pagination.page(&mut connection).await?;
         let (max_id, min_id) = Demon::extremal_demon_ids(&mut connection).await?;
 
@@ -161,9 +349,15 @@ pub mod v2 {
 
     // Same as /api/v1/demons/
     #[get("/listed/")]
-    pub async fn paginate_listed(state: PointercrateState, mut pagination: Query<DemonPositionPagination>) -> ApiResult<HttpResponse> {
+    pub async fn paginate_listed(
+        user: ApiResult<TokenAuth>, state: PointercrateState, mut pagination: Query<DemonPositionPagination>,
+    ) -> ApiResult<HttpResponse> {
         let mut connection = state.connection().await?;
 
+        if !super::sees_unpublished(&user) {
+            pagination.published = Some(true);
+        }
+
         let mut demons = pagination.page(&mut connection).await?;
         let max_position = Demon::max_position(&mut connection).await?;
 
@@ -180,10 +374,13 @@ pub mod v2 {
     }
 
     #[get("/{demon_id}/")]
-    pub async fn get(state: PointercrateState, id: Path<i32>) -> ApiResult<HttpResponse> {
+    pub async fn get(user: ApiResult<TokenAuth>, state: PointercrateState, id: Path<i32>) -> ApiResult<HttpResponse> {
         let mut connection = state.connection().await?;
 
-        let demon = FullDemon::by_id(id.into_inner(), &mut connection).await?;
+        let id = id.into_inner();
+        let demon = FullDemon::by_id(id, &mut connection).await?;
+
+        super::require_visible(&demon, id.to_string(), &user)?;
 
         Ok(HttpResponse::Ok().json_with_etag(&demon))
     }
@@ -201,10 +398,25 @@ pub mod v2 {
 
         if_match.require_etag_match(&demon)?;
 
+        let previous_top = Demon::top_id(&mut connection).await?;
+        let previous_position = demon.demon.base.position;
+        let previously_published = demon.demon.published;
         let demon = demon.apply_patch(patch.into_inner(), &mut connection).await?;
+        let new_top = Demon::new_top(previous_top, &mut connection).await?;
 
         connection.commit().await?;
 
+        state.overview_cache.invalidate();
+        state.hardest_demons.invalidate();
+
+        if let Some(ref new_top) = new_top {
+            notify::new_top_demon(&state, new_top);
+        }
+
+        if super::public_placement_changed(&demon, previous_position, previously_published) {
+            notify::placement_changed(&state);
+        }
+
         Ok(HttpResponse::Ok().json_with_etag(&demon))
     }
 
@@ -247,4 +459,175 @@ pub mod v2 {
 
         Ok(HttpResponse::NoContent().finish())
     }
+
+    #[post("/{demon_id}/aliases/")]
+    pub async fn post_alias(
+        TokenAuth(user): TokenAuth, state: PointercrateState, id: Path<i32>, alias: Json<PostAlias>,
+    ) -> ApiResult<HttpResponse> {
+        user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+        let mut connection = state.audited_connection(&user).await?;
+
+        let demon = Demon::by_id(id.into_inner(), &mut connection).await?;
+        let alias = DemonAlias::insert(&demon.base, alias.alias.as_ref(), &mut connection).await?;
+
+        Ok(HttpResponse::Created().json(alias))
+    }
+
+    #[delete("/{demon_id}/aliases/{alias}/")]
+    pub async fn delete_alias(TokenAuth(user): TokenAuth, state: PointercrateState, path: Path<(i32, String)>) -> ApiResult<HttpResponse> {
+        user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+        let mut connection = state.audited_connection(&user).await?;
+
+        let (id, alias) = path.into_inner();
+
+        let demon = Demon::by_id(id, &mut connection).await?;
+        let alias = DemonAlias::get(CiStr::from_str(&alias), &mut connection).await?;
+
+        // Don't allow deleting an alias through a different demon's URL
+        if alias.demon != demon.base.id {
+            return Err(PointercrateError::ModelNotFound {
+                model: "Alias",
+                identified_by: alias.alias.to_string(),
+            }
+            .into())
+        }
+
+        alias.delete(&mut connection).await?;
+
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    #[post("/{demon_id}/mirrors/")]
+    pub async fn post_mirror(
+        TokenAuth(user): TokenAuth, state: PointercrateState, id: Path<i32>, mirror: Json<PostMirror>,
+    ) -> ApiResult<HttpResponse> {
+        user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+        let mut connection = state.audited_transaction(&user).await?;
+
+        let demon = Demon::by_id(id.into_inner(), &mut connection).await?;
+        let mirrors = append_mirror(&demon.base, &mirror.video, &mut connection).await?;
+
+        connection.commit().await?;
+
+        state.overview_cache.invalidate();
+
+        Ok(HttpResponse::Created().json(mirrors))
+    }
+
+    #[put("/{demon_id}/mirrors/")]
+    pub async fn put_mirrors(
+        TokenAuth(user): TokenAuth, state: PointercrateState, id: Path<i32>, mirrors: Json<Vec<String>>,
+    ) -> ApiResult<HttpResponse> {
+        user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+        let mut connection = state.audited_transaction(&user).await?;
+
+        let demon = Demon::by_id(id.into_inner(), &mut connection).await?;
+        let mirrors = set_mirrors(&demon.base, &mirrors, &mut connection).await?;
+
+        connection.commit().await?;
+
+        state.overview_cache.invalidate();
+
+        Ok(HttpResponse::Ok().json(mirrors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::v2;
+    use crate::state::audit_connection;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_response_json, TestRequest},
+        App,
+    };
+    use serde_json::Value;
+    use sqlx::PgConnection;
+
+    /// Removes everything [`test_unpublished_demons_hidden`] commits
+    async fn remove_committed_demons(connection: &mut PgConnection) {
+        audit_connection(connection, 0).await.unwrap();
+
+        sqlx::query!("DELETE FROM demons WHERE name IN ('Bloodbath', 'Sonic Wave', 'Tartarus')")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM players WHERE name = 'Riot'")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        crate::test::remove_moderator(connection).await;
+    }
+
+    fn names(listing: &Value) -> Vec<&str> {
+        listing.as_array().unwrap().iter().map(|demon| demon["name"].as_str().unwrap()).collect()
+    }
+
+    #[actix_rt::test]
+    async fn test_unpublished_demons_hidden() {
+        let mut database = crate::test::lock_database().await;
+
+        remove_committed_demons(&mut database).await;
+
+        let token = crate::test::commit_moderator(&mut database).await;
+        let sonic_wave = sqlx::query!(
+            "WITH riot AS (INSERT INTO players (name) VALUES ('Riot') RETURNING id) INSERT INTO demons (name, position, requirement, \
+             verifier, publisher, published) SELECT name, position, 50, id, id, published FROM riot, (VALUES ('Bloodbath', 1, TRUE), \
+             ('Sonic Wave', 2, FALSE), ('Tartarus', 3, TRUE)) AS demons (name, position, published) RETURNING id, name::TEXT AS \"name!\""
+        )
+        .fetch_all(&mut database)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|row| row.name == "Sonic Wave")
+        .unwrap()
+        .id;
+
+        let mut app = init_service(
+            App::new()
+                .app_data(crate::test::test_state().await)
+                .service(v2::paginate_listed)
+                .service(v2::get),
+        )
+        .await;
+        let authorization = format!("Bearer {}", token);
+
+        let public: Value = read_response_json(&mut app, TestRequest::get().uri("/listed/").to_request()).await;
+        let sneaky: Value = read_response_json(&mut app, TestRequest::get().uri("/listed/?published=false").to_request()).await;
+        let moderator: Value = read_response_json(
+            &mut app,
+            TestRequest::get()
+                .uri("/listed/?published=false")
+                .header("Authorization", authorization.as_str())
+                .to_request(),
+        )
+        .await;
+
+        let public_status = call_service(&mut app, TestRequest::get().uri(&format!("/{}/", sonic_wave)).to_request())
+            .await
+            .status();
+        let moderator_status = call_service(
+            &mut app,
+            TestRequest::get()
+                .uri(&format!("/{}/", sonic_wave))
+                .header("Authorization", authorization.as_str())
+                .to_request(),
+        )
+        .await
+        .status();
+
+        remove_committed_demons(&mut database).await;
+
+        // the public does not get to ask for unpublished demons
+        assert_eq!(names(&public), vec!["Bloodbath", "Tartarus"]);
+        assert_eq!(names(&sneaky), vec!["Bloodbath", "Tartarus"]);
+        assert_eq!(names(&moderator), vec!["Sonic Wave"]);
+
+        assert_eq!(public_status, StatusCode::NOT_FOUND);
+        assert_eq!(moderator_status, StatusCode::OK);
+    }
 }
@@ -1,6 +1,14 @@
-use crate::config;
-use actix_web::HttpResponse;
+use crate::{
+    config,
+    error::PointercrateError,
+    model::demonlist::demon::export_list,
+    state::PointercrateState,
+    view::demonlist::{overview_demons, OverviewDemon},
+    ApiResult, Result,
+};
+use actix_web::{http::header::CACHE_CONTROL, web::Path, HttpResponse};
 use actix_web_codegen::get;
+use serde::Serialize;
 use serde_json::json;
 
 #[get("/list_information/")]
@@ -8,7 +16,122 @@ pub fn list_information() -> HttpResponse {
     HttpResponse::Ok().json(json! {
         {
             "list_size": config::list_size(),
-            "extended_list_size": config::extended_list_size()
+            "extended_list_size": config::extended_list_size(),
+            "main_list_only": config::main_list_only()
         }
     })
 }
+
+#[get("/demonlist/")]
+pub async fn export(state: PointercrateState) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    Ok(HttpResponse::Ok().json(export_list(&mut connection).await?))
+}
+
+/// A demon as returned by the [`top`] endpoint, meant for embedding the top of the list on other
+/// sites
+#[derive(Debug, Serialize)]
+pub struct TopDemon {
+    id: i32,
+    position: i16,
+    name: String,
+    requirement: i16,
+    publisher: String,
+    video: Option<String>,
+}
+
+impl From<&OverviewDemon> for TopDemon {
+    fn from(demon: &OverviewDemon) -> Self {
+        TopDemon {
+            id: demon.id,
+            position: demon.position,
+            name: demon.name.clone(),
+            requirement: demon.requirement,
+            publisher: demon.publisher.clone(),
+            video: demon.video.clone(),
+        }
+    }
+}
+
+/// Takes the first `n` demons of the given (ordered) overview, never including demons on the legacy
+/// list
+fn top_demons(overview: &[OverviewDemon], n: i64, extended_list_size: i16) -> Result<Vec<TopDemon>> {
+    if n <= 0 {
+        return Err(PointercrateError::BadRequest {
+            message: "The number of demons must be positive".to_owned(),
+        })
+    }
+
+    Ok(overview
+        .iter()
+        .take_while(|demon| demon.position <= extended_list_size)
+        .take(n as usize)
+        .map(TopDemon::from)
+        .collect())
+}
+
+#[get("/demonlist/top/{n}/")]
+pub async fn top(state: PointercrateState, n: Path<i64>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    // Shares the cache with the demonlist overview, so embeds do not cause any additional queries
+    let overview = state
+        .overview_cache
+        .get_or_load(None, overview_demons(&mut connection, None))
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .header(CACHE_CONTROL, format!("public, max-age={}", config::overview_cache_ttl()))
+        .json(top_demons(&overview, n.into_inner(), config::extended_list_size())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::top_demons;
+    use crate::{error::PointercrateError, view::demonlist::OverviewDemon};
+
+    fn overview(length: i16) -> Vec<OverviewDemon> {
+        (1..=length)
+            .map(|position| {
+                OverviewDemon {
+                    id: position as i32,
+                    position,
+                    name: format!("Demon {}", position),
+                    requirement: 50,
+                    publisher: "Riot".to_string(),
+                    publisher_nationality: None,
+                    video: None,
+                    current_position: None,
+                    mirrors: Vec::new(),
+                    legacy_since: None,
+                    tags: Vec::new(),
+                    record_count: None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_top_ten() {
+        let top = top_demons(&overview(150), 10, 100).unwrap();
+        let positions: Vec<i16> = top.iter().map(|demon| demon.position).collect();
+
+        assert_eq!(positions, (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_top_clamped() {
+        // legacy demons are never included
+        assert_eq!(top_demons(&overview(150), 1000, 100).unwrap().len(), 100);
+
+        // lists shorter than the extended list are returned entirely
+        assert_eq!(top_demons(&overview(20), 50, 100).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn test_top_non_positive() {
+        assert!(matches!(top_demons(&overview(10), 0, 100), Err(PointercrateError::BadRequest { .. })));
+        assert!(matches!(top_demons(&overview(10), -5, 100), Err(PointercrateError::BadRequest { .. })));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod demon;
 pub mod misc;
+pub mod moderation;
 pub mod player;
 pub mod record;
 pub mod submitter;
@@ -0,0 +1,17 @@
+use crate::{
+    extractor::auth::TokenAuth, model::demonlist::record::anomalies::Anomalies, permissions::Permissions, state::PointercrateState,
+    ApiResult,
+};
+use actix_web::HttpResponse;
+use actix_web_codegen::get;
+
+/// Lists data that hints at moderation mistakes, such as approved records below their demon's
+/// requirement
+#[get("/anomalies/")]
+pub async fn anomalies(TokenAuth(user): TokenAuth, state: PointercrateState) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListHelper)?;
+
+    let mut connection = state.connection().await?;
+
+    Ok(HttpResponse::Ok().json(Anomalies::find(&mut connection).await?))
+}
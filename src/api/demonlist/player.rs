@@ -1,16 +1,48 @@
 use crate::{
+    error::{JsonError, PointercrateError},
     extractor::{auth::TokenAuth, if_match::IfMatch},
-    model::demonlist::player::{PatchPlayer, Player, PlayerPagination, RankedPlayer, RankingPagination},
+    model::demonlist::{
+        last_modified,
+        player::{
+            merge_duplicates, merge_players, most_improved, progress_of, require_patch_permissions, DatabasePlayer, PatchClaim, PatchPlayer,
+            Player, PlayerAutocomplete, PlayerClaim, PlayerComparison, PlayerPagination, PlayerRecordPagination, RankedPlayer,
+            RankingPagination,
+        },
+    },
     permissions::Permissions,
     state::PointercrateState,
-    util::HttpResponseBuilderExt,
+    util::{etag_matches, HttpResponseBuilderExt},
+    video::youtube::YouTubeApi,
     ApiResult,
 };
 use actix_web::{
-    web::{Json, Path, Query},
-    HttpResponse,
+    http::header::{CONTENT_DISPOSITION, ETAG},
+    web::{Bytes, Json, Path, Query},
+    HttpRequest, HttpResponse,
 };
-use actix_web_codegen::{get, patch};
+use actix_web_codegen::{get, patch, post};
+use chrono::{Duration, Utc};
+use futures::{channel::mpsc, StreamExt};
+use log::error;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ImprovedQuery {
+    /// The size of the window (ending now) over which score improvements are computed, in days
+    #[serde(default = "default_improvement_days")]
+    days: i64,
+
+    #[serde(default = "default_improvement_limit")]
+    limit: i64,
+}
+
+fn default_improvement_days() -> i64 {
+    30
+}
+
+fn default_improvement_limit() -> i64 {
+    50
+}
 
 #[get("/")]
 pub async fn paginate(state: PointercrateState, mut pagination: Query<PlayerPagination>) -> ApiResult<HttpResponse> {
@@ -18,18 +50,38 @@ pub async fn paginate(state: PointercrateState, mut pagination: Query<PlayerPagi
 
     let mut demons = pagination.page(&mut connection).await?;
     let (max_id, min_id) = Player::extremal_player_ids(&mut connection).await?;
+    let total = pagination.count(&mut connection).await?;
+
+    pagination_response!(total = total; "/api/v1/players/", demons, pagination, min_id, max_id, before_id, after_id, base.id)
+}
+
+#[get("/autocomplete/")]
+pub async fn autocomplete(state: PointercrateState, query: Query<PlayerAutocomplete>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let players = query.suggestions(&mut connection).await?;
 
-    pagination_response!("/api/v1/players/", demons, pagination, min_id, max_id, before_id, after_id, base.id)
+    Ok(HttpResponse::Ok().json(players))
 }
 
 #[get("/ranking/")]
-pub async fn ranking(state: PointercrateState, mut pagination: Query<RankingPagination>) -> ApiResult<HttpResponse> {
+pub async fn ranking(request: HttpRequest, state: PointercrateState, mut pagination: Query<RankingPagination>) -> ApiResult<HttpResponse> {
     let mut connection = state.connection().await?;
 
+    // The stats viewer polls the ranking a lot, so we avoid recomputing it if nothing changed
+    let etag = pagination.etag(last_modified(&mut connection).await?);
+
+    if etag_matches(request.headers(), &etag) {
+        return Ok(HttpResponse::NotModified().header(ETAG, etag).finish())
+    }
+
     let mut demons = pagination.page(&mut connection).await?;
     let max_index = RankedPlayer::max_index(&mut connection).await?;
+    let total = pagination.count(&mut connection).await?;
 
     pagination_response!(
+        etag = etag;
+        total = total;
         "/api/v1/players/ranking/",
         demons,
         pagination,
@@ -41,6 +93,47 @@ pub async fn ranking(state: PointercrateState, mut pagination: Query<RankingPagi
     )
 }
 
+#[get("/ranking/export.csv")]
+pub async fn export_ranking(state: PointercrateState, filter: Query<RankingPagination>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+    let filter = filter.into_inner();
+    let (mut sender, receiver) = mpsc::channel(64);
+
+    // The export runs in its own task and hands over the CSV line by line, so that large rankings
+    // are never held in memory completely
+    actix_rt::spawn(async move {
+        if let Err(err) = filter.export_csv(&mut connection, &mut sender).await {
+            error!("Exporting the player ranking failed: {:?}", err);
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header(CONTENT_DISPOSITION, "attachment; filename=\"ranking.csv\"")
+        .streaming(receiver.map(|line: String| Ok::<_, JsonError>(Bytes::from(line)))))
+}
+
+#[get("/ranking/improved/")]
+pub async fn most_improved_ranking(state: PointercrateState, query: Query<ImprovedQuery>) -> ApiResult<HttpResponse> {
+    if query.days < 1 || query.days > 365 {
+        return Err(PointercrateError::BadRequest {
+            message: "Invalid value for the 'days' parameter. It must be between 1 and 365".to_owned(),
+        }
+        .into())
+    }
+
+    if query.limit < 1 || query.limit > 100 {
+        return Err(PointercrateError::InvalidPaginationLimit.into())
+    }
+
+    let mut connection = state.connection().await?;
+
+    let now = Utc::now().naive_utc();
+    let players = most_improved(now - Duration::days(query.days), now, query.limit, &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(players))
+}
+
 #[get("/{player_id}/")]
 pub async fn get(state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
     let mut connection = state.connection().await?;
@@ -53,12 +146,72 @@ pub async fn get(state: PointercrateState, path: Path<i32>) -> ApiResult<HttpRes
     Ok(HttpResponse::Ok().json_with_etag(&player))
 }
 
+/// Gets the players ranked immediately above and below the given player
+#[get("/{player_id}/neighbors/")]
+pub async fn neighbors(state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(player.rank_neighbors(&mut connection).await?))
+}
+
+#[get("/{player_id}/progress/")]
+pub async fn progress(state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(progress_of(&player, &mut connection).await?))
+}
+
+/// Lists the player's records, ordered by the position of the demon they were made on. Only list
+/// helpers get to see records that are not approved
+#[get("/{player_id}/records/")]
+pub async fn records(
+    user: ApiResult<TokenAuth>, state: PointercrateState, path: Path<i32>, mut pagination: Query<PlayerRecordPagination>,
+) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    pagination.restrict_for(user.as_ref().ok().map(|TokenAuth(user)| user.inner()))?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+
+    let mut records = pagination.page(&player, &mut connection).await?;
+    let total = pagination.count(&player, &mut connection).await?;
+    let endpoint = format!("/api/v1/players/{}/records/", player.id);
+
+    pagination_response!(
+        total = total;
+        endpoint,
+        records,
+        pagination,
+        1,
+        total,
+        before_index,
+        after_index,
+        index
+    )
+}
+
+/// Suggests a nationality for the player based on their YouTube channel. Nothing is changed, the
+/// suggestion has to be applied by patching the player
+#[get("/{player_id}/suggest-nationality/")]
+pub async fn suggest_nationality(TokenAuth(user): TokenAuth, state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListModerator)?;
+
+    let mut connection = state.connection().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+    let api = YouTubeApi::from_config(state.http_client.clone());
+
+    Ok(HttpResponse::Ok().json(player.suggest_nationality(api.as_ref(), &mut connection).await?))
+}
+
 #[patch("/{player_id}/")]
 pub async fn patch(
     TokenAuth(user): TokenAuth, if_match: IfMatch, state: PointercrateState, data: Json<PatchPlayer>, path: Path<i32>,
 ) -> ApiResult<HttpResponse> {
-    user.inner().require_permissions(Permissions::ListModerator)?;
-
     let mut connection = state.audited_transaction(&user).await?;
 
     let player = Player::by_id(path.into_inner(), &mut connection)
@@ -66,11 +219,157 @@ pub async fn patch(
         .upgrade(&mut connection)
         .await?;
 
+    // users that claimed the player can change some of its details themselves
+    require_patch_permissions(user.inner(), &player.player.base, &data, &mut connection).await?;
+
     if_match.require_etag_match(&player)?;
 
     let player = player.apply_patch(data.into_inner(), &mut connection).await?;
 
     connection.commit().await?;
 
+    // the overview displays player names (and hides videos of link banned verifiers)
+    state.overview_cache.invalidate();
+
     Ok(HttpResponse::Ok().json_with_etag(&player))
 }
+
+/// Merges the first player into the second one, e.g. after the first player renamed themselves
+#[post("/{player_id}/merge/{into_id}/")]
+pub async fn merge(TokenAuth(user): TokenAuth, state: PointercrateState, path: Path<(i32, i32)>) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+    let mut connection = state.audited_transaction(&user).await?;
+    let (from, into) = path.into_inner();
+
+    let from = DatabasePlayer::by_id(from, &mut connection).await?;
+    let into = DatabasePlayer::by_id(into, &mut connection).await?;
+
+    let player = merge_players(from, into, &mut connection).await?;
+
+    connection.commit().await?;
+
+    // the overview displays publisher names, and the merged player's records and verifications
+    // might include a harder demon
+    state.overview_cache.invalidate();
+    state.hardest_demons.invalidate();
+
+    Ok(HttpResponse::Ok().json_with_etag(&player))
+}
+
+/// Lists all clusters of players whose names only differ in case or whitespace
+#[get("/duplicates/")]
+pub async fn duplicates(TokenAuth(user): TokenAuth, state: PointercrateState) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+    let mut connection = state.connection().await?;
+
+    Ok(HttpResponse::Ok().json(Player::find_duplicates(&mut connection).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeDuplicates {
+    into: i32,
+    players: Vec<i32>,
+
+    /// Must be explicitly set to `true`, merges cannot be undone
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Merges players previously reported by [`duplicates`] into one of them
+#[post("/duplicates/merge/")]
+pub async fn merge_duplicate_players(
+    TokenAuth(user): TokenAuth, state: PointercrateState, data: Json<MergeDuplicates>,
+) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+    let data = data.into_inner();
+
+    if !data.confirm {
+        return Err(PointercrateError::MergeNotConfirmed.into())
+    }
+
+    let mut connection = state.audited_transaction(&user).await?;
+
+    let into = DatabasePlayer::by_id(data.into, &mut connection).await?;
+    let mut from = Vec::new();
+
+    for player_id in data.players {
+        from.push(DatabasePlayer::by_id(player_id, &mut connection).await?);
+    }
+
+    let player = merge_duplicates(from, into, &mut connection).await?;
+
+    connection.commit().await?;
+
+    // the overview displays publisher names
+    state.overview_cache.invalidate();
+
+    Ok(HttpResponse::Ok().json_with_etag(&player))
+}
+
+#[get("/{player_a}/compare/{player_b}/")]
+pub async fn compare(state: PointercrateState, path: Path<(i32, i32)>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+    let (player_a, player_b) = path.into_inner();
+
+    Ok(HttpResponse::Ok().json(PlayerComparison::between(player_a, player_b, &state.hardest_demons, &mut connection).await?))
+}
+
+/// Claims the player for the logged in user. The claim has to be approved by a member of the list
+/// team before it grants any permissions
+#[post("/{player_id}/claim/")]
+pub async fn claim(TokenAuth(user): TokenAuth, state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
+    let mut connection = state.transaction().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+    let claim = PlayerClaim::create(user.inner(), player, &mut connection).await?;
+
+    connection.commit().await?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/api/v1/players/{}/claim/", claim.player.id))
+        .json_with_etag(&claim))
+}
+
+#[get("/{player_id}/claim/")]
+pub async fn get_claim(TokenAuth(user): TokenAuth, state: PointercrateState, path: Path<i32>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+    let claim = PlayerClaim::active_on(player, &mut connection).await?;
+
+    // the claimant can always see the state of their claim
+    if claim.user != user.inner().id {
+        user.inner().require_permissions(Permissions::ListHelper)?;
+    }
+
+    Ok(HttpResponse::Ok().json_with_etag(&claim))
+}
+
+/// Approves or rejects the claim on a player
+#[patch("/{player_id}/claim/")]
+pub async fn patch_claim(
+    TokenAuth(user): TokenAuth, if_match: IfMatch, state: PointercrateState, data: Json<PatchClaim>, path: Path<i32>,
+) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListHelper)?;
+
+    let mut connection = state.transaction().await?;
+
+    let player = DatabasePlayer::by_id(path.into_inner(), &mut connection).await?;
+    let claim = PlayerClaim::active_on(player, &mut connection).await?;
+
+    // otherwise list helpers could grant themselves control over any player
+    if claim.user == user.inner().id {
+        return Err(PointercrateError::PatchOwnClaim.into())
+    }
+
+    if_match.require_etag_match(&claim)?;
+
+    let claim = claim.apply_patch(data.into_inner(), &mut connection).await?;
+
+    connection.commit().await?;
+
+    Ok(HttpResponse::Ok().json_with_etag(&claim))
+}
@@ -1,15 +1,19 @@
 use crate::{
+    antibot::BotCheck,
     config,
     error::{JsonError, PointercrateError},
     extractor::{auth::TokenAuth, if_match::IfMatch, ip::Ip},
+    metrics,
     model::demonlist::{
         record::{
             audit,
             note::{NewNote, Note, PatchNote},
-            FullRecord, PatchRecord, RecordPagination, RecordStatus, Submission,
+            recheck::spawn_recheck,
+            FullRecord, PatchRecord, RecordPagination, RecordStatus, StatusChange, Submission,
         },
         submitter::Submitter,
     },
+    notify,
     permissions::Permissions,
     state::{audit_connection, PointercrateState},
     util::HttpResponseBuilderExt,
@@ -20,6 +24,26 @@ use actix_web::{
     HttpResponse,
 };
 use actix_web_codegen::{delete, get, patch, post};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+/// A record submission, together with the bot check fields set by the submission form
+#[derive(Debug, Deserialize)]
+pub struct SubmissionRequest {
+    #[serde(flatten)]
+    submission: Submission,
+
+    #[serde(flatten)]
+    bot_check: BotCheck,
+}
+
+/// Invalidates the caches depending on approved records: the overview displays the number of
+/// approved records on each demon, and approved records can change their holder's hardest demon
+fn approved_records_changed(state: &PointercrateState) {
+    state.overview_cache.invalidate();
+    state.hardest_demons.invalidate();
+}
 
 #[get("/")]
 pub async fn paginate(
@@ -27,27 +51,8 @@ pub async fn paginate(
 ) -> ApiResult<HttpResponse> {
     let mut connection = state.connection().await?;
 
-    // If the submitter or status fields are set (unless status is set to approved and submitter is
-    // unset, in which case, proceed), you need to be a list mod. If you aren't authenticated, we return
-    // a 401 UNAUTHORIZED, otherwise a 403 FORBIDDEN
-
-    if pagination.submitter.is_some() {
-        match user {
-            Ok(TokenAuth(ref user)) => user.inner().require_permissions(Permissions::ListModerator)?,
-            Err(error) => return Err(error),
-        }
-    }
-
-    match user {
-        Ok(TokenAuth(user)) if user.inner().extended_list_access() => (),
-        Ok(TokenAuth(user)) => user.inner().require_permissions(Permissions::ExtendedAccess)?,
-        _ =>
-            match pagination.status {
-                None => pagination.status = Some(RecordStatus::Approved),
-                Some(status) if status != RecordStatus::Approved => return Err(JsonError(PointercrateError::Unauthorized)),
-                _ => (),
-            },
-    }
+    // Authentication failures only matter if the pagination asks for anything non-public
+    pagination.restrict_for(user.as_ref().ok().map(|TokenAuth(user)| user.inner()))?;
 
     let mut records = pagination.page(&mut connection).await?;
 
@@ -58,8 +63,19 @@ pub async fn paginate(
 
 #[post("/")]
 pub async fn submit(
-    Ip(ip): Ip, user: ApiResult<TokenAuth>, submission: Json<Submission>, state: PointercrateState,
+    Ip(ip): Ip, user: ApiResult<TokenAuth>, request: Json<SubmissionRequest>, state: PointercrateState,
 ) -> ApiResult<HttpResponse> {
+    let SubmissionRequest { submission, bot_check } = request.into_inner();
+
+    // Pretend everything went fine, instead of telling the bot what gave it away
+    if bot_check.is_bot() {
+        info!("Discarding record submission from {} that appears to come from a bot", ip);
+
+        metrics::observe_discarded_submission();
+
+        return Ok(HttpResponse::Created().finish())
+    }
+
     let mut connection = state.transaction().await?;
 
     // NOTE: don't abort if authentication fails! We might not need it!
@@ -68,7 +84,8 @@ pub async fn submit(
     let shall_ratelimit = user.as_ref().map(|user| !user.0.inner().list_team_member()).unwrap_or(true);
 
     // only members of the list team can directly add approved records, or add records without video
-    if submission.status != RecordStatus::Submitted || submission.video.is_none() {
+    // (unless the list does not require videos)
+    if submission.status != RecordStatus::Submitted || (submission.video.is_none() && config::video_required()) {
         // do abort if it fails here!
         let user = user?.0;
 
@@ -80,14 +97,22 @@ pub async fn submit(
 
     let submitter = Submitter::by_ip_or_create(ip, &mut connection, Some(ratelimiter)).await?;
 
-    let record = if shall_ratelimit {
-        FullRecord::create_from(submitter, submission.into_inner(), &mut connection, Some(ratelimiter)).await?
+    let result = if shall_ratelimit {
+        FullRecord::create_ratelimited(submitter, submission, &mut connection, ratelimiter).await
     } else {
-        FullRecord::create_from(submitter, submission.into_inner(), &mut connection, None).await?
+        FullRecord::create_from(submitter, submission, &mut connection, None).await
     };
 
+    metrics::observe_submission(&result);
+
+    let record = result?;
+
     connection.commit().await?;
 
+    if record.status == RecordStatus::Approved {
+        approved_records_changed(&state);
+    }
+
     let response = HttpResponse::Created()
         .header("Location", format!("/api/v1/records/{}/", record.id))
         .json_with_etag(&record);
@@ -95,6 +120,8 @@ pub async fn submit(
     // spawn background task to validate record
     if record.status == RecordStatus::Submitted {
         actix_rt::spawn(record.validate(state));
+    } else if record.status == RecordStatus::Approved {
+        notify::record_approved(&state, &record);
     }
 
     Ok(response)
@@ -165,13 +192,72 @@ pub async fn patch(
 
     if_match.require_etag_match(&record)?;
 
+    let was_approved = record.status == RecordStatus::Approved;
+
     record = record.apply_patch(data.into_inner(), &mut connection).await?;
 
     connection.commit().await?;
 
+    if was_approved || record.status == RecordStatus::Approved {
+        approved_records_changed(&state);
+    }
+
+    if !was_approved && record.status == RecordStatus::Approved {
+        notify::record_approved(&state, &record);
+    }
+
     Ok(HttpResponse::Ok().json_with_etag(&record))
 }
 
+#[patch("/batch/")]
+pub async fn patch_batch(TokenAuth(user): TokenAuth, state: PointercrateState, data: Json<Vec<StatusChange>>) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListHelper)?;
+
+    let mut connection = state.audited_transaction(&user).await?;
+
+    let results = FullRecord::set_status_batch(data.into_inner(), user.inner(), config::extended_list_size(), &mut connection).await?;
+
+    connection.commit().await?;
+
+    if results.iter().any(|result| result.is_ok()) {
+        approved_records_changed(&state);
+    }
+
+    let approved = results
+        .iter()
+        .filter_map(|result| {
+            match result {
+                Ok((previous_status, record)) if *previous_status != RecordStatus::Approved && record.status == RecordStatus::Approved =>
+                    Some(record),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    notify::records_approved(&state, &approved);
+
+    let results = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            match result {
+                Ok((_, record)) => json!({"index": index, "record": record}),
+                Err(error) =>
+                    json!({
+                        "index": index,
+                        "error": {
+                            "code": error.error_code(),
+                            "message": error.to_string(),
+                            "data": error
+                        }
+                    }),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(json!({ "results": results })))
+}
+
 #[delete("/{record_id}/")]
 pub async fn delete(
     TokenAuth(user): TokenAuth, if_match: IfMatch, state: PointercrateState, record_id: Path<i32>,
@@ -200,13 +286,30 @@ pub async fn delete(
 
     if_match.require_etag_match(&record)?;
 
+    let was_approved = record.status == RecordStatus::Approved;
+
     record.delete(&mut connection).await?;
 
     connection.commit().await?;
 
+    if was_approved {
+        approved_records_changed(&state);
+    }
+
     Ok(HttpResponse::NoContent().finish())
 }
 
+/// Starts a recheck of all approved records' videos in the background, flagging records whose video
+/// is no longer available
+#[post("/recheck/")]
+pub async fn recheck_videos(TokenAuth(user): TokenAuth, state: PointercrateState) -> ApiResult<HttpResponse> {
+    user.inner().require_permissions(Permissions::ListAdministrator)?;
+
+    spawn_recheck(state)?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
 #[post("/{record_id}/notes/")]
 pub async fn add_note(
     TokenAuth(user): TokenAuth, data: Json<NewNote>, record_id: Path<i32>, state: PointercrateState,
@@ -286,3 +389,45 @@ pub async fn delete_note(TokenAuth(user): TokenAuth, ids: Path<(i32, i32)>, stat
 
     Ok(HttpResponse::NoContent().finish())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::submit;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+    use serde_json::{json, Value};
+
+    /// Submits a record on a demon that does not exist (so that nothing is ever committed), with the
+    /// given bot check fields
+    async fn submit_with(bot_check: Value) -> StatusCode {
+        let mut app = init_service(App::new().app_data(crate::test::test_state().await).service(submit)).await;
+        let mut submission = json!({"demon": 0, "player": "Samifying", "progress": 95, "video": "https://youtu.be/dQw4w9WgXcQ"});
+
+        submission.as_object_mut().unwrap().extend(bot_check.as_object().unwrap().clone());
+
+        let request = TestRequest::post()
+            .uri("/")
+            .peer_addr("10.0.0.5:443".parse().unwrap())
+            .set_json(&submission)
+            .to_request();
+
+        call_service(&mut app, request).await.status()
+    }
+
+    #[actix_rt::test]
+    async fn test_bot_submissions_discarded() {
+        // discarded before the demon is even looked up
+        assert_eq!(submit_with(json!({"fill_time": 1})).await, StatusCode::CREATED);
+        assert_eq!(submit_with(json!({"fill_time": -60})).await, StatusCode::CREATED);
+        assert_eq!(
+            submit_with(json!({"homepage": "https://cheap-pills.example", "fill_time": 60})).await,
+            StatusCode::CREATED
+        );
+
+        assert_eq!(submit_with(json!({"homepage": "", "fill_time": 60})).await, StatusCode::NOT_FOUND);
+        assert_eq!(submit_with(json!({})).await, StatusCode::NOT_FOUND);
+    }
+}
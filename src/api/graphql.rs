@@ -0,0 +1,385 @@
+//! Read-only GraphQL endpoint for demons and players
+//!
+//! Only compiled in with the `graphql` feature. All resolvers are backed by the same loaders the
+//! REST API uses. Creators and nationalities are fetched through [`DataLoader`]s, so that a query
+//! asking for, say, the creators of every demon on the list results in a single database query
+//! instead of one per demon.
+
+use crate::{
+    config,
+    error::PointercrateError,
+    model::{
+        demonlist::{
+            creator::creators_of_demons,
+            demon::Demon,
+            player::{nationalities_of, DatabasePlayer, RankedPlayer, RankingPagination},
+        },
+        nationality::Nationality,
+    },
+    state::PointercrateState,
+    ApiResult,
+};
+use actix_web::{web::Json, HttpResponse};
+use actix_web_codegen::post;
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    Context, EmptyMutation, EmptySubscription, Enum, Object, Request, Response, Schema,
+};
+use async_trait::async_trait;
+use futures::lock::{Mutex, MutexGuard};
+use lazy_static::lazy_static;
+use sqlx::PgConnection;
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+type PointercrateSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The maximal nesting depth of a query. The schema is not recursive, but `demon { creators {
+/// nationality { ... } } }` is already four levels deep
+const MAX_DEPTH: usize = 6;
+
+/// The maximal complexity of a query, where every field counts as one. The list fields (`demons`,
+/// `ranking` and `creators`) are counted only once, no matter how many elements they resolve to
+const MAX_COMPLEXITY: usize = 100;
+
+lazy_static! {
+    static ref SCHEMA: PointercrateSchema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish();
+}
+
+/// The database connection shared by all resolvers and loaders of a single GraphQL request
+///
+/// This way, a request never holds more than one connection from the pool, no matter how many
+/// fields it resolves.
+#[derive(Clone)]
+pub struct RequestConnection(Arc<Mutex<Box<dyn DerefMut<Target = PgConnection> + Send>>>);
+
+pub struct ConnectionGuard<'a>(MutexGuard<'a, Box<dyn DerefMut<Target = PgConnection> + Send>>);
+
+impl RequestConnection {
+    pub fn new<C: DerefMut<Target = PgConnection> + Send + 'static>(connection: C) -> RequestConnection {
+        RequestConnection(Arc::new(Mutex::new(Box::new(connection))))
+    }
+
+    pub async fn lock(&self) -> ConnectionGuard<'_> {
+        ConnectionGuard(self.0.lock().await)
+    }
+}
+
+impl Deref for ConnectionGuard<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        &self.0
+    }
+}
+
+impl DerefMut for ConnectionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        &mut self.0
+    }
+}
+
+/// Batches lookups of demon creators, keyed by demon ID
+pub struct CreatorLoader(RequestConnection);
+
+#[async_trait]
+impl Loader<i32> for CreatorLoader {
+    type Error = PointercrateError;
+    type Value = Vec<DatabasePlayer>;
+
+    async fn load(&self, demon_ids: &[i32]) -> Result<HashMap<i32, Vec<DatabasePlayer>>, PointercrateError> {
+        creators_of_demons(demon_ids, &mut *self.0.lock().await).await
+    }
+}
+
+/// Batches lookups of player nationalities, keyed by player ID
+pub struct NationalityLoader(RequestConnection);
+
+#[async_trait]
+impl Loader<i32> for NationalityLoader {
+    type Error = PointercrateError;
+    type Value = Nationality;
+
+    async fn load(&self, player_ids: &[i32]) -> Result<HashMap<i32, Nationality>, PointercrateError> {
+        nationalities_of(player_ids, &mut *self.0.lock().await).await
+    }
+}
+
+async fn connection<'a>(ctx: &'a Context<'_>) -> ConnectionGuard<'a> {
+    ctx.data_unchecked::<RequestConnection>().lock().await
+}
+
+/// The parts the demonlist is split into, as configured via `LIST_SIZE` and `EXTENDED_LIST_SIZE`
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ListSection {
+    Main,
+    Extended,
+    Legacy,
+}
+
+impl ListSection {
+    fn contains(self, position: i16) -> bool {
+        match self {
+            ListSection::Main => position <= config::list_size(),
+            ListSection::Extended => position > config::list_size() && position <= config::extended_list_size(),
+            ListSection::Legacy => position > config::extended_list_size(),
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn demon(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<DemonObject> {
+        Ok(DemonObject(Demon::by_id(id, &mut *connection(ctx).await).await?))
+    }
+
+    /// All demons in the given section of the list, ordered by position
+    async fn demons(&self, ctx: &Context<'_>, section: ListSection) -> async_graphql::Result<Vec<DemonObject>> {
+        Ok(Demon::all(&mut *connection(ctx).await)
+            .await?
+            .into_iter()
+            .filter(|demon| section.contains(demon.base.position))
+            .map(DemonObject)
+            .collect())
+    }
+
+    async fn player(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<PlayerObject> {
+        Ok(PlayerObject(DatabasePlayer::by_id(id, &mut *connection(ctx).await).await?))
+    }
+
+    /// A page of the player ranking, starting after the given index
+    async fn ranking(&self, ctx: &Context<'_>, after: Option<i64>, limit: Option<u8>) -> async_graphql::Result<Vec<RankedPlayerObject>> {
+        let mut players = RankingPagination::unfiltered(after, limit)
+            .page(&mut *connection(ctx).await)
+            .await?;
+
+        // The pagination fetches one additional player to determine whether there is a next page
+        players.truncate(limit.unwrap_or(50) as usize);
+
+        Ok(players.into_iter().map(RankedPlayerObject).collect())
+    }
+}
+
+pub struct DemonObject(Demon);
+
+#[Object(name = "Demon")]
+impl DemonObject {
+    async fn id(&self) -> i32 {
+        self.0.base.id
+    }
+
+    async fn name(&self) -> &str {
+        self.0.base.name.as_str()
+    }
+
+    async fn position(&self) -> i16 {
+        self.0.base.position
+    }
+
+    async fn requirement(&self) -> i16 {
+        self.0.requirement
+    }
+
+    async fn video(&self) -> Option<&str> {
+        self.0.video.as_deref()
+    }
+
+    async fn publisher(&self) -> PlayerObject {
+        PlayerObject(self.0.publisher.clone())
+    }
+
+    async fn verifier(&self) -> PlayerObject {
+        PlayerObject(self.0.verifier.clone())
+    }
+
+    async fn creators(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PlayerObject>> {
+        let creators = ctx.data_unchecked::<DataLoader<CreatorLoader>>().load_one(self.0.base.id).await?;
+
+        Ok(creators.unwrap_or_default().into_iter().map(PlayerObject).collect())
+    }
+}
+
+pub struct PlayerObject(DatabasePlayer);
+
+#[Object(name = "Player")]
+impl PlayerObject {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        self.0.name.as_str()
+    }
+
+    async fn banned(&self) -> bool {
+        self.0.banned
+    }
+
+    async fn nationality(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<NationalityObject>> {
+        let nationality = ctx.data_unchecked::<DataLoader<NationalityLoader>>().load_one(self.0.id).await?;
+
+        Ok(nationality.map(NationalityObject))
+    }
+}
+
+pub struct RankedPlayerObject(RankedPlayer);
+
+#[Object(name = "RankedPlayer")]
+impl RankedPlayerObject {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        self.0.name.as_str()
+    }
+
+    async fn rank(&self) -> i64 {
+        self.0.rank
+    }
+
+    async fn score(&self) -> f64 {
+        self.0.score
+    }
+
+    /// The ranking already comes with nationalities, no need to go through the loader here
+    async fn nationality(&self) -> Option<NationalityObject> {
+        self.0.nationality.clone().map(NationalityObject)
+    }
+}
+
+pub struct NationalityObject(Nationality);
+
+#[Object(name = "Nationality")]
+impl NationalityObject {
+    async fn country_code(&self) -> &str {
+        &self.0.iso_country_code
+    }
+
+    async fn nation(&self) -> &str {
+        self.0.nation.as_str()
+    }
+
+    async fn continent(&self) -> Option<&str> {
+        self.0.continent.map(|continent| continent.to_sql())
+    }
+
+    /// The ISO 3166-2 code of the player's subdivision, without the country prefix
+    async fn subdivision_code(&self) -> Option<&str> {
+        self.0.subdivision.as_ref().map(|subdivision| subdivision.iso_code.as_str())
+    }
+
+    async fn subdivision(&self) -> Option<&str> {
+        self.0.subdivision.as_ref().map(|subdivision| subdivision.name.as_str())
+    }
+}
+
+/// Executes a GraphQL request, with all database access going through the given connection
+pub async fn execute(request: Request, connection: RequestConnection) -> Response {
+    let request = request
+        .data(DataLoader::new(CreatorLoader(connection.clone())))
+        .data(DataLoader::new(NationalityLoader(connection.clone())))
+        .data(connection);
+
+    SCHEMA.execute(request).await
+}
+
+#[post("/api/graphql")]
+pub async fn graphql(state: PointercrateState, request: Json<Request>) -> ApiResult<HttpResponse> {
+    let connection = RequestConnection::new(state.connection().await?);
+
+    Ok(HttpResponse::Ok().json(execute(request.into_inner(), connection).await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{execute, RequestConnection};
+    use crate::{cistring::CiStr, model::demonlist::demon::MinimalDemon, state::audit_connection};
+    use async_graphql::Request;
+
+    #[actix_rt::test]
+    async fn test_demon_with_creators_and_player_with_nationality() {
+        let mut connection = crate::test::test_setup().await;
+
+        // Creators are ordered by when they were added, which is only logged on audited connections
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        for creator in &["Samifying", "Aquatias"] {
+            sqlx::query!(
+                "INSERT INTO creators (demon, creator) SELECT $1, id FROM players WHERE name = $2::TEXT",
+                trichotomy.id,
+                creator.to_string()
+            )
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        }
+
+        let aeon_air = sqlx::query!("UPDATE players SET nationality = 'DE' WHERE name = 'Aeon Air' RETURNING id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+        sqlx::query!("UPDATE players SET nationality = 'US' WHERE name = 'Samifying'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let query = format!(
+            "{{ demon(id: {}) {{ name position creators {{ name nationality {{ countryCode }} }} }} player(id: {}) {{ name nationality {{ \
+             countryCode nation }} }} }}",
+            trichotomy.id, aeon_air
+        );
+
+        let response = execute(Request::new(query), RequestConnection::new(Box::new(connection))).await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let response = serde_json::to_value(&response).unwrap();
+        let demon = &response["data"]["demon"];
+        let player = &response["data"]["player"];
+
+        assert_eq!(demon["name"], "Trichotomy");
+        assert_eq!(demon["position"], 2);
+        assert_eq!(demon["creators"][0]["name"], "Samifying");
+        assert_eq!(demon["creators"][0]["nationality"]["countryCode"], "US");
+        assert_eq!(demon["creators"][1]["name"], "Aquatias");
+        assert!(demon["creators"][1]["nationality"].is_null());
+        assert_eq!(player["name"], "Aeon Air");
+        assert_eq!(player["nationality"]["countryCode"], "DE");
+        assert_eq!(player["nationality"]["nation"], "Germany");
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_demon() {
+        let connection = crate::test::test_setup().await;
+
+        let response = execute(Request::new("{ demon(id: -1) { name } }"), RequestConnection::new(Box::new(connection))).await;
+
+        assert_eq!(response.errors.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_complexity_limit() {
+        let connection = crate::test::test_setup().await;
+
+        // aliases allow requesting the same field over and over again
+        let fields = (0..60).map(|alias| format!("demon{}: demons(section: MAIN) {{ name }}", alias)).collect::<Vec<_>>();
+        let query = format!("{{ {} }}", fields.join(" "));
+
+        let response = execute(Request::new(query), RequestConnection::new(Box::new(connection))).await;
+
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("too complex"), "{:?}", response.errors);
+    }
+}
@@ -1,9 +1,34 @@
 use crate::error::PointercrateError;
-use actix_web::{http::Method, HttpRequest, HttpResponse, ResponseError};
+use actix_web::{http::Method, web::ServiceConfig, HttpRequest, HttpResponse, ResponseError};
 
 pub mod auth;
 pub mod demonlist;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod health;
+pub mod nationality;
+pub mod openapi;
+pub mod team;
 pub mod user;
+pub mod video;
+
+/// Registers the GraphQL endpoint at `/api/graphql` if pointercrate was built with the `graphql`
+/// feature
+#[cfg(feature = "graphql")]
+pub fn configure_graphql(config: &mut ServiceConfig) {
+    config.service(graphql::graphql);
+}
+
+#[cfg(not(feature = "graphql"))]
+pub fn configure_graphql(_: &mut ServiceConfig) {}
+
+/// Registers the Prometheus metrics endpoint at `/metrics` if enabled via
+/// [`config::metrics_enabled`](crate::config::metrics_enabled)
+pub fn configure_metrics(config: &mut ServiceConfig) {
+    if crate::config::metrics_enabled() {
+        config.service(health::metrics);
+    }
+}
 
 pub fn handle_404_or_405(request: HttpRequest) -> HttpResponse {
     let path = request.path();
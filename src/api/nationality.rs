@@ -0,0 +1,14 @@
+//! Handlers for all endpoints under the `/api/v1/nationalities` prefix
+
+use crate::{cistring::CiStr, model::nationality::Nationality, state::PointercrateState, ApiResult};
+use actix_web::{web::Path, HttpResponse};
+use actix_web_codegen::get;
+
+#[get("/{iso_code}/subdivisions/")]
+pub async fn subdivisions(state: PointercrateState, iso_code: Path<String>) -> ApiResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let nationality = Nationality::by_country_code_or_name(CiStr::from_str(&iso_code.into_inner()), &mut connection).await?;
+
+    Ok(HttpResponse::Ok().json(nationality.subdivisions(&mut connection).await?))
+}
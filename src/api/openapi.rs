@@ -0,0 +1,362 @@
+//! Machine-readable description of the public parts of the v1 API, as an OpenAPI 3 document
+//!
+//! Query parameters are extracted from the `Deserialize` implementations of the pagination structs,
+//! meaning they cannot get out of sync with what the endpoints actually accept. Response schemas
+//! are written out by hand, but the tests below make sure they match what the model types
+//! serialize to.
+
+use crate::model::{
+    demonlist::{
+        demon::DemonPositionPagination,
+        player::{PlayerPagination, RankingPagination},
+    },
+    nationality::Continent,
+};
+use actix_web::HttpResponse;
+use actix_web_codegen::get;
+use lazy_static::lazy_static;
+use serde::{
+    de::{DeserializeOwned, Error, Visitor},
+    forward_to_deserialize_any, Deserializer,
+};
+use serde_json::{json, Value};
+
+lazy_static! {
+    static ref DOCUMENT: Value = document();
+}
+
+#[get("/openapi.json")]
+pub async fn openapi() -> HttpResponse {
+    HttpResponse::Ok().json(&*DOCUMENT)
+}
+
+fn document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Pointercrate API",
+            "version": "1.0.0",
+            "description": "The public endpoints of the pointercrate v1 API. See https://pointercrate.com/documentation/ for the full documentation"
+        },
+        "servers": [{"url": "https://pointercrate.com"}],
+        "paths": {
+            "/api/v1/players/": paginated_endpoint(
+                "List players, ordered by ID",
+                query_parameters::<PlayerPagination>(),
+                "Player"
+            ),
+            "/api/v1/players/ranking/": paginated_endpoint(
+                "List players by their demonlist score. Ranks are computed before any filters are applied",
+                query_parameters::<RankingPagination>(),
+                "RankedPlayer"
+            ),
+            "/api/v1/demons/": paginated_endpoint(
+                "List the demons currently on the demonlist, ordered by position",
+                query_parameters::<DemonPositionPagination>(),
+                "Demon"
+            ),
+        },
+        "components": {
+            "schemas": {
+                "DatabasePlayer": object(json!({
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "banned": {"type": "boolean"}
+                })),
+                "Player": object(json!({
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "banned": {"type": "boolean"},
+                    "nationality": nullable_reference("Nationality")
+                })),
+                "RankedPlayer": object(json!({
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "rank": {"type": "integer", "description": "The player's rank. Players with equal score share a rank"},
+                    "score": {"type": "number"},
+                    "nationality": nullable_reference("Nationality"),
+                    "last_record_at": {"type": "string", "format": "date-time", "nullable": true, "description": "When the player's most recent record was approved"},
+                    "total_attempts": {"type": "integer", "nullable": true, "description": "The sum of attempts logged for the player's approved records"}
+                })),
+                "Nationality": object(json!({
+                    "country_code": {"type": "string", "description": "The nation's ISO 3166-1 alpha-2 country code"},
+                    "nation": {"type": "string"},
+                    "continent": {"type": "string", "nullable": true, "enum": continents()},
+                    "subdivision": nullable_reference("Subdivision")
+                })),
+                "Subdivision": object(json!({
+                    "iso_code": {"type": "string", "description": "The part of the subdivision's ISO 3166-2 code after the hyphen"},
+                    "name": {"type": "string"}
+                })),
+                "Demon": object(json!({
+                    "id": {"type": "integer"},
+                    "position": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "requirement": {"type": "integer", "description": "The minimal progress required for records to be accepted"},
+                    "video": {"type": "string", "format": "uri", "nullable": true, "description": "A showcase of the level, used for thumbnails"},
+                    "verification_video": {"type": "string", "format": "uri", "nullable": true},
+                    "publisher": {"$ref": "#/components/schemas/DatabasePlayer"},
+                    "verifier": {"$ref": "#/components/schemas/DatabasePlayer"},
+                    "level_id": {"type": "integer", "nullable": true, "description": "The demon's Geometry Dash level ID"},
+                    "verified_on": {"type": "string", "enum": ["PC", "MOBILE", "UNKNOWN"], "description": "The platform the demon was verified on"},
+                    "published": {"type": "boolean", "description": "Whether the demon is visible to the public. Always true unless authenticated as a list moderator"},
+                    "song_name": {"type": "string", "nullable": true, "description": "The name of the song used by the demon"},
+                    "song_artist": {"type": "string", "nullable": true, "description": "The artist of the song used by the demon"}
+                })),
+            }
+        }
+    })
+}
+
+fn paginated_endpoint(summary: &str, parameters: Vec<Value>, schema: &str) -> Value {
+    json!({
+        "get": {
+            "summary": summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "A page of results. Links to the neighboring pages are provided in the `Links` header",
+                    "content": {
+                        "application/json": {
+                            "schema": {"type": "array", "items": {"$ref": format!("#/components/schemas/{}", schema)}}
+                        }
+                    }
+                },
+                "422": {"description": "A query parameter was invalid, for example a `limit` outside of 1 to 100"}
+            }
+        }
+    })
+}
+
+fn object(properties: Value) -> Value {
+    let required: Vec<&String> = properties.as_object().unwrap().keys().collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
+}
+
+fn nullable_reference(schema: &str) -> Value {
+    json!({
+        "nullable": true,
+        "allOf": [{"$ref": format!("#/components/schemas/{}", schema)}]
+    })
+}
+
+fn continents() -> Vec<Value> {
+    Continent::ALL
+        .iter()
+        .map(|continent| serde_json::to_value(continent).unwrap())
+        .collect()
+}
+
+/// Describes the query parameters accepted by the given pagination struct
+fn query_parameters<T: DeserializeOwned>() -> Vec<Value> {
+    field_names::<T>().iter().map(|&name| query_parameter(name)).collect()
+}
+
+fn query_parameter(name: &str) -> Value {
+    let (schema, description) = match name {
+        "limit" =>
+            (
+                json!({"type": "integer", "minimum": 1, "maximum": 100, "default": 50}),
+                "The maximal number of results to return",
+            ),
+        "before" => (json!({"type": "integer"}), "Only return results before this ID/position/rank"),
+        "after" => (json!({"type": "integer"}), "Only return results after this ID/position/rank"),
+        "banned" => (json!({"type": "boolean"}), "Only return (un)banned players"),
+        "continent" => (json!({"type": "string", "enum": continents()}), "Only return players from the given continent"),
+        "nation" =>
+            (
+                json!({"type": "string"}),
+                "Only return players from the nation with the given country code or name. Pass `null` to only return players without \
+                 nationality",
+            ),
+        "subdivision" =>
+            (
+                json!({"type": "string"}),
+                "Only return players from the given subdivision (either a full ISO 3166-2 code, or only the part after the hyphen)",
+            ),
+        "name_contains" =>
+            (
+                json!({"type": "string"}),
+                "Only return results whose name contains the given string, case-insensitively",
+            ),
+        "active_since" =>
+            (
+                json!({"type": "string", "format": "date"}),
+                "Only return players who had a record approved on or after the given day",
+            ),
+        "requirement" | "requirement__gt" | "requirement__lt" | "verifier_id" | "publisher_id" =>
+            (json!({"type": "integer"}), "Filters by the field of the same name"),
+        _ => (json!({"type": "string"}), "Filters by the field of the same name"),
+    };
+
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "description": description,
+        "schema": schema
+    })
+}
+
+/// Gets the names of the fields a struct deriving `Deserialize` expects, with all renames applied
+///
+/// This works by handing the struct's `Deserialize` implementation a deserializer that records the
+/// field names it is told about and then aborts deserialization.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self, _: &'static str, fields: &'static [&'static str], _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+
+            Err(Error::custom("field names extracted"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit unit_struct newtype_struct seq
+            tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{document, field_names};
+    use crate::{
+        cistring::CiString,
+        model::{
+            demonlist::{
+                demon::{Demon, MinimalDemon, VerificationPlatform},
+                player::{DatabasePlayer, Player, RankedPlayer, RankingPagination},
+            },
+            nationality::{Continent, Nationality, Subdivision},
+        },
+    };
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    fn player() -> DatabasePlayer {
+        DatabasePlayer {
+            id: 1,
+            name: CiString("stardust1971".to_string()),
+            banned: false,
+        }
+    }
+
+    fn nationality() -> Nationality {
+        Nationality::new(
+            "US".to_string(),
+            CiString("United States".to_string()),
+            Some(Continent::NorthAmerica),
+            Some(Subdivision::new("CA".to_string(), CiString("California".to_string()))),
+        )
+    }
+
+    /// Asserts that the given value serializes to exactly the properties of the given schema
+    fn assert_matches_schema<T: Serialize>(value: &T, schema: &str) {
+        let document = document();
+        let properties: BTreeSet<&String> = document["components"]["schemas"][schema]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        let value = serde_json::to_value(value).unwrap();
+        let fields: BTreeSet<&String> = value.as_object().unwrap().keys().collect();
+
+        assert_eq!(fields, properties, "schema '{}' out of sync", schema);
+    }
+
+    #[test]
+    fn test_document_is_valid_json() {
+        let serialized = serde_json::to_string(&document()).unwrap();
+        let parsed: Value = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(parsed["openapi"], "3.0.3");
+        assert!(parsed["paths"]["/api/v1/players/ranking/"]["get"].is_object());
+
+        let parameters: Vec<&str> = parsed["paths"]["/api/v1/players/ranking/"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|parameter| parameter["name"].as_str().unwrap())
+            .collect();
+
+        assert!(parameters.contains(&"nation"));
+        assert!(parameters.contains(&"before"));
+    }
+
+    #[test]
+    fn test_field_names_respect_renames() {
+        let fields = field_names::<RankingPagination>();
+
+        assert!(fields.contains(&"after"));
+        assert!(fields.contains(&"subdivision"));
+        assert!(!fields.contains(&"after_index"));
+    }
+
+    #[test]
+    fn test_schemas_match_models() {
+        assert_matches_schema(&player(), "DatabasePlayer");
+        assert_matches_schema(&nationality(), "Nationality");
+        assert_matches_schema(&nationality().subdivision, "Subdivision");
+        assert_matches_schema(
+            &Player {
+                base: player(),
+                nationality: Some(nationality()),
+            },
+            "Player",
+        );
+        assert_matches_schema(
+            &RankedPlayer {
+                id: 1,
+                name: CiString("stardust1971".to_string()),
+                rank: 1,
+                score: 150.0,
+                nationality: None,
+                last_record_at: None,
+                total_attempts: None,
+                index: 1,
+            },
+            "RankedPlayer",
+        );
+        assert_matches_schema(
+            &Demon {
+                base: MinimalDemon {
+                    id: 1,
+                    position: 1,
+                    name: CiString("Bloodbath".to_string()),
+                },
+                requirement: 50,
+                video: None,
+                verification_video: None,
+                publisher: player(),
+                verifier: player(),
+                level_id: None,
+                verified_on: VerificationPlatform::Pc,
+                published: true,
+                song_name: Some("At the Speed of Light".to_string()),
+                song_artist: Some("Dimrain47".to_string()),
+            },
+            "Demon",
+        );
+    }
+}
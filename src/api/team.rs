@@ -0,0 +1,16 @@
+//! Handlers for all endpoints under the `/api/v1/team` prefix
+
+use crate::{extractor::auth::TokenAuth, model::user::User, permissions::Permissions, state::PointercrateState, ApiResult};
+use actix_web::HttpResponse;
+use actix_web_codegen::get;
+
+#[get("/history/")]
+pub async fn history(TokenAuth(user): TokenAuth, state: PointercrateState) -> ApiResult<HttpResponse> {
+    if !user.inner().has_permission(Permissions::Administrator) {
+        user.inner().require_permissions(Permissions::ListHelper)?;
+    }
+
+    let mut connection = state.connection().await?;
+
+    Ok(HttpResponse::Ok().json(User::team_history(&mut connection).await?))
+}
@@ -0,0 +1,29 @@
+//! Handlers for all endpoints under the `/api/v1/video` prefix
+
+use crate::{extractor::ip::Ip, ratelimit::RatelimitScope, state::PointercrateState, video, ApiResult};
+use actix_web::{web::Json, HttpResponse};
+use actix_web_codegen::post;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct PreflightRequest {
+    video: String,
+}
+
+/// Checks a video URL before it gets submitted, so that the submission form can warn about videos
+/// that are invalid or cannot be embedded
+#[post("/validate/")]
+pub async fn validate(Ip(ip): Ip, request: Json<PreflightRequest>, state: PointercrateState) -> ApiResult<HttpResponse> {
+    state.ratelimits.check(RatelimitScope::VideoPreflight, ip)?;
+
+    let mut preflight = video::preflight(&request.video);
+
+    // Thumbnails of twitch VODs can only be resolved via twitch's API
+    if let (Some(video), None) = (&preflight.video, &preflight.thumbnail) {
+        let mut connection = state.connection().await?;
+
+        preflight.thumbnail = video::twitch::vod_thumbnail(video, &state.http_client, &mut connection).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(preflight))
+}
@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use std::{fmt::Debug, fs::File, io::Read, str::FromStr};
 
 fn from_env_or_default<T: FromStr>(key: &str, default: T) -> T
@@ -18,16 +19,219 @@ pub fn guidelines_location() -> String {
     from_env_or_default("GUIDELINES", format!("{}/guidelines/", env!("OUT_DIR")))
 }
 
+/// Whether advertisements are shown. If disabled, no ad markup (and no AdSense scripts) are included
+/// in any page, and no publisher ID needs to be configured
+pub fn ads_enabled() -> bool {
+    from_env_or_default("ADS_ENABLED", true)
+}
+
+/// The position of the demon after which the banner ad is shown on the demonlist overview
+pub fn first_ad_position() -> i16 {
+    from_env_or_default("FIRST_AD_POSITION", 1)
+}
+
+/// An in-feed ad is shown on the demonlist overview after every demon whose position is a multiple
+/// of this. `0` disables in-feed ads
+pub fn ad_interval() -> i16 {
+    from_env_or_default("AD_INTERVAL", 20)
+}
+
 pub fn adsense_publisher_id() -> String {
     std::env::var("ADSENSE_PUBLISHER_ID")
         .expect("No google adsense publisher ID configured. Please remove all advertisement from your custom copy of pointercrate")
 }
 
+/// The google adsense publisher ID to show ads for, or `None` if [ads are disabled](ads_enabled)
+pub fn adsense_publisher() -> Option<String> {
+    if ads_enabled() {
+        Some(adsense_publisher_id())
+    } else {
+        None
+    }
+}
+
+/// Whether the widget of the official Demonlist discord server is embedded into the demonlist pages
+pub fn discord_widget_enabled() -> bool {
+    from_env_or_default("DISCORD_WIDGET_ENABLED", true)
+}
+
 pub fn google_analytics_tag() -> String {
     std::env::var("ANALYTICS_TAG")
         .expect("No google analytics tag configured. Please remove all google analytics code from your custom copy of pointercrate")
 }
 
+/// A custom `Content-Security-Policy` replacing the one derived from the third-party hosts the
+/// pages actually use (see [`middleware::security`](crate::middleware::security)). Set to an empty
+/// string to not send the header at all
+pub fn content_security_policy() -> Option<String> {
+    std::env::var("CONTENT_SECURITY_POLICY").ok()
+}
+
+/// The name of a request header containing the ISO 3166-1 alpha-2 country code of the client, such
+/// as cloudflare's `CF-IPCountry`. If set, the stats viewer initially displays the ranking of that
+/// nation
+pub fn geolocation_header() -> Option<String> {
+    std::env::var("GEOLOCATION_HEADER").ok()
+}
+
+/// The metrics hidden from the demonlist's stats viewer, configured as a comma separated list of
+/// metric names (`completed`, `legacy`, `score`, `rank`, `hardest`, `beaten`, `created`,
+/// `published`, `verified` and `progress`). All metrics are shown by default
+pub fn hidden_stats() -> Vec<String> {
+    let hidden: String = from_env_or_default("HIDDEN_STATS", String::new());
+
+    hidden
+        .split(',')
+        .map(|metric| metric.trim().to_lowercase())
+        .filter(|metric| !metric.is_empty())
+        .collect()
+}
+
+/// The URL of a discord webhook to announce newly approved records to, if any
+pub fn record_webhook_url() -> Option<String> {
+    std::env::var("RECORD_WEBHOOK_URL").ok()
+}
+
+/// The URL of a discord webhook to announce new #1 demons to, if any
+pub fn top_demon_webhook_url() -> Option<String> {
+    std::env::var("TOP_DEMON_WEBHOOK_URL").ok()
+}
+
+/// The URL of a WebSub hub to notify whenever the changelog feed is updated, if any
+pub fn websub_hub_url() -> Option<String> {
+    std::env::var("WEBSUB_HUB_URL").ok()
+}
+
+/// The number of records that can be submitted from a single IP address, or for a single player,
+/// within [`submission_ratelimit_window`] seconds
+pub fn submission_ratelimit() -> u32 {
+    from_env_or_default("SUBMISSION_RATELIMIT", 3)
+}
+
+pub fn submission_ratelimit_window() -> u64 {
+    from_env_or_default("SUBMISSION_RATELIMIT_WINDOW", 20 * 60)
+}
+
+/// Whether records have to be submitted with a video. If not, submissions without video need a
+/// note explaining how to obtain raw footage of the record instead
+pub fn video_required() -> bool {
+    from_env_or_default("VIDEO_REQUIRED", true)
+}
+
+/// The minimal number of seconds between rendering a submission form and submitting it, below
+/// which the submission is assumed to come from a bot and discarded. `0` disables the check
+pub fn submission_min_fill_time() -> i64 {
+    from_env_or_default("SUBMISSION_MIN_FILL_TIME", 3)
+}
+
+/// The length, in seconds, of the sliding window over which requests to the API count towards a
+/// client's quota
+pub fn api_ratelimit_window() -> u64 {
+    from_env_or_default("API_RATELIMIT_WINDOW", 60)
+}
+
+/// The maximal number of characters a record note may consist of
+pub fn max_note_length() -> usize {
+    from_env_or_default("MAX_NOTE_LENGTH", 4000)
+}
+
+/// The number of decimal places record progress may have. Only 0 (whole percentages) and 1 are
+/// supported
+pub fn progress_decimals() -> u8 {
+    from_env_or_default("PROGRESS_DECIMALS", 0u8).min(1)
+}
+
+/// The maximal number of results returned by the demon search endpoint
+pub fn search_limit() -> i64 {
+    from_env_or_default("SEARCH_LIMIT", 20)
+}
+
+/// The maximal number of suggestions returned by the player autocomplete endpoint
+pub fn autocomplete_limit() -> i64 {
+    from_env_or_default("AUTOCOMPLETE_LIMIT", 10)
+}
+
+/// The client ID and app access token used to query twitch's API for VOD thumbnails. If either is
+/// unset, twitch VODs are displayed with a placeholder thumbnail
+pub fn twitch_credentials() -> Option<(String, String)> {
+    Some((std::env::var("TWITCH_CLIENT_ID").ok()?, std::env::var("TWITCH_ACCESS_TOKEN").ok()?))
+}
+
+/// The number of seconds the demons displayed on the overview are cached for
+pub fn overview_cache_ttl() -> u64 {
+    from_env_or_default("OVERVIEW_CACHE_TTL", 60)
+}
+
+/// The number of records whose videos are checked per batch when rechecking record videos for
+/// dead links
+pub fn video_recheck_batch_size() -> i64 {
+    from_env_or_default("VIDEO_RECHECK_BATCH_SIZE", 50)
+}
+
+/// The number of milliseconds to wait between two requests made while rechecking record videos,
+/// so that video hosts don't ratelimit us
+pub fn video_recheck_interval() -> u64 {
+    from_env_or_default("VIDEO_RECHECK_INTERVAL", 1000)
+}
+
+/// The endpoint of YouTube's oEmbed API, which tells us whether a YouTube video still exists
+pub fn youtube_oembed_url() -> String {
+    from_env_or_default("YOUTUBE_OEMBED_URL", "https://www.youtube.com/oembed".to_owned())
+}
+
+/// The key used to query the YouTube Data API, e.g. for the country of a player's channel. If unset,
+/// no nationalities are suggested for players
+pub fn youtube_api_key() -> Option<String> {
+    std::env::var("YOUTUBE_API_KEY").ok()
+}
+
+/// The base URL of the YouTube Data API
+pub fn youtube_api_url() -> String {
+    from_env_or_default("YOUTUBE_API_URL", "https://www.googleapis.com/youtube/v3".to_owned())
+}
+
+/// Whether Prometheus metrics are served at `/metrics`. They are always collected, but not exposed
+/// unless enabled, as they reveal internals such as request rates per route
+pub fn metrics_enabled() -> bool {
+    from_env_or_default("METRICS_ENABLED", false)
+}
+
+/// The earliest date the time machine can go back to, as a `YYYY-MM-DD` date. Requests for earlier
+/// dates are clamped to it
+pub fn time_machine_earliest_date() -> NaiveDate {
+    from_env_or_default("TIME_MACHINE_EARLIEST_DATE", NaiveDate::from_ymd(2017, 1, 4))
+}
+
+/// The caveat about the accuracy of old list data shown in the time machine form
+pub fn time_machine_caveat() -> String {
+    from_env_or_default(
+        "TIME_MACHINE_CAVEAT",
+        "Note however that data before August 4th 2017 is only provided on a best-effort basis and not guaranteed to be 100% accurate. \
+         Particularly data from before April 4th 2017 contains significant errors!"
+            .to_owned(),
+    )
+}
+
+/// The record requirements suggested for newly added demons, as a list of `(position, requirement)`
+/// tiers sorted by position. A demon is in the first tier whose position is greater than or equal to
+/// its own. Demons below the last tier are suggested a requirement of 100%.
+///
+/// Configured as a comma separated list of `position:requirement` pairs.
+pub fn requirement_tiers() -> Vec<(i16, i16)> {
+    let tiers: String = from_env_or_default("REQUIREMENT_TIERS", "10:50,25:55,50:60".to_owned());
+
+    tiers
+        .split(',')
+        .map(|tier| {
+            let mut split = tier.splitn(2, ':');
+            let position = split.next().unwrap().trim().parse().expect("Malformed REQUIREMENT_TIERS");
+            let requirement = split.next().unwrap_or("").trim().parse().expect("Malformed REQUIREMENT_TIERS");
+
+            (position, requirement)
+        })
+        .collect()
+}
+
 pub fn list_size() -> i16 {
     from_env_or_default("LIST_SIZE", 50)
 }
@@ -36,6 +240,86 @@ pub fn extended_list_size() -> i16 {
     from_env_or_default("EXTENDED_LIST_SIZE", 100)
 }
 
+/// Whether only a main list is run, without any extended list. This is the case if
+/// [`extended_list_size`] is no larger than [`list_size`]
+pub fn main_list_only() -> bool {
+    extended_list_size() <= list_size()
+}
+
+/// The sections the demonlist overview is split into, as `(name, last position)` pairs in order.
+/// The final section has no last position and contains all remaining demons.
+///
+/// Configured as a comma separated list of `name:last position` entries, the final entry consisting
+/// of only a name. Defaults to the [`default_list_sections`] for the configured list sizes.
+pub fn list_sections() -> Vec<(String, Option<i16>)> {
+    let sections = match std::env::var("LIST_SECTIONS") {
+        Ok(sections) => sections,
+        Err(_) => return default_list_sections(list_size(), extended_list_size()),
+    };
+
+    sections
+        .split(',')
+        .map(|section| {
+            let mut split = section.splitn(2, ':');
+            let name = split.next().unwrap().trim().to_owned();
+            let end = split
+                .next()
+                .map(|end| end.trim().parse().expect("Malformed LIST_SECTIONS"));
+
+            (name, end)
+        })
+        .collect()
+}
+
+/// A main list of `list_size` demons, followed by the extended list up to `extended_list_size` and
+/// the legacy list. The extended list is left out if it would be empty (see [`main_list_only`])
+pub fn default_list_sections(list_size: i16, extended_list_size: i16) -> Vec<(String, Option<i16>)> {
+    let mut sections = vec![("Main List".to_owned(), Some(list_size))];
+
+    if extended_list_size > list_size {
+        sections.push(("Extended List".to_owned(), Some(extended_list_size)));
+    }
+
+    sections.push(("Legacy List".to_owned(), None));
+    sections
+}
+
+/// The link to the submission guidelines, as shown on the demonlist
+pub fn guidelines_url() -> String {
+    from_env_or_default("GUIDELINES_URL", "/guidelines/".to_owned())
+}
+
+/// The text describing the guidelines in the demonlist sidebar
+pub fn guidelines_text() -> String {
+    from_env_or_default(
+        "GUIDELINES_TEXT",
+        "All demonlist operations are carried out in accordance to our guidelines. Be sure to check them before submitting a record to \
+         ensure a flawless experience!"
+            .to_owned(),
+    )
+}
+
+/// The text shown above the "Submit a record!" button in the demonlist sidebar
+pub fn submit_text() -> String {
+    from_env_or_default(
+        "SUBMIT_TEXT",
+        "Note: Please do not submit nonsense, it only makes it harder for us all and will get you banned. Also note that the form rejects \
+         duplicate submissions."
+            .to_owned(),
+    )
+}
+
+/// The text explaining which demons records can be submitted for, shown in the record submission
+/// form. Any occurrence of `{extended_list_size}` is replaced with the [`extended_list_size`].
+pub fn submission_demon_text() -> String {
+    let text: String = from_env_or_default(
+        "SUBMISSION_DEMON_TEXT",
+        "The demon the record was made on. Only demons in the top {extended_list_size} are accepted. This excludes legacy demons!".to_owned(),
+    );
+
+    text.replace("{extended_list_size}", &extended_list_size().to_string())
+}
+
 pub fn secret() -> Vec<u8> {
     let path: String = from_env_or_default("SECRET_FILE", ".secret".into());
     let file = File::open(path).expect("Unable to open secret file");
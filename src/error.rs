@@ -1,9 +1,10 @@
 //! Moduling containing the [`PointercrateError`] enum.
 
 use crate::{
+    middleware::request_id::current_request_id,
     model::demonlist::{demon::MinimalDemon, record::RecordStatus},
     permissions::Permissions,
-    ratelimit::RatelimitScope,
+    ratelimit::ExceededRatelimit,
     util::preferred_mime_type,
     view::{error::ErrorPage, Page},
 };
@@ -119,6 +120,13 @@ pub enum PointercrateError {
     #[display(fmt = "You are banned from submitting records to the demonlist!")]
     BannedFromSubmissions,
 
+    /// `403 FORBIDDEN` error returned when a member of the list team attempts to approve or reject
+    /// their own claim on a player
+    ///
+    /// Error Code `40305`
+    #[display(fmt = "You cannot approve or reject your own claim on a player")]
+    PatchOwnClaim,
+
     /// `404 NOT FOUND`
     ///
     /// Error Code `40400`
@@ -192,6 +200,56 @@ pub enum PointercrateError {
     #[display(fmt = "This video is already used by record #{}", id)]
     DuplicateVideo { id: i32 },
 
+    /// `409 CONFLICT` error returned if someone tries to add an alias that coincides with the name
+    /// of an existing demon
+    ///
+    /// Error Code: `40907`
+    #[display(fmt = "The alias is the name of the demon at position {}", position)]
+    AliasCollision {
+        /// The position of the [`Demon`] with that name
+        position: i16,
+    },
+
+    /// `409 CONFLICT` error returned if someone tries to add an alias that is already in use
+    ///
+    /// Error Code: `40908`
+    #[display(fmt = "This alias is already used by the demon with id {}", demon)]
+    AliasExists {
+        /// The id of the [`Demon`] the alias belongs to
+        demon: i32,
+    },
+
+    /// `409 CONFLICT` error returned if someone tries to give a demon a name that is an alias of
+    /// another demon
+    ///
+    /// Error Code: `40912`
+    #[display(fmt = "This name is an alias of the demon with id {}", demon)]
+    NameIsAlias {
+        /// The id of the [`Demon`] the alias belongs to
+        demon: i32,
+    },
+
+    /// `409 CONFLICT` error returned if someone tries to add a video mirror to a demon that is
+    /// already its primary video or one of its mirrors
+    ///
+    /// Error Code: `40909`
+    #[display(fmt = "This video is already the demon's primary video or one of its mirrors")]
+    MirrorExists,
+
+    /// `409 CONFLICT` error returned if someone tries to claim a player that has already been
+    /// claimed (and the claim was not rejected)
+    ///
+    /// Error Code: `40910`
+    #[display(fmt = "This player has already been claimed")]
+    ClaimExists,
+
+    /// `409 CONFLICT` error returned if someone tries to start a recheck of record videos while one
+    /// is already running
+    ///
+    /// Error Code: `40911`
+    #[display(fmt = "A recheck of record videos is already in progress")]
+    RecheckInProgress,
+
     /// `411 LENGTH REQUIRED`
     ///
     /// Error Code `41100`
@@ -277,6 +335,24 @@ pub enum PointercrateError {
         maximal: i16,
     },
 
+    /// `422 UNPROCESSABLE ENTITY` variant returned if attempted to position an unpublished demon
+    /// in between published ones
+    ///
+    /// Error Code `42248`
+    #[display(
+        fmt = "Unpublished demons need to be positioned after all published demons, that is, at a position greater than or equal to {} \
+               and smaller than or equal to {}",
+        minimal,
+        maximal
+    )]
+    InvalidUnpublishedPosition {
+        /// The minimal position an unpublished demon can be placed at
+        minimal: i16,
+
+        /// The maximal position an unpublished demon can be placed at
+        maximal: i16,
+    },
+
     /// `422 UNPROCESSABLE ENTITY` variant
     ///
     /// Error Code `42215`
@@ -379,6 +455,125 @@ pub enum PointercrateError {
     #[display(fmt = "Notes mustn't be empty!")]
     NoteEmpty,
 
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a video URL contains malformed percent-encoded
+    /// sequences
+    ///
+    /// Error Code `42231`
+    #[display(fmt = "The given video URL contains malformed percent-encoded characters")]
+    MalformedVideoUrl,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a subdivision is set for a player without a
+    /// nationality
+    ///
+    /// Error Code `42232`
+    #[display(fmt = "Cannot set a subdivision for a player without nationality")]
+    NoNationSet,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if an alias consisting only of whitespace is
+    /// added to a demon
+    ///
+    /// Error Code `42233`
+    #[display(fmt = "Demon aliases cannot be empty")]
+    InvalidAlias,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a video URL points to a playlist, channel or
+    /// user page instead of a single video
+    ///
+    /// Error Code `42234`
+    #[display(fmt = "The given URL points to a playlist, channel or user instead of a single video")]
+    NotSingleVideo,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a time machine date is malformed or does not
+    /// exist in the calendar (such as February 30th)
+    ///
+    /// Error Code `42235`
+    #[display(fmt = "'{}' is not a valid date", date)]
+    InvalidTimeMachineDate { date: String },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a demon tag is empty or longer than 32
+    /// characters
+    ///
+    /// Error Code `42236`
+    #[display(fmt = "Demon tags must be between 1 and 32 characters long")]
+    InvalidTag,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a list administrator tries to merge a player
+    /// into themselves
+    ///
+    /// Error Code `42237`
+    #[display(fmt = "A player cannot be merged into themselves")]
+    MergeIntoSelf,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a video URL points to a private or otherwise
+    /// restricted video (such as a members-only or age-gated one) that cannot be embedded
+    ///
+    /// Error Code `42238`
+    #[display(fmt = "The given video is private or restricted and cannot be embedded")]
+    VideoNotEmbeddable,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a list administrator tries to merge players
+    /// whose names are not duplicates of each other
+    ///
+    /// Error Code `42239`
+    #[display(fmt = "The given players are not duplicates of each other")]
+    NotDuplicates,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a request to merge duplicate players did not
+    /// explicitly confirm the merge
+    ///
+    /// Error Code `42240`
+    #[display(fmt = "Merging duplicate players requires explicit confirmation")]
+    MergeNotConfirmed,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a name (of a demon or player) is empty or
+    /// consists only of whitespace
+    ///
+    /// Error Code `42241`
+    #[display(fmt = "Names must not be empty")]
+    EmptyName,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a record note is longer than
+    /// [`max_note_length`](crate::config::max_note_length) characters
+    ///
+    /// Error Code `42242`
+    #[display(fmt = "Notes must be at most {} characters long", max_length)]
+    NoteTooLong { max_length: usize },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if record progress has more decimal places than
+    /// allowed by [`progress_decimals`](crate::config::progress_decimals)
+    ///
+    /// Error Code `42243`
+    #[display(fmt = "Record progress may have at most {} decimal places", decimals)]
+    InvalidProgressPrecision { decimals: u8 },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a record is submitted without video while
+    /// [`video_required`](crate::config::video_required) is set
+    ///
+    /// Error Code `42244`
+    #[display(fmt = "Records have to be submitted with a video")]
+    VideoRequired,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a record is submitted without video (and
+    /// videos are optional), but also without a note explaining how to get the raw footage
+    ///
+    /// Error Code `42245`
+    #[display(fmt = "Records submitted without video need a note explaining how to obtain raw footage of them")]
+    RawFootageNoteRequired,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a demon's song name or artist is longer than
+    /// [`MAX_SONG_METADATA_LENGTH`](crate::model::demonlist::demon::MAX_SONG_METADATA_LENGTH)
+    /// characters
+    ///
+    /// Error Code `42246`
+    #[display(fmt = "Song names and artists must be at most {} characters long", max_length)]
+    SongMetadataTooLong { max_length: usize },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a record's attempt count is negative
+    ///
+    /// Error Code `42247`
+    #[display(fmt = "Attempt counts cannot be negative")]
+    NegativeAttempts,
+
     /// `428 PRECONDITION REQUIRED`
     ///
     /// Error Code `42800`
@@ -391,7 +586,7 @@ pub enum PointercrateError {
     #[display(fmt = "{} Try again in {:.2?}", scope, remaining)]
     Ratelimited {
         #[serde(skip)]
-        scope: RatelimitScope,
+        scope: ExceededRatelimit,
 
         remaining: Duration,
     },
@@ -435,6 +630,14 @@ pub enum PointercrateError {
         #[serde(skip)]
         message: String,
     },
+
+    /// Wrapper around a validation error, attributing it to the request field that caused it
+    ///
+    /// `field` is a path into the request body, such as `requirement` or `creators[2]`. Has the
+    /// same error code as the wrapped error, and the field is reported alongside it in API
+    /// responses. Construct via [`PointercrateError::in_field`].
+    #[display(fmt = "{}", error)]
+    InvalidField { field: String, error: Box<PointercrateError> },
 }
 
 fn serialize_method<S>(methods: &[Method], serializer: S) -> Result<S::Ok, S::Error>
@@ -462,6 +665,7 @@ impl PointercrateError {
             PointercrateError::DeleteSelf => 40302,
             PointercrateError::PatchSelf => 40303,
             PointercrateError::BannedFromSubmissions => 40304,
+            PointercrateError::PatchOwnClaim => 40305,
 
             PointercrateError::NotFound => 40400,
             PointercrateError::ModelNotFound { .. } => 40401,
@@ -473,6 +677,12 @@ impl PointercrateError {
             PointercrateError::DemonExists { .. } => 40904,
             PointercrateError::CreatorExists => 40905,
             PointercrateError::DuplicateVideo { .. } => 40906,
+            PointercrateError::AliasCollision { .. } => 40907,
+            PointercrateError::AliasExists { .. } => 40908,
+            PointercrateError::MirrorExists => 40909,
+            PointercrateError::ClaimExists => 40910,
+            PointercrateError::RecheckInProgress => 40911,
+            PointercrateError::NameIsAlias { .. } => 40912,
 
             PointercrateError::LengthRequired => 41100,
 
@@ -489,6 +699,7 @@ impl PointercrateError {
             PointercrateError::UnexpectedNull { .. } => 42211,
             PointercrateError::InvalidRequirement => 42212,
             PointercrateError::InvalidPosition { .. } => 42213,
+            PointercrateError::InvalidUnpublishedPosition { .. } => 42248,
             PointercrateError::InvalidProgress { .. } => 42215,
             PointercrateError::SubmissionExists { .. } => 42217,
             PointercrateError::PlayerBanned => 42218,
@@ -503,6 +714,23 @@ impl PointercrateError {
             PointercrateError::DemonNameNotUnique { .. } => 42228,
             PointercrateError::MutuallyExclusive => 42229,
             PointercrateError::NoteEmpty => 42230,
+            PointercrateError::MalformedVideoUrl => 42231,
+            PointercrateError::NoNationSet => 42232,
+            PointercrateError::InvalidAlias => 42233,
+            PointercrateError::NotSingleVideo => 42234,
+            PointercrateError::InvalidTimeMachineDate { .. } => 42235,
+            PointercrateError::InvalidTag => 42236,
+            PointercrateError::MergeIntoSelf => 42237,
+            PointercrateError::VideoNotEmbeddable => 42238,
+            PointercrateError::NotDuplicates => 42239,
+            PointercrateError::MergeNotConfirmed => 42240,
+            PointercrateError::EmptyName => 42241,
+            PointercrateError::NoteTooLong { .. } => 42242,
+            PointercrateError::InvalidProgressPrecision { .. } => 42243,
+            PointercrateError::VideoRequired => 42244,
+            PointercrateError::RawFootageNoteRequired => 42245,
+            PointercrateError::SongMetadataTooLong { .. } => 42246,
+            PointercrateError::NegativeAttempts => 42247,
 
             PointercrateError::PreconditionRequired => 42800,
 
@@ -515,6 +743,33 @@ impl PointercrateError {
             PointercrateError::DatabaseConnectionError => 50005,
 
             PointercrateError::Other { status, .. } => status.as_u16() * 100,
+
+            PointercrateError::InvalidField { error, .. } => error.error_code(),
+        }
+    }
+
+    /// Attributes this error to the given request field, replacing any field it was previously
+    /// attributed to
+    pub fn in_field(self, field: impl Into<String>) -> PointercrateError {
+        PointercrateError::InvalidField {
+            field: field.into(),
+            error: Box::new(self.without_field()),
+        }
+    }
+
+    /// The request field this error is attributed to, if any
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            PointercrateError::InvalidField { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Strips the attribution to a request field from this error
+    pub fn without_field(self) -> PointercrateError {
+        match self {
+            PointercrateError::InvalidField { error, .. } => *error,
+            error => error,
         }
     }
 
@@ -603,10 +858,24 @@ impl ResponseError for JsonError {
             response.header("Allow", allowed_methods.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(","));
         }*/
 
+        if let PointercrateError::Ratelimited { remaining, .. } = &self.0 {
+            // Round up, so that clients honoring the header don't retry a split second too early
+            let seconds = remaining.as_secs() + if remaining.subsec_nanos() > 0 { 1 } else { 0 };
+
+            response.header("Retry-After", seconds.to_string());
+        }
+
+        let data = match &self.0 {
+            PointercrateError::InvalidField { error, .. } => &**error,
+            error => error,
+        };
+
         response.json(json!({
             "code": self.0.error_code(),
             "message": self.0.to_string(),
-            "data": self
+            "field": self.0.field(),
+            "data": data,
+            "request_id": current_request_id()
         }))
     }
 }
@@ -1,11 +1,12 @@
 use crate::error::{JsonError, PointercrateError};
 use actix_web::{
     dev::{Payload, PayloadStream},
+    http::HeaderMap,
     FromRequest, HttpRequest,
 };
-use futures::future::{err, ok, ready, Ready};
+use futures::future::{ready, Ready};
 use log::{error, warn};
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 pub struct Ip(pub IpAddr);
 
@@ -15,45 +16,43 @@ impl FromRequest for Ip {
     type Future = Ready<Result<Ip, JsonError>>;
 
     fn from_request(request: &HttpRequest, _payload: &mut Payload<PayloadStream>) -> Self::Future {
-        if let Some(sockaddr) = request.peer_addr() {
-            // We'll have nginx reverse-proxying for us, so we gotta check this
-            if IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)) == sockaddr.ip() {
-                match request.headers().get("X-FORWARDED-FOR") {
-                    Some(value) =>
-                        ready(
-                            value
-                                .to_str()
-                                .map_err(|_| PointercrateError::InvalidHeaderValue { header: "X-FORWARDED-FOR" }.into())
-                                .and_then(|forwarded_for| {
-                                    forwarded_for
-                                        .parse()
-                                        .map_err(|_| PointercrateError::InvalidHeaderValue { header: "X-FORWARDED-FOR" }.into())
-                                })
-                                .map(Ip),
-                        ),
-                    None =>
-                        if cfg!(debug_assertions) {
-                            warn!(
-                                "Request from local machine, but no 'X-FORWARDED-FOR' header is set. Allowing, since this is a debug build"
-                            );
-
-                            ok(Ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))))
-                        } else {
-                            error!(
-                                "Request from local machine, but no 'X-FORWARDED-FOR' header is set. Since this is a release build, this \
-                                 is a configuration error!"
-                            );
-
-                            err(PointercrateError::InternalServerError.into())
-                        },
-                }
-            } else {
-                ok(Ip(sockaddr.ip()))
-            }
-        } else {
+        if request.peer_addr().is_none() {
             warn!("Remote address for request to {} not retrievable, aborting!", request.uri());
-
-            err(PointercrateError::Unauthorized.into())
         }
+
+        ready(client_ip(request.peer_addr(), request.headers()).map(Ip).map_err(Into::into))
+    }
+}
+
+/// Determines the IP address of the client that made a request, given the request's remote address
+/// and headers
+pub fn client_ip(peer_addr: Option<SocketAddr>, headers: &HeaderMap) -> Result<IpAddr, PointercrateError> {
+    let sockaddr = peer_addr.ok_or(PointercrateError::Unauthorized)?;
+
+    // We'll have nginx reverse-proxying for us, so we gotta check this
+    if IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)) != sockaddr.ip() {
+        return Ok(sockaddr.ip())
+    }
+
+    match headers.get("X-FORWARDED-FOR") {
+        Some(value) =>
+            value
+                .to_str()
+                .map_err(|_| PointercrateError::InvalidHeaderValue { header: "X-FORWARDED-FOR" })?
+                .parse()
+                .map_err(|_| PointercrateError::InvalidHeaderValue { header: "X-FORWARDED-FOR" }),
+        None =>
+            if cfg!(debug_assertions) {
+                warn!("Request from local machine, but no 'X-FORWARDED-FOR' header is set. Allowing, since this is a debug build");
+
+                Ok(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))
+            } else {
+                error!(
+                    "Request from local machine, but no 'X-FORWARDED-FOR' header is set. Since this is a release build, this is a \
+                     configuration error!"
+                );
+
+                Err(PointercrateError::InternalServerError)
+            },
     }
 }
@@ -0,0 +1,283 @@
+//! Lightweight localization of the strings used in the demonlist templates
+//!
+//! Strings are identified by keys such as `section.main.description`. Every key has an English
+//! default, translations for other locales are optional: keys without a translation fall back to
+//! English. Only strings that forks commonly needed to change are externalized so far, namely the
+//! list section descriptions and the record submission form.
+
+use actix_web::{http::header::ACCEPT_LANGUAGE, HttpRequest};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Locale {
+    English,
+    German,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::English
+    }
+}
+
+impl Locale {
+    /// Gets the locale for the given language tag (e.g. `de` or `de-AT`), if we support it
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(|c| c == '-' || c == '_').next()?.to_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Locale::English),
+            "de" => Some(Locale::German),
+            _ => None,
+        }
+    }
+
+    /// Determines the locale to render a page in
+    ///
+    /// An explicitly requested language (via the `lang` query parameter) takes precedence over the
+    /// `Accept-Language` header. Falls back to English if neither names a supported locale.
+    pub fn negotiate(lang: Option<&str>, accept_language: Option<&str>) -> Locale {
+        if let Some(locale) = lang.and_then(Locale::from_tag) {
+            return locale
+        }
+
+        let mut languages: Vec<(&str, f32)> = accept_language
+            .unwrap_or("")
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .map(|quality| quality.trim().parse().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                Some((tag, quality))
+            })
+            .filter(|&(_, quality)| quality > 0.0)
+            .collect();
+
+        // stable, so that languages with equal quality keep the client's order
+        languages.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+        languages
+            .into_iter()
+            .find_map(|(tag, _)| Locale::from_tag(tag))
+            .unwrap_or_default()
+    }
+
+    /// Determines the locale to render the response to the given request in, based on its `lang`
+    /// query parameter and `Accept-Language` header. See [`Locale::negotiate`]
+    pub fn of_request(request: &HttpRequest) -> Locale {
+        let lang = url::form_urlencoded::parse(request.query_string().as_bytes())
+            .find(|(key, _)| key == "lang")
+            .map(|(_, value)| value.into_owned());
+        let accept_language = request.headers().get(ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok());
+
+        Locale::negotiate(lang.as_deref(), accept_language)
+    }
+
+    fn translations(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::English => ENGLISH,
+            Locale::German => GERMAN,
+        }
+    }
+}
+
+/// Looks up the string with the given key in the given locale, falling back to English
+///
+/// Panics if the key does not exist at all, as that is a bug in the template using it
+pub fn t(locale: Locale, key: &str) -> &'static str {
+    let lookup = |locale: Locale| {
+        locale
+            .translations()
+            .iter()
+            .find(|&&(candidate, _)| candidate == key)
+            .map(|&(_, text)| text)
+    };
+
+    lookup(locale)
+        .or_else(|| lookup(Locale::English))
+        .unwrap_or_else(|| panic!("Unknown translation key '{}'", key))
+}
+
+static ENGLISH: &[(&str, &str)] = &[
+    (
+        "section.main.description",
+        "The main section of the Demonlist. These demons are the hardest rated levels in the game. Records are accepted above a given \
+         threshold and award a large amount of points!",
+    ),
+    (
+        "section.extended.description",
+        "These are demons that dont qualify for the main section of the list, but are still of high relevance. Only 100% records are \
+         accepted for these demons! Note that non-100% that were submitted/approved before a demon fell off the main list will be retained",
+    ),
+    (
+        "section.legacy.description",
+        "These are demons that used to be on the list, but got pushed off as new demons were added. They are here for nostalgic reasons. \
+         This list is in no order whatsoever and will not be maintained any longer at all. This means no new records will be added for \
+         these demons.",
+    ),
+    ("submission.title", "Record Submission"),
+    ("submission.demon", "Demon:"),
+    ("submission.holder", "Holder:"),
+    (
+        "submission.holder.description",
+        "The holder of the record. Please enter the holders Geometry Dash name here, even if their YouTube name differs! Click the pencil \
+         to select a player!",
+    ),
+    ("submission.holder.none", "None Selected"),
+    ("submission.progress", "Progress:"),
+    (
+        "submission.progress.description",
+        "The progress made as percentage. Only values greater than or equal to the demons record requirement and smaller than or equal to \
+         100 are accepted!",
+    ),
+    ("submission.attempts", "Attempts:"),
+    (
+        "submission.attempts.description",
+        "The number of attempts the holder needed for the record. Logging attempts is entirely optional, leave this empty if you do not \
+         want to!",
+    ),
+    ("submission.video", "Video: "),
+    (
+        "submission.video.description",
+        "A proof video of the legitimacy of the given record. If the record was achieved on stream, but wasn't uploaded anywhere else, \
+         please provide a twitch link to that stream.",
+    ),
+    ("submission.note", "Note: "),
+    ("submission.video.note", "Please pay attention to only submit well-formed URLs!"),
+    (
+        "submission.video.optional",
+        "Videos are optional on this list. If you do not provide one, please explain how to obtain raw footage of the record in the \
+         notes below.",
+    ),
+    ("submission.notes", "Notes or comments: "),
+    (
+        "submission.notes.description.before",
+        "Provide any additional notes you'd like to pass on to the list moderator receiving your submission. In particular, any required ",
+    ),
+    ("submission.notes.description.footage", "raw footage"),
+    ("submission.notes.description.after", " goes here."),
+    ("submission.notes.placeholder", "Your dreams and hopes for this record... or something like that"),
+    ("submission.guidelines.before", "By submitting the record you acknowledge the "),
+    ("submission.guidelines.link", "submission guidelines"),
+    ("submission.guidelines.after", "."),
+    ("submission.submit", "Submit record"),
+    ("submission.success", "Your record has been submitted! It will be reviewed by a member of the list team soon."),
+    ("submission.holder_dialog.title", "Select player:"),
+    (
+        "submission.holder_dialog.description",
+        "To select the player holding this record, search them up on the left to see if they already have records on the list and click \
+         them. In case the player does not exist, fill out only the text field on the right.",
+    ),
+    ("submission.holder_dialog.button", "Select"),
+];
+
+static GERMAN: &[(&str, &str)] = &[
+    (
+        "section.main.description",
+        "Der Hauptteil der Demonlist. Diese Demons sind die am schwersten bewerteten Level im Spiel. Rekorde werden ab einer bestimmten \
+         Schwelle akzeptiert und bringen viele Punkte!",
+    ),
+    (
+        "section.extended.description",
+        "Diese Demons gehören nicht mehr zum Hauptteil der Liste, sind aber trotzdem von großer Bedeutung. Für diese Demons werden nur \
+         100%-Rekorde akzeptiert! Rekorde unter 100%, die eingereicht oder akzeptiert wurden, bevor ein Demon aus dem Hauptteil gefallen \
+         ist, bleiben erhalten",
+    ),
+    (
+        "section.legacy.description",
+        "Diese Demons waren früher auf der Liste, wurden aber von neuen Demons verdrängt. Sie sind aus nostalgischen Gründen hier. Diese \
+         Liste ist in keiner bestimmten Reihenfolge und wird nicht mehr gepflegt. Für diese Demons werden also keine neuen Rekorde \
+         hinzugefügt.",
+    ),
+    ("submission.title", "Rekord einreichen"),
+    ("submission.demon", "Demon:"),
+    ("submission.holder", "Spieler:"),
+    (
+        "submission.holder.description",
+        "Der Spieler, der den Rekord erzielt hat. Bitte gib hier seinen Geometry Dash Namen an, auch wenn sich sein YouTube Name \
+         unterscheidet! Klicke auf den Stift, um einen Spieler auszuwählen!",
+    ),
+    ("submission.holder.none", "Keiner ausgewählt"),
+    ("submission.progress", "Fortschritt:"),
+    (
+        "submission.progress.description",
+        "Der erreichte Fortschritt in Prozent. Es werden nur Werte akzeptiert, die mindestens der Rekordanforderung des Demons \
+         entsprechen und höchstens 100 sind!",
+    ),
+    ("submission.attempts", "Versuche:"),
+    (
+        "submission.attempts.description",
+        "Die Anzahl an Versuchen, die der Spieler für den Rekord gebraucht hat. Die Angabe ist freiwillig, lass das Feld einfach leer, \
+         wenn du sie nicht machen möchtest!",
+    ),
+    ("submission.video", "Video: "),
+    (
+        "submission.video.description",
+        "Ein Video als Beweis für den Rekord. Falls der Rekord im Stream erzielt, aber nirgendwo sonst hochgeladen wurde, gib bitte einen \
+         Twitch Link zu diesem Stream an.",
+    ),
+    ("submission.note", "Hinweis: "),
+    ("submission.video.note", "Bitte reiche nur gültige URLs ein!"),
+    (
+        "submission.video.optional",
+        "Videos sind auf dieser Liste optional. Falls du keins angibst, erkläre bitte in den Notizen unten, wie wir an Rohmaterial des \
+         Rekords kommen.",
+    ),
+    ("submission.notes", "Notizen oder Kommentare: "),
+    (
+        "submission.notes.description.before",
+        "Zusätzliche Anmerkungen für den Listenmoderator, der deine Einreichung bearbeitet. Insbesondere gehört benötigtes ",
+    ),
+    ("submission.notes.description.footage", "Rohmaterial"),
+    ("submission.notes.description.after", " hierher."),
+    ("submission.notes.placeholder", "Deine Hoffnungen und Träume für diesen Rekord... oder so ähnlich"),
+    ("submission.guidelines.before", "Mit dem Einreichen des Rekords erkennst du die "),
+    ("submission.guidelines.link", "Richtlinien für Einreichungen"),
+    ("submission.guidelines.after", " an."),
+    ("submission.submit", "Rekord einreichen"),
+    ("submission.success", "Dein Rekord wurde eingereicht! Er wird bald von einem Mitglied des Listenteams überprüft."),
+    ("submission.holder_dialog.title", "Spieler auswählen:"),
+    (
+        "submission.holder_dialog.description",
+        "Um den Spieler auszuwählen, der diesen Rekord hält, suche ihn links, um zu sehen, ob er bereits Rekorde auf der Liste hat, und \
+         klicke ihn an. Falls es den Spieler noch nicht gibt, fülle nur das Textfeld rechts aus.",
+    ),
+    ("submission.holder_dialog.button", "Auswählen"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{t, Locale, ENGLISH, GERMAN};
+
+    #[test]
+    fn test_unknown_locale_falls_back_to_english() {
+        assert_eq!(Locale::negotiate(Some("xx"), None), Locale::English);
+        assert_eq!(Locale::negotiate(None, Some("fr-FR,fr;q=0.9")), Locale::English);
+        assert_eq!(Locale::negotiate(None, None), Locale::English);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Locale::negotiate(None, Some("fr-FR,de;q=0.8,en;q=0.5")), Locale::German);
+        assert_eq!(Locale::negotiate(None, Some("en;q=0.5,de-AT")), Locale::German);
+        assert_eq!(Locale::negotiate(None, Some("de;q=0,en")), Locale::English);
+
+        // the query parameter overrides the header
+        assert_eq!(Locale::negotiate(Some("en"), Some("de")), Locale::English);
+        assert_eq!(Locale::negotiate(Some("xx"), Some("de")), Locale::German);
+    }
+
+    #[test]
+    fn test_translations_only_use_known_keys() {
+        for (key, _) in GERMAN {
+            assert!(ENGLISH.iter().any(|(english_key, _)| english_key == key), "{}", key);
+        }
+
+        assert_eq!(t(Locale::German, "submission.submit"), "Rekord einreichen");
+        assert_eq!(t(Locale::English, "submission.submit"), "Submit record");
+    }
+}
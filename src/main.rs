@@ -4,26 +4,27 @@
 
 use crate::{
     error::{HtmlError, JsonError, PointercrateError},
-    middleware::etag::Etag,
+    middleware::{etag::Etag, ratelimit::ApiRatelimit, request_id::RequestTracing, security::SecurityHeaders},
     state::PointercrateState,
 };
 use actix_files::{Files, NamedFile};
 use actix_web::{
     http::ContentEncoding,
-    middleware::{Compress, Logger, NormalizePath},
+    middleware::{Compress, NormalizePath},
     web,
     web::{route, scope, JsonConfig, PathConfig, QueryConfig},
     App, HttpRequest, HttpServer,
 };
 use api::{
     auth,
-    demonlist::{demon, misc, player, record, submitter},
-    user,
+    demonlist::{demon, misc, moderation, player, record, submitter},
+    nationality, team, user,
 };
 use std::net::SocketAddr;
 
 #[macro_use]
 mod util;
+mod antibot;
 mod api;
 mod cistring;
 mod config;
@@ -31,8 +32,11 @@ mod documentation;
 mod error;
 mod extractor;
 mod gd;
+mod i18n;
+mod metrics;
 mod middleware;
 mod model;
+mod notify;
 mod permissions;
 mod ratelimit;
 mod state;
@@ -67,7 +71,9 @@ async fn main() -> std::io::Result<()> {
             .app_data(path_config)
             .app_data(query_config)
             .wrap(Etag)
-            .wrap(Logger::default())
+            .wrap(SecurityHeaders::from_config())
+            .wrap(ApiRatelimit(application_state.clone()))
+            .wrap(RequestTracing)
             .wrap(NormalizePath::default())
             .wrap(Compress::new(ContentEncoding::Gzip))
             .app_data(application_state.clone())
@@ -80,14 +86,27 @@ async fn main() -> std::io::Result<()> {
                 "/ads.txt",
                 web::get().to(|req: HttpRequest| NamedFile::open("ads.txt").unwrap().into_response(&req).unwrap()),
             )
+            .service(api::health::health)
+            .service(api::health::ready)
+            .configure(api::configure_graphql)
+            .configure(api::configure_metrics)
             .service(view::home::index)
             .service(view::login::index)
             .service(view::login::post)
             .service(view::login::register)
             .service(view::demonlist::demon_permalink)
+            .service(view::demonlist::position_history)
+            .service(view::demonlist::list_changelog)
+            .service(view::demonlist::changelog_feed)
+            .service(view::demonlist::additions_calendar)
+            .service(view::demonlist::list_diff)
+            .service(view::demonlist::list_statistics)
             .service(view::demonlist::stats_viewer2)
+            .service(view::demonlist::submit)
+            .service(view::demonlist::form_opened)
             .service(view::demonlist::page)
             .service(view::demonlist::index)
+            .service(view::demonlist::at)
             .service(view::account::index)
             .service(view::documentation::index)
             .service(view::documentation::topic)
@@ -96,6 +115,9 @@ async fn main() -> std::io::Result<()> {
             .service(
                 scope("/api/v1")
                     .service(misc::list_information)
+                    .service(misc::export)
+                    .service(misc::top)
+                    .service(api::openapi::openapi)
                     .service(
                         scope("/auth")
                             .service(auth::register)
@@ -112,6 +134,10 @@ async fn main() -> std::io::Result<()> {
                             .service(user::delete)
                             .service(user::patch),
                     )
+                    .service(scope("/team").service(team::history))
+                    .service(scope("/nationalities").service(nationality::subdivisions))
+                    .service(scope("/moderation").service(moderation::anomalies))
+                    .service(scope("/video").service(api::video::validate))
                     .service(
                         scope("/submitters")
                             .service(submitter::get)
@@ -120,11 +146,16 @@ async fn main() -> std::io::Result<()> {
                     )
                     .service(
                         scope("/demons")
+                            .service(demon::search)
+                            .service(demon::unbeaten)
+                            .service(demon::longevity)
+                            .service(demon::post_batch)
                             .service(demon::v1::get)
                             .service(demon::v1::paginate)
                             .service(demon::v1::patch)
                             .service(demon::v1::delete_creator)
                             .service(demon::v1::post_creator)
+                            .service(demon::audit_log)
                             .service(demon::post),
                     )
                     .service(
@@ -132,8 +163,10 @@ async fn main() -> std::io::Result<()> {
                             .service(record::delete)
                             .service(record::get)
                             .service(record::paginate)
+                            .service(record::patch_batch)
                             .service(record::patch)
                             .service(record::submit)
+                            .service(record::recheck_videos)
                             .service(record::add_note)
                             .service(record::patch_note)
                             .service(record::delete_note)
@@ -143,7 +176,21 @@ async fn main() -> std::io::Result<()> {
                         scope("/players")
                             .service(player::patch)
                             .service(player::paginate)
+                            .service(player::autocomplete)
                             .service(player::ranking)
+                            .service(player::export_ranking)
+                            .service(player::most_improved_ranking)
+                            .service(player::compare)
+                            .service(player::progress)
+                            .service(player::records)
+                            .service(player::neighbors)
+                            .service(player::suggest_nationality)
+                            .service(player::merge)
+                            .service(player::duplicates)
+                            .service(player::merge_duplicate_players)
+                            .service(player::claim)
+                            .service(player::get_claim)
+                            .service(player::patch_claim)
                             .service(player::get),
                     ),
             )
@@ -156,6 +203,11 @@ async fn main() -> std::io::Result<()> {
                         .service(demon::v2::patch)
                         .service(demon::v2::delete_creator)
                         .service(demon::v2::post_creator)
+                        .service(demon::v2::post_alias)
+                        .service(demon::v2::delete_alias)
+                        .service(demon::v2::post_mirror)
+                        .service(demon::v2::put_mirrors)
+                        .service(demon::audit_log)
                         .service(demon::post),
                 ),
             )
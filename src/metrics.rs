@@ -0,0 +1,144 @@
+//! Prometheus metrics about the requests we handle, the database connection pool and record
+//! submissions
+//!
+//! Metrics are always collected, but only served at `/metrics` if enabled via
+//! [`config::metrics_enabled`](crate::config::metrics_enabled).
+
+use crate::{
+    api::health::PoolStatus,
+    error::PointercrateError,
+    model::demonlist::record::{FullRecord, RecordStatus},
+    Result,
+};
+use actix_web::http::StatusCode;
+use lazy_static::lazy_static;
+use log::error;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder, HistogramVec, IntCounterVec, IntGaugeVec,
+    TextEncoder,
+};
+use std::time::Duration;
+
+/// The route label of all requests that did not match any route, so that requests for arbitrary
+/// paths do not each create a new time series
+const UNMATCHED: &str = "unmatched";
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "pointercrate_http_requests_total",
+        "Number of HTTP requests handled, by route and status code",
+        &["route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "pointercrate_http_request_duration_seconds",
+        "Time taken to handle HTTP requests, by route",
+        &["route"]
+    )
+    .unwrap();
+    static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "pointercrate_db_pool_connections",
+        "Number of connections in the database connection pool, by state (idle or active)",
+        &["state"]
+    )
+    .unwrap();
+    static ref RECORD_SUBMISSIONS: IntCounterVec = register_int_counter_vec!(
+        "pointercrate_record_submissions_total",
+        "Number of record submissions, by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+}
+
+/// Determines the value of a request's `route` label from the pattern of the route it matched
+///
+/// Requests for static files are all labelled as `/static2/`. All requests not matching any route
+/// (404 and 405 responses, and redirects to add a trailing slash) are labelled as unmatched, as are
+/// requests whose route has no pattern, so that the number of labels stays bounded.
+pub fn route_label(pattern: Option<String>, path: &str, status: StatusCode) -> String {
+    if status == StatusCode::NOT_FOUND || status == StatusCode::METHOD_NOT_ALLOWED {
+        return UNMATCHED.to_string()
+    }
+
+    match pattern {
+        Some(pattern) => pattern,
+        None if path.starts_with("/static2/") => "/static2/".to_string(),
+        None => UNMATCHED.to_string(),
+    }
+}
+
+/// Records a handled request
+pub fn observe_request(pattern: Option<String>, path: &str, status: StatusCode, duration: Duration) {
+    let route = route_label(pattern, path, status);
+
+    HTTP_REQUESTS.with_label_values(&[route.as_str(), status.as_str()]).inc();
+    HTTP_REQUEST_DURATION.with_label_values(&[route.as_str()]).observe(duration.as_secs_f64());
+}
+
+/// The outcome label of a record submission with the given result
+fn submission_outcome(result: &Result<FullRecord>) -> &'static str {
+    match result {
+        Ok(record) =>
+            match record.status {
+                RecordStatus::Submitted => "submitted",
+                RecordStatus::Approved => "approved",
+                RecordStatus::Rejected => "rejected",
+                RecordStatus::UnderConsideration => "under_consideration",
+            },
+        Err(PointercrateError::Ratelimited { .. }) => "ratelimited",
+        Err(PointercrateError::SubmissionExists { .. }) => "duplicate",
+        Err(_) => "invalid",
+    }
+}
+
+/// Records the result of a record submission, both via the API and the submission form
+pub fn observe_submission(result: &Result<FullRecord>) {
+    RECORD_SUBMISSIONS.with_label_values(&[submission_outcome(result)]).inc();
+}
+
+/// Records a submission silently discarded by the [bot checks](crate::antibot)
+pub fn observe_discarded_submission() {
+    RECORD_SUBMISSIONS.with_label_values(&["bot"]).inc();
+}
+
+/// Renders all metrics in the Prometheus text format, after updating the connection pool gauges
+/// from the given pool status
+pub fn render(pool: &PoolStatus) -> String {
+    let idle = pool.idle as i64;
+
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS.with_label_values(&["active"]).set(i64::from(pool.size) - idle);
+
+    let mut buffer = Vec::new();
+
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics: {:?}", err);
+    }
+
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::route_label;
+    use actix_web::http::StatusCode;
+
+    #[test]
+    fn test_route_label() {
+        let pattern = |pattern: &str| Some(pattern.to_string());
+
+        assert_eq!(
+            route_label(pattern("/api/v1/records/{record_id}/"), "/api/v1/records/123/", StatusCode::OK),
+            "/api/v1/records/{record_id}/"
+        );
+        assert_eq!(route_label(pattern("/demonlist/"), "/demonlist/", StatusCode::OK), "/demonlist/");
+        assert_eq!(route_label(None, "/static2/js/demonlist.v2.2.js", StatusCode::OK), "/static2/");
+        assert_eq!(route_label(None, "/wp-admin.php/", StatusCode::NOT_FOUND), "unmatched");
+        assert_eq!(route_label(None, "/api/v1/records", StatusCode::FOUND), "unmatched");
+        assert_eq!(route_label(None, "/some/arbitrary/path/", StatusCode::OK), "unmatched");
+        assert_eq!(
+            route_label(pattern("/api/v1/records/{record_id}/"), "/api/v1/records/123/", StatusCode::METHOD_NOT_ALLOWED),
+            "unmatched"
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod etag;
+pub mod ratelimit;
+pub mod request_id;
+pub mod security;
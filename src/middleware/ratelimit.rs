@@ -0,0 +1,163 @@
+//! Module containing middleware enforcing the per-client quotas of the API
+
+use crate::{
+    error::{JsonError, PointercrateError},
+    extractor::ip::client_ip,
+    model::user::{AuthenticatedUser, Authorization},
+    ratelimit::{ApiAccess, ApiClient},
+    state::PointercrateState,
+    util::header,
+    Result,
+};
+use actix_web::{
+    body::Body,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures::future::{ok, Ready};
+use log::debug;
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+const X_RATELIMIT_LIMIT: &str = "x-ratelimit-limit";
+const X_RATELIMIT_REMAINING: &str = "x-ratelimit-remaining";
+
+/// Middleware counting every request to the API towards the quota of the client making it
+///
+/// Clients are identified by their access token, if they provide a valid one, and by their IP
+/// address otherwise. Requests exceeding the client's quota are answered with `429 TOO MANY
+/// REQUESTS` without ever reaching the endpoint.
+#[derive(Clone)]
+pub struct ApiRatelimit(pub PointercrateState);
+pub struct ApiRatelimitMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    state: PointercrateState,
+}
+
+impl<S> Transform<S> for ApiRatelimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Ready<std::result::Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Transform = ApiRatelimitMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ApiRatelimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            state: self.0.clone(),
+        })
+    }
+}
+
+impl<S> Service for ApiRatelimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>>>>;
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(ctx)
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        if !req.path().starts_with("/api/") {
+            return Box::pin(self.service.borrow_mut().call(req))
+        }
+
+        let service = self.service.clone();
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let access = ApiAccess::of(req.method());
+            let quota = match client_of(&req, &state).await {
+                Ok(client) => state.api_quotas.check(client, access),
+                Err(error) => Err(error),
+            };
+
+            let quota = match quota {
+                Ok(quota) => quota,
+                Err(error) => {
+                    let exhausted = matches!(error, PointercrateError::Ratelimited { .. });
+                    let mut response = req.error_response(JsonError(error));
+
+                    if exhausted {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(X_RATELIMIT_REMAINING), HeaderValue::from(0u32));
+                    }
+
+                    return Ok(response)
+                },
+            };
+
+            let future = service.borrow_mut().call(req);
+            let mut response = future.await?;
+
+            if let Some(quota) = quota {
+                let headers = response.headers_mut();
+
+                headers.insert(HeaderName::from_static(X_RATELIMIT_LIMIT), HeaderValue::from(quota.limit));
+                headers.insert(HeaderName::from_static(X_RATELIMIT_REMAINING), HeaderValue::from(quota.remaining));
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Determines whose quota the given request counts towards
+///
+/// Requests with an invalid access token are treated as anonymous, the endpoint itself will reject
+/// them if it requires authorization. Valid access tokens are cached for a short while, see
+/// [`ApiQuotas::cached_client`](crate::ratelimit::ApiQuotas::cached_client).
+async fn client_of(request: &ServiceRequest, state: &PointercrateState) -> Result<ApiClient> {
+    let ip = client_ip(request.peer_addr(), request.headers())?;
+
+    let access_token = match header(request.headers(), "Authorization")? {
+        Some(authorization) => authorization.strip_prefix("Bearer ").map(ToString::to_string),
+        None => request.cookie("access_token").map(|cookie| cookie.value().to_string()),
+    };
+
+    if let Some(access_token) = access_token {
+        if let Some(client) = state.api_quotas.cached_client(&access_token) {
+            return Ok(client)
+        }
+
+        let authorization = Authorization::Token {
+            access_token: access_token.clone(),
+            csrf_token: None,
+        };
+        let mut connection = state.connection().await?;
+
+        match AuthenticatedUser::token_auth(&authorization, &state.secret, &mut connection).await {
+            Ok(user) => {
+                let client = ApiClient::Token {
+                    user: user.inner().id,
+                    tier: user.inner().quota_tier(&mut connection).await?,
+                };
+
+                state.api_quotas.cache_client(access_token, client);
+
+                return Ok(client)
+            },
+            Err(_) => debug!("Invalid access token, counting request towards the quota of {}", ip),
+        }
+    }
+
+    Ok(ApiClient::Anonymous(ip))
+}
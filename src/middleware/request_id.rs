@@ -0,0 +1,270 @@
+//! Module containing middleware assigning an ID to every request and logging it
+//!
+//! The ID is sent back in the `X-Request-Id` header and included in error responses, so that users
+//! can quote it in bug reports, which we can then match against our logs. Requests already carrying
+//! a (sane) `X-Request-Id`, e.g. because a reverse proxy assigned one, keep their ID.
+
+use crate::{metrics, util::header};
+use actix_web::{
+    body::Body,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures::future::{ok, Ready};
+use lazy_static::lazy_static;
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, info_span, Span};
+
+pub const X_REQUEST_ID: &str = "x-request-id";
+
+/// The maximal length of request IDs we accept from clients
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+lazy_static! {
+    /// Distinguishes the IDs generated by this process from those of previous runs, as the counter
+    /// starts from zero every time
+    static ref INSTANCE: u64 = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+}
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The ID of the request currently being processed on this thread. Set while the future
+    /// handling a request is being polled
+    static CURRENT_REQUEST_ID: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// The ID of a request, available from the request's extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// The ID of the request currently being processed, if any
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.with(|current| current.borrow().clone())
+}
+
+fn scoped<R>(request_id: &str, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT_REQUEST_ID.with(|current| current.replace(Some(request_id.to_string())));
+    let result = f();
+
+    CURRENT_REQUEST_ID.with(|current| *current.borrow_mut() = previous);
+
+    result
+}
+
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LENGTH
+        && request_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn generate_request_id() -> String {
+    format!("{:x}-{:x}", *INSTANCE, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+fn request_id_of(request: &ServiceRequest) -> String {
+    match header(request.headers(), X_REQUEST_ID) {
+        Ok(Some(request_id)) if is_valid_request_id(request_id) => request_id.to_string(),
+        _ => generate_request_id(),
+    }
+}
+
+/// Middleware assigning each request an ID and logging method, path, status and duration of every
+/// request inside a `tracing` span carrying that ID. Also feeds the request [`metrics`]
+///
+/// Errors bubbling up from the wrapped service are turned into responses here, so that they end
+/// up with an ID as well.
+#[derive(Debug, Copy, Clone)]
+pub struct RequestTracing;
+pub struct RequestTracingMiddleware<S>(S);
+
+impl<S> Transform<S> for RequestTracing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Transform = RequestTracingMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestTracingMiddleware(service))
+    }
+}
+
+impl<S> Service for RequestTracingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(ctx)
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let start = Instant::now();
+        let request_id = request_id_of(&req);
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let span = info_span!("request", request_id = %request_id, method = %method, path = %path);
+
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let http_request = req.request().clone();
+        let inner = {
+            let _entered = span.enter();
+
+            scoped(&request_id, || self.0.call(req))
+        };
+
+        let response_request_id = request_id.clone();
+        let future = async move {
+            let mut response = match inner.await {
+                Ok(response) => response,
+                Err(error) => ServiceResponse::from_err(error, http_request),
+            };
+
+            let duration = start.elapsed();
+
+            info!(
+                request_id = %response_request_id,
+                method = %method,
+                path = %path,
+                status = response.status().as_u16(),
+                duration_ms = duration.as_millis() as u64,
+                "request completed"
+            );
+
+            metrics::observe_request(response.request().match_pattern(), &path, response.status(), duration);
+
+            if let Ok(value) = HeaderValue::from_str(&response_request_id) {
+                response.headers_mut().insert(HeaderName::from_static(X_REQUEST_ID), value);
+            }
+
+            Ok(response)
+        };
+
+        Box::pin(RequestScoped {
+            request_id,
+            span,
+            inner: Box::pin(future),
+        })
+    }
+}
+
+/// Future polling the inner future with the request's ID set as [`current_request_id`] and its
+/// span entered
+struct RequestScoped<F> {
+    request_id: String,
+    span: Span,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for RequestScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RequestScoped { request_id, span, inner } = &mut *self;
+        let _entered = span.enter();
+
+        scoped(request_id, || inner.as_mut().poll(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_request_id, is_valid_request_id, RequestId, RequestTracing, X_REQUEST_ID};
+    use crate::error::{JsonError, PointercrateError};
+    use actix_web::{
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App, HttpMessage, HttpRequest, HttpResponse,
+    };
+    use serde_json::{json, Value};
+
+    async fn echo_request_id(request: HttpRequest) -> HttpResponse {
+        let extension = HttpMessage::extensions(&request).get::<RequestId>().map(|id| id.0.clone());
+
+        HttpResponse::Ok().json(json!({
+            "extension": extension,
+            "current": current_request_id()
+        }))
+    }
+
+    async fn fail() -> Result<HttpResponse, JsonError> {
+        Err(JsonError(PointercrateError::NotFound))
+    }
+
+    #[test]
+    fn test_request_id_validation() {
+        assert!(is_valid_request_id("5f3a1c2b-1f"));
+        assert!(is_valid_request_id("abc_DEF-123"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("abc def"));
+        assert!(!is_valid_request_id("<script>"));
+        assert!(!is_valid_request_id(&"a".repeat(65)));
+    }
+
+    #[actix_rt::test]
+    async fn test_request_id_header() {
+        let mut app = init_service(
+            App::new()
+                .wrap(RequestTracing)
+                .route("/", web::get().to(echo_request_id))
+                .route("/fail", web::get().to(fail)),
+        )
+        .await;
+
+        let response = call_service(&mut app, TestRequest::get().uri("/").to_request()).await;
+        let request_id = response.headers().get(X_REQUEST_ID).unwrap().to_str().unwrap().to_string();
+        let body: Value = serde_json::from_slice(&read_body(response).await).unwrap();
+
+        // The ID seen by the handler is the one sent back to the client
+        assert_eq!(body["extension"], request_id.as_str());
+        assert_eq!(body["current"], request_id.as_str());
+
+        // Every request gets its own ID
+        let response = call_service(&mut app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_ne!(response.headers().get(X_REQUEST_ID).unwrap(), request_id.as_str());
+
+        // Errors carry the ID of their request in their body
+        let response = call_service(&mut app, TestRequest::get().uri("/fail").to_request()).await;
+        let request_id = response.headers().get(X_REQUEST_ID).unwrap().to_str().unwrap().to_string();
+        let body: Value = serde_json::from_slice(&read_body(response).await).unwrap();
+
+        assert_eq!(body["code"], 40400);
+        assert_eq!(body["request_id"], request_id.as_str());
+
+        // IDs assigned by a reverse proxy are kept, invalid ones replaced
+        let response = call_service(
+            &mut app,
+            TestRequest::get().uri("/").header(X_REQUEST_ID, "proxy-1234").to_request(),
+        )
+        .await;
+
+        assert_eq!(response.headers().get(X_REQUEST_ID).unwrap(), "proxy-1234");
+
+        let response = call_service(&mut app, TestRequest::get().uri("/").header(X_REQUEST_ID, "no spaces").to_request()).await;
+
+        assert_ne!(response.headers().get(X_REQUEST_ID).unwrap(), "no spaces");
+
+        // Outside of a request, there is no current ID
+        assert_eq!(current_request_id(), None);
+    }
+}
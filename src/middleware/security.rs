@@ -0,0 +1,229 @@
+//! Module containing middleware adding security related headers to all responses
+//!
+//! Most notably, this sends a `Content-Security-Policy` only allowing the third-party hosts our
+//! pages actually load resources from. Handlers setting any of these headers themselves take
+//! precedence.
+
+use crate::{config, video::EMBED_ORIGINS};
+use actix_web::{
+    body::Body,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{CONTENT_SECURITY_POLICY, REFERRER_POLICY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS},
+        HeaderName, HeaderValue,
+    },
+    Error,
+};
+use futures::future::{ok, Ready};
+use log::error;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Hosts the AdSense script loads ads and further scripts from
+const ADSENSE_ORIGINS: &[&str] = &[
+    "https://pagead2.googlesyndication.com",
+    "https://tpc.googlesyndication.com",
+    "https://googleads.g.doubleclick.net",
+];
+
+/// The host the embedded discord widget is loaded from
+const DISCORD_ORIGIN: &str = "https://discordapp.com";
+
+/// Derives the default `Content-Security-Policy` from the third-party hosts used by our pages
+///
+/// Inline scripts and styles have to be allowed, as the pages contain plenty of both. Flags (from
+/// the cdnjs flag icon stylesheet), fonts, jQuery, analytics and video embeds are used on every
+/// page, the AdSense hosts are only allowed if ads are enabled, and discord only if its widget is.
+pub fn content_security_policy(ads_enabled: bool, discord_widget_enabled: bool) -> String {
+    let ads = if ads_enabled { ADSENSE_ORIGINS.join(" ") } else { String::new() };
+    let discord = if discord_widget_enabled { DISCORD_ORIGIN } else { "" };
+
+    let directives = [
+        "default-src 'self'".to_string(),
+        format!("script-src 'self' 'unsafe-inline' https://ajax.googleapis.com https://www.googletagmanager.com {}", ads),
+        "style-src 'self' 'unsafe-inline' https://fonts.googleapis.com https://cdnjs.cloudflare.com".to_string(),
+        "font-src 'self' https://fonts.gstatic.com".to_string(),
+        // thumbnails are loaded from the various video hosts
+        "img-src 'self' data: https:".to_string(),
+        "connect-src 'self' https://*.google-analytics.com".to_string(),
+        format!("frame-src {} {} {}", EMBED_ORIGINS.join(" "), discord, ads),
+        "frame-ancestors 'self'".to_string(),
+        "object-src 'none'".to_string(),
+        "base-uri 'self'".to_string(),
+    ];
+
+    directives
+        .iter()
+        .map(|directive| directive.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Middleware adding `Content-Security-Policy`, `X-Content-Type-Options`, `Referrer-Policy` and
+/// `X-Frame-Options` headers to all responses
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    content_security_policy: Option<HeaderValue>,
+}
+
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SecurityHeaders {
+    /// Uses the given `Content-Security-Policy`, or none at all
+    pub fn new(content_security_policy: Option<String>) -> Self {
+        let content_security_policy = content_security_policy.and_then(|policy| {
+            HeaderValue::from_str(&policy)
+                .map_err(|err| error!("Invalid Content-Security-Policy {:?}, not sending any: {:?}", policy, err))
+                .ok()
+        });
+
+        SecurityHeaders { content_security_policy }
+    }
+
+    /// Uses the [configured](config::content_security_policy) `Content-Security-Policy`, falling
+    /// back to the [default one](content_security_policy)
+    pub fn from_config() -> Self {
+        match config::content_security_policy() {
+            Some(policy) if policy.trim().is_empty() => SecurityHeaders::new(None),
+            Some(policy) => SecurityHeaders::new(Some(policy)),
+            None =>
+                SecurityHeaders::new(Some(content_security_policy(
+                    config::ads_enabled(),
+                    config::discord_widget_enabled(),
+                ))),
+        }
+    }
+}
+
+impl<S> Transform<S> for SecurityHeaders
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Transform = SecurityHeadersMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (REFERRER_POLICY, HeaderValue::from_static("strict-origin-when-cross-origin")),
+            (X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN")),
+        ];
+
+        if let Some(ref policy) = self.content_security_policy {
+            headers.push((CONTENT_SECURITY_POLICY, policy.clone()));
+        }
+
+        ok(SecurityHeadersMiddleware { service, headers })
+    }
+}
+
+impl<S> Service for SecurityHeadersMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let inner = self.service.call(req);
+        let headers = self.headers.clone();
+
+        Box::pin(async move {
+            let mut response = inner.await?;
+
+            for (name, value) in headers {
+                if !response.headers().contains_key(&name) {
+                    response.headers_mut().insert(name, value);
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_security_policy, SecurityHeaders};
+    use actix_web::{
+        http::header::{CONTENT_SECURITY_POLICY, REFERRER_POLICY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS},
+        test::{call_service, init_service, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn csp_header(ads_enabled: bool, discord_widget_enabled: bool) -> String {
+        let mut app = init_service(
+            App::new()
+                .wrap(SecurityHeaders::new(Some(content_security_policy(ads_enabled, discord_widget_enabled))))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let response = call_service(&mut app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(response.headers().get(X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert_eq!(response.headers().get(X_FRAME_OPTIONS).unwrap(), "SAMEORIGIN");
+        assert_eq!(response.headers().get(REFERRER_POLICY).unwrap(), "strict-origin-when-cross-origin");
+
+        response.headers().get(CONTENT_SECURITY_POLICY).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[actix_rt::test]
+    async fn test_adsense_only_allowed_with_ads() {
+        let with_ads = csp_header(true, true).await;
+        let without_ads = csp_header(false, true).await;
+
+        assert!(with_ads.contains("https://pagead2.googlesyndication.com"));
+        assert!(!without_ads.contains("googlesyndication"));
+
+        for policy in &[with_ads, without_ads] {
+            assert!(policy.contains("frame-ancestors 'self'"));
+            assert!(policy.contains("https://cdnjs.cloudflare.com"));
+            assert!(policy.contains("https://www.youtube.com"));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_discord_only_allowed_with_widget() {
+        assert!(csp_header(false, true).await.contains("https://discordapp.com"));
+
+        let without_widget = csp_header(false, false).await;
+
+        assert!(!without_widget.contains("discord"));
+        assert!(without_widget.contains("frame-src https://www.youtube.com"));
+        assert!(!without_widget.contains("  "));
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_headers_take_precedence() {
+        let mut app = init_service(
+            App::new()
+                .wrap(SecurityHeaders::new(None))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().header(X_FRAME_OPTIONS, "DENY").finish() })),
+        )
+        .await;
+
+        let response = call_service(&mut app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(response.headers().get(X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert!(response.headers().get(CONTENT_SECURITY_POLICY).is_none());
+    }
+}
@@ -1,5 +1,7 @@
 // pub use self::post::PostCreator;
-pub use self::get::{created_by, creators_of};
+pub use self::get::{created_by, creators_by_demon, creators_of};
+#[cfg(feature = "graphql")]
+pub use self::get::creators_of_demons;
 use derive_more::Display;
 pub use post::PostCreator;
 
@@ -6,6 +6,7 @@ use crate::{
 };
 use futures::stream::StreamExt;
 use sqlx::PgConnection;
+use std::collections::HashMap;
 
 impl Creator {
     pub async fn get(demon: &MinimalDemon, player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Creator> {
@@ -32,10 +33,14 @@ impl Creator {
     }
 }
 
+/// Gets the creators of the given demon, in the order they were (most recently) added
+///
+/// Creators added before we started keeping an audit log come first, ordered by their ID
 pub async fn creators_of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
     let mut stream = sqlx::query!(
         r#"SELECT players.id, players.name AS "name: String", players.banned FROM players INNER JOIN creators ON players.id = creators.creator WHERE 
-         creators.demon = $1"#,
+         creators.demon = $1 ORDER BY (SELECT MAX(audit_id) FROM creator_additions WHERE creator_additions.demon = creators.demon AND 
+         creator_additions.creator = creators.creator) NULLS FIRST, players.id"#,
         demon.id
     )
     .fetch(connection);
@@ -54,10 +59,63 @@ pub async fn creators_of(demon: &MinimalDemon, connection: &mut PgConnection) ->
     Ok(players)
 }
 
+/// Gets the creators of all demons in a single query, keyed by demon ID
+///
+/// The creators of each demon are ordered the same way as in [`creators_of`]. Demons without any
+/// creators do not have an entry in the returned map.
+pub async fn creators_by_demon(connection: &mut PgConnection) -> Result<HashMap<i32, Vec<DatabasePlayer>>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT creators.demon, players.id, players.name AS "name: String", players.banned FROM players INNER JOIN creators ON players.id = 
+         creators.creator ORDER BY creators.demon, (SELECT MAX(audit_id) FROM creator_additions WHERE creator_additions.demon = creators.demon AND 
+         creator_additions.creator = creators.creator) NULLS FIRST, players.id"#
+    )
+    .fetch(connection);
+    let mut creators: HashMap<i32, Vec<DatabasePlayer>> = HashMap::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        creators.entry(row.demon).or_default().push(DatabasePlayer {
+            id: row.id,
+            name: CiString(row.name),
+            banned: row.banned,
+        })
+    }
+
+    Ok(creators)
+}
+
+/// Gets the creators of the demons with the given IDs in a single query, keyed by demon ID
+///
+/// Behaves like [`creators_by_demon`], except that only the given demons are considered.
+#[cfg(feature = "graphql")]
+pub async fn creators_of_demons(demon_ids: &[i32], connection: &mut PgConnection) -> Result<HashMap<i32, Vec<DatabasePlayer>>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT creators.demon, players.id, players.name AS "name: String", players.banned FROM players INNER JOIN creators ON players.id = 
+         creators.creator WHERE creators.demon = ANY($1) ORDER BY creators.demon, (SELECT MAX(audit_id) FROM creator_additions WHERE 
+         creator_additions.demon = creators.demon AND creator_additions.creator = creators.creator) NULLS FIRST, players.id"#,
+        demon_ids
+    )
+    .fetch(connection);
+    let mut creators: HashMap<i32, Vec<DatabasePlayer>> = HashMap::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        creators.entry(row.demon).or_default().push(DatabasePlayer {
+            id: row.id,
+            name: CiString(row.name),
+            banned: row.banned,
+        })
+    }
+
+    Ok(creators)
+}
+
 pub async fn created_by(player_id: i32, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
     let mut stream = sqlx::query!(
         r#"SELECT demons.id, demons.name as "name: String", demons.position FROM demons INNER JOIN creators ON demons.id = creators.demon WHERE 
-         creators.creator=$1"#,
+         creators.creator=$1 AND demons.published"#,
         player_id
     )
     .fetch(connection);
@@ -75,3 +133,51 @@ pub async fn created_by(player_id: i32, connection: &mut PgConnection) -> Result
 
     Ok(demons)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{creators_by_demon, creators_of};
+    use crate::{cistring::CiStr, model::demonlist::demon::MinimalDemon, state::audit_connection};
+
+    #[actix_rt::test]
+    async fn test_creators_by_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        // Only with an audited connection do creator additions get logged, which is what determines
+        // their order
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+        let terminal_void = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+
+        for (demon, creator) in &[
+            (trichotomy.id, "Samifying"),
+            (terminal_void.id, "Mullsy"),
+            (trichotomy.id, "Aquatias"),
+        ] {
+            sqlx::query!(
+                "INSERT INTO creators (demon, creator) SELECT $1, id FROM players WHERE name = $2::TEXT",
+                *demon,
+                creator.to_string()
+            )
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        }
+
+        let creators = creators_by_demon(&mut connection).await.unwrap();
+        let names = |demon_id: i32| -> Vec<String> { creators[&demon_id].iter().map(|player| player.name.to_string()).collect() };
+
+        assert_eq!(creators.len(), 2);
+        assert_eq!(names(trichotomy.id), vec!["Samifying", "Aquatias"]);
+        assert_eq!(names(terminal_void.id), vec!["Mullsy"]);
+        assert_eq!(creators[&trichotomy.id], creators_of(&trichotomy, &mut connection).await.unwrap());
+
+        // abstract interpretation has no creators
+        let abstract_interpretation = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection)
+            .await
+            .unwrap();
+
+        assert!(!creators.contains_key(&abstract_interpretation.id));
+    }
+}
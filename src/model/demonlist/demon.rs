@@ -1,25 +1,48 @@
 pub use self::{
+    alias::{aliases_of, DemonAlias, PostAlias},
+    audit::{DemonAuditAction, DemonAuditEntry},
+    export::export_list,
     get::{published_by, verified_by},
+    longevity::{longevity_ranking, Longevity, RankedLongevity},
+    mirror::{append_mirror, mirrors_of, set_mirrors, PostMirror},
     paginate::{DemonIdPagination, DemonPositionPagination},
     patch::PatchDemon,
     post::PostDemon,
+    search::{DemonSearch, SearchResult},
+    tag::{add_tags, tags_of},
 };
 use crate::{
     cistring::{CiStr, CiString},
+    config,
     error::PointercrateError,
     model::demonlist::{player::DatabasePlayer, record::MinimalRecordP},
     Result,
 };
+use chrono::NaiveDateTime;
 use derive_more::Display;
-use log::info;
-use serde::Serialize;
-use sqlx::PgConnection;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sqlx::{Done, PgConnection};
 use std::hash::{Hash, Hasher};
 
+/// The maximal length (in characters) of a demon's song name and artist
+pub const MAX_SONG_METADATA_LENGTH: usize = 100;
+
+/// Key of the advisory lock taken by [`Demon::lock_positions`]. Arbitrary, but has to be the same
+/// across all connections
+const POSITION_LOCK: i64 = 0x6465_6d6f_6e73;
+
+mod alias;
+pub mod audit;
+mod export;
 mod get;
+mod longevity;
+mod mirror;
 mod paginate;
 mod patch;
 mod post;
+mod search;
+mod tag;
 
 /// Struct modelling a demon. These objects are returned from the paginating `/demons/` endpoint
 #[derive(Debug, Serialize, Hash, Display, Eq, PartialEq)]
@@ -32,8 +55,12 @@ pub struct Demon {
     /// accepted
     pub requirement: i16,
 
+    /// A showcase of the level, used for thumbnails
     pub video: Option<String>,
 
+    /// The video of this [`Demon`]'s verification, if it differs from the showcase [`Demon::video`]
+    pub verification_video: Option<String>,
+
     /// This [`Demon`]'s publisher
     pub publisher: DatabasePlayer,
 
@@ -45,6 +72,59 @@ pub struct Demon {
     /// This is automatically queried based on the level name, but can be manually overridden by a
     /// list mod.
     pub level_id: Option<u64>,
+
+    /// The platform this [`Demon`] was verified on
+    pub verified_on: VerificationPlatform,
+
+    /// Whether this [`Demon`] is visible to the public. Unpublished demons are only shown to list
+    /// moderators, and are always positioned after all published demons
+    pub published: bool,
+
+    /// The name of the song used by this [`Demon`], if known
+    pub song_name: Option<String>,
+
+    /// The artist of the song used by this [`Demon`], if known
+    pub song_artist: Option<String>,
+}
+
+/// The platform a demon was verified on
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Hash, PartialEq, Eq, Display)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VerificationPlatform {
+    #[display(fmt = "PC")]
+    Pc,
+
+    #[display(fmt = "Mobile")]
+    Mobile,
+
+    /// Used for all demons added before the platform was tracked
+    #[display(fmt = "Unknown")]
+    Unknown,
+}
+
+impl Default for VerificationPlatform {
+    fn default() -> Self {
+        VerificationPlatform::Unknown
+    }
+}
+
+impl VerificationPlatform {
+    pub fn to_sql(self) -> &'static str {
+        match self {
+            VerificationPlatform::Pc => "PC",
+            VerificationPlatform::Mobile => "MOBILE",
+            VerificationPlatform::Unknown => "UNKNOWN",
+        }
+    }
+
+    pub fn from_sql(sql: &str) -> Self {
+        match sql {
+            "PC" => VerificationPlatform::Pc,
+            "MOBILE" => VerificationPlatform::Mobile,
+            "UNKNOWN" => VerificationPlatform::Unknown,
+            _ => unreachable!(),
+        }
+    }
 }
 
 /// Absolutely minimal representation of a demon to be sent when a demon is part of another object
@@ -76,12 +156,22 @@ pub struct FullDemon {
     pub demon: Demon,
     pub creators: Vec<DatabasePlayer>,
     pub records: Vec<MinimalRecordP>,
+
+    /// The record of the first player to beat this demon after its verifier, see
+    /// [`first_victor_on`](crate::model::demonlist::record::first_victor_on)
+    pub first_victor: Option<MinimalRecordP>,
+
+    /// Fallback videos for when the primary video is unavailable, in order of preference
+    pub mirrors: Vec<String>,
+
+    /// Lowercase tags categorizing this demon, in alphabetical order
+    pub tags: Vec<String>,
 }
 
 impl Hash for FullDemon {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.demon.hash(state);
-        // creators have sub-endpoint -> no hash
+        // creators and mirrors have sub-endpoints, tags cannot be changed -> no hash
     }
 }
 
@@ -94,6 +184,28 @@ impl MinimalDemon {
             .await?
             .requirement)
     }
+
+    /// Queries whether this demon is published from the database without collecting any of the
+    /// other data
+    pub async fn published(&self, connection: &mut PgConnection) -> Result<bool> {
+        Ok(sqlx::query!("SELECT published FROM demons WHERE id = $1", self.id)
+            .fetch_one(connection)
+            .await?
+            .published)
+    }
+
+    /// Gets the most recent point in time at which this demon fell onto the legacy list, or `None`
+    /// if it is not currently on the legacy list.
+    pub async fn legacy_since(&self, connection: &mut PgConnection) -> Result<Option<NaiveDateTime>> {
+        Ok(sqlx::query!(
+            "SELECT since FROM demon_legacy_since($1) WHERE id = $2",
+            config::extended_list_size(),
+            self.id
+        )
+        .fetch_optional(connection)
+        .await?
+        .and_then(|row| row.since))
+    }
 }
 
 impl FullDemon {
@@ -154,24 +266,100 @@ impl FullDemon {
 }
 
 impl Demon {
+    /// Suggests a record requirement for a demon at the given position, based on the configured
+    /// [requirement tiers](config::requirement_tiers)
+    pub fn suggested_requirement(position: i16) -> i16 {
+        suggested_requirement(position, &config::requirement_tiers())
+    }
+
+    /// The progress a record on a demon with the given requirement at the given position actually
+    /// has to reach. Demons outside the main list only accept 100% records, regardless of their
+    /// requirement
+    pub fn effective_requirement(requirement: i16, position: i16) -> i16 {
+        effective_requirement(requirement, position, config::list_size())
+    }
+
+    pub fn validate_name(name: &CiStr) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(PointercrateError::EmptyName.in_field("name"))
+        }
+
+        Ok(())
+    }
+
+    /// Validates a song name or artist, returning it with surrounding whitespace removed. Blank
+    /// values mean the song (artist) is unknown
+    pub fn validate_song_metadata(value: String, field: &'static str) -> Result<Option<String>> {
+        let value = value.trim();
+
+        if value.chars().count() > MAX_SONG_METADATA_LENGTH {
+            return Err(PointercrateError::SongMetadataTooLong {
+                max_length: MAX_SONG_METADATA_LENGTH,
+            }
+            .in_field(field))
+        }
+
+        Ok(if value.is_empty() { None } else { Some(value.to_string()) })
+    }
+
     pub fn validate_requirement(requirement: i16) -> Result<()> {
         if requirement < 0 || requirement > 100 {
-            return Err(PointercrateError::InvalidRequirement)
+            return Err(PointercrateError::InvalidRequirement.in_field("requirement"))
         }
 
         Ok(())
     }
 
-    pub async fn validate_position(position: i16, connection: &mut PgConnection) -> Result<()> {
-        let maximal_position = Demon::max_position(connection).await?;
-
-        if position > maximal_position || position < 1 {
-            return Err(PointercrateError::InvalidPosition { maximal: maximal_position })
+    /// Validates the position of a new demon
+    ///
+    /// Unpublished demons are always positioned after all published ones, so that published demons
+    /// have the same positions for the public as for the list team. New unpublished demons can
+    /// therefore only be added after the last published demon, and new published ones only before
+    /// it.
+    pub async fn validate_position(position: i16, published: bool, connection: &mut PgConnection) -> Result<()> {
+        let maximal_published_position = Demon::max_published_position(&mut *connection).await?;
+
+        if published {
+            if position > maximal_published_position || position < 1 {
+                return Err(PointercrateError::InvalidPosition {
+                    maximal: maximal_published_position,
+                }
+                .in_field("position"))
+            }
+        } else {
+            let maximal_position = Demon::max_position(connection).await? + 1;
+
+            if position > maximal_position || position <= maximal_published_position {
+                return Err(PointercrateError::InvalidUnpublishedPosition {
+                    minimal: maximal_published_position + 1,
+                    maximal: maximal_position,
+                }
+                .in_field("position"))
+            }
         }
 
         Ok(())
     }
 
+    /// Acquires the lock serializing all modifications of demon positions, which is held until the
+    /// current transaction ends
+    ///
+    /// Row locks are not enough here, since they cannot prevent concurrent inserts. Without this,
+    /// two demons created at the same time could both validate against the same maximal position and
+    /// both shift the list, leaving it with duplicate positions or holes. Must be run within a
+    /// transaction, and before any positions are read!
+    pub(crate) async fn lock_positions(connection: &mut PgConnection) -> Result<()> {
+        debug!("Acquiring demon position lock");
+
+        // not using query! here, as sqlx cannot decode the `void` returned by the function
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(POSITION_LOCK)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
     /// Increments the position of all demons with positions equal to or greater than the given one,
     /// by one.
     async fn shift_down(starting_at: i16, connection: &mut PgConnection) -> Result<()> {
@@ -184,6 +372,38 @@ impl Demon {
         Ok(())
     }
 
+    /// Gets the IDs of all demons currently on the main list
+    ///
+    /// Used together with [`Demon::grandfather_records`] to detect demons leaving the main list.
+    pub(crate) async fn main_list_ids(connection: &mut PgConnection) -> Result<Vec<i32>> {
+        Ok(sqlx::query!("SELECT id FROM demons WHERE position <= $1", config::list_size())
+            .fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect())
+    }
+
+    /// Marks all non-100% records on those of the given demons that are no longer on the main list as
+    /// grandfathered, meaning they stay valid even though such records can no longer be submitted
+    pub(crate) async fn grandfather_records(previous_main_list: &[i32], connection: &mut PgConnection) -> Result<()> {
+        let grandfathered = sqlx::query!(
+            "UPDATE records SET grandfathered = TRUE FROM demons WHERE records.demon = demons.id AND demons.id = ANY($1) AND \
+             demons.position > $2 AND records.progress < 100",
+            previous_main_list,
+            config::list_size()
+        )
+        .execute(connection)
+        .await?
+        .rows_affected();
+
+        if grandfathered > 0 {
+            info!("Grandfathered {} non-100% records on demons that left the main list", grandfathered);
+        }
+
+        Ok(())
+    }
+
     /// Decrements the position of all demons with positions equal to or smaller than the given one,
     /// by one.
     async fn shift_up(until: i16, connection: &mut PgConnection) -> Result<()> {
@@ -205,6 +425,16 @@ impl Demon {
             .ok_or(PointercrateError::NotFound)
     }
 
+    /// Gets the highest position of a published demon, or `0` if no demon is published
+    pub async fn max_published_position(connection: &mut PgConnection) -> Result<i16> {
+        Ok(
+            sqlx::query!(r#"SELECT COALESCE(MAX(position), 0)::SMALLINT AS "max_position!" FROM demons WHERE published"#)
+                .fetch_one(connection)
+                .await?
+                .max_position,
+        )
+    }
+
     /// Gets the maximal and minimal submitter id currently in use
     ///
     /// The returned tuple is of the form (max, min)
@@ -243,3 +473,113 @@ impl Demon {
         }
     }
 }
+
+fn suggested_requirement(position: i16, tiers: &[(i16, i16)]) -> i16 {
+    tiers
+        .iter()
+        .find(|(tier_position, _)| position <= *tier_position)
+        .map(|(_, requirement)| *requirement)
+        .unwrap_or(100)
+}
+
+pub(crate) fn effective_requirement(requirement: i16, position: i16, list_size: i16) -> i16 {
+    if position > list_size {
+        100
+    } else {
+        requirement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{effective_requirement, suggested_requirement, Demon, MinimalDemon, MAX_SONG_METADATA_LENGTH};
+    use crate::{cistring::CiStr, error::PointercrateError, state::audit_connection};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_effective_requirement() {
+        assert_eq!(effective_requirement(54, 1, 75), 54);
+        assert_eq!(effective_requirement(54, 75, 75), 54);
+        assert_eq!(effective_requirement(54, 76, 75), 100);
+    }
+
+    #[test]
+    fn test_suggested_requirement_tiers() {
+        let tiers = [(10, 50), (25, 55), (50, 60)];
+
+        assert_eq!(suggested_requirement(1, &tiers), 50);
+        assert_eq!(suggested_requirement(10, &tiers), 50);
+        assert_eq!(suggested_requirement(11, &tiers), 55);
+        assert_eq!(suggested_requirement(25, &tiers), 55);
+        assert_eq!(suggested_requirement(26, &tiers), 60);
+        assert_eq!(suggested_requirement(50, &tiers), 60);
+        assert_eq!(suggested_requirement(51, &tiers), 100);
+        assert_eq!(suggested_requirement(150, &tiers), 100);
+        assert_eq!(suggested_requirement(1, &[]), 100);
+    }
+
+    #[test]
+    fn test_suggested_requirement_is_valid() {
+        for position in 1..200 {
+            assert!(Demon::validate_requirement(Demon::suggested_requirement(position)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_validate_song_metadata() {
+        assert_eq!(
+            Demon::validate_song_metadata("  Theory of Everything 2 ".to_string(), "song_name").unwrap(),
+            Some("Theory of Everything 2".to_string())
+        );
+        assert_eq!(Demon::validate_song_metadata("   ".to_string(), "song_artist").unwrap(), None);
+        assert!(Demon::validate_song_metadata("a".repeat(MAX_SONG_METADATA_LENGTH), "song_name").is_ok());
+
+        assert_eq!(
+            Demon::validate_song_metadata("a".repeat(MAX_SONG_METADATA_LENGTH + 1), "song_artist"),
+            Err(PointercrateError::SongMetadataTooLong {
+                max_length: MAX_SONG_METADATA_LENGTH
+            }
+            .in_field("song_artist"))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_legacy_since_uses_most_recent_transition() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("taraturusus"), &mut connection).await.unwrap();
+
+        assert_eq!(demon.legacy_since(&mut connection).await.unwrap(), None);
+
+        // Fake a history in which the demon was added at #90, fell to legacy, re-entered the
+        // extended list and then fell to legacy again. The modification log stores the position
+        // a demon had _before_ each change.
+        sqlx::query!("UPDATE demons SET position = 150 WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM demon_modifications WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demon_additions SET time = '2018-01-01' WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!(
+            "INSERT INTO demon_modifications (userid, id, position, time) VALUES (0, $1, 90, '2019-01-01'), (0, $1, 120, '2019-06-01'), \
+             (0, $1, 95, '2020-01-01')",
+            demon.id
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            demon.legacy_since(&mut connection).await.unwrap(),
+            Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0))
+        );
+    }
+}
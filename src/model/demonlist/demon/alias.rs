@@ -0,0 +1,219 @@
+//! Alternative names under which demons can be looked up
+//!
+//! Aliases are case-insensitive and unique across all demons. To keep name lookups unambiguous, an
+//! alias can never coincide with the name of an existing demon, and no demon can be named after
+//! another demon's alias.
+
+use crate::{
+    cistring::{CiStr, CiString},
+    error::PointercrateError,
+    model::demonlist::demon::MinimalDemon,
+    Result,
+};
+use derive_more::Display;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgConnection};
+
+#[derive(Debug, Serialize, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "alias '{}' of demon {}", alias, demon)]
+pub struct DemonAlias {
+    pub alias: CiString,
+    pub demon: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostAlias {
+    pub alias: CiString,
+}
+
+impl DemonAlias {
+    pub async fn get(alias: &CiStr, connection: &mut PgConnection) -> Result<DemonAlias> {
+        let alias = CiStr::from_str(alias.trim());
+
+        let result = sqlx::query!(
+            r#"SELECT alias::text AS "alias!: String", demon FROM demon_aliases WHERE alias = cast($1::text as citext)"#,
+            alias.to_string()
+        )
+        .fetch_one(connection)
+        .await;
+
+        match result {
+            Ok(row) =>
+                Ok(DemonAlias {
+                    alias: CiString(row.alias),
+                    demon: row.demon,
+                }),
+            Err(Error::RowNotFound) =>
+                Err(PointercrateError::ModelNotFound {
+                    model: "Alias",
+                    identified_by: alias.to_string(),
+                }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn insert(demon: &MinimalDemon, alias: &CiStr, connection: &mut PgConnection) -> Result<DemonAlias> {
+        let alias = CiStr::from_str(alias.trim());
+
+        info!("Adding alias '{}' to demon {}", alias, demon);
+
+        if alias.is_empty() {
+            return Err(PointercrateError::InvalidAlias)
+        }
+
+        if let Some(row) = sqlx::query!(
+            "SELECT position FROM demons WHERE name = cast($1::text as citext) LIMIT 1",
+            alias.to_string()
+        )
+        .fetch_optional(&mut *connection)
+        .await?
+        {
+            return Err(PointercrateError::AliasCollision { position: row.position })
+        }
+
+        match DemonAlias::get(alias, connection).await {
+            Ok(existing) => return Err(PointercrateError::AliasExists { demon: existing.demon }),
+            Err(PointercrateError::ModelNotFound { .. }) => (),
+            Err(err) => return Err(err),
+        }
+
+        sqlx::query!("INSERT INTO demon_aliases (alias, demon) VALUES ($1::text, $2)", alias.to_string(), demon.id)
+            .execute(connection)
+            .await?;
+
+        Ok(DemonAlias {
+            alias: alias.to_owned(),
+            demon: demon.id,
+        })
+    }
+
+    /// Ensures that the given name is not an alias of any demon other than the one with id `demon`
+    pub async fn validate_name(name: &CiStr, demon: Option<i32>, connection: &mut PgConnection) -> Result<()> {
+        let row = sqlx::query!(
+            "SELECT demon FROM demon_aliases WHERE alias = cast($1::text as citext) AND demon IS DISTINCT FROM $2",
+            name.trim(),
+            demon
+        )
+        .fetch_optional(connection)
+        .await?;
+
+        match row {
+            Some(row) => Err(PointercrateError::NameIsAlias { demon: row.demon }.in_field("name")),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn delete(self, connection: &mut PgConnection) -> Result<()> {
+        info!("Deleting {}", self);
+
+        sqlx::query!("DELETE FROM demon_aliases WHERE alias = cast($1::text as citext)", self.alias.to_string())
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub async fn aliases_of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<CiString>> {
+    Ok(sqlx::query!(
+        r#"SELECT alias::text AS "alias!: String" FROM demon_aliases WHERE demon = $1 ORDER BY alias"#,
+        demon.id
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| CiString(row.alias))
+    .collect())
+}
+
+impl MinimalDemon {
+    pub async fn by_alias(alias: &CiStr, connection: &mut PgConnection) -> Result<MinimalDemon> {
+        let alias = DemonAlias::get(alias, connection).await?;
+
+        MinimalDemon::by_id(alias.demon, connection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aliases_of, DemonAlias};
+    use crate::{
+        cistring::{CiStr, CiString},
+        error::PointercrateError,
+        model::demonlist::demon::MinimalDemon,
+    };
+
+    #[actix_rt::test]
+    async fn test_alias_resolution_is_case_insensitive() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+
+        DemonAlias::insert(&demon, CiStr::from_str("TV"), &mut connection).await.unwrap();
+
+        let resolved = MinimalDemon::by_alias(CiStr::from_str("tv"), &mut connection).await.unwrap();
+
+        assert_eq!(resolved, demon);
+        assert_eq!(aliases_of(&demon, &mut connection).await.unwrap().len(), 1);
+
+        // name lookups fall back to aliases
+        let by_name = MinimalDemon::by_name(CiStr::from_str("Tv"), &mut connection).await.unwrap();
+
+        assert_eq!(by_name, demon);
+
+        DemonAlias::get(CiStr::from_str("tV"), &mut connection)
+            .await
+            .unwrap()
+            .delete(&mut connection)
+            .await
+            .unwrap();
+
+        assert!(MinimalDemon::by_alias(CiStr::from_str("tv"), &mut connection).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_alias_colliding_with_demon_name() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+
+        match DemonAlias::insert(&demon, CiStr::from_str("TRICHOTOMY"), &mut connection).await {
+            Err(PointercrateError::AliasCollision { position: 2 }) => (),
+            result => panic!("expected alias collision, got {:?}", result),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_demon_name_colliding_with_alias() {
+        let mut connection = crate::test::test_setup().await;
+
+        let mut void = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+        let mut trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        DemonAlias::insert(&void, CiStr::from_str("the void"), &mut connection).await.unwrap();
+
+        assert_eq!(
+            trichotomy.set_name(CiString("The Void".to_string()), &mut connection).await,
+            Err(PointercrateError::NameIsAlias { demon: void.id }.in_field("name"))
+        );
+
+        // A demon may take on one of its own aliases as its name
+        void.set_name(CiString("The Void".to_string()), &mut connection).await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_duplicate_alias() {
+        let mut connection = crate::test::test_setup().await;
+
+        let void = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        DemonAlias::insert(&void, CiStr::from_str("the void"), &mut connection).await.unwrap();
+
+        match DemonAlias::insert(&trichotomy, CiStr::from_str("The Void"), &mut connection).await {
+            Err(PointercrateError::AliasExists { demon }) => assert_eq!(demon, void.id),
+            result => panic!("expected existing alias, got {:?}", result),
+        }
+    }
+}
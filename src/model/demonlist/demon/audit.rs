@@ -0,0 +1,229 @@
+//! Field-level audit log for demons
+//!
+//! Entries are written from within the same transaction as the change they describe. Each entry
+//! stores, for every field that changed, both its old and its new value. For additions, the old
+//! value of each field is `null`.
+//!
+//! Note that demons shifted around as a side effect of another demon being added or moved do not
+//! get entries here. Their position changes are only tracked in `demon_modifications`.
+
+use crate::{
+    model::{demonlist::demon::Demon, NamedId},
+    Result,
+};
+use chrono::NaiveDateTime;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DemonAuditAction {
+    Addition,
+    Modification,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DemonAuditEntry {
+    pub time: NaiveDateTime,
+    pub audit_id: i32,
+    pub demon_id: i32,
+
+    /// The user who performed the change
+    pub user: NamedId,
+    pub action: DemonAuditAction,
+
+    /// Maps the name of every field that changed to an object containing its `old` and `new` value
+    pub changes: Map<String, Value>,
+}
+
+impl DemonAuditAction {
+    fn to_sql(self) -> &'static str {
+        match self {
+            DemonAuditAction::Addition => "addition",
+            DemonAuditAction::Modification => "modification",
+        }
+    }
+
+    fn from_sql(sql: &str) -> Self {
+        match sql {
+            "addition" => DemonAuditAction::Addition,
+            "modification" => DemonAuditAction::Modification,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Captures the audited fields of the given demon
+pub fn snapshot(demon: &Demon) -> Map<String, Value> {
+    let mut snapshot = Map::new();
+
+    snapshot.insert("name".to_string(), json!(demon.base.name));
+    snapshot.insert("position".to_string(), json!(demon.base.position));
+    snapshot.insert("requirement".to_string(), json!(demon.requirement));
+    snapshot.insert("video".to_string(), json!(demon.video));
+    snapshot.insert("verification_video".to_string(), json!(demon.verification_video));
+    snapshot.insert("verified_on".to_string(), json!(demon.verified_on));
+    snapshot.insert("published".to_string(), json!(demon.published));
+    snapshot.insert("song_name".to_string(), json!(demon.song_name));
+    snapshot.insert("song_artist".to_string(), json!(demon.song_artist));
+    snapshot.insert("verifier".to_string(), json!({"id": demon.verifier.id, "name": demon.verifier.name}));
+    snapshot.insert("publisher".to_string(), json!({"id": demon.publisher.id, "name": demon.publisher.name}));
+
+    snapshot
+}
+
+/// Computes the changes between two snapshots, only containing fields whose value actually differs
+pub fn diff(before: &Map<String, Value>, after: &Map<String, Value>) -> Map<String, Value> {
+    let mut changes = Map::new();
+
+    for (field, new) in after {
+        let old = before.get(field).unwrap_or(&Value::Null);
+
+        if old != new {
+            changes.insert(field.clone(), json!({"old": old, "new": new}));
+        }
+    }
+
+    changes
+}
+
+/// Logs the addition of the given demon
+pub async fn log_addition(demon: &Demon, connection: &mut PgConnection) -> Result<()> {
+    insert_entry(demon.base.id, DemonAuditAction::Addition, diff(&Map::new(), &snapshot(demon)), connection).await
+}
+
+/// Logs all changes made to the given demon since `before` was captured. Does nothing if nothing
+/// changed.
+pub async fn log_modification(before: &Map<String, Value>, demon: &Demon, connection: &mut PgConnection) -> Result<()> {
+    let changes = diff(before, &snapshot(demon));
+
+    if changes.is_empty() {
+        return Ok(())
+    }
+
+    insert_entry(demon.base.id, DemonAuditAction::Modification, changes, connection).await
+}
+
+async fn insert_entry(demon_id: i32, action: DemonAuditAction, changes: Map<String, Value>, connection: &mut PgConnection) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO demon_audit_log (userid, id, action, changes) (SELECT id, $1, $2::TEXT, $3::TEXT::JSONB FROM active_user LIMIT 1)",
+        demon_id,
+        action.to_sql(),
+        Value::Object(changes).to_string()
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets all audit log entries for the given demon, in chronological order
+pub async fn entries_for_demon(demon_id: i32, connection: &mut PgConnection) -> Result<Vec<DemonAuditEntry>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT time, audit_id, userid, members.name AS "username?", action, changes::TEXT AS "changes!"
+           FROM demon_audit_log
+           LEFT OUTER JOIN members ON members.member_id = userid
+           WHERE id = $1
+           ORDER BY time, audit_id"#,
+        demon_id
+    )
+    .fetch(connection);
+
+    let mut entries = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        entries.push(DemonAuditEntry {
+            time: row.time,
+            audit_id: row.audit_id,
+            demon_id,
+            user: NamedId {
+                id: row.userid,
+                name: row.username,
+            },
+            action: DemonAuditAction::from_sql(&row.action),
+            // we wrote these ourselves, so they're always valid JSON objects
+            changes: serde_json::from_str(&row.changes).unwrap_or_default(),
+        })
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, entries_for_demon, DemonAuditAction};
+    use crate::{
+        cistring::CiString,
+        model::demonlist::demon::{Demon, FullDemon, PatchDemon, PostDemon},
+        state::audit_connection,
+    };
+    use serde_json::{json, Map};
+
+    #[test]
+    fn test_diff_only_contains_changed_fields() {
+        let mut before = Map::new();
+        before.insert("name".to_string(), json!("Bloodbath"));
+        before.insert("requirement".to_string(), json!(50));
+
+        let mut after = before.clone();
+        after.insert("requirement".to_string(), json!(60));
+
+        let changes = diff(&before, &after);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes["requirement"], json!({"old": 50, "new": 60}));
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_addition_and_modification() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let post: PostDemon = serde_json::from_value(json!({
+            "name": "Bloodbath",
+            "position": 1,
+            "requirement": 50,
+            "verifier": "Riot",
+            "publisher": "Riot",
+            "creators": [],
+            "video": null
+        }))
+        .unwrap();
+
+        let demon_id = FullDemon::create_from(post, &mut connection).await.unwrap().demon.base.id;
+        let entries = entries_for_demon(demon_id, &mut connection).await.unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, DemonAuditAction::Addition);
+        assert_eq!(entries[0].changes["name"], json!({"old": null, "new": "Bloodbath"}));
+        assert_eq!(entries[0].changes["position"], json!({"old": null, "new": 1}));
+
+        Demon::by_id(demon_id, &mut connection)
+            .await
+            .unwrap()
+            .apply_patch(
+                PatchDemon {
+                    requirement: Some(60),
+                    name: Some(CiString("Bloodbath".to_string())),
+                    ..Default::default()
+                },
+                &mut connection,
+            )
+            .await
+            .unwrap();
+
+        let entries = entries_for_demon(demon_id, &mut connection).await.unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].action, DemonAuditAction::Modification);
+        // the name was "changed" to the same value, so it must not show up in the diff
+        assert_eq!(entries[1].changes.len(), 1);
+        assert_eq!(entries[1].changes["requirement"], json!({"old": 50, "new": 60}));
+    }
+}
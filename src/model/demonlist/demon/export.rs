@@ -0,0 +1,39 @@
+//! Export of the entire list in a single request, so that third parties do not need to page
+//! through `/demons/` and then request every demon's creators separately
+
+use crate::{
+    model::demonlist::{creator::creators_by_demon, demon::Demon, player::DatabasePlayer},
+    Result,
+};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize)]
+pub struct ExportedDemon {
+    #[serde(flatten)]
+    pub demon: Demon,
+
+    /// This [`Demon`]'s creators, in the order they were added. Empty if no creators were recorded
+    pub creators: Vec<DatabasePlayer>,
+
+    /// The progress records on this [`Demon`] actually need to reach, see
+    /// [`Demon::effective_requirement`]
+    pub effective_requirement: i16,
+}
+
+/// Gets all demons with their creators, ordered by position
+pub async fn export_list(connection: &mut PgConnection) -> Result<Vec<ExportedDemon>> {
+    let mut creators = creators_by_demon(connection).await?;
+
+    Ok(Demon::all(connection)
+        .await?
+        .into_iter()
+        .map(|demon| {
+            ExportedDemon {
+                creators: creators.remove(&demon.base.id).unwrap_or_default(),
+                effective_requirement: Demon::effective_requirement(demon.requirement, demon.base.position),
+                demon,
+            }
+        })
+        .collect())
+}
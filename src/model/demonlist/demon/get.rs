@@ -3,9 +3,9 @@ use crate::{
     error::PointercrateError,
     model::demonlist::{
         creator::creators_of,
-        demon::{Demon, FullDemon, MinimalDemon},
+        demon::{mirrors_of, tags_of, Demon, FullDemon, MinimalDemon, VerificationPlatform},
         player::DatabasePlayer,
-        record::approved_records_on,
+        record::{approved_records_on, first_victor_on, MinimalRecordP},
     },
     Result,
 };
@@ -16,7 +16,17 @@ impl MinimalDemon {
     pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<MinimalDemon> {
         let row = sqlx::query!(r#"SELECT id, name as "name: String", position FROM demons WHERE id = $1"#, id)
             .fetch_one(connection)
-            .await?;
+            .await
+            .map_err(|err| {
+                match err {
+                    Error::RowNotFound =>
+                        PointercrateError::ModelNotFound {
+                            model: "Demon",
+                            identified_by: id.to_string(),
+                        },
+                    _ => err.into(),
+                }
+            })?;
 
         Ok(MinimalDemon {
             id,
@@ -30,7 +40,7 @@ impl MinimalDemon {
             r#"SELECT id, name as "name: String", position FROM demons WHERE name = cast($1::text as citext)"#, // FIXME(sqlx) once CITEXT is supported
             name.to_string()
         )
-        .fetch(connection);
+        .fetch(&mut *connection);
 
         let mut demon = None;
         let mut further_demons = Vec::new();
@@ -51,14 +61,21 @@ impl MinimalDemon {
             }
         }
 
+        drop(stream);
+
         if further_demons.is_empty() {
             match demon {
                 Some(demon) => Ok(demon),
+                // Fall back to aliases, since those are guaranteed not to collide with actual names
                 None =>
-                    Err(PointercrateError::ModelNotFound {
-                        model: "Demon",
-                        identified_by: name.to_string(),
-                    }),
+                    match MinimalDemon::by_alias(name, connection).await {
+                        Err(PointercrateError::ModelNotFound { .. }) =>
+                            Err(PointercrateError::ModelNotFound {
+                                model: "Demon",
+                                identified_by: name.to_string(),
+                            }),
+                        result => result,
+                    },
             }
         } else {
             further_demons.extend(demon);
@@ -78,16 +95,32 @@ impl FullDemon {
     }
 }
 
+/// Picks the record of the given demon's first victor from the given list of its approved records
+pub(super) async fn first_victor(
+    demon: &MinimalDemon, records: &[MinimalRecordP], connection: &mut PgConnection,
+) -> Result<Option<MinimalRecordP>> {
+    Ok(match first_victor_on(demon, connection).await? {
+        Some(record_id) => records.iter().find(|record| record.id == record_id).cloned(),
+        None => None,
+    })
+}
+
 // FIXME: optimally, we want to only have one of these
 impl Demon {
     async fn upgrade(self, connection: &mut PgConnection) -> Result<FullDemon> {
         let creators = creators_of(&self.base, connection).await?;
         let records = approved_records_on(&self.base, connection).await?;
+        let first_victor = first_victor(&self.base, &records, connection).await?;
+        let mirrors = mirrors_of(&self.base, connection).await?;
+        let tags = tags_of(&self.base, connection).await?;
 
         Ok(FullDemon {
             demon: self,
             creators,
             records,
+            first_victor,
+            mirrors,
+            tags,
         })
     }
 
@@ -124,11 +157,59 @@ impl Demon {
                 }
             })
     }
+
+    /// Gets the ID of the published demon currently at #1, or `None` if the list is empty
+    ///
+    /// Unpublished demons do not count, so the first published demon is at #1 even if an
+    /// unpublished one is placed above it.
+    pub async fn top_id(connection: &mut PgConnection) -> Result<Option<i32>> {
+        Ok(sqlx::query!(r#"SELECT id AS "id!" FROM published_positions WHERE position = 1"#)
+            .fetch_optional(connection)
+            .await?
+            .map(|row| row.id))
+    }
+
+    /// Gets the published demon currently at #1, unless it is the one with the given ID
+    ///
+    /// Meant to be called with the result of an earlier call to [`Demon::top_id`] to detect whether
+    /// some modification of the list put a new demon at the top. A demon that stays at #1 while being
+    /// modified is not considered new.
+    pub async fn new_top(previous_top: Option<i32>, connection: &mut PgConnection) -> Result<Option<Demon>> {
+        match Demon::top_id(connection).await? {
+            Some(id) if Some(id) != previous_top => Ok(Some(Demon::by_id(id, connection).await?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Gets all published demons that no player apart from their verifier has an approved 100%
+    /// record on, ordered by position
+    ///
+    /// Demons on the legacy list (that is, below `extended_list_size`) are only included if
+    /// `include_legacy` is set.
+    pub async fn unbeaten(include_legacy: bool, extended_list_size: i16, connection: &mut PgConnection) -> Result<Vec<Demon>> {
+        Ok(sqlx::query_file_as!(FetchedDemon, "sql/unbeaten_demons.sql", include_legacy, extended_list_size)
+            .fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Gets all published demons, ordered by position
+    pub async fn all(connection: &mut PgConnection) -> Result<Vec<Demon>> {
+        Ok(sqlx::query_file_as!(FetchedDemon, "sql/all_demons.sql")
+            .fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
 }
 
+/// Gets all published demons the given player published
 pub async fn published_by(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
     let mut stream = sqlx::query!(
-        r#"SELECT id, name AS "name: String", position FROM demons WHERE publisher = $1"#,
+        r#"SELECT id, name AS "name: String", position FROM demons WHERE publisher = $1 AND published"#,
         player.id
     )
     .fetch(connection);
@@ -148,9 +229,10 @@ pub async fn published_by(player: &DatabasePlayer, connection: &mut PgConnection
     Ok(demons)
 }
 
+/// Gets all published demons the given player verified
 pub async fn verified_by(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
     let mut stream = sqlx::query!(
-        r#"SELECT id, name as "name: String", position FROM demons WHERE verifier = $1"#,
+        r#"SELECT id, name as "name: String", position FROM demons WHERE verifier = $1 AND published"#,
         player.id
     )
     .fetch(connection);
@@ -176,6 +258,7 @@ struct FetchedDemon {
     position: i16,
     requirement: i16,
     video: Option<String>,
+    verification_video: Option<String>,
     publisher_id: i32,
     publisher_name: String,
     publisher_banned: bool,
@@ -183,6 +266,10 @@ struct FetchedDemon {
     verifier_name: String,
     verifier_banned: bool,
     level_id: Option<i64>,
+    verified_on: String,
+    published: bool,
+    song_name: Option<String>,
+    song_artist: Option<String>,
 }
 
 impl Into<Demon> for FetchedDemon {
@@ -195,6 +282,7 @@ impl Into<Demon> for FetchedDemon {
             },
             requirement: self.requirement,
             video: self.video,
+            verification_video: self.verification_video,
             publisher: DatabasePlayer {
                 id: self.publisher_id,
                 name: CiString(self.publisher_name),
@@ -206,6 +294,67 @@ impl Into<Demon> for FetchedDemon {
                 banned: self.verifier_banned,
             },
             level_id: self.level_id.map(|id| id as u64),
+            verified_on: VerificationPlatform::from_sql(&self.verified_on),
+            published: self.published,
+            song_name: self.song_name,
+            song_artist: self.song_artist,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::model::demonlist::demon::Demon;
+
+    #[actix_rt::test]
+    async fn test_unbeaten() {
+        let mut connection = crate::test::test_setup().await;
+
+        // taraturusus gets a completion only by its verifier, abstract interpretation an external one.
+        // Trichotomy and terminal void already have external completions.
+        sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, 'APPROVED', demons.verifier, submitter_id, \
+             demons.id FROM demons, submitters WHERE demons.name = 'taraturusus'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, 'APPROVED', players.id, submitter_id, demons.id \
+             FROM demons, players, submitters WHERE demons.name = 'abstract interpretation' AND players.name = 'Samifying'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        let unbeaten = Demon::unbeaten(false, 100, &mut connection).await.unwrap();
+        let names: Vec<&str> = unbeaten.iter().map(|demon| demon.base.name.as_str()).collect();
+
+        assert_eq!(names, vec!["taraturusus"]);
+
+        // taraturusus is at #4, so on the legacy list if the list only has 3 demons
+        assert!(Demon::unbeaten(false, 3, &mut connection).await.unwrap().is_empty());
+        assert_eq!(Demon::unbeaten(true, 3, &mut connection).await.unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_new_top() {
+        let mut connection = crate::test::test_setup().await;
+
+        let previous_top = Demon::top_id(&mut connection).await.unwrap();
+        let mut trichotomy = Demon::by_position(2, &mut connection).await.unwrap();
+
+        trichotomy.base.mv(1, &mut connection).await.unwrap();
+
+        let new_top = Demon::new_top(previous_top, &mut connection).await.unwrap().unwrap();
+
+        assert_eq!(new_top.base.name.as_str(), "Trichotomy");
+
+        // modifying the demon at the top without moving it does not make it a new #1 again
+        let previous_top = Demon::top_id(&mut connection).await.unwrap();
+
+        trichotomy.set_requirement(90, &mut connection).await.unwrap();
+
+        assert!(Demon::new_top(previous_top, &mut connection).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Statistics about how long demons have been on the list, derived from their position history
+//! (see the `demon_longevity` SQL function)
+//!
+//! Only time spent at a qualifying position counts, so the counters of demons on the legacy list
+//! are frozen at the point they fell to legacy (and resume should they ever re-enter the list).
+
+use crate::{cistring::CiString, config, model::demonlist::demon::MinimalDemon, Result};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+pub struct Longevity {
+    /// The number of full days spent on the main list, that is, in the top [`config::list_size`]
+    pub days_on_main_list: i32,
+
+    /// The number of full days spent on the list in general, that is, anywhere but on the legacy
+    /// list
+    pub days_on_list: i32,
+
+    /// The best position ever held
+    pub peak_position: i16,
+}
+
+/// An entry of the [longevity ranking](longevity_ranking)
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct RankedLongevity {
+    #[serde(flatten)]
+    pub demon: MinimalDemon,
+
+    #[serde(flatten)]
+    pub longevity: Longevity,
+}
+
+impl MinimalDemon {
+    /// Computes the [`Longevity`] of this demon, or `None` if no position history is available for
+    /// it
+    pub async fn longevity(&self, connection: &mut PgConnection) -> Result<Option<Longevity>> {
+        Ok(sqlx::query!(
+            r#"SELECT days_on_main_list AS "days_on_main_list!", days_on_list AS "days_on_list!", peak_position AS "peak_position!"
+             FROM demon_longevity($1, $2) WHERE id = $3"#,
+            config::list_size(),
+            config::extended_list_size(),
+            self.id
+        )
+        .fetch_optional(connection)
+        .await?
+        .map(|row| {
+            Longevity {
+                days_on_main_list: row.days_on_main_list,
+                days_on_list: row.days_on_list,
+                peak_position: row.peak_position,
+            }
+        }))
+    }
+}
+
+/// Ranks all published demons that ever made it onto the main list by the time they spent there,
+/// longest first. Ties are broken by current position
+pub async fn longevity_ranking(connection: &mut PgConnection) -> Result<Vec<RankedLongevity>> {
+    Ok(sqlx::query!(
+        r#"SELECT demons.id, demons.name::TEXT AS "name!", demons.position AS "position!", days_on_main_list AS "days_on_main_list!",
+         days_on_list AS "days_on_list!", peak_position AS "peak_position!" FROM demon_longevity($1, $2) AS longevity INNER JOIN demons ON
+         demons.id = longevity.id WHERE demons.published AND peak_position <= $1 ORDER BY days_on_main_list DESC, demons.position"#,
+        config::list_size(),
+        config::extended_list_size()
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| {
+        RankedLongevity {
+            demon: MinimalDemon {
+                id: row.id,
+                position: row.position,
+                name: CiString(row.name),
+            },
+            longevity: Longevity {
+                days_on_main_list: row.days_on_main_list,
+                days_on_list: row.days_on_list,
+                peak_position: row.peak_position,
+            },
+        }
+    })
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{longevity_ranking, Longevity};
+    use crate::{cistring::CiStr, model::demonlist::demon::MinimalDemon, state::audit_connection};
+
+    #[actix_rt::test]
+    async fn test_longevity_across_add_and_drop_history() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("taraturusus"), &mut connection).await.unwrap();
+
+        // Fake a history in which the demon was added at #40, dropped to #60 (extended list) after 60
+        // days, fell to legacy after another 60 days, re-entered the main list at #30 for 10 days and
+        // then fell to legacy for good. The modification log stores the position a demon had
+        // _before_ each change.
+        sqlx::query!("UPDATE demons SET position = 150 WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM demon_modifications WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demon_additions SET time = '2018-01-01' WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!(
+            "INSERT INTO demon_modifications (userid, id, position, time) VALUES (0, $1, 40, '2018-03-02'), (0, $1, 60, '2018-05-01'), \
+             (0, $1, 120, '2019-01-01'), (0, $1, 30, '2019-01-11')",
+            demon.id
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // Counters are frozen since the demon is on the legacy list
+        assert_eq!(
+            demon.longevity(&mut connection).await.unwrap(),
+            Some(Longevity {
+                days_on_main_list: 70,
+                days_on_list: 130,
+                peak_position: 30
+            })
+        );
+
+        // A demon still on the list keeps counting
+        let top = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection)
+            .await
+            .unwrap();
+
+        sqlx::query!(
+            "UPDATE demon_additions SET time = (NOW() AT TIME ZONE 'utc') - INTERVAL '10 days' WHERE id = $1",
+            top.id
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            top.longevity(&mut connection).await.unwrap(),
+            Some(Longevity {
+                days_on_main_list: 10,
+                days_on_list: 10,
+                peak_position: 1
+            })
+        );
+
+        let ranking = longevity_ranking(&mut connection).await.unwrap();
+
+        assert_eq!(ranking[0].demon.id, demon.id);
+        assert_eq!(ranking[1].demon.id, top.id);
+    }
+}
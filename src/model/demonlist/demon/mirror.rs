@@ -0,0 +1,205 @@
+//! Mirrors of a demon's verification video
+//!
+//! A demon's primary video is stored in the `video` column of the `demons` table. Mirrors are kept
+//! in a separate, ordered list, which is used as a fallback in case the primary video gets taken
+//! down.
+
+use crate::{error::PointercrateError, model::demonlist::demon::MinimalDemon, Result};
+use log::info;
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Deserialize)]
+pub struct PostMirror {
+    pub video: String,
+}
+
+/// The mirrors of the given demon, in order
+///
+/// Like the primary video, these are hidden if the demon's verifier is link banned.
+pub async fn mirrors_of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query!(
+        r#"SELECT mirrors.video::TEXT AS "video!: String" FROM demon_video_mirrors AS mirrors INNER JOIN demons ON demons.id = mirrors.demon 
+         INNER JOIN players AS verifiers ON verifiers.id = demons.verifier WHERE mirrors.demon = $1 AND verifiers.link_banned IS NOT TRUE ORDER BY 
+         mirrors.position"#,
+        demon.id
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| row.video)
+    .collect())
+}
+
+/// Locks the given demon's row and retrieves all its mirrors, regardless of whether the verifier is
+/// link banned
+async fn lock_mirrors(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<String>> {
+    sqlx::query!("SELECT id FROM demons WHERE id = $1 FOR UPDATE", demon.id)
+        .fetch_one(&mut *connection)
+        .await?;
+
+    Ok(sqlx::query!(
+        r#"SELECT video::TEXT AS "video!: String" FROM demon_video_mirrors WHERE demon = $1 ORDER BY position"#,
+        demon.id
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| row.video)
+    .collect())
+}
+
+/// Validates the given video and adds it to the end of the given demon's mirror list
+///
+/// Returns the updated list of mirrors. Must be run within a transaction!
+pub async fn append_mirror(demon: &MinimalDemon, video: &str, connection: &mut PgConnection) -> Result<Vec<String>> {
+    let video = crate::video::validate(video).map_err(|error| error.in_field("video"))?;
+    let mut mirrors = lock_mirrors(demon, connection).await?;
+
+    check_mirror(demon, &video, &mirrors, connection).await?;
+
+    info!("Adding mirror {} to demon {}", video, demon);
+
+    sqlx::query!(
+        "INSERT INTO demon_video_mirrors (demon, video, position) VALUES ($1, $2::TEXT, $3)",
+        demon.id,
+        video,
+        mirrors.len() as i16 + 1
+    )
+    .execute(connection)
+    .await?;
+
+    mirrors.push(video);
+
+    Ok(mirrors)
+}
+
+/// Replaces the given demon's mirror list with the given videos, in the given order
+///
+/// Must be run within a transaction!
+pub async fn set_mirrors(demon: &MinimalDemon, videos: &[String], connection: &mut PgConnection) -> Result<Vec<String>> {
+    lock_mirrors(demon, connection).await?;
+
+    let mut mirrors = Vec::with_capacity(videos.len());
+
+    for (index, video) in videos.iter().enumerate() {
+        let video = crate::video::validate(video).map_err(|error| error.in_field(format!("mirrors[{}]", index)))?;
+
+        check_mirror(demon, &video, &mirrors, connection).await?;
+
+        mirrors.push(video);
+    }
+
+    info!("Setting mirrors of demon {} to {:?}", demon, mirrors);
+
+    sqlx::query!("DELETE FROM demon_video_mirrors WHERE demon = $1", demon.id)
+        .execute(&mut *connection)
+        .await?;
+
+    for (position, video) in mirrors.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO demon_video_mirrors (demon, video, position) VALUES ($1, $2::TEXT, $3)",
+            demon.id,
+            video,
+            position as i16 + 1
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    Ok(mirrors)
+}
+
+/// Ensures that the given (already validated) video is neither the demon's primary video nor
+/// already contained in the given list of mirrors
+async fn check_mirror(demon: &MinimalDemon, video: &str, mirrors: &[String], connection: &mut PgConnection) -> Result<()> {
+    let primary = sqlx::query!("SELECT video::TEXT FROM demons WHERE id = $1", demon.id)
+        .fetch_one(connection)
+        .await?
+        .video;
+
+    if primary.as_deref() == Some(video) || mirrors.iter().any(|mirror| mirror == video) {
+        return Err(PointercrateError::MirrorExists)
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append_mirror, mirrors_of, set_mirrors};
+    use crate::{cistring::CiStr, error::PointercrateError, model::demonlist::demon::MinimalDemon};
+
+    #[actix_rt::test]
+    async fn test_mirrors_are_validated() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        let mirrors = append_mirror(&demon, "https://youtu.be/dQw4w9WgXcQ", &mut connection).await.unwrap();
+
+        assert_eq!(mirrors, vec!["https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned()]);
+
+        assert_eq!(
+            append_mirror(&demon, "https://www.pornhub.com/view_video.php?viewkey=1", &mut connection).await,
+            Err(PointercrateError::UnsupportedVideoHost.in_field("video"))
+        );
+        assert_eq!(
+            append_mirror(&demon, "https://www.youtube.com/watch?v=dQw4w9WgXcQ", &mut connection).await,
+            Err(PointercrateError::MirrorExists)
+        );
+        assert_eq!(
+            set_mirrors(
+                &demon,
+                &["https://vimeo.com/1".to_owned(), "ftp://vimeo.com/2".to_owned()],
+                &mut connection
+            )
+            .await,
+            Err(PointercrateError::InvalidUrlScheme.in_field("mirrors[1]"))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_mirror_order_is_preserved() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        append_mirror(&demon, "https://vimeo.com/3", &mut connection).await.unwrap();
+        append_mirror(&demon, "https://vimeo.com/1", &mut connection).await.unwrap();
+        append_mirror(&demon, "https://vimeo.com/2", &mut connection).await.unwrap();
+
+        assert_eq!(mirrors_of(&demon, &mut connection).await.unwrap(), vec![
+            "https://vimeo.com/3".to_owned(),
+            "https://vimeo.com/1".to_owned(),
+            "https://vimeo.com/2".to_owned()
+        ]);
+
+        let reordered = vec!["https://vimeo.com/2".to_owned(), "https://vimeo.com/3".to_owned()];
+
+        assert_eq!(set_mirrors(&demon, &reordered, &mut connection).await.unwrap(), reordered);
+        assert_eq!(mirrors_of(&demon, &mut connection).await.unwrap(), reordered);
+    }
+
+    #[actix_rt::test]
+    async fn test_mirrors_of_link_banned_verifier_are_hidden() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        append_mirror(&demon, "https://vimeo.com/1", &mut connection).await.unwrap();
+
+        sqlx::query!("UPDATE players SET link_banned = TRUE FROM demons WHERE demons.verifier = players.id AND demons.id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        assert!(mirrors_of(&demon, &mut connection).await.unwrap().is_empty());
+
+        // The hidden mirrors are still taken into account when adding new ones
+        assert_eq!(
+            append_mirror(&demon, "https://vimeo.com/2", &mut connection).await.unwrap(),
+            vec!["https://vimeo.com/1".to_owned(), "https://vimeo.com/2".to_owned()]
+        );
+    }
+}
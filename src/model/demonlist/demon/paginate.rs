@@ -2,7 +2,7 @@ use crate::{
     cistring::CiString,
     error::PointercrateError,
     model::demonlist::{
-        demon::{Demon, MinimalDemon},
+        demon::{Demon, MinimalDemon, VerificationPlatform},
         player::DatabasePlayer,
     },
     util::non_nullable,
@@ -51,6 +51,10 @@ pub struct DemonIdPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "requirement__lt")]
     requirement_lt: Option<i16>,
+
+    /// Only settable by list moderators, everyone else only ever sees published demons
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub published: Option<bool>,
 }
 
 impl DemonIdPagination {
@@ -88,6 +92,7 @@ impl DemonIdPagination {
             .bind(self.publisher_id)
             .bind(self.publisher_name.as_ref().map(|s| s.as_str()))
             .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(self.published)
             .bind(self.limit.unwrap_or(50) as i32 + 1)
             .fetch(connection);
 
@@ -106,6 +111,7 @@ impl DemonIdPagination {
                 },
                 requirement: row.get("requirement"),
                 video,
+                verification_video: row.get("verification_video"),
                 publisher: DatabasePlayer {
                     id: row.get("publisher_id"),
                     name: CiString(row.get("publisher_name")),
@@ -117,6 +123,10 @@ impl DemonIdPagination {
                     banned: row.get("verifier_banned"),
                 },
                 level_id: row.get::<Option<i64>, _>("level_id").map(|id| id as u64),
+                verified_on: VerificationPlatform::from_sql(row.get("verified_on")),
+                published: row.get("published"),
+                song_name: row.get("song_name"),
+                song_artist: row.get("song_artist"),
             })
         }
 
@@ -163,6 +173,10 @@ pub struct DemonPositionPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "requirement__lt")]
     requirement_lt: Option<i16>,
+
+    /// Only settable by list moderators, everyone else only ever sees published demons
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub published: Option<bool>,
 }
 
 impl DemonPositionPagination {
@@ -200,6 +214,7 @@ impl DemonPositionPagination {
             .bind(self.publisher_id)
             .bind(self.publisher_name.as_ref().map(|s| s.as_str()))
             .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(self.published)
             .bind(self.limit.unwrap_or(50) as i32 + 1)
             .fetch(connection);
 
@@ -218,6 +233,7 @@ impl DemonPositionPagination {
                 },
                 requirement: row.get("requirement"),
                 video,
+                verification_video: row.get("verification_video"),
                 publisher: DatabasePlayer {
                     id: row.get("publisher_id"),
                     name: CiString(row.get("publisher_name")),
@@ -229,6 +245,10 @@ impl DemonPositionPagination {
                     banned: row.get("verifier_banned"),
                 },
                 level_id: row.get::<Option<i64>, _>("level_id").map(|id| id as u64),
+                verified_on: VerificationPlatform::from_sql(row.get("verified_on")),
+                published: row.get("published"),
+                song_name: row.get("song_name"),
+                song_artist: row.get("song_artist"),
             })
         }
 
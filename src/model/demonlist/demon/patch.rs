@@ -1,8 +1,11 @@
-use super::{Demon, FullDemon};
+use super::{audit, get::first_victor, Demon, FullDemon, VerificationPlatform};
 use crate::{
     cistring::CiString,
     error::PointercrateError,
-    model::demonlist::{demon::MinimalDemon, player::DatabasePlayer},
+    model::demonlist::{
+        demon::{DemonAlias, MinimalDemon},
+        player::DatabasePlayer,
+    },
     util::{non_nullable, nullable},
     Result,
 };
@@ -21,6 +24,9 @@ pub struct PatchDemon {
     #[serde(default, deserialize_with = "nullable")]
     pub video: Option<Option<String>>,
 
+    #[serde(default, deserialize_with = "nullable")]
+    pub verification_video: Option<Option<String>>,
+
     #[serde(default, deserialize_with = "non_nullable")]
     pub requirement: Option<i16>,
 
@@ -29,16 +35,34 @@ pub struct PatchDemon {
 
     #[serde(default, deserialize_with = "non_nullable")]
     pub publisher: Option<CiString>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub verified_on: Option<VerificationPlatform>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub published: Option<bool>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    pub song_name: Option<Option<String>>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    pub song_artist: Option<Option<String>>,
 }
 
 impl FullDemon {
     pub async fn apply_patch(mut self, patch: PatchDemon, connection: &mut PgConnection) -> Result<Self> {
         let changes_requirement = patch.requirement.is_some();
+        let changes_verifier = patch.verifier.is_some();
 
         let updated_demon = self.demon.apply_patch(patch, connection).await?;
 
         if changes_requirement {
-            self.records.retain(|record| record.progress >= updated_demon.requirement);
+            self.records.retain(|record| record.progress.percent >= updated_demon.requirement);
+        }
+
+        // Records by the verifier do not count towards the first victory
+        if changes_verifier {
+            self.first_victor = first_victor(&updated_demon.base, &self.records, connection).await?;
         }
 
         Ok(FullDemon {
@@ -53,10 +77,19 @@ impl Demon {
     pub async fn apply_patch(mut self, patch: PatchDemon, connection: &mut PgConnection) -> Result<Self> {
         // duplicate names are OK nowadays
 
+        // (un)publishing moves the demon (see `set_published`), so it needs to happen before any explicit
+        // move, which is validated against the new publication state. Both are logged as audit log
+        // entries of their own
+        if let Some(published) = patch.published {
+            self.set_published(published, connection).await?;
+        }
+
         if let Some(position) = patch.position {
-            self.base.mv(position, connection).await?;
+            self.move_to(position, connection).await?;
         }
 
+        let before = audit::snapshot(&self);
+
         if let Some(name) = patch.name {
             self.base.set_name(name, connection).await?;
         }
@@ -68,13 +101,24 @@ impl Demon {
             }
         }
 
+        if let Some(video) = patch.verification_video {
+            match video {
+                None => self.remove_verification_video(connection).await?,
+                Some(video) => self.set_verification_video(video, connection).await?,
+            }
+        }
+
         if let Some(verifier) = patch.verifier {
+            DatabasePlayer::validate_name(verifier.as_ref()).map_err(|error| error.in_field("verifier"))?;
+
             let player = DatabasePlayer::by_name_or_create(verifier.as_ref(), connection).await?;
 
             self.set_verifier(player, connection).await?;
         }
 
         if let Some(publisher) = patch.publisher {
+            DatabasePlayer::validate_name(publisher.as_ref()).map_err(|error| error.in_field("publisher"))?;
+
             let player = DatabasePlayer::by_name_or_create(publisher.as_ref(), connection).await?;
 
             self.set_publisher(player, connection).await?;
@@ -84,9 +128,35 @@ impl Demon {
             self.set_requirement(requirement, connection).await?;
         }
 
+        if let Some(verified_on) = patch.verified_on {
+            self.set_verified_on(verified_on, connection).await?;
+        }
+
+        if let Some(song_name) = patch.song_name {
+            self.set_song_name(song_name, connection).await?;
+        }
+
+        if let Some(song_artist) = patch.song_artist {
+            self.set_song_artist(song_artist, connection).await?;
+        }
+
+        audit::log_modification(&before, &self, connection).await?;
+
         Ok(self)
     }
 
+    /// Moves this demon to the given position, shifting all demons in between up or down by one
+    /// and recording the move in the audit log
+    ///
+    /// See [`MinimalDemon::mv`] for the valid range of positions. Must be run within a transaction!
+    pub async fn move_to(&mut self, to: i16, connection: &mut PgConnection) -> Result<()> {
+        let before = audit::snapshot(self);
+
+        self.base.mv(to, connection).await?;
+
+        audit::log_modification(&before, self, connection).await
+    }
+
     pub async fn set_verifier(&mut self, verifier: DatabasePlayer, connection: &mut PgConnection) -> Result<()> {
         if verifier.id != self.verifier.id {
             sqlx::query!("UPDATE demons SET verifier = $1 WHERE id = $2", verifier.id, self.base.id)
@@ -112,9 +182,7 @@ impl Demon {
     }
 
     pub async fn set_requirement(&mut self, requirement: i16, connection: &mut PgConnection) -> Result<()> {
-        if requirement < 0 || requirement > 100 {
-            return Err(PointercrateError::InvalidRequirement)
-        }
+        Demon::validate_requirement(requirement)?;
 
         // Delete associated notes
         sqlx::query!("DELETE FROM records WHERE demon = $1 AND progress < $2", self.base.id, requirement)
@@ -131,7 +199,7 @@ impl Demon {
     }
 
     pub async fn set_video(&mut self, video: String, connection: &mut PgConnection) -> Result<()> {
-        let video = crate::video::validate(&video)?;
+        let video = crate::video::validate(&video).map_err(|error| error.in_field("video"))?;
 
         sqlx::query!("UPDATE demons SET video = $1::text WHERE id = $2", video, self.base.id)
             .execute(connection)
@@ -151,11 +219,115 @@ impl Demon {
 
         Ok(())
     }
+
+    pub async fn set_verification_video(&mut self, video: String, connection: &mut PgConnection) -> Result<()> {
+        let video = crate::video::validate(&video).map_err(|error| error.in_field("verification_video"))?;
+
+        sqlx::query!("UPDATE demons SET verification_video = $1::text WHERE id = $2", video, self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.verification_video = Some(video);
+
+        Ok(())
+    }
+
+    pub async fn set_verified_on(&mut self, verified_on: VerificationPlatform, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!(
+            "UPDATE demons SET verified_on = CAST($1::TEXT AS VERIFICATION_PLATFORM) WHERE id = $2",
+            verified_on.to_sql(),
+            self.base.id
+        )
+        .execute(connection)
+        .await?;
+
+        self.verified_on = verified_on;
+
+        Ok(())
+    }
+
+    /// Publishes or unpublishes this demon, recording the change in the audit log
+    ///
+    /// Unpublished demons are always positioned after all published ones (see
+    /// [`Demon::validate_position`]). Publishing a demon therefore makes it the last published
+    /// demon, and unpublishing one moves it to the end of the list. Must be run within a
+    /// transaction!
+    pub async fn set_published(&mut self, published: bool, connection: &mut PgConnection) -> Result<()> {
+        if published == self.published {
+            return Ok(())
+        }
+
+        let before = audit::snapshot(self);
+
+        Demon::lock_positions(connection).await?;
+
+        let to = if published {
+            Demon::max_published_position(connection).await? + 1
+        } else {
+            Demon::max_position(connection).await?
+        };
+
+        self.base.shift_to(to, connection).await?;
+
+        sqlx::query!("UPDATE demons SET published = $1 WHERE id = $2", published, self.base.id)
+            .execute(&mut *connection)
+            .await?;
+
+        self.published = published;
+
+        audit::log_modification(&before, self, connection).await
+    }
+
+    /// Sets the name of this demon's song, removing it if `None` (or blank) is given
+    pub async fn set_song_name(&mut self, song_name: Option<String>, connection: &mut PgConnection) -> Result<()> {
+        let song_name = match song_name {
+            Some(song_name) => Demon::validate_song_metadata(song_name, "song_name")?,
+            None => None,
+        };
+
+        sqlx::query!("UPDATE demons SET song_name = $1 WHERE id = $2", song_name, self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.song_name = song_name;
+
+        Ok(())
+    }
+
+    /// Sets the artist of this demon's song, removing it if `None` (or blank) is given
+    pub async fn set_song_artist(&mut self, song_artist: Option<String>, connection: &mut PgConnection) -> Result<()> {
+        let song_artist = match song_artist {
+            Some(song_artist) => Demon::validate_song_metadata(song_artist, "song_artist")?,
+            None => None,
+        };
+
+        sqlx::query!("UPDATE demons SET song_artist = $1 WHERE id = $2", song_artist, self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.song_artist = song_artist;
+
+        Ok(())
+    }
+
+    pub async fn remove_verification_video(&mut self, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!("UPDATE demons SET verification_video = NULL WHERE id = $1", self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.verification_video = None;
+
+        Ok(())
+    }
 }
 
 impl MinimalDemon {
     pub async fn set_name(&mut self, name: CiString, connection: &mut PgConnection) -> Result<()> {
+        Demon::validate_name(name.as_ref())?;
+
         if self.name != name {
+            DemonAlias::validate_name(name.as_ref(), Some(self.id), &mut *connection).await?;
+
             sqlx::query!("UPDATE demons SET name = $1::text WHERE id = $2", name.to_string(), self.id)
                 .execute(connection)
                 .await?;
@@ -168,21 +340,53 @@ impl MinimalDemon {
 
     /// Moves this demon to the specified position
     ///
-    /// Validates that `to` is `> 0` and less than or equal to the currently highest position on the
-    /// list (to preven "holes")
+    /// Published demons can only be moved in between other published demons, and unpublished ones
+    /// only in between other unpublished ones (see [`Demon::validate_position`]). Validates that
+    /// `to` is `> 0` and at most one greater than the highest position in the demon's part of the
+    /// list. Moving a demon one past the end moves it to the end of its part (to prevent "holes").
     pub async fn mv(&mut self, to: i16, connection: &mut PgConnection) -> Result<()> {
-        let maximal_position = Demon::max_position(connection).await?;
+        Demon::lock_positions(connection).await?;
 
-        if to > maximal_position || to < 1 {
-            return Err(PointercrateError::InvalidPosition { maximal: maximal_position })
-        }
+        let maximal_published_position = Demon::max_published_position(&mut *connection).await?;
+
+        let to = if self.published(&mut *connection).await? {
+            if to > maximal_published_position + 1 || to < 1 {
+                return Err(PointercrateError::InvalidPosition {
+                    maximal: maximal_published_position + 1,
+                }
+                .in_field("position"))
+            }
+
+            to.min(maximal_published_position)
+        } else {
+            let maximal_position = Demon::max_position(&mut *connection).await?;
 
+            if to > maximal_position + 1 || to <= maximal_published_position {
+                return Err(PointercrateError::InvalidUnpublishedPosition {
+                    minimal: maximal_published_position + 1,
+                    maximal: maximal_position + 1,
+                }
+                .in_field("position"))
+            }
+
+            to.min(maximal_position)
+        };
+
+        self.shift_to(to, connection).await
+    }
+
+    /// Moves this demon to the given position without any validation, shifting all demons in
+    /// between up or down by one. Must be run within a transaction, holding the
+    /// [position lock](Demon::lock_positions)!
+    async fn shift_to(&mut self, to: i16, connection: &mut PgConnection) -> Result<()> {
         if to == self.position {
             warn!("No-op move of demon {}", self);
 
             return Ok(())
         }
 
+        let previous_main_list = Demon::main_list_ids(connection).await?;
+
         // FIXME: Temporarily move the demon somewhere else because otherwise the unique constraints
         // complains. I actually dont know why, its DEFERRABLE INITIALLY IMMEDIATE (whatever the
         // fuck that means, it made it work in the python version)
@@ -221,9 +425,11 @@ impl MinimalDemon {
         debug!("Performing actual move to position {}", to);
 
         sqlx::query!("UPDATE demons SET position = $2 WHERE id = $1", self.id, to)
-            .execute(connection)
+            .execute(&mut *connection)
             .await?;
 
+        Demon::grandfather_records(&previous_main_list, connection).await?;
+
         info!("Moved demon {} from {} to {} successfully!", self, self.position, to);
 
         self.position = to;
@@ -236,11 +442,14 @@ impl MinimalDemon {
 mod tests {
     use crate::{
         cistring::{CiStr, CiString},
+        error::PointercrateError,
         model::demonlist::{
-            demon::{Demon, FullDemon, PatchDemon},
+            demon::{audit::entries_for_demon, Demon, FullDemon, PatchDemon},
             player::DatabasePlayer,
         },
+        state::audit_connection,
     };
+    use serde_json::json;
 
     #[actix_rt::test]
     async fn test_change_record_requirement() {
@@ -309,11 +518,170 @@ mod tests {
         let demon = demon.unwrap();
 
         for record in &demon.records {
-            assert_eq!(record.progress, 100);
+            assert_eq!(record.progress.percent, 100);
         }
 
         let demon_reloaded = FullDemon::by_position(1, &mut connection).await.unwrap();
 
         assert_eq!(demon, demon_reloaded);
     }
+
+    #[actix_rt::test]
+    async fn test_patch_position_and_name() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let patch = PatchDemon {
+            position: Some(5),
+            name: Some(CiString("Bloodbath".to_string())),
+            ..Default::default()
+        };
+
+        let demon = Demon::by_position(2, &mut connection).await.unwrap();
+        let demon = demon.apply_patch(patch, &mut connection).await.unwrap();
+
+        assert_eq!(demon.base.position, 4);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["abstract interpretation", "terminal void", "taraturusus", "Bloodbath"]
+        );
+
+        let entries = entries_for_demon(demon.base.id, &mut connection).await.unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].changes["position"], json!({"old": 2, "new": 4}));
+        assert_eq!(entries[1].changes["name"], json!({"old": "Trichotomy", "new": "Bloodbath"}));
+    }
+
+    async fn names_by_position(connection: &mut sqlx::PgConnection) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for position in 1..=4 {
+            names.push(Demon::by_position(position, &mut *connection).await.unwrap().base.name.to_string());
+        }
+
+        names
+    }
+
+    #[actix_rt::test]
+    async fn test_move_demon_up() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let mut demon = Demon::by_position(4, &mut connection).await.unwrap();
+
+        demon.move_to(2, &mut connection).await.unwrap();
+
+        assert_eq!(demon.base.position, 2);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["abstract interpretation", "taraturusus", "Trichotomy", "terminal void"]
+        );
+
+        let entries = entries_for_demon(demon.base.id, &mut connection).await.unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].changes["position"], json!({"old": 4, "new": 2}));
+    }
+
+    #[actix_rt::test]
+    async fn test_move_demon_down() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let mut demon = Demon::by_position(1, &mut connection).await.unwrap();
+
+        demon.move_to(3, &mut connection).await.unwrap();
+
+        assert_eq!(demon.base.position, 3);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["Trichotomy", "terminal void", "abstract interpretation", "taraturusus"]
+        );
+
+        // moving one past the end of the list moves the demon to the end, anything further would
+        // leave a hole
+        demon.move_to(5, &mut connection).await.unwrap();
+
+        assert_eq!(demon.base.position, 4);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["Trichotomy", "terminal void", "taraturusus", "abstract interpretation"]
+        );
+        assert_eq!(demon.move_to(6, &mut connection).await.unwrap_err().field(), Some("position"));
+    }
+
+    #[actix_rt::test]
+    async fn test_move_demon_to_same_position() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let mut demon = Demon::by_position(2, &mut connection).await.unwrap();
+
+        demon.move_to(2, &mut connection).await.unwrap();
+
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["abstract interpretation", "Trichotomy", "terminal void", "taraturusus"]
+        );
+        assert!(entries_for_demon(demon.base.id, &mut connection).await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_unpublished_demons_stay_last() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let mut demon = Demon::by_position(2, &mut connection).await.unwrap();
+
+        // unpublishing moves Trichotomy behind all published demons, so that they keep their public
+        // positions
+        demon.set_published(false, &mut connection).await.unwrap();
+
+        assert_eq!(demon.base.position, 4);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["abstract interpretation", "terminal void", "taraturusus", "Trichotomy"]
+        );
+        assert_eq!(
+            demon.move_to(2, &mut connection).await.unwrap_err(),
+            PointercrateError::InvalidUnpublishedPosition { minimal: 4, maximal: 5 }.in_field("position")
+        );
+
+        // published demons cannot be moved in between unpublished ones either
+        let mut first = Demon::by_position(1, &mut connection).await.unwrap();
+
+        assert_eq!(
+            first.move_to(5, &mut connection).await.unwrap_err(),
+            PointercrateError::InvalidPosition { maximal: 4 }.in_field("position")
+        );
+
+        // publishing makes it the last published demon, from where it can be moved
+        let patch = PatchDemon {
+            published: Some(true),
+            position: Some(2),
+            ..Default::default()
+        };
+        let demon = demon.apply_patch(patch, &mut connection).await.unwrap();
+
+        assert!(demon.published);
+        assert_eq!(demon.base.position, 2);
+        assert_eq!(
+            names_by_position(&mut connection).await,
+            vec!["abstract interpretation", "Trichotomy", "terminal void", "taraturusus"]
+        );
+
+        let entries = entries_for_demon(demon.base.id, &mut connection).await.unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].changes["published"], json!({"old": true, "new": false}));
+        assert_eq!(entries[0].changes["position"], json!({"old": 2, "new": 4}));
+        assert_eq!(entries[1].changes["published"], json!({"old": false, "new": true}));
+        assert_eq!(entries[2].changes["position"], json!({"old": 4, "new": 2}));
+    }
 }
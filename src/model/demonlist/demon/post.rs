@@ -1,8 +1,9 @@
 use crate::{
     cistring::CiString,
+    error::PointercrateError,
     model::demonlist::{
         creator::Creator,
-        demon::{Demon, FullDemon, MinimalDemon},
+        demon::{add_tags, audit, Demon, DemonAlias, FullDemon, MinimalDemon, VerificationPlatform},
         player::DatabasePlayer,
     },
     Result,
@@ -15,11 +16,67 @@ use sqlx::PgConnection;
 pub struct PostDemon {
     name: CiString,
     position: i16,
-    requirement: i16,
+
+    /// Defaults to the [suggested requirement](Demon::suggested_requirement) for the given position
+    #[serde(default)]
+    requirement: Option<i16>,
     verifier: CiString,
     publisher: CiString,
+
+    /// The names of the demon's creators, see [`normalize_creators`] for how these are processed
     creators: Vec<CiString>,
     video: Option<String>,
+
+    /// The video of the verification, if it is not the same as `video`
+    #[serde(default)]
+    verification_video: Option<String>,
+
+    /// Defaults to [`VerificationPlatform::Unknown`]
+    #[serde(default)]
+    verified_on: VerificationPlatform,
+
+    /// Unpublished demons are hidden from the public until patched to be published
+    #[serde(default = "default_published")]
+    published: bool,
+
+    #[serde(default)]
+    song_name: Option<String>,
+
+    #[serde(default)]
+    song_artist: Option<String>,
+
+    /// Tags to categorize the demon by. Normalized to lowercase, duplicates are ignored
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_published() -> bool {
+    true
+}
+
+/// Validates and normalizes the creators list of a new demon
+///
+/// Blank names are rejected, and names that only differ in case or surrounding whitespace are
+/// collapsed into the first of them, since they would refer to the same player object anyway. An
+/// empty list means the publisher created the demon alone.
+fn normalize_creators(creators: Vec<CiString>, publisher: &CiString) -> Result<Vec<CiString>> {
+    let mut normalized: Vec<CiString> = Vec::new();
+
+    for (index, creator) in creators.into_iter().enumerate() {
+        DatabasePlayer::validate_name(creator.as_ref()).map_err(|error| error.in_field(format!("creators[{}]", index)))?;
+
+        let creator = CiString(creator.trim().to_string());
+
+        if !normalized.contains(&creator) {
+            normalized.push(creator);
+        }
+    }
+
+    if normalized.is_empty() {
+        normalized.push(CiString(publisher.trim().to_string()));
+    }
+
+    Ok(normalized)
 }
 
 impl FullDemon {
@@ -27,29 +84,65 @@ impl FullDemon {
     pub async fn create_from(data: PostDemon, connection: &mut PgConnection) -> Result<FullDemon> {
         info!("Creating new demon from {:?}", data);
 
-        Demon::validate_requirement(data.requirement)?;
+        let requirement = data.requirement.unwrap_or_else(|| Demon::suggested_requirement(data.position));
+
+        Demon::validate_name(data.name.as_ref())?;
+        Demon::validate_requirement(requirement)?;
+        DemonAlias::validate_name(data.name.as_ref(), None, &mut *connection).await?;
 
         let video = match data.video {
-            Some(ref video) => Some(crate::video::validate(video)?),
+            Some(ref video) => Some(crate::video::validate(video).map_err(|error| error.in_field("video"))?),
+            None => None,
+        };
+
+        let verification_video = match data.verification_video {
+            Some(ref video) => Some(crate::video::validate(video).map_err(|error| error.in_field("verification_video"))?),
+            None => None,
+        };
+
+        let song_name = match data.song_name {
+            Some(song_name) => Demon::validate_song_metadata(song_name, "song_name")?,
             None => None,
         };
 
-        Demon::validate_position(data.position, connection).await?;
+        let song_artist = match data.song_artist {
+            Some(song_artist) => Demon::validate_song_metadata(song_artist, "song_artist")?,
+            None => None,
+        };
+
+        DatabasePlayer::validate_name(data.verifier.as_ref()).map_err(|error| error.in_field("verifier"))?;
+        DatabasePlayer::validate_name(data.publisher.as_ref()).map_err(|error| error.in_field("publisher"))?;
+
+        let creator_names = normalize_creators(data.creators, &data.publisher)?;
+
+        // Serializes concurrent demon creation, so that the validated position is still valid by the time
+        // we shift the list
+        Demon::lock_positions(connection).await?;
+        Demon::validate_position(data.position, data.published, connection).await?;
 
         let publisher = DatabasePlayer::by_name_or_create(data.publisher.as_ref(), connection).await?;
         let verifier = DatabasePlayer::by_name_or_create(data.verifier.as_ref(), connection).await?;
 
+        let previous_main_list = Demon::main_list_ids(connection).await?;
+
         Demon::shift_down(data.position, connection).await?;
+        Demon::grandfather_records(&previous_main_list, connection).await?;
 
         let id_of_inserted = sqlx::query!(
-            "INSERT INTO demons (name, position, requirement, video, verifier, publisher) VALUES ($1::text,$2,$3,$4::text,$5,$6) \
+            "INSERT INTO demons (name, position, requirement, video, verification_video, verifier, publisher, verified_on, published, \
+             song_name, song_artist) VALUES ($1::text,$2,$3,$4::text,$5::text,$6,$7,CAST($8::TEXT AS VERIFICATION_PLATFORM),$9,$10,$11) \
              RETURNING id",
             data.name.to_string(),
             data.position,
-            data.requirement,
+            requirement,
             video.as_ref(),
+            verification_video.as_ref(),
             verifier.id,
-            publisher.id
+            publisher.id,
+            data.verified_on.to_sql(),
+            data.published,
+            song_name.as_ref(),
+            song_artist.as_ref()
         )
         .fetch_one(&mut *connection)
         .await?
@@ -61,26 +154,469 @@ impl FullDemon {
                 position: data.position,
                 name: data.name,
             },
-            requirement: data.requirement,
+            requirement,
             video,
+            verification_video,
             publisher,
             verifier,
             level_id: None,
+            verified_on: data.verified_on,
+            published: data.published,
+            song_name,
+            song_artist,
         };
 
+        audit::log_addition(&demon, connection).await?;
+
         let mut creators = Vec::new();
 
-        for creator in data.creators {
+        for creator in creator_names {
             let player = DatabasePlayer::by_name_or_create(creator.as_ref(), &mut *connection).await?;
             Creator::insert(&demon.base, &player, connection).await?;
 
             creators.push(player);
         }
 
+        let tags = add_tags(&demon.base, &data.tags, connection).await?;
+
         Ok(FullDemon {
             demon,
             creators,
             records: Vec::new(),
+            first_victor: None,
+            mirrors: Vec::new(),
+            tags,
         })
     }
 }
+
+impl FullDemon {
+    /// Creates all the given demons, in order of their positions
+    ///
+    /// Every demon is created inside its own savepoint, so that a failing demon does not prevent the
+    /// remaining ones from being processed. This allows the caller to report on every demon in the
+    /// batch before deciding whether to commit. Unlike [`FullDemon::create_from`], this rejects
+    /// demons whose name is already taken, either by an existing demon or an earlier demon in the
+    /// batch.
+    ///
+    /// The returned results are in the same order as the given demons. Must be run within a
+    /// transaction!
+    pub async fn create_batch(batch: Vec<PostDemon>, connection: &mut PgConnection) -> Result<Vec<Result<FullDemon>>> {
+        info!("Creating batch of {} demons", batch.len());
+
+        let mut batch = batch.into_iter().enumerate().collect::<Vec<_>>();
+        let mut results = (0..batch.len()).map(|_| None).collect::<Vec<_>>();
+
+        // Inserting in ascending order of position means that later demons never shift earlier ones
+        batch.sort_by_key(|(_, demon)| demon.position);
+
+        for (index, demon) in batch {
+            sqlx::query("SAVEPOINT batch_demon").execute(&mut *connection).await?;
+
+            let result = match sqlx::query!(
+                "SELECT position FROM demons WHERE name = cast($1::text as citext) LIMIT 1",
+                demon.name.to_string()
+            )
+            .fetch_optional(&mut *connection)
+            .await?
+            {
+                Some(row) => Err(PointercrateError::DemonExists { position: row.position }),
+                None => FullDemon::create_from(demon, connection).await,
+            };
+
+            match result {
+                Ok(_) => sqlx::query("RELEASE SAVEPOINT batch_demon").execute(&mut *connection).await?,
+                Err(_) => sqlx::query("ROLLBACK TO SAVEPOINT batch_demon").execute(&mut *connection).await?,
+            };
+
+            results[index] = Some(result);
+        }
+
+        Ok(results.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_creators, PostDemon};
+    use crate::{
+        cistring::CiString,
+        error::PointercrateError,
+        model::demonlist::demon::{Demon, FullDemon, VerificationPlatform},
+        state::audit_connection,
+    };
+    use actix_rt::time::delay_for;
+    use serde_json::json;
+    use sqlx::PgConnection;
+    use std::time::Duration;
+
+    fn post_demon(name: &str, position: i16) -> PostDemon {
+        serde_json::from_value(json!({
+            "name": name,
+            "position": position,
+            "requirement": 50,
+            "verifier": "Riot",
+            "publisher": "Riot",
+            "creators": ["Riot"],
+            "video": null
+        }))
+        .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_valid_batch() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let results = FullDemon::create_batch(vec![post_demon("Sonic Wave", 5), post_demon("Bloodbath", 1)], &mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().demon.base.position, 5);
+        assert_eq!(results[1].as_ref().unwrap().demon.base.position, 1);
+
+        // the demons already on the list were shifted down by one each
+        let trichotomy = Demon::by_position(3, &mut connection).await.unwrap();
+
+        assert_eq!(trichotomy.base.name.as_str(), "Trichotomy");
+        assert_eq!(Demon::by_position(5, &mut connection).await.unwrap().base.name.as_str(), "Sonic Wave");
+        assert_eq!(Demon::by_position(6, &mut connection).await.unwrap().base.name.as_str(), "taraturusus");
+    }
+
+    #[actix_rt::test]
+    async fn test_batch_with_duplicate_name() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let results = FullDemon::create_batch(
+            vec![post_demon("Bloodbath", 1), post_demon("trichotomy", 2), post_demon("Sonic Wave", 3)],
+            &mut connection,
+        )
+        .await
+        .unwrap();
+
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().unwrap_err(), &PointercrateError::DemonExists { position: 3 });
+        assert!(results[2].is_ok());
+
+        // the failed demon was rolled back, the others were not
+        assert_eq!(Demon::by_position(3, &mut connection).await.unwrap().base.name.as_str(), "Sonic Wave");
+        assert_eq!(Demon::by_position(4, &mut connection).await.unwrap().base.name.as_str(), "Trichotomy");
+    }
+
+    #[actix_rt::test]
+    async fn test_omitted_requirement_is_suggested() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let post: PostDemon = serde_json::from_value(json!({
+            "name": "Bloodbath",
+            "position": 2,
+            "verifier": "Riot",
+            "publisher": "Riot",
+            "creators": [],
+            "video": null
+        }))
+        .unwrap();
+
+        let demon = FullDemon::create_from(post, &mut connection).await.unwrap();
+
+        assert_eq!(demon.demon.requirement, Demon::suggested_requirement(2));
+    }
+
+    #[actix_rt::test]
+    async fn test_videos_are_validated_independently() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let post = |video: &str, verification_video: &str| -> PostDemon {
+            serde_json::from_value(json!({
+                "name": "Bloodbath",
+                "position": 1,
+                "verifier": "Riot",
+                "publisher": "Riot",
+                "creators": [],
+                "video": video,
+                "verification_video": verification_video
+            }))
+            .unwrap()
+        };
+
+        let showcase = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        let verification = "https://www.youtube.com/watch?v=oHg5SJYRHA0";
+
+        assert!(FullDemon::create_from(post(showcase, "https://example.com/verification"), &mut connection)
+            .await
+            .is_err());
+        assert!(FullDemon::create_from(post("https://example.com/showcase", verification), &mut connection)
+            .await
+            .is_err());
+
+        let demon = FullDemon::create_from(post(showcase, verification), &mut connection).await.unwrap().demon;
+
+        assert_eq!(demon.video.as_deref(), Some(showcase));
+        assert_eq!(demon.verification_video.as_deref(), Some(verification));
+        assert_eq!(Demon::by_id(demon.base.id, &mut connection).await.unwrap(), demon);
+    }
+
+    #[actix_rt::test]
+    async fn test_verified_on() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let cases = vec![
+            ("PC", VerificationPlatform::Pc),
+            ("MOBILE", VerificationPlatform::Mobile),
+            ("UNKNOWN", VerificationPlatform::Unknown),
+        ];
+
+        for (index, (verified_on, expected)) in cases.into_iter().enumerate() {
+            let post: PostDemon = serde_json::from_value(json!({
+                "name": format!("Bloodbath {}", index),
+                "position": 1,
+                "verifier": "Riot",
+                "publisher": "Riot",
+                "creators": [],
+                "video": null,
+                "verified_on": verified_on
+            }))
+            .unwrap();
+
+            let demon = FullDemon::create_from(post, &mut connection).await.unwrap().demon;
+
+            assert_eq!(demon.verified_on, expected);
+            assert_eq!(Demon::by_id(demon.base.id, &mut connection).await.unwrap(), demon);
+        }
+
+        // Existing clients not knowing about the field get the default
+        let demon = FullDemon::create_from(post_demon("Sonic Wave", 1), &mut connection).await.unwrap().demon;
+
+        assert_eq!(demon.verified_on, VerificationPlatform::Unknown);
+        assert!(serde_json::from_value::<PostDemon>(json!({
+            "name": "Cataclysm",
+            "position": 1,
+            "verifier": "Ggb0y",
+            "publisher": "Ggb0y",
+            "creators": [],
+            "video": null,
+            "verified_on": "TABLET"
+        }))
+        .is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_validation_errors_carry_field() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let cases = vec![
+            (json!({"name": "  "}), "name", PointercrateError::EmptyName),
+            (json!({"position": 0}), "position", PointercrateError::InvalidPosition { maximal: 4 }),
+            (
+                json!({"position": 2, "published": false}),
+                "position",
+                PointercrateError::InvalidUnpublishedPosition { minimal: 5, maximal: 5 },
+            ),
+            (json!({"requirement": 101}), "requirement", PointercrateError::InvalidRequirement),
+            (json!({"video": "ftp://youtube.com"}), "video", PointercrateError::InvalidUrlScheme),
+            (
+                json!({"verification_video": "https://example.com"}),
+                "verification_video",
+                PointercrateError::UnsupportedVideoHost,
+            ),
+            (json!({"verifier": ""}), "verifier", PointercrateError::EmptyName),
+            (json!({"creators": ["Riot", "Bo", " "]}), "creators[2]", PointercrateError::EmptyName),
+        ];
+
+        for (overrides, field, error) in cases {
+            let mut post = json!({
+                "name": "Bloodbath",
+                "position": 1,
+                "requirement": 50,
+                "verifier": "Riot",
+                "publisher": "Riot",
+                "creators": [],
+                "video": null
+            });
+
+            for (key, value) in overrides.as_object().unwrap() {
+                post[key] = value.clone();
+            }
+
+            let result = FullDemon::create_from(serde_json::from_value(post).unwrap(), &mut connection).await;
+            let result_error = result.unwrap_err();
+
+            assert_eq!(result_error.field(), Some(field));
+            assert_eq!(result_error.error_code(), error.error_code());
+            assert_eq!(result_error.without_field(), error);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_unpublished_demon_added_last() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let post = json!({
+            "name": "Bloodbath",
+            "position": 5,
+            "verifier": "Riot",
+            "publisher": "Riot",
+            "creators": [],
+            "video": null,
+            "published": false
+        });
+        let demon = FullDemon::create_from(serde_json::from_value(post).unwrap(), &mut connection).await.unwrap();
+
+        assert_eq!(demon.demon.base.position, 5);
+        assert!(!demon.demon.published);
+
+        // published demons cannot be added behind it
+        let result = FullDemon::create_from(post_demon("Sonic Wave", 5), &mut connection).await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            PointercrateError::InvalidPosition { maximal: 4 }.in_field("position")
+        );
+    }
+
+    fn names(names: &[&str]) -> Vec<CiString> {
+        names.iter().map(|name| CiString(name.to_string())).collect()
+    }
+
+    #[test]
+    fn test_creators_are_deduplicated() {
+        let creators = normalize_creators(names(&["Riot", "Bo", "riot ", "BO", "Zafkiel"]), &CiString("Riot".to_string())).unwrap();
+
+        assert_eq!(creators, names(&["Riot", "Bo", "Zafkiel"]));
+    }
+
+    #[test]
+    fn test_publisher_is_implicit_creator() {
+        assert_eq!(normalize_creators(Vec::new(), &CiString(" Riot".to_string())).unwrap(), names(&["Riot"]));
+    }
+
+    #[test]
+    fn test_blank_creator_is_rejected() {
+        let error = normalize_creators(names(&["Riot", "\t"]), &CiString("Riot".to_string())).unwrap_err();
+
+        assert_eq!(error, PointercrateError::EmptyName.in_field("creators[1]"));
+    }
+
+    #[actix_rt::test]
+    async fn test_created_demon_has_normalized_creators() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let post: PostDemon = serde_json::from_value(json!({
+            "name": "Bloodbath",
+            "position": 1,
+            "verifier": "Riot",
+            "publisher": "Riot",
+            "creators": ["Riot", "Bo", "RIOT"],
+            "video": null
+        }))
+        .unwrap();
+
+        let demon = FullDemon::create_from(post, &mut connection).await.unwrap();
+        let creators: Vec<&str> = demon.creators.iter().map(|creator| creator.name.as_str()).collect();
+
+        assert_eq!(creators, vec!["Riot", "Bo"]);
+
+        let post: PostDemon = serde_json::from_value(json!({
+            "name": "Sonic Wave",
+            "position": 1,
+            "verifier": "Cyclic",
+            "publisher": "Riot",
+            "creators": [],
+            "video": null
+        }))
+        .unwrap();
+
+        let demon = FullDemon::create_from(post, &mut connection).await.unwrap();
+
+        assert_eq!(demon.creators, vec![demon.demon.publisher.clone()]);
+    }
+
+    /// Removes everything [`test_concurrent_creation_is_serialized`] commits
+    async fn remove_committed_demons(connection: &mut PgConnection) {
+        audit_connection(connection, 0).await.unwrap();
+
+        sqlx::query!("DELETE FROM creators WHERE demon IN (SELECT id FROM demons WHERE name IN ('Bloodbath', 'Sonic Wave', 'Tartarus'))")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM demons WHERE name IN ('Bloodbath', 'Sonic Wave', 'Tartarus')")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM players WHERE name = 'Riot'")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_concurrent_creation_is_serialized() {
+        // Each transaction only sees the other's demon once it is committed, so no other test may run
+        // in the meantime
+        let mut database = crate::test::lock_database().await;
+        let mut first = crate::test::test_connection().await;
+        let mut second = crate::test::test_connection().await;
+
+        // in case a previous run failed before cleaning up
+        remove_committed_demons(&mut database).await;
+
+        // the test database is empty outside of test transactions, but demons can only be added at
+        // positions already taken
+        sqlx::query!(
+            "WITH riot AS (INSERT INTO players (name) VALUES ('Riot') RETURNING id) INSERT INTO demons (name, position, requirement, \
+             verifier, publisher) SELECT 'Tartarus', 1, 50, id, id FROM riot"
+        )
+        .execute(&mut database)
+        .await
+        .unwrap();
+
+        for connection in vec![&mut first, &mut second] {
+            audit_connection(connection, 0).await.unwrap();
+            sqlx::query!("BEGIN TRANSACTION").execute(&mut *connection).await.unwrap();
+        }
+
+        FullDemon::create_from(post_demon("Bloodbath", 1), &mut first).await.unwrap();
+
+        // The first transaction now holds the position lock, so the second one may only validate its
+        // position and shift the list once the first demon is committed
+        let (_, created) = futures::join!(
+            async {
+                delay_for(Duration::from_millis(250)).await;
+
+                sqlx::query!("COMMIT").execute(&mut first).await.unwrap();
+            },
+            FullDemon::create_from(post_demon("Sonic Wave", 1), &mut second)
+        );
+
+        let positions = sqlx::query!(r#"SELECT name::TEXT AS "name!", position FROM demons ORDER BY position"#)
+            .fetch_all(&mut second)
+            .await
+            .map(|rows| rows.into_iter().map(|row| (row.name, row.position)).collect::<Vec<_>>());
+
+        sqlx::query!("ROLLBACK").execute(&mut second).await.unwrap();
+        remove_committed_demons(&mut database).await;
+
+        assert_eq!(created.unwrap().demon.base.position, 1);
+        assert_eq!(positions.unwrap(), vec![
+            ("Sonic Wave".to_string(), 1),
+            ("Bloodbath".to_string(), 2),
+            ("Tartarus".to_string(), 3)
+        ]);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{cistring::CiString, config, model::demonlist::demon::VerificationPlatform, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+#[derive(Debug, Deserialize)]
+pub struct DemonSearch {
+    #[serde(default)]
+    pub q: Option<CiString>,
+
+    /// Only return demons with the given tag
+    #[serde(default)]
+    pub tag: Option<String>,
+
+    /// Only return demons verified on the given platform
+    #[serde(default)]
+    pub verified_on: Option<VerificationPlatform>,
+}
+
+/// A single match of a [`DemonSearch`], containing the same information as the demonlist overview
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct SearchResult {
+    pub id: i32,
+    pub position: i16,
+    pub name: String,
+    pub publisher: String,
+    pub video: Option<String>,
+
+    /// Whether the demon is on the legacy list
+    pub legacy: bool,
+}
+
+impl DemonSearch {
+    /// Searches demon names, aliases, publisher names and song names and artists for the given
+    /// query, case-insensitively.
+    /// If a tag or platform is given, only demons with that tag or verified on that platform are
+    /// considered (and the query may be omitted).
+    ///
+    /// Unpublished demons are never returned. Returns at most [`config::search_limit`] results,
+    /// ordered by position
+    pub async fn search(&self, connection: &mut PgConnection) -> Result<Vec<SearchResult>> {
+        let query = self.q.as_ref().map(|q| q.trim()).unwrap_or("");
+        let tag = self.tag.as_ref().map(|tag| tag.trim().to_lowercase());
+
+        if query.is_empty() && tag.is_none() && self.verified_on.is_none() {
+            return Ok(Vec::new())
+        }
+
+        let rows = sqlx::query!(
+            r#"SELECT demons.id, position AS "position!", demons.name AS "name: String", CASE WHEN verifiers.link_banned THEN NULL ELSE video::TEXT END,
+             players.name AS "publisher: String" FROM demons INNER JOIN players ON demons.publisher = players.id INNER JOIN players AS verifiers ON
+             demons.verifier = verifiers.id WHERE position IS NOT NULL AND published AND (LOWER(demons.name::TEXT) LIKE $1 OR LOWER(players.name::TEXT) LIKE $1 OR
+             LOWER(demons.song_name) LIKE $1 OR LOWER(demons.song_artist) LIKE $1 OR EXISTS (SELECT FROM demon_aliases WHERE demon = demons.id AND
+             LOWER(alias::TEXT) LIKE $1)) AND ($3::TEXT IS NULL OR EXISTS (SELECT FROM demon_tags WHERE demon = demons.id AND tag = $3)) AND
+             ($4::TEXT IS NULL OR demons.verified_on = CAST($4::TEXT AS VERIFICATION_PLATFORM)) ORDER BY position LIMIT $2"#,
+            like_pattern(query),
+            config::search_limit(),
+            tag,
+            self.verified_on.map(VerificationPlatform::to_sql)
+        )
+        .fetch_all(connection)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                SearchResult {
+                    id: row.id,
+                    position: row.position,
+                    name: row.name,
+                    publisher: row.publisher,
+                    video: row.video,
+                    legacy: row.position > config::extended_list_size(),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Turns the given search query into a `LIKE` pattern matching any string containing it
+fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{like_pattern, DemonSearch, SearchResult};
+    use crate::{
+        cistring::{CiStr, CiString},
+        model::demonlist::demon::{add_tags, MinimalDemon, VerificationPlatform},
+    };
+
+    fn search(query: &str) -> DemonSearch {
+        DemonSearch {
+            q: Some(CiString(query.to_string())),
+            tag: None,
+            verified_on: None,
+        }
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("Void"), "%void%");
+        assert_eq!(like_pattern("100%_"), "%100\\%\\_%");
+    }
+
+    #[actix_rt::test]
+    async fn test_search_partial_name() {
+        let mut connection = crate::test::test_setup().await;
+
+        let results = search("RMINAL vo").search(&mut connection).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "terminal void");
+        assert_eq!(results[0].position, 3);
+        assert!(!results[0].legacy);
+    }
+
+    #[actix_rt::test]
+    async fn test_search_by_publisher() {
+        let mut connection = crate::test::test_setup().await;
+
+        let results = search("stardust").search(&mut connection).await.unwrap();
+        let names: Vec<_> = results.iter().map(|result| result.name.as_str()).collect();
+
+        assert_eq!(names, vec!["abstract interpretation", "Trichotomy"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_search_by_song() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE demons SET song_name = 'Sidereal Time', song_artist = 'Waterflame' WHERE name = 'Trichotomy'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demons SET song_name = 'Final Battle', song_artist = 'Waterflame' WHERE name = 'terminal void'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let results = search("sidereal").search(&mut connection).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Trichotomy");
+
+        let results = search("WATERFLAME").search(&mut connection).await.unwrap();
+        let names: Vec<_> = results.iter().map(|result| result.name.as_str()).collect();
+
+        assert_eq!(names, vec!["Trichotomy", "terminal void"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_search_includes_legacy() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE demons SET position = 500 WHERE name = 'taraturusus'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let results = search("taratur").search(&mut connection).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].position, 500);
+        assert!(results[0].legacy);
+    }
+
+    #[actix_rt::test]
+    async fn test_search_by_tag() {
+        let mut connection = crate::test::test_setup().await;
+
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+        let terminal_void = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+
+        add_tags(&trichotomy, &["memory".to_owned()], &mut connection).await.unwrap();
+        add_tags(&terminal_void, &["memory".to_owned(), "wave-heavy".to_owned()], &mut connection)
+            .await
+            .unwrap();
+
+        let by_tag = |q: Option<&str>, tag: &str| {
+            DemonSearch {
+                q: q.map(|q| CiString(q.to_string())),
+                tag: Some(tag.to_string()),
+                verified_on: None,
+            }
+        };
+
+        let results = by_tag(None, "Memory").search(&mut connection).await.unwrap();
+        let names: Vec<_> = results.iter().map(|result| result.name.as_str()).collect();
+
+        assert_eq!(names, vec!["Trichotomy", "terminal void"]);
+
+        let results = by_tag(Some("void"), "memory").search(&mut connection).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "terminal void");
+
+        assert!(by_tag(None, "low-fps").search(&mut connection).await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_search_by_verification_platform() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE demons SET verified_on = 'MOBILE' WHERE name = 'Trichotomy' OR name = 'terminal void'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demons SET verified_on = 'PC' WHERE name = 'abstract interpretation'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let by_platform = |q: Option<&str>, verified_on: VerificationPlatform| {
+            DemonSearch {
+                q: q.map(|q| CiString(q.to_string())),
+                tag: None,
+                verified_on: Some(verified_on),
+            }
+        };
+        let names = |results: Vec<SearchResult>| results.into_iter().map(|result| result.name).collect::<Vec<_>>();
+
+        let results = by_platform(None, VerificationPlatform::Mobile).search(&mut connection).await.unwrap();
+
+        assert_eq!(names(results), vec!["Trichotomy", "terminal void"]);
+
+        let results = by_platform(None, VerificationPlatform::Pc).search(&mut connection).await.unwrap();
+
+        assert_eq!(names(results), vec!["abstract interpretation"]);
+
+        // Untouched demons keep the default from the migration
+        let results = by_platform(None, VerificationPlatform::Unknown).search(&mut connection).await.unwrap();
+
+        assert_eq!(names(results), vec!["taraturusus"]);
+
+        let results = by_platform(Some("stardust"), VerificationPlatform::Mobile)
+            .search(&mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(names(results), vec!["Trichotomy"]);
+
+        let search: DemonSearch = serde_urlencoded::from_str("verified_on=MOBILE").unwrap();
+
+        assert_eq!(search.verified_on, Some(VerificationPlatform::Mobile));
+    }
+}
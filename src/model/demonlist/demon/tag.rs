@@ -0,0 +1,90 @@
+//! Tags categorizing demons beyond their position (e.g. "memory", "wave-heavy" or "low-fps")
+//!
+//! Tags are free-form, but always stored in lowercase, so that different spellings of the same tag
+//! do not show up as distinct tags.
+
+use crate::{error::PointercrateError, model::demonlist::demon::MinimalDemon, Result};
+use log::info;
+use sqlx::PgConnection;
+
+pub async fn tags_of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query!(r#"SELECT tag::TEXT AS "tag!: String" FROM demon_tags WHERE demon = $1 ORDER BY tag"#, demon.id)
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|row| row.tag)
+        .collect())
+}
+
+/// Normalizes the given tags and adds them to the given demon, ignoring tags the demon already has
+///
+/// Returns the updated list of tags
+pub async fn add_tags(demon: &MinimalDemon, tags: &[String], connection: &mut PgConnection) -> Result<Vec<String>> {
+    let tags = normalize_tags(tags)?;
+
+    info!("Adding tags {:?} to demon {}", tags, demon);
+
+    for tag in &tags {
+        sqlx::query!(
+            "INSERT INTO demon_tags (demon, tag) VALUES ($1, $2::TEXT) ON CONFLICT DO NOTHING",
+            demon.id,
+            tag
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    tags_of(demon, connection).await
+}
+
+/// Trims and lowercases the given tags, removing duplicates
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+
+        if tag.is_empty() || tag.chars().count() > 32 {
+            return Err(PointercrateError::InvalidTag)
+        }
+
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_tags, normalize_tags, tags_of};
+    use crate::{cistring::CiStr, error::PointercrateError, model::demonlist::demon::MinimalDemon};
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![" Memory".to_owned(), "wave-heavy".to_owned(), "MEMORY".to_owned()];
+
+        assert_eq!(normalize_tags(&tags), Ok(vec!["memory".to_owned(), "wave-heavy".to_owned()]));
+        assert_eq!(normalize_tags(&["  ".to_owned()]), Err(PointercrateError::InvalidTag));
+        assert_eq!(normalize_tags(&["a".repeat(33)]), Err(PointercrateError::InvalidTag));
+    }
+
+    #[actix_rt::test]
+    async fn test_add_tags_deduplicates() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        add_tags(&demon, &["Memory".to_owned(), "memory".to_owned()], &mut connection)
+            .await
+            .unwrap();
+
+        let tags = add_tags(&demon, &["low-fps".to_owned(), "MEMORY".to_owned()], &mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(tags, vec!["low-fps".to_owned(), "memory".to_owned()]);
+        assert_eq!(tags_of(&demon, &mut connection).await.unwrap(), tags);
+    }
+}
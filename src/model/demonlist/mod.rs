@@ -1,5 +1,15 @@
+use crate::Result;
+use chrono::NaiveDateTime;
+use sqlx::PgConnection;
+
 pub mod creator;
 pub mod demon;
 pub mod player;
 pub mod record;
 pub mod submitter;
+
+/// Gets the point in time at which anything on the demonlist (demons, records, players or video
+/// mirrors) was last modified
+pub async fn last_modified(connection: &mut PgConnection) -> Result<NaiveDateTime> {
+    Ok(sqlx::query!("SELECT time FROM list_last_modified").fetch_one(connection).await?.time)
+}
@@ -1,7 +1,20 @@
 pub use self::{
-    paginate::{PlayerPagination, RankingPagination},
+    autocomplete::PlayerAutocomplete,
+    claim::{require_patch_permissions, ClaimStatus, PatchClaim, PlayerClaim},
+    compare::{PlayerComparison, PlayerStats, StatsDiff},
+    duplicates::{cluster_duplicates, merge_duplicates, normalize_name, DuplicateCluster},
+    hardest::{hardest_demon, HardestDemonCache},
+    improved::{most_improved, ImprovedPlayer},
+    merge::{former_names, merge_players},
+    neighbors::{RankNeighbor, RankNeighbors},
+    paginate::{PlayerPagination, RankingPagination, RankingSort},
     patch::PatchPlayer,
+    progress::progress_of,
+    records::{PlayerRecord, PlayerRecordPagination},
+    suggestion::{NationalitySuggestion, NoSuggestion},
 };
+#[cfg(feature = "graphql")]
+pub use self::get::nationalities_of;
 use crate::{
     cistring::CiString,
     model::{
@@ -10,14 +23,26 @@ use crate::{
     },
     Result,
 };
+use chrono::NaiveDateTime;
 use derive_more::Display;
 use serde::Serialize;
 use sqlx::PgConnection;
 use std::hash::{Hash, Hasher};
 
+mod autocomplete;
+mod claim;
+mod compare;
+mod duplicates;
 mod get;
+mod hardest;
+mod improved;
+mod merge;
+mod neighbors;
 mod paginate;
 mod patch;
+mod progress;
+mod records;
+mod suggestion;
 
 #[derive(Debug, Hash, Eq, PartialEq, Serialize, Display, Clone)]
 #[display(fmt = "{} (ID: {})", name, id)]
@@ -36,6 +61,9 @@ pub struct FullPlayer {
     pub created: Vec<MinimalDemon>,
     pub verified: Vec<MinimalDemon>,
     pub published: Vec<MinimalDemon>,
+
+    /// See [`DatabasePlayer::total_attempts`]
+    pub total_attempts: Option<i64>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Display)]
@@ -46,6 +74,13 @@ pub struct RankedPlayer {
     pub rank: i64,
     pub score: f64,
     pub nationality: Option<Nationality>,
+
+    /// The time the player's most recent record was approved. `None` if they have no approved
+    /// records (or only ones older than the audit log)
+    pub last_record_at: Option<NaiveDateTime>,
+
+    /// The sum of attempts logged for the player's approved records, `None` if they did not log any
+    pub total_attempts: Option<i64>,
     #[serde(skip)]
     pub index: i64,
 }
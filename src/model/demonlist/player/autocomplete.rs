@@ -0,0 +1,140 @@
+//! Player name suggestions for forms asking for a player, e.g. a demon's publisher or verifier
+
+use crate::{
+    cistring::{CiStr, CiString},
+    config,
+    model::demonlist::player::DatabasePlayer,
+    Result,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Deserialize)]
+pub struct PlayerAutocomplete {
+    #[serde(default)]
+    pub q: Option<CiString>,
+}
+
+impl PlayerAutocomplete {
+    pub async fn suggestions(&self, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
+        let prefix = self.q.as_ref().map(|q| q.trim()).unwrap_or("");
+
+        DatabasePlayer::autocomplete(CiStr::from_str(prefix), config::autocomplete_limit(), connection).await
+    }
+}
+
+impl DatabasePlayer {
+    /// Gets at most `limit` players whose name starts with the given prefix, case-insensitively
+    ///
+    /// A player whose name matches the prefix exactly always comes first. All others are ordered
+    /// by their number of approved records, so that the players most likely meant show up on top.
+    pub async fn autocomplete(prefix: &CiStr, limit: i64, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
+        if prefix.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        let mut stream = sqlx::query!(
+            r#"SELECT id, name AS "name: String", banned FROM players WHERE LOWER(name::TEXT) LIKE $1 ORDER BY LOWER(name::TEXT) = $2 DESC,
+             (SELECT COUNT(*) FROM records WHERE records.player = players.id AND status_ = 'APPROVED') DESC, name LIMIT $3"#,
+            prefix_pattern(prefix),
+            prefix.to_lowercase(),
+            limit
+        )
+        .fetch(connection);
+
+        let mut players = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            players.push(DatabasePlayer {
+                id: row.id,
+                name: CiString(row.name),
+                banned: row.banned,
+            })
+        }
+
+        Ok(players)
+    }
+}
+
+/// Turns the given prefix into a `LIKE` pattern matching any string starting with it
+fn prefix_pattern(prefix: &str) -> String {
+    let escaped = prefix
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prefix_pattern;
+    use crate::{cistring::CiStr, model::demonlist::player::DatabasePlayer};
+
+    #[test]
+    fn test_prefix_pattern() {
+        assert_eq!(prefix_pattern("Su"), "su%");
+        assert_eq!(prefix_pattern("_x%"), "\\_x\\%%");
+    }
+
+    #[actix_rt::test]
+    async fn test_autocomplete_ranked_by_activity() {
+        let mut connection = crate::test::test_setup().await;
+
+        for name in &["Sunix", "Sunrise", "Subwoofer", "Jesus"] {
+            sqlx::query!("INSERT INTO players (name) VALUES ($1::TEXT)", name.to_string())
+                .execute(&mut connection)
+                .await
+                .unwrap();
+        }
+
+        // Sunrise has two approved records, Subwoofer one, and Sunix only a submission, which
+        // does not count
+        for (player, demon, status) in &[
+            ("Sunrise", "Trichotomy", "APPROVED"),
+            ("Sunrise", "terminal void", "APPROVED"),
+            ("Subwoofer", "Trichotomy", "APPROVED"),
+            ("Sunix", "Trichotomy", "SUBMITTED"),
+        ] {
+            sqlx::query!(
+                "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, CAST($3::TEXT AS record_status), \
+                 players.id, submitter_id, demons.id FROM players, demons, submitters WHERE players.name = $1::TEXT AND demons.name = \
+                 $2::TEXT LIMIT 1",
+                player.to_string(),
+                demon.to_string(),
+                status.to_string()
+            )
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        }
+
+        let names = |players: Vec<DatabasePlayer>| players.into_iter().map(|player| player.name.0).collect::<Vec<_>>();
+
+        let suggestions = DatabasePlayer::autocomplete(CiStr::from_str("Su"), 10, &mut connection).await.unwrap();
+
+        assert_eq!(names(suggestions), vec!["Sunrise", "Subwoofer", "Sunix"]);
+
+        let suggestions = DatabasePlayer::autocomplete(CiStr::from_str("su"), 2, &mut connection).await.unwrap();
+
+        assert_eq!(names(suggestions), vec!["Sunrise", "Subwoofer"]);
+
+        // An exact match trumps activity
+        sqlx::query!("INSERT INTO players (name) VALUES ('Su')")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let suggestions = DatabasePlayer::autocomplete(CiStr::from_str("Su"), 10, &mut connection).await.unwrap();
+
+        assert_eq!(names(suggestions), vec!["Su", "Sunrise", "Subwoofer", "Sunix"]);
+        assert!(DatabasePlayer::autocomplete(CiStr::from_str(""), 10, &mut connection)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    error::PointercrateError,
+    model::{
+        demonlist::player::{DatabasePlayer, PatchPlayer},
+        user::User,
+    },
+    permissions::Permissions,
+    util::non_nullable,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgConnection};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClaimStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ClaimStatus {
+    fn to_sql(self) -> &'static str {
+        match self {
+            ClaimStatus::Pending => "PENDING",
+            ClaimStatus::Approved => "APPROVED",
+            ClaimStatus::Rejected => "REJECTED",
+        }
+    }
+
+    fn from_sql(sql: &str) -> Self {
+        match sql {
+            "PENDING" => ClaimStatus::Pending,
+            "APPROVED" => ClaimStatus::Approved,
+            "REJECTED" => ClaimStatus::Rejected,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A user's claim to be a specific player
+///
+/// Once a member of the list team approved the claim, the user can change the player's nationality
+/// and subdivision themselves. Each player can only be claimed by a single user at a time.
+#[derive(Debug, Serialize, Hash, PartialEq, Eq)]
+pub struct PlayerClaim {
+    pub id: i32,
+
+    /// The ID of the user claiming the player
+    pub user: i32,
+    pub player: DatabasePlayer,
+    pub status: ClaimStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchClaim {
+    #[serde(default, deserialize_with = "non_nullable")]
+    status: Option<ClaimStatus>,
+}
+
+impl PlayerClaim {
+    /// Creates a new, pending claim of the given user on the given player
+    ///
+    /// Fails with [`PointercrateError::ClaimExists`] if the player has already been claimed (by any
+    /// user) and that claim has not been rejected.
+    pub async fn create(user: &User, player: DatabasePlayer, connection: &mut PgConnection) -> Result<PlayerClaim> {
+        // Relying on the unique index instead of checking for existing claims beforehand, as two
+        // concurrent claims could otherwise both pass the check
+        let id = sqlx::query!(
+            "INSERT INTO player_claims (member_id, player_id) VALUES ($1, $2) ON CONFLICT (player_id) WHERE status <> 'REJECTED' DO \
+             NOTHING RETURNING id",
+            user.id,
+            player.id
+        )
+        .fetch_optional(connection)
+        .await?
+        .ok_or(PointercrateError::ClaimExists)?
+        .id;
+
+        Ok(PlayerClaim {
+            id,
+            user: user.id,
+            player,
+            status: ClaimStatus::Pending,
+        })
+    }
+
+    /// Gets the claim on the given player that has not been rejected
+    pub async fn active_on(player: DatabasePlayer, connection: &mut PgConnection) -> Result<PlayerClaim> {
+        let result = sqlx::query!(
+            r#"SELECT id, member_id, status::TEXT AS "status!" FROM player_claims WHERE player_id = $1 AND status <> 'REJECTED'"#,
+            player.id
+        )
+        .fetch_one(connection)
+        .await;
+
+        match result {
+            Ok(row) =>
+                Ok(PlayerClaim {
+                    id: row.id,
+                    user: row.member_id,
+                    player,
+                    status: ClaimStatus::from_sql(&row.status),
+                }),
+            Err(Error::RowNotFound) =>
+                Err(PointercrateError::ModelNotFound {
+                    model: "PlayerClaim",
+                    identified_by: player.id.to_string(),
+                }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn apply_patch(mut self, patch: PatchClaim, connection: &mut PgConnection) -> Result<Self> {
+        if let Some(status) = patch.status {
+            sqlx::query!(
+                "UPDATE player_claims SET status = CAST($1::TEXT AS CLAIM_STATUS) WHERE id = $2",
+                status.to_sql(),
+                self.id
+            )
+            .execute(connection)
+            .await?;
+
+            self.status = status;
+        }
+
+        Ok(self)
+    }
+}
+
+/// Checks whether the given user is allowed to apply the given patch to the given player
+///
+/// List moderators can modify any player. Other users can only change the nationality and
+/// subdivision of players they have an approved claim on.
+pub async fn require_patch_permissions(
+    user: &User, player: &DatabasePlayer, patch: &PatchPlayer, connection: &mut PgConnection,
+) -> Result<()> {
+    if user.has_permission(Permissions::ListModerator) {
+        return Ok(())
+    }
+
+    if patch.claimant_editable() {
+        let approved = sqlx::query!(
+            r#"SELECT EXISTS (SELECT 1 FROM player_claims WHERE player_id = $1 AND member_id = $2 AND status = 'APPROVED') AS "approved!""#,
+            player.id,
+            user.id
+        )
+        .fetch_one(connection)
+        .await?
+        .approved;
+
+        if approved {
+            return Ok(())
+        }
+    }
+
+    Err(PointercrateError::MissingPermissions {
+        required: Permissions::ListModerator,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{require_patch_permissions, ClaimStatus, PatchClaim, PlayerClaim};
+    use crate::{
+        cistring::CiStr,
+        error::PointercrateError,
+        model::{
+            demonlist::player::{DatabasePlayer, PatchPlayer},
+            user::User,
+        },
+    };
+    use sqlx::PgConnection;
+
+    async fn second_user(connection: &mut PgConnection) -> User {
+        let id = sqlx::query!("INSERT INTO members (name, password_hash) VALUES ('claimant', 'not a real hash') RETURNING member_id")
+            .fetch_one(&mut *connection)
+            .await
+            .unwrap()
+            .member_id;
+
+        User::by_id(id, connection).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_claim_player() {
+        let mut connection = crate::test::test_setup().await;
+
+        let user = User::by_name("stadust_existing", &mut connection).await.unwrap();
+        let player = DatabasePlayer::by_name(CiStr::from_str("Aeon Air"), &mut connection).await.unwrap();
+
+        let claim = PlayerClaim::create(&user, player.clone(), &mut connection).await.unwrap();
+
+        assert_eq!(claim.user, user.id);
+        assert_eq!(claim.status, ClaimStatus::Pending);
+        assert_eq!(PlayerClaim::active_on(player, &mut connection).await.unwrap(), claim);
+    }
+
+    #[actix_rt::test]
+    async fn test_duplicate_claim() {
+        let mut connection = crate::test::test_setup().await;
+
+        let user = User::by_name("stadust_existing", &mut connection).await.unwrap();
+        let other = second_user(&mut connection).await;
+        let player = DatabasePlayer::by_name(CiStr::from_str("Aeon Air"), &mut connection).await.unwrap();
+
+        let claim = PlayerClaim::create(&user, player.clone(), &mut connection).await.unwrap();
+
+        assert_eq!(
+            PlayerClaim::create(&other, player.clone(), &mut connection).await,
+            Err(PointercrateError::ClaimExists)
+        );
+        assert_eq!(
+            PlayerClaim::create(&user, player.clone(), &mut connection).await,
+            Err(PointercrateError::ClaimExists)
+        );
+
+        // once the claim has been rejected, the player can be claimed again
+        claim
+            .apply_patch(
+                PatchClaim {
+                    status: Some(ClaimStatus::Rejected),
+                },
+                &mut connection,
+            )
+            .await
+            .unwrap();
+
+        assert!(PlayerClaim::create(&other, player, &mut connection).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn test_edit_permissions_after_approval() {
+        let mut connection = crate::test::test_setup().await;
+
+        let user = User::by_name("stadust_existing", &mut connection).await.unwrap();
+        let player = DatabasePlayer::by_name(CiStr::from_str("Aeon Air"), &mut connection).await.unwrap();
+        let nationality: PatchPlayer = serde_json::from_value(serde_json::json!({"nationality": "DE", "subdivision": null})).unwrap();
+        let rename: PatchPlayer = serde_json::from_value(serde_json::json!({"name": "Aeon Water"})).unwrap();
+
+        let claim = PlayerClaim::create(&user, player.clone(), &mut connection).await.unwrap();
+
+        // a pending claim grants no permissions
+        assert!(require_patch_permissions(&user, &player, &nationality, &mut connection).await.is_err());
+
+        claim
+            .apply_patch(
+                PatchClaim {
+                    status: Some(ClaimStatus::Approved),
+                },
+                &mut connection,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(require_patch_permissions(&user, &player, &nationality, &mut connection).await, Ok(()));
+
+        // only the nationality and subdivision can be changed by the claimant
+        assert!(require_patch_permissions(&user, &player, &rename, &mut connection).await.is_err());
+
+        // and only on the claimed player
+        let other_player = DatabasePlayer::by_name(CiStr::from_str("Aaron Ari"), &mut connection).await.unwrap();
+
+        assert!(require_patch_permissions(&user, &other_player, &nationality, &mut connection)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    model::demonlist::{
+        demon::MinimalDemon,
+        player::{FullPlayer, HardestDemonCache, Player},
+    },
+    Result,
+};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::HashSet;
+
+/// The statistics the stats viewer displays for a single player
+#[derive(Debug, Serialize)]
+pub struct PlayerStats {
+    #[serde(flatten)]
+    pub player: Player,
+
+    /// The number of demons this player has completed, either via a 100% record or by verifying
+    /// it
+    pub beaten: usize,
+    pub created: usize,
+    pub published: usize,
+    pub verified: usize,
+    pub score: f64,
+
+    /// The player's rank, or `None` if the player does not appear in the ranking at all (e.g.
+    /// because they are banned)
+    pub rank: Option<i64>,
+    pub hardest: Option<MinimalDemon>,
+
+    /// The time the player's most recent record was approved, see
+    /// [`RankedPlayer::last_record_at`](super::RankedPlayer::last_record_at)
+    pub last_record_at: Option<NaiveDateTime>,
+}
+
+/// The differences between two players' [`PlayerStats`]
+///
+/// All differences are computed as "first player minus second player". Note that for `rank` and
+/// `hardest` a _negative_ value means the first player is ahead.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StatsDiff {
+    pub beaten: i64,
+    pub created: i64,
+    pub published: i64,
+    pub verified: i64,
+    pub score: f64,
+
+    /// The first player's score relative to the second player's score, in percent. `None` if the
+    /// second player has no score at all
+    pub score_percentage: Option<f64>,
+
+    /// `None` if either player is unranked
+    pub rank: Option<i64>,
+
+    /// The difference in position of both players' hardest demons. `None` if either player has
+    /// not beaten any demon
+    pub hardest: Option<i16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerComparison {
+    pub a: PlayerStats,
+    pub b: PlayerStats,
+    pub diff: StatsDiff,
+}
+
+impl PlayerStats {
+    /// Computes the stats of the given player. `ranking` is the player's rank and score, if they
+    /// appear in the ranking. `hardest` is the player's [hardest demon](super::hardest_demon)
+    pub fn new(
+        player: FullPlayer, ranking: Option<(i64, f64)>, hardest: Option<MinimalDemon>, last_record_at: Option<NaiveDateTime>,
+    ) -> PlayerStats {
+        let beaten_demons: HashSet<i32> = player
+            .records
+            .iter()
+            .filter(|record| record.progress.percent == 100)
+            .map(|record| record.demon.id)
+            .chain(player.verified.iter().map(|demon| demon.id))
+            .collect();
+
+        PlayerStats {
+            beaten: beaten_demons.len(),
+            created: player.created.len(),
+            published: player.published.len(),
+            verified: player.verified.len(),
+            score: ranking.map(|(_, score)| score).unwrap_or(0.0),
+            rank: ranking.map(|(rank, _)| rank),
+            hardest,
+            last_record_at,
+            player: player.player,
+        }
+    }
+
+    pub async fn by_id(player_id: i32, hardest_demons: &HardestDemonCache, connection: &mut PgConnection) -> Result<PlayerStats> {
+        let player = Player::by_id(player_id, connection).await?.upgrade(connection).await?;
+
+        let ranking = sqlx::query!(
+            r#"SELECT rank AS "rank!: i64", score AS "score!: f64" FROM players_with_score WHERE id = $1"#,
+            player_id
+        )
+        .fetch_optional(connection)
+        .await?
+        .map(|row| (row.rank, row.score));
+        let hardest = hardest_demons.get_or_load(player_id, connection).await?;
+        let last_record_at = sqlx::query!("SELECT last_record_at FROM players WHERE id = $1", player_id)
+            .fetch_one(connection)
+            .await?
+            .last_record_at;
+
+        Ok(PlayerStats::new(player, ranking, hardest, last_record_at))
+    }
+}
+
+impl StatsDiff {
+    pub fn between(a: &PlayerStats, b: &PlayerStats) -> StatsDiff {
+        StatsDiff {
+            beaten: a.beaten as i64 - b.beaten as i64,
+            created: a.created as i64 - b.created as i64,
+            published: a.published as i64 - b.published as i64,
+            verified: a.verified as i64 - b.verified as i64,
+            score: a.score - b.score,
+            score_percentage: if b.score == 0.0 { None } else { Some(a.score / b.score * 100.0) },
+            rank: match (a.rank, b.rank) {
+                (Some(rank_a), Some(rank_b)) => Some(rank_a - rank_b),
+                _ => None,
+            },
+            hardest: match (&a.hardest, &b.hardest) {
+                (Some(hardest_a), Some(hardest_b)) => Some(hardest_a.position - hardest_b.position),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl PlayerComparison {
+    pub async fn between(
+        player_a: i32, player_b: i32, hardest_demons: &HardestDemonCache, connection: &mut PgConnection,
+    ) -> Result<PlayerComparison> {
+        let a = PlayerStats::by_id(player_a, hardest_demons, connection).await?;
+        let b = PlayerStats::by_id(player_b, hardest_demons, connection).await?;
+        let diff = StatsDiff::between(&a, &b);
+
+        Ok(PlayerComparison { a, b, diff })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlayerComparison, PlayerStats, StatsDiff};
+    use crate::{
+        cistring::{CiStr, CiString},
+        model::demonlist::{
+            demon::MinimalDemon,
+            player::{DatabasePlayer, FullPlayer, HardestDemonCache, Player},
+        },
+    };
+    use std::time::Duration;
+
+    fn demon(id: i32, position: i16) -> MinimalDemon {
+        MinimalDemon {
+            id,
+            position,
+            name: CiString(format!("demon {}", id)),
+        }
+    }
+
+    fn player(id: i32, verified: Vec<MinimalDemon>) -> FullPlayer {
+        FullPlayer {
+            player: Player {
+                base: DatabasePlayer {
+                    id,
+                    name: CiString(format!("player {}", id)),
+                    banned: false,
+                },
+                nationality: None,
+            },
+            records: Vec::new(),
+            created: Vec::new(),
+            verified,
+            published: Vec::new(),
+            total_attempts: None,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = PlayerStats::new(player(1, vec![demon(1, 1), demon(2, 5)]), Some((1, 300.0)), Some(demon(1, 1)), None);
+        let b = PlayerStats::new(player(2, vec![demon(3, 3)]), Some((2, 150.0)), Some(demon(3, 3)), None);
+
+        assert_eq!(StatsDiff::between(&a, &b), StatsDiff {
+            beaten: 1,
+            created: 0,
+            published: 0,
+            verified: 1,
+            score: 150.0,
+            score_percentage: Some(200.0),
+            rank: Some(-1),
+            hardest: Some(-2),
+        });
+    }
+
+    #[test]
+    fn test_diff_against_player_without_records() {
+        let a = PlayerStats::new(player(1, vec![demon(1, 1)]), Some((1, 250.0)), Some(demon(1, 1)), None);
+        let b = PlayerStats::new(player(2, Vec::new()), None, None, None);
+
+        let diff = StatsDiff::between(&a, &b);
+
+        assert_eq!(diff.beaten, 1);
+        assert_eq!(diff.score, 250.0);
+        assert_eq!(diff.score_percentage, None);
+        assert_eq!(diff.rank, None);
+        assert_eq!(diff.hardest, None);
+
+        // and the other way around
+        let diff = StatsDiff::between(&b, &a);
+
+        assert_eq!(diff.beaten, -1);
+        assert_eq!(diff.score_percentage, Some(0.0));
+    }
+
+    #[actix_rt::test]
+    async fn test_compare_players() {
+        let mut connection = crate::test::test_setup().await;
+
+        let stardust = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection)
+            .await
+            .unwrap();
+        let samifying = DatabasePlayer::by_name(CiStr::from_str("Samifying"), &mut connection)
+            .await
+            .unwrap();
+
+        let hardest_demons = HardestDemonCache::new(Duration::from_secs(60));
+        let comparison = PlayerComparison::between(stardust.id, samifying.id, &hardest_demons, &mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.a.beaten, 2);
+        assert_eq!(comparison.a.hardest.as_ref().map(|demon| demon.position), Some(1));
+        assert_eq!(comparison.b.beaten, 0);
+        assert_eq!(comparison.b.rank, None);
+        assert!(comparison.a.last_record_at.is_some());
+        assert_eq!(comparison.b.last_record_at, None);
+        assert_eq!(comparison.diff.beaten, 2);
+        assert_eq!(comparison.diff.score_percentage, None);
+    }
+}
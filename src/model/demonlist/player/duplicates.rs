@@ -0,0 +1,171 @@
+//! Detection of duplicate player objects
+//!
+//! Since player objects are created implicitly whenever a record for an unknown player name is
+//! submitted, the list accumulates players whose names only differ in case or whitespace (e.g.
+//! "Sunix" and "Sunix "). These are detected here, but never merged automatically, as a list
+//! administrator should always confirm that two players are actually the same person.
+
+use crate::{
+    cistring::{CiStr, CiString},
+    error::PointercrateError,
+    model::demonlist::player::{merge_players, DatabasePlayer, FullPlayer, Player},
+    Result,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+
+/// A group of players whose names are identical after [normalization](normalize_name)
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DuplicateCluster {
+    pub normalized_name: CiString,
+
+    /// The players in this cluster, ordered by ID
+    pub players: Vec<DatabasePlayer>,
+}
+
+/// Normalizes a player name for duplicate detection by trimming it and collapsing all inner
+/// whitespace into single spaces
+///
+/// Case is irrelevant, as [`CiString`]s already compare case-insensitively.
+pub fn normalize_name(name: &CiStr) -> CiString {
+    CiString(name.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Groups the given players by their normalized name, only retaining groups of at least two
+/// players
+///
+/// Clusters are sorted by their normalized name.
+pub fn cluster_duplicates(players: Vec<DatabasePlayer>) -> Vec<DuplicateCluster> {
+    let mut clusters: BTreeMap<CiString, Vec<DatabasePlayer>> = BTreeMap::new();
+
+    for player in players {
+        clusters.entry(normalize_name(player.name.as_ref())).or_default().push(player);
+    }
+
+    clusters
+        .into_iter()
+        .filter(|(_, players)| players.len() > 1)
+        .map(|(normalized_name, mut players)| {
+            players.sort_by_key(|player| player.id);
+
+            DuplicateCluster { normalized_name, players }
+        })
+        .collect()
+}
+
+impl Player {
+    /// Finds all clusters of players that are likely to be duplicates of each other
+    pub async fn find_duplicates(connection: &mut PgConnection) -> Result<Vec<DuplicateCluster>> {
+        let mut stream = sqlx::query!(r#"SELECT id, name AS "name: String", banned FROM players"#).fetch(connection);
+        let mut players = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            players.push(DatabasePlayer {
+                id: row.id,
+                name: CiString(row.name),
+                banned: row.banned,
+            });
+        }
+
+        Ok(cluster_duplicates(players))
+    }
+}
+
+/// Merges all the players in `from` into `into`, after making sure they are actually duplicates of
+/// each other
+///
+/// Must be run within a transaction!
+pub async fn merge_duplicates(from: Vec<DatabasePlayer>, into: DatabasePlayer, connection: &mut PgConnection) -> Result<FullPlayer> {
+    let normalized_name = normalize_name(into.name.as_ref());
+
+    if from.is_empty() || from.iter().any(|player| normalize_name(player.name.as_ref()) != normalized_name) {
+        return Err(PointercrateError::NotDuplicates)
+    }
+
+    for player in from {
+        merge_players(player, into.clone(), &mut *connection).await?;
+    }
+
+    Player::by_id(into.id, &mut *connection).await?.upgrade(connection).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cluster_duplicates, merge_duplicates, normalize_name};
+    use crate::{
+        cistring::{CiStr, CiString},
+        error::PointercrateError,
+        model::demonlist::player::{DatabasePlayer, Player},
+    };
+
+    fn player(id: i32, name: &str) -> DatabasePlayer {
+        DatabasePlayer {
+            id,
+            name: CiString(name.to_string()),
+            banned: false,
+        }
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name(CiStr::from_str("  Sunix \t")).as_str(), "Sunix");
+        assert_eq!(normalize_name(CiStr::from_str("Aeon  Air")).as_str(), "Aeon Air");
+    }
+
+    #[test]
+    fn test_cluster_case_and_whitespace_variants() {
+        let clusters = cluster_duplicates(vec![
+            player(4, "sunix"),
+            player(1, "Sunix"),
+            player(2, "Aeon Air"),
+            player(3, "Sunix "),
+            player(5, " Aeon  air"),
+            player(6, "Aaron Ari"),
+            player(7, "Sunix2"),
+            player(8, "AeonAir"),
+        ]);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].normalized_name, CiString("aeon air".to_string()));
+        assert_eq!(clusters[0].players, vec![player(2, "Aeon Air"), player(5, " Aeon  air")]);
+        assert_eq!(clusters[1].normalized_name, CiString("sunix".to_string()));
+        assert_eq!(clusters[1].players, vec![player(1, "Sunix"), player(3, "Sunix "), player(4, "sunix")]);
+    }
+
+    #[actix_rt::test]
+    async fn test_find_and_merge_duplicates() {
+        let mut connection = crate::test::test_setup().await;
+
+        let duplicate = sqlx::query!("INSERT INTO players (name) VALUES (' Aeon  Air ') RETURNING id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+
+        let clusters = Player::find_duplicates(&mut connection).await.unwrap();
+
+        // "Aaron Ari" is similar, but not a duplicate
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].players.len(), 2);
+        assert_eq!(clusters[0].players[1].id, duplicate);
+
+        let aeon_air = clusters[0].players[0].clone();
+        let aaron_ari = DatabasePlayer::by_name(CiStr::from_str("Aaron Ari"), &mut connection).await.unwrap();
+
+        assert_eq!(
+            merge_duplicates(vec![aaron_ari], aeon_air.clone(), &mut connection).await,
+            Err(PointercrateError::NotDuplicates)
+        );
+
+        let merged = merge_duplicates(vec![clusters[0].players[1].clone()], aeon_air.clone(), &mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(merged.player.base, aeon_air);
+        assert!(Player::find_duplicates(&mut connection).await.unwrap().is_empty());
+    }
+}
@@ -8,11 +8,15 @@ use crate::{
             player::{DatabasePlayer, FullPlayer, Player},
             record::approved_records_by,
         },
-        nationality::Nationality,
+        nationality::{Continent, Nationality, Subdivision},
     },
     Result,
 };
+#[cfg(feature = "graphql")]
+use futures::StreamExt;
 use sqlx::{Error, PgConnection};
+#[cfg(feature = "graphql")]
+use std::collections::HashMap;
 
 // Required until https://github.com/launchbadge/sqlx/pull/108 is merged
 struct FetchedPlayer {
@@ -21,6 +25,56 @@ struct FetchedPlayer {
     banned: bool,
     nation: Option<String>,
     iso_country_code: Option<String>,
+    continent: Option<String>,
+    subdivision_code: Option<String>,
+    subdivision_name: Option<String>,
+}
+
+impl FetchedPlayer {
+    fn nationality(&self) -> Option<Nationality> {
+        match (&self.nation, &self.iso_country_code) {
+            (Some(nation), Some(iso_country_code)) =>
+                Some(Nationality {
+                    iso_country_code: iso_country_code.clone(),
+                    nation: CiString(nation.clone()),
+                    continent: self.continent.as_deref().map(Continent::from_sql),
+                    subdivision: self
+                        .subdivision_code
+                        .clone()
+                        .zip(self.subdivision_name.clone())
+                        .map(|(code, name)| Subdivision::new(code, CiString(name))),
+                }),
+            _ => None,
+        }
+    }
+}
+
+/// Gets the nationalities of the players with the given IDs in a single query, keyed by player ID
+///
+/// Players without a nationality (or IDs without a player) do not have an entry in the returned
+/// map.
+#[cfg(feature = "graphql")]
+pub async fn nationalities_of(player_ids: &[i32], connection: &mut PgConnection) -> Result<HashMap<i32, Nationality>> {
+    let mut stream = sqlx::query_as!(
+        FetchedPlayer,
+        r#"SELECT id, players.name AS "name: String", banned, nationalities.nation::text, iso_country_code::text, continent::text, subdivisions.iso_code AS 
+         "subdivision_code?", subdivisions.name::text AS "subdivision_name?" FROM players INNER JOIN nationalities ON players.nationality = 
+         nationalities.iso_country_code LEFT OUTER JOIN subdivisions ON players.nationality = subdivisions.nation AND players.subdivision = 
+         subdivisions.iso_code WHERE id = ANY($1)"#,
+        player_ids
+    )
+    .fetch(connection);
+    let mut nationalities = HashMap::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        if let Some(nationality) = row.nationality() {
+            nationalities.insert(row.id, nationality);
+        }
+    }
+
+    Ok(nationalities)
 }
 
 impl Player {
@@ -29,6 +83,7 @@ impl Player {
         let published = published_by(&self.base, connection).await?;
         let verified = verified_by(&self.base, connection).await?;
         let created = created_by(self.base.id, connection).await?;
+        let total_attempts = self.base.total_attempts(connection).await?;
 
         Ok(FullPlayer {
             player: self,
@@ -36,38 +91,32 @@ impl Player {
             created,
             verified,
             published,
+            total_attempts,
         })
     }
 
     pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<Player> {
         let result = sqlx::query_as!(
             FetchedPlayer,
-            r#"SELECT id, name AS "name: String", banned, nation::text, iso_country_code::text FROM players LEFT OUTER JOIN nationalities ON 
-             players.nationality = nationalities.iso_country_code WHERE id = $1"#,
+            r#"SELECT id, players.name AS "name: String", banned, nationalities.nation::text, iso_country_code::text, continent::text, subdivisions.iso_code AS 
+             "subdivision_code?", subdivisions.name::text AS "subdivision_name?" FROM players LEFT OUTER JOIN nationalities ON players.nationality = 
+             nationalities.iso_country_code LEFT OUTER JOIN subdivisions ON players.nationality = subdivisions.nation AND players.subdivision = 
+             subdivisions.iso_code WHERE id = $1"#,
             id
         )
         .fetch_one(connection)
         .await;
 
         match result {
-            Ok(row) => {
-                let nationality = if let (Some(nation), Some(iso_country_code)) = (row.nation, row.iso_country_code) {
-                    Some(Nationality {
-                        iso_country_code,
-                        nation: CiString(nation),
-                    })
-                } else {
-                    None
-                };
+            Ok(row) =>
                 Ok(Player {
+                    nationality: row.nationality(),
                     base: DatabasePlayer {
                         id: row.id,
                         name: CiString(row.name),
                         banned: row.banned,
                     },
-                    nationality,
-                })
-            },
+                }),
             Err(Error::RowNotFound) =>
                 Err(PointercrateError::ModelNotFound {
                     model: "Player",
@@ -126,6 +175,16 @@ impl DatabasePlayer {
         }
     }
 
+    /// Validates a player name given in a request, before it is looked up or used to create a new
+    /// player object
+    pub fn validate_name(name: &CiStr) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(PointercrateError::EmptyName)
+        }
+
+        Ok(())
+    }
+
     pub async fn by_name_or_create(name: &CiStr, connection: &mut PgConnection) -> Result<DatabasePlayer> {
         let name = CiStr::from_str(name.trim());
 
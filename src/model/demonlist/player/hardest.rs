@@ -0,0 +1,176 @@
+//! The hardest demon a player has completed, as shown in the stats viewer
+//!
+//! The actual computation happens in the `player_hardest_demon` SQL function, so that it is
+//! consistent everywhere it is used. Since the stats viewer looks it up for every player that is
+//! clicked in the ranking, results are memoized per player for a short while.
+
+use crate::{cistring::CiString, model::demonlist::demon::MinimalDemon, Result};
+use log::debug;
+use sqlx::PgConnection;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Gets the hardest demon the player with the given ID has completed, either via an approved 100%
+/// record or by verifying it
+///
+/// Ties are broken by demon ID. Legacy demons are only returned if the player has completed
+/// nothing on the main or extended list.
+pub async fn hardest_demon(player_id: i32, connection: &mut PgConnection) -> Result<Option<MinimalDemon>> {
+    Ok(sqlx::query!(
+        r#"SELECT id, name AS "name: String", position FROM demons WHERE id = player_hardest_demon($1)"#,
+        player_id
+    )
+    .fetch_optional(connection)
+    .await?
+    .map(|row| {
+        MinimalDemon {
+            id: row.id,
+            name: CiString(row.name),
+            position: row.position,
+        }
+    }))
+}
+
+#[derive(Clone)]
+pub struct HardestDemonCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<i32, (Instant, Option<MinimalDemon>)>>>,
+}
+
+impl HardestDemonCache {
+    pub fn new(ttl: Duration) -> Self {
+        HardestDemonCache {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Gets the hardest demon of the player with the given ID from the cache, loading it from the
+    /// database if it isn't cached (or the cached value expired)
+    pub async fn get_or_load(&self, player_id: i32, connection: &mut PgConnection) -> Result<Option<MinimalDemon>> {
+        if let Some((loaded_at, hardest)) = self.entries.lock().unwrap().get(&player_id) {
+            if loaded_at.elapsed() < self.ttl {
+                return Ok(hardest.clone())
+            }
+        }
+
+        debug!("Hardest demon of player {} not cached, loading from database", player_id);
+
+        let hardest = hardest_demon(player_id, connection).await?;
+        let ttl = self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|_, (loaded_at, _)| loaded_at.elapsed() < ttl);
+        entries.insert(player_id, (Instant::now(), hardest.clone()));
+
+        Ok(hardest)
+    }
+
+    /// Drops all cached values. Should be called whenever demons are moved, approved records are
+    /// added, changed or removed, or players are merged, as all of these can change a player's
+    /// hardest demon
+    pub fn invalidate(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hardest_demon, HardestDemonCache};
+    use crate::{
+        cistring::CiStr,
+        model::demonlist::{demon::MinimalDemon, player::DatabasePlayer},
+    };
+    use std::time::Duration;
+
+    #[actix_rt::test]
+    async fn test_hardest_demon_tiebreak() {
+        let mut connection = crate::test::test_setup().await;
+
+        let stardust = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection).await.unwrap();
+        let first = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection)
+            .await
+            .unwrap();
+
+        // stardust1971 verified both of these, put them at the same position
+        sqlx::query!("SET CONSTRAINTS unique_position DEFERRED").execute(&mut connection).await.unwrap();
+        sqlx::query!("UPDATE demons SET position = 1 WHERE name = 'Trichotomy'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let hardest = hardest_demon(stardust.id, &mut connection).await.unwrap().unwrap();
+
+        assert_eq!(hardest.position, 1);
+        assert_eq!(hardest.id, first.id);
+
+        // unpublished demons do not count
+        sqlx::query!("UPDATE demons SET published = FALSE WHERE id = $1", first.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let hardest = hardest_demon(stardust.id, &mut connection).await.unwrap().unwrap();
+
+        assert_eq!(hardest.name.as_str(), "Trichotomy");
+    }
+
+    #[actix_rt::test]
+    async fn test_hardest_demon_legacy_only() {
+        let mut connection = crate::test::test_setup().await;
+        let cache = HardestDemonCache::new(Duration::from_secs(60));
+
+        let samifying = DatabasePlayer::by_name(CiStr::from_str("Samifying"), &mut connection).await.unwrap();
+
+        assert_eq!(hardest_demon(samifying.id, &mut connection).await.unwrap(), None);
+
+        let legacy = sqlx::query!(
+            "INSERT INTO demons (name, position, requirement, verifier, publisher) SELECT 'Legacy', 150, 100, id, id FROM players WHERE \
+             name = 'Aquatias' RETURNING id"
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+        sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, 'APPROVED', $1, submitter_id, $2 FROM \
+             submitters LIMIT 1",
+            samifying.id,
+            legacy
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // a legacy demon counts as hardest if the player has nothing else
+        assert_eq!(cache.get_or_load(samifying.id, &mut connection).await.unwrap().map(|demon| demon.id), Some(legacy));
+
+        let taraturusus = MinimalDemon::by_name(CiStr::from_str("taraturusus"), &mut connection).await.unwrap();
+
+        sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, 'APPROVED', $1, submitter_id, $2 FROM \
+             submitters LIMIT 1",
+            samifying.id,
+            taraturusus.id
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // but not anymore once they completed something on the list
+        assert_eq!(hardest_demon(samifying.id, &mut connection).await.unwrap(), Some(taraturusus));
+
+        // the cache keeps returning the memoized value until it is invalidated
+        assert_eq!(cache.get_or_load(samifying.id, &mut connection).await.unwrap().map(|demon| demon.id), Some(legacy));
+
+        cache.invalidate();
+
+        assert_eq!(
+            cache.get_or_load(samifying.id, &mut connection).await.unwrap().map(|demon| demon.id),
+            Some(taraturusus.id)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{cistring::CiString, Result};
+use chrono::NaiveDateTime;
+use futures::stream::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// A player whose demonlist score increased over some period of time
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ImprovedPlayer {
+    pub id: i32,
+    pub name: CiString,
+
+    /// The player's score at the end of the period
+    pub score: f64,
+
+    /// The player's score at the start of the period, `0` if they did not have one back then
+    pub previous_score: f64,
+    pub improvement: f64,
+}
+
+/// Gets the (at most `limit`) players whose score increased the most between `since` and
+/// `until`, biggest improvement first
+///
+/// Both scores are reconstructed from the audit log via the `player_scores_at` function, the same
+/// way the time machine reconstructs the list. Banned players are excluded.
+pub async fn most_improved(
+    since: NaiveDateTime, until: NaiveDateTime, limit: i64, connection: &mut PgConnection,
+) -> Result<Vec<ImprovedPlayer>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT players.id, players.name::TEXT AS "name!", current.score AS "score!", COALESCE(previous.score, 0.0) AS "previous_score!"
+         FROM player_scores_at($2) AS current INNER JOIN players ON players.id = current.player LEFT OUTER JOIN player_scores_at($1) AS
+         previous ON previous.player = current.player WHERE NOT players.banned AND players.id != 1534 AND current.score > COALESCE(previous.score,
+         0.0) ORDER BY current.score - COALESCE(previous.score, 0.0) DESC, players.id LIMIT $3"#,
+        since,
+        until,
+        limit
+    )
+    .fetch(connection);
+
+    let mut players = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        players.push(ImprovedPlayer {
+            id: row.id,
+            name: CiString(row.name),
+            score: row.score,
+            previous_score: row.previous_score,
+            improvement: row.score - row.previous_score,
+        })
+    }
+
+    Ok(players)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::most_improved;
+    use chrono::{Duration, Utc};
+
+    #[actix_rt::test]
+    async fn test_most_improved() {
+        let mut connection = crate::test::test_setup().await;
+
+        // The snapshots are taken in the future, so that any audit log entries created while
+        // setting up the test data (which are timestamped with the start of the transaction) lie
+        // before both of them
+        let now = Utc::now().naive_utc();
+        let since = now + Duration::days(1);
+        let changed = now + Duration::days(2);
+        let until = now + Duration::days(3);
+
+        // Aeon Air improves their 90% on Trichotomy to 100%, and Samifying (who did not have any
+        // score before) gets a 100% on taraturusus approved
+        sqlx::query!(
+            "WITH updated AS (UPDATE records SET progress = 100 FROM players WHERE records.player = players.id AND players.name = 'Aeon Air' \
+             RETURNING records.id) INSERT INTO record_modifications (time, userid, id, progress) SELECT $1, 0, id, 90 FROM updated",
+            changed
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "WITH added AS (INSERT INTO records (progress, status_, player, demon, submitter) SELECT 100, 'APPROVED', players.id, \
+             demons.id, submitter_id FROM players, demons, submitters WHERE players.name = 'Samifying' AND demons.name = 'taraturusus' \
+             RETURNING id) INSERT INTO record_additions (time, userid, id) SELECT $1, 0, id FROM added",
+            changed
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        let improved = most_improved(since, until, 10, &mut connection).await.unwrap();
+
+        let improvements: Vec<(&str, f64, f64)> = improved
+            .iter()
+            .map(|player| (player.name.as_str(), player.previous_score, player.improvement))
+            .collect();
+
+        // the scores of all other players did not change, so they are not included
+        assert_eq!(improvements.len(), 2);
+        assert_eq!(improvements[0].0, "Samifying");
+        assert_eq!(improvements[0].1, 0.0);
+        assert!((improvements[0].2 - 194.28275765904834).abs() < 1e-9);
+        assert_eq!(improvements[1].0, "Aeon Air");
+        assert!((improvements[1].1 - 41.779513045991536).abs() < 1e-9);
+        assert!((improvements[1].2 - 186.70124037866603).abs() < 1e-9);
+
+        // the limit is respected
+        assert_eq!(most_improved(since, until, 1, &mut connection).await.unwrap().len(), 1);
+    }
+}
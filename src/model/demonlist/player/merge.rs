@@ -0,0 +1,155 @@
+//! Merging of player objects, and the history of names players were previously known under
+//!
+//! Players frequently change their in-game name. If records under the new name were already
+//! submitted, the list ends up with two player objects for the same person, which list
+//! administrators can then merge. The name of the merged player is kept in the history, so that
+//! the player can still be found under it.
+
+use crate::{
+    cistring::{CiStr, CiString},
+    error::PointercrateError,
+    model::demonlist::player::{DatabasePlayer, FullPlayer, Player},
+    Result,
+};
+use log::info;
+use sqlx::PgConnection;
+
+/// Gets the names the given player was previously known under, most recent first
+pub async fn former_names(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<CiString>> {
+    Ok(sqlx::query!(
+        r#"SELECT name::TEXT AS "name!: String" FROM player_name_history WHERE player = $1 ORDER BY time DESC"#,
+        player.id
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| CiString(row.name))
+    .collect())
+}
+
+pub(super) async fn log_former_name(player: &DatabasePlayer, name: &CiStr, connection: &mut PgConnection) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO player_name_history (player, name) VALUES ($1, $2::TEXT)",
+        player.id,
+        name.to_string()
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Merges the player `from` into the player `into`, deleting `from`
+///
+/// All records, creator entries and verifier/publisher references are transferred to `into`. The
+/// name of `from` (and all names it was previously known under) are added to the name history of
+/// `into`.
+///
+/// Must be run within a transaction!
+pub async fn merge_players(from: DatabasePlayer, into: DatabasePlayer, connection: &mut PgConnection) -> Result<FullPlayer> {
+    if from.id == into.id {
+        return Err(PointercrateError::MergeIntoSelf)
+    }
+
+    info!("Merging player {} into player {}", from, into);
+
+    let mut into = Player::by_id(into.id, &mut *connection).await?.upgrade(&mut *connection).await?;
+
+    log_former_name(&into.player.base, from.name.as_ref(), &mut *connection).await?;
+
+    into.merge(from, &mut *connection).await?;
+
+    // `FullPlayer::merge` only refreshes the record list, so reload the player to also pick up the
+    // transferred creator, verifier and publisher entries
+    Player::by_id(into.player.base.id, &mut *connection)
+        .await?
+        .upgrade(connection)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{former_names, merge_players};
+    use crate::{
+        cistring::{CiStr, CiString},
+        error::PointercrateError,
+        model::demonlist::{
+            creator::created_by,
+            demon::MinimalDemon,
+            player::{DatabasePlayer, PlayerPagination},
+        },
+    };
+    use serde_json::json;
+
+    async fn player(name: &str, connection: &mut sqlx::PgConnection) -> DatabasePlayer {
+        DatabasePlayer::by_name(CiStr::from_str(name), connection).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_merge_into_self() {
+        let mut connection = crate::test::test_setup().await;
+
+        let stardust = player("stardust1971", &mut connection).await;
+
+        assert_eq!(
+            merge_players(stardust.clone(), stardust, &mut connection).await,
+            Err(PointercrateError::MergeIntoSelf)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_merge_transfers_records_and_creators() {
+        let mut connection = crate::test::test_setup().await;
+
+        let stardust = player("stardust1971", &mut connection).await;
+        let samifying = player("Samifying", &mut connection).await;
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        sqlx::query!("INSERT INTO creators (demon, creator) VALUES ($1, $2)", trichotomy.id, stardust.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let merged = merge_players(stardust, samifying.clone(), &mut connection).await.unwrap();
+
+        assert_eq!(merged.player.base, samifying);
+
+        // stardust1971 had approved records on abstract interpretation (90%) and Trichotomy (100%)
+        let mut progress: Vec<(String, i16)> = merged
+            .records
+            .iter()
+            .map(|record| (record.demon.name.to_string(), record.progress.percent))
+            .collect();
+        progress.sort();
+
+        assert_eq!(progress, vec![
+            ("Trichotomy".to_string(), 100),
+            ("abstract interpretation".to_string(), 90)
+        ]);
+        assert_eq!(merged.verified.len(), 2);
+        assert_eq!(merged.published.len(), 2);
+        assert_eq!(created_by(samifying.id, &mut connection).await.unwrap(), vec![trichotomy]);
+
+        assert!(DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_former_name_is_searchable() {
+        let mut connection = crate::test::test_setup().await;
+
+        let stardust = player("stardust1971", &mut connection).await;
+        let samifying = player("Samifying", &mut connection).await;
+
+        merge_players(stardust, samifying.clone(), &mut connection).await.unwrap();
+
+        assert_eq!(former_names(&samifying, &mut connection).await.unwrap(), vec![CiString(
+            "stardust1971".to_string()
+        )]);
+
+        let pagination: PlayerPagination = serde_json::from_value(json!({"name_contains": "stardust"})).unwrap();
+        let players = pagination.page(&mut connection).await.unwrap();
+
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].base, samifying);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{model::demonlist::player::DatabasePlayer, Result};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// A player directly above or below some other player in the ranking
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RankNeighbor {
+    pub id: i32,
+    pub name: String,
+    pub rank: i64,
+    pub score: f64,
+}
+
+/// A player's position in the ranking, together with the players ranked immediately above and
+/// below them
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RankNeighbors {
+    /// The player's rank, or `None` if the player does not appear in the ranking at all (e.g.
+    /// because they are banned). Unranked players have no neighbors
+    pub rank: Option<i64>,
+    pub score: f64,
+
+    /// The player ranked immediately above, `None` for the top player
+    pub higher: Option<RankNeighbor>,
+
+    /// The player ranked immediately below, `None` for the last player
+    pub lower: Option<RankNeighbor>,
+}
+
+fn neighbor(id: Option<i32>, name: Option<String>, rank: Option<i64>, score: Option<f64>) -> Option<RankNeighbor> {
+    Some(RankNeighbor {
+        id: id?,
+        name: name?,
+        rank: rank?,
+        score: score?,
+    })
+}
+
+impl DatabasePlayer {
+    /// Gets the players ranked immediately above and below this one
+    ///
+    /// Players with equal score share a rank, but are still strictly ordered in the ranking, so
+    /// neighbors may have the same rank as this player.
+    pub async fn rank_neighbors(&self, connection: &mut PgConnection) -> Result<RankNeighbors> {
+        let row = sqlx::query!(
+            r#"SELECT rank AS "rank!: i64", score AS "score!: f64", higher_id AS "higher_id?", higher_name AS "higher_name?",
+             higher_rank AS "higher_rank?: i64", higher_score AS "higher_score?: f64", lower_id AS "lower_id?", lower_name AS "lower_name?",
+             lower_rank AS "lower_rank?: i64", lower_score AS "lower_score?: f64" FROM (SELECT id, rank, score, LAG(id) OVER ranking AS
+             higher_id, LAG(name::TEXT) OVER ranking AS higher_name, LAG(rank) OVER ranking AS higher_rank, LAG(score) OVER ranking AS
+             higher_score, LEAD(id) OVER ranking AS lower_id, LEAD(name::TEXT) OVER ranking AS lower_name, LEAD(rank) OVER ranking AS
+             lower_rank, LEAD(score) OVER ranking AS lower_score FROM players_with_score WINDOW ranking AS (ORDER BY index)) AS neighbors
+             WHERE id = $1"#,
+            self.id
+        )
+        .fetch_optional(connection)
+        .await?;
+
+        Ok(match row {
+            Some(row) =>
+                RankNeighbors {
+                    rank: Some(row.rank),
+                    score: row.score,
+                    higher: neighbor(row.higher_id, row.higher_name, row.higher_rank, row.higher_score),
+                    lower: neighbor(row.lower_id, row.lower_name, row.lower_rank, row.lower_score),
+                },
+            None =>
+                RankNeighbors {
+                    rank: None,
+                    score: 0.0,
+                    higher: None,
+                    lower: None,
+                },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cistring::CiStr, model::demonlist::player::DatabasePlayer};
+    use sqlx::PgConnection;
+
+    async fn player(name: &str, connection: &mut PgConnection) -> DatabasePlayer {
+        DatabasePlayer::by_name(CiStr::from_str(name), connection).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_rank_neighbors() {
+        let mut connection = crate::test::test_setup().await;
+
+        // The test data ranks stardust1971, Aaron Ari, Aquatias, Mullsy and Aeon Air in this order
+        let aquatias = player("Aquatias", &mut connection).await;
+        let neighbors = aquatias.rank_neighbors(&mut connection).await.unwrap();
+
+        let higher = neighbors.higher.unwrap();
+        let lower = neighbors.lower.unwrap();
+
+        assert_eq!(neighbors.rank, Some(3));
+        assert_eq!(higher.name, "Aaron Ari");
+        assert_eq!(higher.rank, 2);
+        assert_eq!(lower.name, "Mullsy");
+        assert_eq!(lower.rank, 4);
+        assert!(higher.score > neighbors.score && neighbors.score > lower.score);
+
+        let top = player("stardust1971", &mut connection).await.rank_neighbors(&mut connection).await.unwrap();
+
+        assert_eq!(top.rank, Some(1));
+        assert_eq!(top.higher, None);
+        assert_eq!(top.lower.unwrap().name, "Aaron Ari");
+
+        let last = player("Aeon Air", &mut connection).await.rank_neighbors(&mut connection).await.unwrap();
+
+        assert_eq!(last.higher.unwrap().name, "Mullsy");
+        assert_eq!(last.lower, None);
+
+        // Samifying has no records and thus is not ranked at all
+        let unranked = player("Samifying", &mut connection).await.rank_neighbors(&mut connection).await.unwrap();
+
+        assert_eq!(unranked.rank, None);
+        assert_eq!(unranked.higher, None);
+        assert_eq!(unranked.lower, None);
+    }
+}
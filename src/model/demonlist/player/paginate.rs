@@ -3,14 +3,19 @@ use crate::{
     error::PointercrateError,
     model::{
         demonlist::player::{DatabasePlayer, Player, RankedPlayer},
-        nationality::Nationality,
+        nationality::{Continent, Nationality, Subdivision},
     },
     util::{non_nullable, nullable},
     Result,
 };
-use futures::StreamExt;
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgConnection, Row};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerPagination {
@@ -76,6 +81,11 @@ impl PlayerPagination {
                     Some(Nationality {
                         iso_country_code: country_code,
                         nation: CiString(nation),
+                        continent: row.get::<Option<String>, _>("continent").as_deref().map(Continent::from_sql),
+                        subdivision: row
+                            .get::<Option<String>, _>("subdivision_code")
+                            .zip(row.get::<Option<String>, _>("subdivision_name"))
+                            .map(|(code, name)| Subdivision::new(code, CiString(name))),
                     }),
                 _ => None,
             };
@@ -92,9 +102,65 @@ impl PlayerPagination {
 
         Ok(players)
     }
+
+    /// Counts all players matching this pagination's filters, ignoring `before`, `after` and
+    /// `limit`
+    pub async fn count(&self, connection: &mut PgConnection) -> Result<i64> {
+        let row = sqlx::query(include_str!("../../../../sql/count_players.sql"))
+            .bind(self.name.as_ref().map(|s| s.as_str()))
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(self.banned)
+            .bind(&self.nation)
+            .bind(self.nation == Some(None))
+            .fetch_one(connection)
+            .await?;
+
+        Ok(row.get(0))
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// The orders the player ranking can be sorted in
+///
+/// Ties are always broken by player ID, so that the indices used for pagination are stable.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingSort {
+    /// Highest score first
+    Score,
+
+    /// Alphabetically, ignoring case
+    Name,
+
+    /// Most approved records first
+    RecordCount,
+
+    /// Most recently approved record first. Players without any approved record come last
+    LastActive,
+
+    /// Most attempts logged on approved records first. Players who did not log any attempts count
+    /// as having zero
+    Attempts,
+}
+
+impl Default for RankingSort {
+    fn default() -> Self {
+        RankingSort::Score
+    }
+}
+
+impl RankingSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            RankingSort::Score => "score DESC, id",
+            RankingSort::Name => "name, id",
+            RankingSort::RecordCount => "COALESCE(record_count, 0) DESC, id",
+            RankingSort::LastActive => "last_record_at DESC NULLS LAST, id",
+            RankingSort::Attempts => "COALESCE(total_attempts, 0) DESC, id",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
 pub struct RankingPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "before")]
@@ -111,9 +177,56 @@ pub struct RankingPagination {
     nation: Option<Option<String>>,
     #[serde(default, deserialize_with = "non_nullable")]
     name_contains: Option<CiString>,
+    #[serde(default, deserialize_with = "non_nullable")]
+    continent: Option<Continent>,
+
+    /// Either a full ISO 3166-2 code (e.g. `US-CA`) or only the part after the hyphen
+    #[serde(default, deserialize_with = "non_nullable")]
+    subdivision: Option<String>,
+
+    /// Only include players who had a record approved on or after this day
+    #[serde(default, deserialize_with = "non_nullable")]
+    active_since: Option<NaiveDate>,
+
+    /// The order of the ranking. The `before` and `after` indices refer to positions in this order
+    #[serde(default, deserialize_with = "non_nullable")]
+    sort: Option<RankingSort>,
 }
 
 impl RankingPagination {
+    /// A pagination over the unfiltered ranking, starting after the given index
+    #[cfg(any(test, feature = "graphql"))]
+    pub fn unfiltered(after_index: Option<i64>, limit: Option<u8>) -> RankingPagination {
+        RankingPagination {
+            before_index: None,
+            after_index,
+            limit,
+            nation: None,
+            name_contains: None,
+            continent: None,
+            subdivision: None,
+            active_since: None,
+            sort: None,
+        }
+    }
+
+    /// Computes the (weak) ETag of the ranking page selected by this pagination
+    ///
+    /// Since the ranking only changes if the list was [modified](crate::model::demonlist::last_modified),
+    /// the ETag can be computed without retrieving the ranking itself.
+    pub fn etag(&self, last_modified: NaiveDateTime) -> String {
+        let mut hasher = DefaultHasher::new();
+
+        last_modified.hash(&mut hasher);
+        self.hash(&mut hasher);
+
+        format!("W/\"{:x}\"", hasher.finish())
+    }
+
+    fn active_since(&self) -> Option<NaiveDateTime> {
+        self.active_since.map(|date| date.and_hms(0, 0, 0))
+    }
+
     pub async fn page(&self, connection: &mut PgConnection) -> Result<Vec<RankedPlayer>> {
         if let Some(limit) = self.limit {
             if limit < 1 || limit > 100 {
@@ -127,7 +240,11 @@ impl RankingPagination {
             "ASC"
         };
 
-        let query = format!(include_str!("../../../../sql/paginate_player_ranking.sql"), order);
+        let query = format!(
+            include_str!("../../../../sql/paginate_player_ranking.sql"),
+            self.sort.unwrap_or_default().order_by(),
+            order
+        );
 
         let mut stream = sqlx::query(&query)
             .bind(self.before_index)
@@ -136,6 +253,9 @@ impl RankingPagination {
             .bind(&self.nation)
             .bind(self.nation == Some(None))
             .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .bind(self.continent.map(Continent::to_sql))
+            .bind(self.subdivision.as_ref().map(|code| code.to_uppercase()))
+            .bind(self.active_since())
             .fetch(connection);
 
         let mut players = Vec::new();
@@ -148,6 +268,11 @@ impl RankingPagination {
                     Some(Nationality {
                         iso_country_code: country_code,
                         nation: CiString(nation),
+                        continent: row.get::<Option<String>, _>("continent").as_deref().map(Continent::from_sql),
+                        subdivision: row
+                            .get::<Option<String>, _>("subdivision_code")
+                            .zip(row.get::<Option<String>, _>("subdivision_name"))
+                            .map(|(code, name)| Subdivision::new(code, CiString(name))),
                     }),
                 _ => None,
             };
@@ -158,10 +283,518 @@ impl RankingPagination {
                 rank: row.get("rank"),
                 nationality,
                 score: row.get("score"),
+                last_record_at: row.get("last_record_at"),
+                total_attempts: row.get("total_attempts"),
                 index: row.get("index"),
             })
         }
 
         Ok(players)
     }
+
+    /// Counts all ranked players matching this pagination's filters, ignoring `before`, `after`
+    /// and `limit`
+    pub async fn count(&self, connection: &mut PgConnection) -> Result<i64> {
+        let row = sqlx::query(include_str!("../../../../sql/count_player_ranking.sql"))
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(&self.nation)
+            .bind(self.nation == Some(None))
+            .bind(self.continent.map(Continent::to_sql))
+            .bind(self.subdivision.as_ref().map(|code| code.to_uppercase()))
+            .bind(self.active_since())
+            .fetch_one(connection)
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    /// Writes the complete ranking matching this pagination's filters into `sink`, formatted as
+    /// CSV and preceded by a header row.
+    ///
+    /// The pagination parameters (`before`, `after` and `limit`) are ignored. Each row is sent as
+    /// soon as it is retrieved from the database, so the ranking is never buffered in full. If the
+    /// receiving end of `sink` is dropped (e.g. because the client disconnected), the export stops
+    /// early.
+    pub async fn export_csv(&self, connection: &mut PgConnection, sink: &mut Sender<String>) -> Result<()> {
+        if sink
+            .send("rank,name,nationality,score,demons_completed,hardest_demon\n".to_string())
+            .await
+            .is_err()
+        {
+            return Ok(())
+        }
+
+        let mut stream = sqlx::query(include_str!("../../../../sql/export_player_ranking.sql"))
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(&self.nation)
+            .bind(self.nation == Some(None))
+            .bind(self.continent.map(Continent::to_sql))
+            .bind(self.subdivision.as_ref().map(|code| code.to_uppercase()))
+            .bind(self.active_since())
+            .fetch(connection);
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            let line = format!(
+                "{},{},{},{:.2},{},{}\n",
+                row.get::<i64, _>("rank"),
+                csv_field(row.get("name")),
+                csv_field(row.get::<Option<String>, _>("nation").as_deref().unwrap_or("")),
+                row.get::<f64, _>("score"),
+                row.get::<i64, _>("completed"),
+                csv_field(row.get::<Option<String>, _>("hardest").as_deref().unwrap_or(""))
+            );
+
+            if sink.send(line).await.is_err() {
+                break
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Quotes the given value if it contains characters with special meaning in CSV
+///
+/// Player names come straight from record submissions, so values spreadsheets would interpret as
+/// formulas are prefixed with a `'` to be displayed as text instead.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(|c: char| matches!(c, '=' | '+' | '-' | '@' | '\t' | '\r')) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csv_field, PlayerPagination, RankingPagination, RankingSort};
+    use crate::{
+        model::{
+            demonlist::{last_modified, player::RankedPlayer},
+            nationality::Continent,
+        },
+        state::audit_connection,
+        Result,
+    };
+    use actix_web::{web::Query, HttpResponse};
+    use futures::{channel::mpsc, StreamExt};
+    use sqlx::PgConnection;
+
+    /// Builds the response the ranking endpoint would send for the given pagination
+    async fn ranking_response(pagination: RankingPagination, connection: &mut PgConnection) -> HttpResponse {
+        let mut pagination = Query(pagination);
+        let mut players = pagination.page(connection).await.unwrap();
+        let max_index = RankedPlayer::max_index(connection).await.unwrap();
+        let total = pagination.count(connection).await.unwrap();
+
+        let response: Result<HttpResponse> = pagination_response!(
+            total = total;
+            "/api/v1/players/ranking/",
+            players,
+            pagination,
+            1,
+            max_index,
+            before_index,
+            after_index,
+            index
+        );
+
+        response.unwrap()
+    }
+
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> &'a str {
+        response.headers().get(name).unwrap().to_str().unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_players_filter_by_nation_and_name() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET nationality = 'DE' WHERE name = 'Aeon Air' OR name = 'Mullsy'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE players SET nationality = 'US' WHERE name = 'Aaron Ari'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let pagination = Query::<PlayerPagination>::from_query("nation=DE&name_contains=a").unwrap();
+        let players = pagination.page(&mut connection).await.unwrap();
+
+        // Mullsy is german, but does not match the name filter. stardust1971 and Aaron Ari match the
+        // name filter, but are not german
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].base.name.as_str(), "Aeon Air");
+        assert_eq!(pagination.count(&mut connection).await.unwrap(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_filters_by_continent() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET nationality = 'DE' WHERE name = 'stardust1971'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE players SET nationality = 'US' WHERE name = 'Aaron Ari'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let pagination = RankingPagination {
+            before_index: None,
+            after_index: None,
+            limit: None,
+            nation: None,
+            name_contains: None,
+            continent: Some(Continent::Europe),
+            subdivision: None,
+            active_since: None,
+            sort: None,
+        };
+
+        let players = pagination.page(&mut connection).await.unwrap();
+
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name.as_str(), "stardust1971");
+        assert_eq!(
+            players[0].nationality.as_ref().and_then(|nationality| nationality.continent),
+            Some(Continent::Europe)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_filters_by_subdivision() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET nationality = 'US', subdivision = 'CA' WHERE name = 'stardust1971'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE players SET nationality = 'US', subdivision = 'TX' WHERE name = 'Aaron Ari'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let mut pagination = RankingPagination {
+            before_index: None,
+            after_index: None,
+            limit: None,
+            nation: Some(Some("US".to_string())),
+            name_contains: None,
+            continent: None,
+            subdivision: Some("US-CA".to_string()),
+            active_since: None,
+            sort: None,
+        };
+
+        let players = pagination.page(&mut connection).await.unwrap();
+
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name.as_str(), "stardust1971");
+        assert_eq!(
+            players[0]
+                .nationality
+                .as_ref()
+                .and_then(|nationality| nationality.subdivision.as_ref())
+                .map(|subdivision| subdivision.name.as_str()),
+            Some("California")
+        );
+
+        // the part after the hyphen alone works, too
+        pagination.subdivision = Some("tx".to_string());
+
+        let players = pagination.page(&mut connection).await.unwrap();
+
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name.as_str(), "Aaron Ari");
+    }
+
+    #[actix_rt::test]
+    async fn test_export_ranking_csv() {
+        let mut connection = crate::test::test_setup().await;
+        let (mut sender, receiver) = mpsc::channel(16);
+
+        let pagination = RankingPagination {
+            before_index: None,
+            after_index: None,
+            limit: None,
+            nation: None,
+            name_contains: None,
+            continent: None,
+            subdivision: None,
+            active_since: None,
+            sort: None,
+        };
+
+        pagination.export_csv(&mut connection, &mut sender).await.unwrap();
+        drop(sender);
+
+        let lines: Vec<String> = receiver.collect().await;
+
+        assert_eq!(lines[0], "rank,name,nationality,score,demons_completed,hardest_demon\n");
+        assert_eq!(lines[1], "1,stardust1971,,796.35,2,abstract interpretation\n");
+        assert_eq!(lines[2], "2,Aaron Ari,,438.54,2,Trichotomy\n");
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("Aeon Air"), "Aeon Air");
+        assert_eq!(csv_field("Korea, Republic of"), "\"Korea, Republic of\"");
+        assert_eq!(csv_field("\"quoted\""), "\"\"\"quoted\"\"\"");
+    }
+
+    #[test]
+    fn test_csv_field_formula_escaping() {
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("-Cool"), "'-Cool");
+        assert_eq!(csv_field("\tCool"), "'\tCool");
+        assert_eq!(
+            csv_field("=HYPERLINK(\"https://evil.example\", \"Aeon Air\")"),
+            "\"'=HYPERLINK(\"\"https://evil.example\"\", \"\"Aeon Air\"\")\""
+        );
+        assert_eq!(csv_field("Cool=1"), "Cool=1");
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_pagination_headers() {
+        let mut connection = crate::test::test_setup().await;
+
+        let pagination = |after_index| {
+            RankingPagination {
+                before_index: None,
+                after_index,
+                limit: Some(2),
+                nation: None,
+                name_contains: None,
+                continent: None,
+                subdivision: None,
+                active_since: None,
+                sort: None,
+            }
+        };
+
+        // There are 5 ranked players in the test data, so with a limit of 2 there are three pages
+        let first_page = ranking_response(pagination(None), &mut connection).await;
+        let links = header(&first_page, "link");
+
+        assert_eq!(header(&first_page, "x-total-count"), "5");
+        assert_eq!(header(&first_page, "links"), links);
+        assert!(links.contains("</api/v1/players/ranking/?after=0&limit=2>; rel=first"));
+        assert!(links.contains("</api/v1/players/ranking/?before=6&limit=2>; rel=last"));
+        assert!(links.contains("</api/v1/players/ranking/?after=2&limit=2>; rel=next"));
+        assert!(!links.contains("rel=prev"));
+
+        let second_page = ranking_response(pagination(Some(2)), &mut connection).await;
+        let links = header(&second_page, "link");
+
+        assert_eq!(header(&second_page, "x-total-count"), "5");
+        assert!(links.contains("</api/v1/players/ranking/?after=4&limit=2>; rel=next"));
+        assert!(links.contains("</api/v1/players/ranking/?before=3&limit=2>; rel=prev"));
+
+        let last_page = ranking_response(pagination(Some(4)), &mut connection).await;
+        let links = header(&last_page, "link");
+
+        assert!(!links.contains("rel=next"));
+        assert!(links.contains("</api/v1/players/ranking/?before=5&limit=2>; rel=prev"));
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_last_record_at() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let everyone = Query::<RankingPagination>::from_query("").unwrap();
+        let last_record_at = |players: &[RankedPlayer], name: &str| {
+            players
+                .iter()
+                .find(|player| player.name.as_str() == name)
+                .map(|player| player.last_record_at)
+                .unwrap()
+        };
+
+        let players = everyone.page(&mut connection).await.unwrap();
+
+        // Mullsy only verified a demon, and Aquatias' only record is rejected
+        assert!(last_record_at(&players, "stardust1971").is_some());
+        assert!(last_record_at(&players, "Aaron Ari").is_some());
+        assert_eq!(last_record_at(&players, "Mullsy"), None);
+        assert_eq!(last_record_at(&players, "Aquatias"), None);
+
+        sqlx::query!("UPDATE records SET status_ = 'APPROVED' WHERE progress = 80")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        // Pretend Aeon Air has been inactive for a while
+        sqlx::query!("UPDATE players SET last_record_at = '2020-01-01' WHERE name = 'Aeon Air'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let players = everyone.page(&mut connection).await.unwrap();
+
+        assert!(last_record_at(&players, "Aquatias").is_some());
+
+        let active = Query::<RankingPagination>::from_query("active_since=2021-01-01").unwrap();
+        let players = active.page(&mut connection).await.unwrap();
+        let names: Vec<&str> = players.iter().map(|player| player.name.as_str()).collect();
+
+        assert_eq!(names, vec!["stardust1971", "Aaron Ari", "Aquatias"]);
+        assert_eq!(active.count(&mut connection).await.unwrap(), 3);
+    }
+
+    /// The names of all players in the ranking sorted by the given order, fetched page by page
+    async fn sorted_names(sort: RankingSort, limit: u8, connection: &mut PgConnection) -> Vec<String> {
+        let mut pagination = RankingPagination::unfiltered(None, Some(limit));
+        let mut names = Vec::new();
+
+        pagination.sort = Some(sort);
+
+        loop {
+            let mut players = pagination.page(connection).await.unwrap();
+            let next_page_exists = players.len() > limit as usize;
+
+            players.truncate(limit as usize);
+            pagination.after_index = players.last().map(|player| player.index);
+            names.extend(players.into_iter().map(|player| player.name.as_str().to_string()));
+
+            if !next_page_exists {
+                return names
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_sort_orders() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET last_record_at = '2021-03-01' WHERE name = 'stardust1971' OR name = 'Aaron Ari'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE players SET last_record_at = '2021-05-01' WHERE name = 'Aeon Air'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE players SET last_record_at = NULL WHERE name = 'Aquatias' OR name = 'Mullsy'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(sorted_names(RankingSort::Score, 50, &mut connection).await, vec![
+            "stardust1971",
+            "Aaron Ari",
+            "Aquatias",
+            "Mullsy",
+            "Aeon Air"
+        ]);
+        assert_eq!(sorted_names(RankingSort::Name, 50, &mut connection).await, vec![
+            "Aaron Ari",
+            "Aeon Air",
+            "Aquatias",
+            "Mullsy",
+            "stardust1971"
+        ]);
+        // stardust1971 and Aaron Ari both have two approved records, Aquatias and Mullsy none. Ties
+        // are broken by ID
+        assert_eq!(sorted_names(RankingSort::RecordCount, 50, &mut connection).await, vec![
+            "stardust1971",
+            "Aaron Ari",
+            "Aeon Air",
+            "Aquatias",
+            "Mullsy"
+        ]);
+        assert_eq!(sorted_names(RankingSort::LastActive, 50, &mut connection).await, vec![
+            "Aeon Air",
+            "stardust1971",
+            "Aaron Ari",
+            "Aquatias",
+            "Mullsy"
+        ]);
+
+        let query = Query::<RankingPagination>::from_query("sort=record_count").unwrap();
+
+        assert_eq!(query.sort, Some(RankingSort::RecordCount));
+        assert!(Query::<RankingPagination>::from_query("sort=hardest").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_sort_stable_pagination() {
+        let mut connection = crate::test::test_setup().await;
+
+        // Lots of ties, none of which may cause a player to show up on two pages (or none at all)
+        for sort in &[RankingSort::Score, RankingSort::Name, RankingSort::RecordCount, RankingSort::LastActive] {
+            let everyone = sorted_names(*sort, 50, &mut connection).await;
+
+            for limit in 1..=4 {
+                assert_eq!(sorted_names(*sort, limit, &mut connection).await, everyone, "{:?} with limit {}", sort, limit);
+            }
+        }
+
+        let pagination = Query::<RankingPagination>::from_query("sort=name&limit=2").unwrap().into_inner();
+        let response = ranking_response(pagination, &mut connection).await;
+
+        // The sort order is kept when following links
+        assert!(header(&response, "link").contains("</api/v1/players/ranking/?after=2&limit=2&sort=name>; rel=next"));
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_etag_changes_on_record_write() {
+        let mut connection = crate::test::test_setup().await;
+
+        // list_last_modified is only touched when a transaction commits, which test transactions never do
+        sqlx::query!("SET CONSTRAINTS ALL IMMEDIATE").execute(&mut connection).await.unwrap();
+
+        let pagination = Query::<RankingPagination>::from_query("limit=2").unwrap().into_inner();
+        let etag = pagination.etag(last_modified(&mut connection).await.unwrap());
+
+        // Reading the ranking does not change anything
+        ranking_response(pagination.clone(), &mut connection).await;
+
+        assert_eq!(pagination.etag(last_modified(&mut connection).await.unwrap()), etag);
+
+        // Different pages of the same ranking have different ETags
+        let next_page = Query::<RankingPagination>::from_query("limit=2&after=2").unwrap().into_inner();
+
+        assert_ne!(next_page.etag(last_modified(&mut connection).await.unwrap()), etag);
+
+        sqlx::query!("UPDATE records SET progress = 95 FROM players WHERE players.id = records.player AND players.name = 'Aeon Air'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        assert_ne!(pagination.etag(last_modified(&mut connection).await.unwrap()), etag);
+    }
+
+    #[actix_rt::test]
+    async fn test_ranking_etag_changes_on_creator_write() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("SET CONSTRAINTS ALL IMMEDIATE").execute(&mut connection).await.unwrap();
+
+        let pagination = Query::<RankingPagination>::from_query("limit=2").unwrap().into_inner();
+        let etag = pagination.etag(last_modified(&mut connection).await.unwrap());
+
+        // Creators of published demons are part of the ranking
+        sqlx::query!(
+            "INSERT INTO creators (demon, creator) SELECT demons.id, players.id FROM demons, players WHERE demons.name = 'Trichotomy' AND \
+             players.name = 'Samifying'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        assert_ne!(pagination.etag(last_modified(&mut connection).await.unwrap()), etag);
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     error::PointercrateError,
     model::{
         demonlist::{
-            player::{DatabasePlayer, FullPlayer, Player},
+            player::{merge::log_former_name, DatabasePlayer, FullPlayer, Player},
             record::{approved_records_by, FullRecord},
         },
         nationality::Nationality,
@@ -25,6 +25,25 @@ pub struct PatchPlayer {
 
     #[serde(default, deserialize_with = "nullable")]
     nationality: Option<Option<CiString>>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    subdivision: Option<Option<CiString>>,
+}
+
+impl PatchPlayer {
+    /// Whether this patch only changes fields that users with an approved claim on the player are
+    /// allowed to change themselves, namely the nationality and subdivision
+    pub fn claimant_editable(&self) -> bool {
+        // No `..` here, so that new fields have to be explicitly allowed (or not)
+        let PatchPlayer {
+            name,
+            banned,
+            nationality: _,
+            subdivision: _,
+        } = self;
+
+        name.is_none() && banned.is_none()
+    }
 }
 
 impl FullPlayer {
@@ -39,6 +58,13 @@ impl FullPlayer {
             }
         }
 
+        if let Some(subdivision) = patch.subdivision {
+            match subdivision {
+                Some(code) => self.player.set_subdivision(code.as_str(), connection).await?,
+                None => self.player.reset_subdivision(connection).await?,
+            }
+        }
+
         if let Some(banned) = patch.banned {
             if banned && !self.player.base.banned {
                 self.player.base.ban(connection).await?;
@@ -73,6 +99,8 @@ impl FullPlayer {
                 Err(PointercrateError::ModelNotFound { .. }) => (),
                 Err(err) => return Err(err),
             }
+
+            log_former_name(&self.player.base, self.player.base.name.as_ref(), &mut *connection).await?;
         }
 
         sqlx::query!(
@@ -161,6 +189,11 @@ impl FullPlayer {
 
         info!("Moved {} records from {} to {}", updated.rows_affected(), with, self);
 
+        // Keep the names the second player was previously known under
+        sqlx::query!("UPDATE player_name_history SET player = $1 WHERE player = $2", self.player.base.id, with.id)
+            .execute(&mut *connection)
+            .await?;
+
         // Delete the second player
         sqlx::query!("DELETE FROM players WHERE id = $1", with.id)
             .execute(connection)
@@ -172,7 +205,7 @@ impl FullPlayer {
 
 impl Player {
     pub async fn reset_nationality(&mut self, connection: &mut PgConnection) -> Result<()> {
-        sqlx::query!("UPDATE players SET nationality = NULL WHERE id = $1", self.base.id)
+        sqlx::query!("UPDATE players SET nationality = NULL, subdivision = NULL WHERE id = $1", self.base.id)
             .execute(connection)
             .await?;
 
@@ -181,10 +214,19 @@ impl Player {
         Ok(())
     }
 
-    pub async fn set_nationality(&mut self, nationality: Nationality, connection: &mut PgConnection) -> Result<()> {
+    /// Sets this player's nationality
+    ///
+    /// The player's subdivision is retained if the nation does not change, and reset otherwise.
+    pub async fn set_nationality(&mut self, mut nationality: Nationality, connection: &mut PgConnection) -> Result<()> {
+        nationality.subdivision = match self.nationality {
+            Some(ref old) if old.iso_country_code == nationality.iso_country_code => old.subdivision.clone(),
+            _ => None,
+        };
+
         sqlx::query!(
-            "UPDATE players SET nationality = $1::text WHERE id = $2",
+            "UPDATE players SET nationality = $1::text, subdivision = $2::text WHERE id = $3",
             nationality.iso_country_code,
+            nationality.subdivision.as_ref().map(|subdivision| subdivision.iso_code.as_str()),
             self.base.id
         )
         .execute(connection)
@@ -194,6 +236,38 @@ impl Player {
 
         Ok(())
     }
+
+    pub async fn reset_subdivision(&mut self, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!("UPDATE players SET subdivision = NULL WHERE id = $1", self.base.id)
+            .execute(connection)
+            .await?;
+
+        if let Some(ref mut nationality) = self.nationality {
+            nationality.subdivision = None;
+        }
+
+        Ok(())
+    }
+
+    /// Sets this player's subdivision to the subdivision of their nation with the given code
+    ///
+    /// Fails with [`PointercrateError::NoNationSet`] if the player does not have a nationality.
+    pub async fn set_subdivision(&mut self, code: &str, connection: &mut PgConnection) -> Result<()> {
+        let nationality = self.nationality.as_mut().ok_or(PointercrateError::NoNationSet)?;
+        let subdivision = nationality.subdivision_by_code(code, &mut *connection).await?;
+
+        sqlx::query!(
+            "UPDATE players SET subdivision = $1::text WHERE id = $2",
+            subdivision.iso_code,
+            self.base.id
+        )
+        .execute(connection)
+        .await?;
+
+        nationality.subdivision = Some(subdivision);
+
+        Ok(())
+    }
 }
 
 impl DatabasePlayer {
@@ -244,6 +318,7 @@ impl DatabasePlayer {
 mod tests {
     use crate::{
         cistring::{CiStr, CiString},
+        error::PointercrateError,
         model::demonlist::{
             player::{DatabasePlayer, PatchPlayer, Player},
             record::{RecordPagination, RecordStatus},
@@ -273,6 +348,69 @@ mod tests {
         assert_eq!(player_after.unwrap(), player_before);
     }
 
+    #[actix_rt::test]
+    async fn test_set_subdivision() {
+        let mut connection = crate::test::test_setup().await;
+        let player_id = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection)
+            .await
+            .unwrap()
+            .id;
+        let player = Player::by_id(player_id, &mut connection).await.unwrap();
+        let player = player.upgrade(&mut connection).await.unwrap();
+
+        let patch = PatchPlayer {
+            nationality: Some(Some(CiString("US".to_owned()))),
+            subdivision: Some(Some(CiString("US-CA".to_owned()))),
+            ..Default::default()
+        };
+
+        let patched_player = player.apply_patch(patch, &mut connection).await.unwrap();
+        let subdivision = patched_player
+            .player
+            .nationality
+            .as_ref()
+            .and_then(|nationality| nationality.subdivision.as_ref())
+            .unwrap();
+
+        assert_eq!(subdivision.iso_code, "CA");
+
+        // see if database and model are consistent
+        let player = Player::by_id(player_id, &mut connection).await.unwrap();
+
+        assert_eq!(player, patched_player.player);
+
+        // changing the nation resets the subdivision
+        let patch = PatchPlayer {
+            nationality: Some(Some(CiString("DE".to_owned()))),
+            ..Default::default()
+        };
+
+        let patched_player = patched_player.apply_patch(patch, &mut connection).await.unwrap();
+
+        assert_eq!(patched_player.player.nationality.unwrap().subdivision, None);
+    }
+
+    #[actix_rt::test]
+    async fn test_subdivision_requires_nationality() {
+        let mut connection = crate::test::test_setup().await;
+        let player_id = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection)
+            .await
+            .unwrap()
+            .id;
+        let player = Player::by_id(player_id, &mut connection).await.unwrap();
+        let player = player.upgrade(&mut connection).await.unwrap();
+
+        let patch = PatchPlayer {
+            subdivision: Some(Some(CiString("US-CA".to_owned()))),
+            ..Default::default()
+        };
+
+        match player.apply_patch(patch, &mut connection).await {
+            Err(PointercrateError::NoNationSet) => (),
+            result => panic!("expected NoNationSet error, got {:?}", result),
+        }
+    }
+
     #[actix_rt::test]
     async fn test_ban_player() {
         let mut connection = crate::test::test_setup().await;
@@ -0,0 +1,76 @@
+use crate::{
+    cistring::CiString,
+    model::demonlist::{demon::MinimalDemon, player::DatabasePlayer, record::Progress},
+    Result,
+};
+use futures::stream::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// All progress values a player has on a single demon, not counting completions
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DemonProgress {
+    pub demon: MinimalDemon,
+
+    /// The progress values of all approved records of the player on this demon, highest first
+    pub progress: Vec<Progress>,
+}
+
+/// Gets the progress the given player has made on demons they have not completed (or at least,
+/// have approved records below 100% on), ordered by demon position
+pub async fn progress_of(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<DemonProgress>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT progress, progress_decimal, demons.id AS demon_id, demons.name AS "name: String", demons.position FROM records INNER JOIN
+         demons ON records.demon = demons.id WHERE status_ = 'APPROVED' AND records.player = $1 AND progress < 100 AND demons.published
+         ORDER BY demons.position, progress DESC, progress_decimal DESC"#,
+        player.id
+    )
+    .fetch(connection);
+
+    let mut progress: Vec<DemonProgress> = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        let record_progress = Progress::new(row.progress, row.progress_decimal);
+
+        match progress.last_mut() {
+            Some(last) if last.demon.id == row.demon_id => last.progress.push(record_progress),
+            _ =>
+                progress.push(DemonProgress {
+                    demon: MinimalDemon {
+                        id: row.demon_id,
+                        position: row.position,
+                        name: CiString(row.name),
+                    },
+                    progress: vec![record_progress],
+                }),
+        }
+    }
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::progress_of;
+    use crate::{
+        cistring::CiStr,
+        model::demonlist::{demon::MinimalDemon, player::DatabasePlayer, record::Progress},
+    };
+
+    #[actix_rt::test]
+    async fn test_only_approved_non_completions_of_player() {
+        let mut connection = crate::test::test_setup().await;
+
+        // stardust1971 has an approved 90% on abstract interpretation, an approved 100% on
+        // Trichotomy and an unapproved 100% on abstract interpretation. Aeon Air also has an
+        // approved 90%, on Trichotomy.
+        let player = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection).await.unwrap();
+        let demon = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection).await.unwrap();
+        let progress = progress_of(&player, &mut connection).await.unwrap();
+
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].demon, demon);
+        assert_eq!(progress[0].progress, vec![Progress::from(90)]);
+    }
+}
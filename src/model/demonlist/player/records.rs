@@ -0,0 +1,189 @@
+use crate::{
+    cistring::CiString,
+    error::PointercrateError,
+    model::{
+        demonlist::{
+            demon::MinimalDemon,
+            player::DatabasePlayer,
+            record::{MinimalRecordD, Progress, RecordPagination, RecordStatus},
+        },
+        user::User,
+    },
+    util::non_nullable,
+    Result,
+};
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Row};
+
+/// A record in a player's [record listing](PlayerRecordPagination)
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct PlayerRecord {
+    #[serde(flatten)]
+    pub record: MinimalRecordD,
+
+    /// The index of this record in the player's records, ordered by demon position
+    #[serde(skip)]
+    pub index: i64,
+}
+
+/// Pagination over the records of a single player, ordered by the position of the demon they
+/// were made on. The `before` and `after` indices refer to positions in this order
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PlayerRecordPagination {
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "before")]
+    pub before_index: Option<i64>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "after")]
+    pub after_index: Option<i64>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub limit: Option<u8>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub status: Option<RecordStatus>,
+
+    /// Only settable by list moderators, everyone else only ever sees records on published demons
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub demon_published: Option<bool>,
+}
+
+impl PlayerRecordPagination {
+    /// Restricts this pagination to the records the given user (or an unauthenticated client, if
+    /// [`None`]) is allowed to see
+    ///
+    /// Delegates to [`RecordPagination::restrict_for`], so the same rules apply to the `status`
+    /// and `demon_published` filters of both listings.
+    pub fn restrict_for(&mut self, user: Option<&User>) -> Result<()> {
+        let mut restricted = RecordPagination::default();
+
+        restricted.status = self.status;
+        restricted.demon_published = self.demon_published;
+        restricted.restrict_for(user)?;
+
+        self.status = restricted.status;
+        self.demon_published = restricted.demon_published;
+
+        Ok(())
+    }
+
+    /// Retrieves the page of the given player's records matching this pagination
+    ///
+    /// Like all other paginations, this returns one more record than requested, and the page is in
+    /// reverse order if only _before_ is set.
+    pub async fn page(&self, player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<PlayerRecord>> {
+        if let Some(limit) = self.limit {
+            if limit < 1 || limit > 100 {
+                return Err(PointercrateError::InvalidPaginationLimit)
+            }
+        }
+
+        let order = if self.before_index.is_some() && self.after_index.is_none() {
+            "DESC"
+        } else {
+            "ASC"
+        };
+
+        let query = format!(include_str!("../../../../sql/paginate_player_records.sql"), order);
+
+        let mut stream = sqlx::query(&query)
+            .bind(player.id)
+            .bind(self.status.map(|status| status.to_sql()))
+            .bind(self.demon_published)
+            .bind(self.before_index)
+            .bind(self.after_index)
+            .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .fetch(connection);
+
+        let mut records = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            records.push(PlayerRecord {
+                record: MinimalRecordD {
+                    id: row.get("id"),
+                    progress: Progress::new(row.get("progress"), row.get("progress_decimal")),
+                    video: row.get("video"),
+                    status: RecordStatus::from_sql(&row.get::<String, _>("status")),
+                    demon: MinimalDemon {
+                        id: row.get("demon_id"),
+                        position: row.get("position"),
+                        name: CiString(row.get("demon_name")),
+                    },
+                },
+                index: row.get("index"),
+            })
+        }
+
+        Ok(records)
+    }
+
+    /// Counts all of the given player's records matching this pagination's `status` and
+    /// `demon_published` filters
+    pub async fn count(&self, player: &DatabasePlayer, connection: &mut PgConnection) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!" FROM records INNER JOIN demons ON records.demon = demons.id WHERE player = $1 AND (status_ =
+             CAST($2::TEXT AS record_status) OR $2 IS NULL) AND (demons.published = $3 OR $3 IS NULL)"#,
+            player.id,
+            self.status.map(|status| status.to_sql()),
+            self.demon_published
+        )
+        .fetch_one(connection)
+        .await?
+        .count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PlayerRecordPagination;
+    use crate::{
+        cistring::CiStr,
+        error::PointercrateError,
+        model::demonlist::{player::DatabasePlayer, record::RecordStatus},
+    };
+
+    #[actix_rt::test]
+    async fn test_anonymous_sees_approved_records_only() {
+        let mut connection = crate::test::test_setup().await;
+
+        // stardust1971 has a submitted and an approved record on abstract interpretation (#1), and an
+        // approved one on Trichotomy (#2)
+        let player = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection).await.unwrap();
+
+        let mut pagination = PlayerRecordPagination {
+            limit: Some(1),
+            ..Default::default()
+        };
+
+        pagination.restrict_for(None).unwrap();
+
+        assert_eq!(pagination.status, Some(RecordStatus::Approved));
+        assert_eq!(pagination.demon_published, Some(true));
+        assert_eq!(pagination.count(&player, &mut connection).await.unwrap(), 2);
+
+        // One more record than requested is returned to signal that another page exists
+        let first = pagination.page(&player, &mut connection).await.unwrap();
+
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].record.demon.position, 1);
+        assert!(first.iter().all(|record| record.record.status == RecordStatus::Approved));
+
+        pagination.after_index = Some(first[0].index);
+
+        let second = pagination.page(&player, &mut connection).await.unwrap();
+
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].record.demon.position, 2);
+
+        let mut submitted = PlayerRecordPagination {
+            status: Some(RecordStatus::Submitted),
+            ..Default::default()
+        };
+
+        assert_eq!(submitted.restrict_for(None), Err(PointercrateError::Unauthorized));
+    }
+}
@@ -0,0 +1,194 @@
+//! Nationality suggestions based on the country of a player's YouTube channel
+//!
+//! A player's channel is the YouTube channel of the user with an approved claim on them. The
+//! suggested nationality is never applied automatically, a moderator has to confirm it by patching
+//! the player.
+
+use crate::{
+    cistring::CiStr,
+    error::PointercrateError,
+    model::{demonlist::player::DatabasePlayer, nationality::Nationality},
+    video::youtube::YouTubeApi,
+    Result,
+};
+use log::{debug, warn};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// The reason no nationality could be suggested for a player
+#[derive(Debug, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NoSuggestion {
+    /// The player has not been claimed, or the claimant has not linked a YouTube channel
+    NoChannel,
+
+    /// The channel does not have a country set (or does not exist anymore)
+    NoCountry,
+
+    /// The channel's country is not one of our nationalities
+    UnknownCountry,
+
+    /// The YouTube Data API is not configured or could not be reached
+    Unavailable,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct NationalitySuggestion {
+    pub channel: Option<String>,
+    pub nationality: Option<Nationality>,
+
+    /// Why no nationality was suggested, if none was
+    pub reason: Option<NoSuggestion>,
+}
+
+impl NationalitySuggestion {
+    fn none(channel: Option<String>, reason: NoSuggestion) -> Self {
+        NationalitySuggestion {
+            channel,
+            nationality: None,
+            reason: Some(reason),
+        }
+    }
+}
+
+impl DatabasePlayer {
+    /// Gets the YouTube channel of the user with an approved claim on this player, if any
+    pub async fn youtube_channel(&self, connection: &mut PgConnection) -> Result<Option<String>> {
+        Ok(sqlx::query!(
+            r#"SELECT members.youtube_channel::TEXT AS "youtube_channel!" FROM player_claims INNER JOIN members ON members.member_id =
+             player_claims.member_id WHERE player_id = $1 AND status = 'APPROVED' AND youtube_channel IS NOT NULL"#,
+            self.id
+        )
+        .fetch_optional(connection)
+        .await?
+        .map(|row| row.youtube_channel))
+    }
+
+    /// Suggests a nationality for this player based on the country of their YouTube channel
+    ///
+    /// Failures to talk to the YouTube Data API are not propagated, they simply result in no
+    /// nationality being suggested.
+    pub async fn suggest_nationality(&self, api: Option<&YouTubeApi>, connection: &mut PgConnection) -> Result<NationalitySuggestion> {
+        let channel = match self.youtube_channel(&mut *connection).await? {
+            Some(channel) => channel,
+            None => return Ok(NationalitySuggestion::none(None, NoSuggestion::NoChannel)),
+        };
+
+        let api = match api {
+            Some(api) => api,
+            None => return Ok(NationalitySuggestion::none(Some(channel), NoSuggestion::Unavailable)),
+        };
+
+        let country = match api.channel_country(&channel).await {
+            Ok(Some(country)) => country,
+            Ok(None) => return Ok(NationalitySuggestion::none(Some(channel), NoSuggestion::NoCountry)),
+            Err(error) => {
+                warn!("Failed to look up the country of YouTube channel {}: {}", channel, error);
+
+                return Ok(NationalitySuggestion::none(Some(channel), NoSuggestion::Unavailable))
+            },
+        };
+
+        debug!("YouTube channel {} of player {} is located in {}", channel, self, country);
+
+        match Nationality::by_country_code_or_name(CiStr::from_str(&country), connection).await {
+            Ok(nationality) =>
+                Ok(NationalitySuggestion {
+                    channel: Some(channel),
+                    nationality: Some(nationality),
+                    reason: None,
+                }),
+            Err(PointercrateError::ModelNotFound { .. }) => Ok(NationalitySuggestion::none(Some(channel), NoSuggestion::UnknownCountry)),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NoSuggestion;
+    use crate::{
+        cistring::CiStr,
+        model::demonlist::player::DatabasePlayer,
+        video::youtube::{tests::mock_api, YouTubeApi},
+    };
+    use reqwest::Client;
+    use sqlx::PgConnection;
+
+    /// Links the given channel to the given player via an approved claim
+    async fn link_channel(player: &DatabasePlayer, channel: &str, connection: &mut PgConnection) {
+        let member_id = sqlx::query!(
+            "INSERT INTO members (name, password_hash, youtube_channel) VALUES ($1, 'not a real hash', $2) RETURNING member_id",
+            format!("claimant of {}", player.name),
+            channel
+        )
+        .fetch_one(&mut *connection)
+        .await
+        .unwrap()
+        .member_id;
+
+        sqlx::query!(
+            "INSERT INTO player_claims (member_id, player_id, status) VALUES ($1, $2, 'APPROVED')",
+            member_id,
+            player.id
+        )
+        .execute(connection)
+        .await
+        .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_suggest_nationality() {
+        let mut connection = crate::test::test_setup().await;
+        let api = YouTubeApi::new(Client::new(), mock_api(), "key".to_string());
+
+        let aeon_air = DatabasePlayer::by_name(CiStr::from_str("Aeon Air"), &mut connection).await.unwrap();
+        let mullsy = DatabasePlayer::by_name(CiStr::from_str("Mullsy"), &mut connection).await.unwrap();
+        let aquatias = DatabasePlayer::by_name(CiStr::from_str("Aquatias"), &mut connection).await.unwrap();
+
+        link_channel(&aeon_air, "https://www.youtube.com/channel/UCgermangermangermangerm", &mut connection).await;
+        link_channel(&mullsy, "https://www.youtube.com/@nocountry", &mut connection).await;
+
+        let suggestion = aeon_air.suggest_nationality(Some(&api), &mut connection).await.unwrap();
+
+        assert_eq!(suggestion.reason, None);
+        assert_eq!(suggestion.nationality.unwrap().iso_country_code, "DE");
+
+        // Only a suggestion, nothing is applied
+        let nationality = sqlx::query!("SELECT nationality FROM players WHERE id = $1", aeon_air.id)
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .nationality;
+
+        assert_eq!(nationality, None);
+
+        let suggestion = mullsy.suggest_nationality(Some(&api), &mut connection).await.unwrap();
+
+        assert_eq!(suggestion.channel.as_deref(), Some("https://www.youtube.com/@nocountry"));
+        assert_eq!(suggestion.reason, Some(NoSuggestion::NoCountry));
+
+        let suggestion = aquatias.suggest_nationality(Some(&api), &mut connection).await.unwrap();
+
+        assert_eq!(suggestion.reason, Some(NoSuggestion::NoChannel));
+
+        let suggestion = aeon_air.suggest_nationality(None, &mut connection).await.unwrap();
+
+        assert_eq!(suggestion.reason, Some(NoSuggestion::Unavailable));
+    }
+
+    #[actix_rt::test]
+    async fn test_suggest_nationality_api_failure() {
+        let mut connection = crate::test::test_setup().await;
+        let api = YouTubeApi::new(Client::new(), mock_api(), "key".to_string());
+
+        let aeon_air = DatabasePlayer::by_name(CiStr::from_str("Aeon Air"), &mut connection).await.unwrap();
+
+        link_channel(&aeon_air, "https://www.youtube.com/user/broken", &mut connection).await;
+
+        let suggestion = aeon_air.suggest_nationality(Some(&api), &mut connection).await.unwrap();
+
+        assert_eq!(suggestion.nationality, None);
+        assert_eq!(suggestion.reason, Some(NoSuggestion::Unavailable));
+    }
+}
@@ -22,10 +22,13 @@
 //!   the 'under consideration' status makes. A record under consideration IS NOT UNIQUE!
 
 pub use self::{
-    get::{approved_records_by, approved_records_on},
+    attempts::validate_attempts,
+    batch::StatusChange,
+    get::{approved_records_by, approved_records_on, first_victor_on},
     paginate::RecordPagination,
     patch::PatchRecord,
     post::Submission,
+    progress::{progress_step, validate_configured_progress, Progress},
 };
 use crate::{
     model::{
@@ -45,13 +48,18 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+pub mod anomalies;
+mod attempts;
 pub mod audit;
+mod batch;
 mod delete;
 mod get;
 pub mod note;
 mod paginate;
 mod patch;
 mod post;
+mod progress;
+pub mod recheck;
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub enum RecordStatus {
@@ -62,7 +70,7 @@ pub enum RecordStatus {
 }
 
 impl RecordStatus {
-    fn to_sql(&self) -> String {
+    pub(crate) fn to_sql(&self) -> String {
         match self {
             RecordStatus::Submitted => "SUBMITTED",
             RecordStatus::Approved => "APPROVED",
@@ -72,7 +80,7 @@ impl RecordStatus {
         .to_owned()
     }
 
-    fn from_sql(sql: &str) -> Self {
+    pub(crate) fn from_sql(sql: &str) -> Self {
         match sql {
             "SUBMITTED" => RecordStatus::Submitted,
             "APPROVED" => RecordStatus::Approved,
@@ -134,8 +142,19 @@ impl<'de> Deserialize<'de> for RecordStatus {
 #[display(fmt = "{} {}% on {} (ID: {})", player, progress, demon, id)]
 pub struct FullRecord {
     pub id: i32,
-    pub progress: i16,
+    pub progress: Progress,
     pub video: Option<String>,
+
+    /// The point (in seconds) in the video at which the actual completion starts, if the video
+    /// contains more than just the completion (e.g. a stream VOD)
+    pub video_timestamp: Option<i32>,
+
+    /// Whether the last [recheck](recheck) of this record's video found it to be unavailable
+    pub video_dead: bool,
+
+    /// The number of attempts the player needed for this record. Logging attempts is optional, so
+    /// this is `None` for most records
+    pub attempts: Option<i32>,
     pub status: RecordStatus,
     pub player: DatabasePlayer,
     pub demon: MinimalDemon,
@@ -148,10 +167,13 @@ impl Hash for FullRecord {
         self.id.hash(state);
         self.progress.hash(state);
         self.video.hash(state);
+        self.video_timestamp.hash(state);
+        self.attempts.hash(state);
         self.status.hash(state);
         self.player.id.hash(state);
         self.demon.id.hash(state);
         // notes have sub-endpoint -> no hash
+        // video_dead is maintained by the video recheck -> no hash
         // submitter cannot be patched -> no hash
     }
 }
@@ -160,7 +182,7 @@ impl Hash for FullRecord {
 #[display(fmt = "{} {}% on {} (ID: {})", player, progress, demon, id)]
 pub struct MinimalRecordPD {
     pub id: i32,
-    pub progress: i16,
+    pub progress: Progress,
     pub video: Option<String>,
     pub status: RecordStatus,
     pub demon: MinimalDemon,
@@ -171,18 +193,21 @@ pub struct MinimalRecordPD {
 #[display(fmt = "{}% on {} (ID: {})", progress, demon, id)]
 pub struct MinimalRecordD {
     pub id: i32,
-    pub progress: i16,
+    pub progress: Progress,
     pub video: Option<String>,
     pub status: RecordStatus,
     pub demon: MinimalDemon,
 }
 
-#[derive(Debug, Hash, Serialize, Display, PartialEq, Eq)]
+#[derive(Debug, Hash, Serialize, Display, PartialEq, Eq, Clone)]
 #[display(fmt = "{} - {}% (ID: {})", player, progress, id)]
 pub struct MinimalRecordP {
     pub id: i32,
-    pub progress: i16,
+    pub progress: Progress,
     pub video: Option<String>,
+
+    /// See [`FullRecord::video_timestamp`]
+    pub video_timestamp: Option<i32>,
     pub status: RecordStatus,
     pub player: DatabasePlayer,
     pub nationality: Option<Nationality>,
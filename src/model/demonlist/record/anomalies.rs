@@ -0,0 +1,136 @@
+//! Detection of approved records that should not have been approved
+//!
+//! None of these are ever fixed automatically, they are only surfaced to the list team as a safety
+//! net against moderation mistakes.
+
+use crate::{
+    cistring::CiString,
+    config,
+    model::demonlist::{
+        demon::{Demon, MinimalDemon},
+        player::DatabasePlayer,
+        record::{MinimalRecordPD, Progress, RecordStatus},
+    },
+    Result,
+};
+use futures::stream::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// An approved record whose progress is below the current requirement of its demon
+#[derive(Debug, Serialize)]
+pub struct BelowRequirement {
+    #[serde(flatten)]
+    pub record: MinimalRecordPD,
+
+    /// The demon's current [effective requirement](Demon::effective_requirement)
+    pub requirement: i16,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Anomalies {
+    /// Approved records below their demon's requirement, that were not grandfathered in (see
+    /// [`Demon::grandfather_records`](crate::model::demonlist::demon::Demon::grandfather_records))
+    pub below_requirement: Vec<BelowRequirement>,
+}
+
+impl Anomalies {
+    pub async fn find(connection: &mut PgConnection) -> Result<Anomalies> {
+        Ok(Anomalies {
+            below_requirement: below_requirement(connection).await?,
+        })
+    }
+}
+
+async fn below_requirement(connection: &mut PgConnection) -> Result<Vec<BelowRequirement>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT records.id, records.progress, records.progress_decimal, records.video::TEXT, players.id AS player_id,
+         players.name::TEXT AS "player_name!", players.banned, demons.id AS demon_id, demons.name::TEXT AS "demon_name!", demons.position, demons.requirement FROM records
+         INNER JOIN players ON records.player = players.id INNER JOIN demons ON records.demon = demons.id WHERE records.status_ = 'APPROVED'
+         AND records.progress < CASE WHEN demons.position > $1 THEN 100 ELSE demons.requirement END AND NOT records.grandfathered
+         ORDER BY demons.position, records.id"#,
+        config::list_size()
+    )
+    .fetch(connection);
+
+    let mut anomalies = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        anomalies.push(BelowRequirement {
+            record: MinimalRecordPD {
+                id: row.id,
+                progress: Progress::new(row.progress, row.progress_decimal),
+                video: row.video,
+                status: RecordStatus::Approved,
+                demon: MinimalDemon {
+                    id: row.demon_id,
+                    position: row.position,
+                    name: CiString(row.demon_name),
+                },
+                player: DatabasePlayer {
+                    id: row.player_id,
+                    name: CiString(row.player_name),
+                    banned: row.banned,
+                },
+            },
+            requirement: Demon::effective_requirement(row.requirement, row.position),
+        })
+    }
+
+    Ok(anomalies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Anomalies;
+
+    #[actix_rt::test]
+    async fn test_waived_records_not_reported() {
+        let mut connection = crate::test::test_setup().await;
+
+        // Record 2 (90% on abstract interpretation) and record 4 (90% on Trichotomy) both end up below
+        // requirement, but the latter was grandfathered in
+        sqlx::query!("UPDATE demons SET requirement = 95 WHERE name IN ('abstract interpretation', 'Trichotomy')")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE records SET grandfathered = TRUE WHERE id = 4")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let anomalies = Anomalies::find(&mut connection).await.unwrap();
+        let flagged: Vec<i32> = anomalies.below_requirement.iter().map(|anomaly| anomaly.record.id).collect();
+
+        assert_eq!(flagged, vec![2]);
+        assert_eq!(anomalies.below_requirement[0].requirement, 95);
+        assert_eq!(anomalies.below_requirement[0].record.progress.percent, 90);
+    }
+
+    #[actix_rt::test]
+    async fn test_requirement_outside_main_list() {
+        let mut connection = crate::test::test_setup().await;
+
+        // Moving Trichotomy out of the main list grandfathers in record 4 (90%)
+        let trichotomy = crate::test::move_trichotomy_to_extended(&mut connection).await;
+
+        // 95% is above Trichotomy's requirement of 84, but only 100% records count outside the main list
+        let record_id = sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 95, 'APPROVED', id, (SELECT submitter_id FROM \
+             submitters LIMIT 1), $1 FROM players WHERE name = 'Mullsy' RETURNING id",
+            trichotomy.id
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        let anomalies = Anomalies::find(&mut connection).await.unwrap();
+        let flagged: Vec<i32> = anomalies.below_requirement.iter().map(|anomaly| anomaly.record.id).collect();
+
+        assert_eq!(flagged, vec![record_id]);
+        assert_eq!(anomalies.below_requirement[0].requirement, 100);
+    }
+}
@@ -0,0 +1,88 @@
+//! Attempt counts logged with records
+//!
+//! Logging attempts is entirely opt-in, so most records do not have an attempt count. Players
+//! without any logged attempts have no total either, rather than a total of zero.
+
+use crate::{error::PointercrateError, model::demonlist::player::DatabasePlayer, Result};
+use sqlx::PgConnection;
+
+/// Validates an attempt count given for a record
+pub fn validate_attempts(attempts: i32) -> Result<i32> {
+    if attempts < 0 {
+        return Err(PointercrateError::NegativeAttempts)
+    }
+
+    Ok(attempts)
+}
+
+impl DatabasePlayer {
+    /// Sums up the attempts logged for this player's approved records, or `None` if none of them
+    /// has an attempt count
+    pub async fn total_attempts(&self, connection: &mut PgConnection) -> Result<Option<i64>> {
+        Ok(sqlx::query!(
+            "SELECT SUM(attempts) AS total_attempts FROM records WHERE player = $1 AND status_ = 'APPROVED'",
+            self.id
+        )
+        .fetch_one(connection)
+        .await?
+        .total_attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_attempts;
+    use crate::{
+        cistring::CiStr,
+        error::PointercrateError,
+        model::demonlist::player::{DatabasePlayer, RankingPagination},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_attempts() {
+        assert_eq!(validate_attempts(0), Ok(0));
+        assert_eq!(validate_attempts(12345), Ok(12345));
+        assert_eq!(validate_attempts(-1), Err(PointercrateError::NegativeAttempts));
+    }
+
+    #[actix_rt::test]
+    async fn test_total_attempts() {
+        let mut connection = crate::test::test_setup().await;
+
+        // stardust1971 has approved records on abstract interpretation and Trichotomy, and a
+        // submitted one on abstract interpretation. Aaron Ari has approved records on Trichotomy and
+        // terminal void
+        sqlx::query!(
+            "UPDATE records SET attempts = CASE WHEN status_ = 'SUBMITTED' THEN 1000 WHEN demon = 1 THEN 2500 ELSE 1200 END FROM players \
+             WHERE players.id = records.player AND players.name = 'stardust1971'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE records SET attempts = 4000 FROM players, demons WHERE players.id = records.player AND demons.id = records.demon AND \
+             players.name = 'Aaron Ari' AND demons.name = 'Trichotomy'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        let stardust = DatabasePlayer::by_name(CiStr::from_str("stardust1971"), &mut connection).await.unwrap();
+        let aaron_ari = DatabasePlayer::by_name(CiStr::from_str("Aaron Ari"), &mut connection).await.unwrap();
+        let mullsy = DatabasePlayer::by_name(CiStr::from_str("Mullsy"), &mut connection).await.unwrap();
+
+        // Attempts on submitted records do not count, records without attempts are skipped
+        assert_eq!(stardust.total_attempts(&mut connection).await.unwrap(), Some(3700));
+        assert_eq!(aaron_ari.total_attempts(&mut connection).await.unwrap(), Some(4000));
+        assert_eq!(mullsy.total_attempts(&mut connection).await.unwrap(), None);
+
+        let pagination: RankingPagination = serde_json::from_value(json!({"sort": "attempts"})).unwrap();
+        let ranking = pagination.page(&mut connection).await.unwrap();
+
+        assert_eq!(ranking[0].id, aaron_ari.id);
+        assert_eq!(ranking[0].total_attempts, Some(4000));
+        assert_eq!(ranking[1].id, stardust.id);
+        assert_eq!(ranking[2].total_attempts, None);
+    }
+}
@@ -1,18 +1,19 @@
-use crate::{model::demonlist::record::RecordStatus, Result};
+use crate::{
+    model::{
+        demonlist::record::{Progress, RecordStatus},
+        NamedId,
+    },
+    Result,
+};
 use chrono::NaiveDateTime;
 use futures::StreamExt;
 use serde::Serialize;
 use sqlx::PgConnection;
 
-#[derive(Serialize)]
-pub struct NamedId {
-    id: i32,
-    name: Option<String>,
-}
-
 #[derive(Serialize)]
 pub struct RecordModificationData {
-    progress: Option<i16>,
+    progress: Option<Progress>,
+    attempts: Option<i32>,
     video: Option<String>,
     status: Option<RecordStatus>,
     player: Option<NamedId>,
@@ -70,6 +71,8 @@ pub async fn entries_for_record(record_id: i32, connection: &mut PgConnection) -
                   members.name AS "username?",
                   userid,
                   progress,
+                  record_modifications.progress_decimal,
+                  record_modifications.attempts,
                   record_modifications.video,
                   status_::TEXT,
                   players.name::TEXT AS player_name,
@@ -93,7 +96,11 @@ pub async fn entries_for_record(record_id: i32, connection: &mut PgConnection) -
                 audit_id: modification.audit_id,
                 record_id,
                 r#type: RecordEntryType::Modification(RecordModificationData {
-                    progress: modification.progress,
+                    // entries predating decimal progress do not log any tenths
+                    progress: modification
+                        .progress
+                        .map(|percent| Progress::new(percent, modification.progress_decimal.unwrap_or(0))),
+                    attempts: modification.attempts,
                     status: modification.status_.as_deref().map(RecordStatus::from_sql),
                     player: match modification.player_id {
                         Some(id) =>
@@ -146,3 +153,62 @@ pub async fn entries_for_record(record_id: i32, connection: &mut PgConnection) -
 
     Ok(entries)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::entries_for_record;
+    use crate::state::audit_connection;
+    use serde_json::{json, Value};
+
+    #[actix_rt::test]
+    async fn test_decimal_progress_changes() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        // the rejected 80% record by Aquatias
+        let record_id = sqlx::query!("UPDATE records SET progress_decimal = 5 WHERE progress = 80 RETURNING id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+        sqlx::query!("UPDATE records SET progress = 85, progress_decimal = 0 WHERE id = $1", record_id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let entries = entries_for_record(record_id, &mut connection).await.unwrap();
+        let progress: Vec<Value> = entries
+            .iter()
+            .map(|entry| serde_json::to_value(entry).unwrap()["type"]["Modification"]["progress"].clone())
+            .collect();
+
+        assert_eq!(progress, vec![json!(80), json!(80.5)]);
+    }
+
+    #[actix_rt::test]
+    async fn test_attempt_changes() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let record_id = sqlx::query!("UPDATE records SET attempts = 1200 WHERE progress = 80 RETURNING id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+        sqlx::query!("UPDATE records SET attempts = 1500 WHERE id = $1", record_id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let entries = entries_for_record(record_id, &mut connection).await.unwrap();
+        let attempts: Vec<Value> = entries
+            .iter()
+            .map(|entry| serde_json::to_value(entry).unwrap()["type"]["Modification"]["attempts"].clone())
+            .collect();
+
+        // the record had no attempts logged before the first modification
+        assert_eq!(attempts, vec![Value::Null, json!(1200)]);
+    }
+}
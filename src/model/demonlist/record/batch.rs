@@ -0,0 +1,190 @@
+use crate::{
+    model::{
+        demonlist::record::{FullRecord, RecordStatus},
+        user::User,
+    },
+    permissions::Permissions,
+    Result,
+};
+use log::info;
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+/// A single status change in a batch of status changes
+#[derive(Debug, Deserialize)]
+pub struct StatusChange {
+    pub id: i32,
+    pub status: RecordStatus,
+}
+
+impl FullRecord {
+    /// Applies a batch of status changes on behalf of the given user
+    ///
+    /// Every change is applied in its own savepoint. Changes that fail validation (including those
+    /// the user does not have the permissions for, as records on demons below `extended_list_size`
+    /// can only be modified by list moderators) are rolled back individually, while the rest of the
+    /// batch is still applied. Any other error (such as a database error) aborts the whole batch.
+    ///
+    /// The returned results are in the same order as the given changes, and contain the status each
+    /// record had before the change alongside the updated record. Must be run within a transaction!
+    pub async fn set_status_batch(
+        batch: Vec<StatusChange>, user: &User, extended_list_size: i16, connection: &mut PgConnection,
+    ) -> Result<Vec<Result<(RecordStatus, FullRecord)>>> {
+        info!("Applying batch of {} status changes on behalf of {}", batch.len(), user);
+
+        let mut results = Vec::with_capacity(batch.len());
+
+        for change in batch {
+            sqlx::query("SAVEPOINT batch_record").execute(&mut *connection).await?;
+
+            let result = Self::apply_status_change(change, user, extended_list_size, connection).await;
+
+            match result {
+                Ok(_) => sqlx::query("RELEASE SAVEPOINT batch_record").execute(&mut *connection).await?,
+                // Errors in the 5xx range indicate that something went wrong on our end, so we shouldn't
+                // commit anything
+                Err(ref error) if error.error_code() >= 50000 => return Err(error.clone()),
+                Err(_) => sqlx::query("ROLLBACK TO SAVEPOINT batch_record").execute(&mut *connection).await?,
+            };
+
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    async fn apply_status_change(
+        change: StatusChange, user: &User, extended_list_size: i16, connection: &mut PgConnection,
+    ) -> Result<(RecordStatus, FullRecord)> {
+        let mut record = FullRecord::by_id(change.id, connection).await?;
+
+        if record.demon.position > extended_list_size {
+            user.require_permissions(Permissions::ListModerator)?;
+        } else {
+            user.require_permissions(Permissions::ListHelper)?;
+        }
+
+        let previous_status = record.status;
+
+        record.validate_status_change(change.status, connection).await?;
+        record.set_status(change.status, connection).await?;
+
+        Ok((previous_status, record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusChange;
+    use crate::{
+        error::PointercrateError,
+        model::{
+            demonlist::record::{FullRecord, RecordStatus},
+            user::User,
+        },
+        permissions::Permissions,
+    };
+    use sqlx::PgConnection;
+
+    fn helper() -> User {
+        User {
+            id: 0,
+            name: "helper".to_owned(),
+            permissions: Permissions::ListHelper,
+            display_name: None,
+            youtube_channel: None,
+        }
+    }
+
+    async fn record_id(player: &str, demon: &str, status: &str, connection: &mut PgConnection) -> i32 {
+        sqlx::query!(
+            "SELECT records.id FROM records INNER JOIN players ON records.player = players.id INNER JOIN demons ON records.demon = demons.id \
+             WHERE players.name = $1::TEXT AND demons.name = $2::TEXT AND status_ = CAST($3::TEXT AS record_status)",
+            player.to_string(),
+            demon.to_string(),
+            status.to_string()
+        )
+        .fetch_one(connection)
+        .await
+        .unwrap()
+        .id
+    }
+
+    #[actix_rt::test]
+    async fn test_mixed_batch() {
+        let mut connection = crate::test::test_setup().await;
+
+        let submitted = record_id("stardust1971", "abstract interpretation", "SUBMITTED", &mut connection).await;
+        let approved = record_id("Aeon Air", "Trichotomy", "APPROVED", &mut connection).await;
+
+        let results = FullRecord::set_status_batch(
+            vec![
+                StatusChange {
+                    id: submitted,
+                    status: RecordStatus::Approved,
+                },
+                StatusChange {
+                    id: 0,
+                    status: RecordStatus::Approved,
+                },
+                StatusChange {
+                    id: approved,
+                    status: RecordStatus::Rejected,
+                },
+            ],
+            &helper(),
+            100,
+            &mut connection,
+        )
+        .await
+        .unwrap();
+
+        let (previous_status, record) = results[0].as_ref().unwrap();
+
+        assert_eq!(*previous_status, RecordStatus::Submitted);
+        assert_eq!(record.status, RecordStatus::Approved);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().1.status, RecordStatus::Rejected);
+
+        // the other items of the batch were still applied
+        assert_eq!(FullRecord::by_id(submitted, &mut connection).await.unwrap().status, RecordStatus::Approved);
+        assert_eq!(FullRecord::by_id(approved, &mut connection).await.unwrap().status, RecordStatus::Rejected);
+    }
+
+    #[actix_rt::test]
+    async fn test_batch_with_missing_permissions() {
+        let mut connection = crate::test::test_setup().await;
+
+        // With an extended list size of 2, terminal void (#3) is on the legacy list, where only list
+        // moderators can modify records
+        let legacy = record_id("Aaron Ari", "terminal void", "APPROVED", &mut connection).await;
+        let listed = record_id("Aaron Ari", "Trichotomy", "APPROVED", &mut connection).await;
+
+        let results = FullRecord::set_status_batch(
+            vec![
+                StatusChange {
+                    id: legacy,
+                    status: RecordStatus::Rejected,
+                },
+                StatusChange {
+                    id: listed,
+                    status: RecordStatus::Rejected,
+                },
+            ],
+            &helper(),
+            2,
+            &mut connection,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            results[0].as_ref().unwrap_err(),
+            &PointercrateError::MissingPermissions {
+                required: Permissions::ListModerator
+            }
+        );
+        assert!(results[1].is_ok());
+        assert_eq!(FullRecord::by_id(legacy, &mut connection).await.unwrap().status, RecordStatus::Approved);
+    }
+}
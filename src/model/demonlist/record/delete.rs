@@ -18,7 +18,16 @@ impl FullRecord {
 
 #[cfg(test)]
 mod tests {
-    use crate::model::demonlist::record::FullRecord;
+    use crate::model::demonlist::record::{FullRecord, RecordStatus};
+    use sqlx::PgConnection;
+
+    async fn record_count(demon: &str, connection: &mut PgConnection) -> i32 {
+        sqlx::query!("SELECT record_count FROM demons WHERE name = $1::TEXT", demon.to_string())
+            .fetch_one(connection)
+            .await
+            .unwrap()
+            .record_count
+    }
 
     #[actix_rt::test]
     async fn test_delete_record() {
@@ -57,4 +66,43 @@ mod tests {
         assert!(result.is_ok(), "{:?}", result.unwrap_err());
         assert!(FullRecord::by_id(id, &mut connection).await.is_err());
     }
+
+    #[actix_rt::test]
+    async fn test_record_count_maintained() {
+        let mut connection = crate::test::test_setup().await;
+
+        assert_eq!(record_count("abstract interpretation", &mut connection).await, 1);
+
+        let id = sqlx::query!("SELECT id FROM records WHERE status_='REJECTED'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+
+        let mut record = FullRecord::by_id(id, &mut connection).await.unwrap();
+
+        record.set_status(RecordStatus::Approved, &mut connection).await.unwrap();
+
+        assert_eq!(record_count("abstract interpretation", &mut connection).await, 2);
+
+        record.delete(&mut connection).await.unwrap();
+
+        assert_eq!(record_count("abstract interpretation", &mut connection).await, 1);
+
+        // records that were never approved do not count
+        let id = sqlx::query!("SELECT id FROM records WHERE status_='SUBMITTED'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+
+        FullRecord::by_id(id, &mut connection)
+            .await
+            .unwrap()
+            .delete(&mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(record_count("abstract interpretation", &mut connection).await, 1);
+    }
 }
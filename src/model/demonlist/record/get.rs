@@ -5,10 +5,10 @@ use crate::{
         demonlist::{
             demon::MinimalDemon,
             player::DatabasePlayer,
-            record::{note::notes_on, FullRecord, MinimalRecordD, MinimalRecordP, RecordStatus},
+            record::{note::notes_on, FullRecord, MinimalRecordD, MinimalRecordP, Progress, RecordStatus},
             submitter::Submitter,
         },
-        nationality::Nationality,
+        nationality::{Continent, Nationality, Subdivision},
     },
     Result,
 };
@@ -18,7 +18,11 @@ use sqlx::{Error, PgConnection};
 // Required until https://github.com/launchbadge/sqlx/pull/108 is merged
 struct FetchedRecord {
     progress: i16,
+    progress_decimal: i16,
     video: Option<String>,
+    video_timestamp: Option<i32>,
+    video_dead: bool,
+    attempts: Option<i32>,
     status: String,
     player_id: i32,
     player_name: String,
@@ -40,8 +44,11 @@ impl FullRecord {
             Ok(row) =>
                 Ok(FullRecord {
                     id,
-                    progress: row.progress,
+                    progress: Progress::new(row.progress, row.progress_decimal),
                     video: row.video,
+                    video_timestamp: row.video_timestamp,
+                    video_dead: row.video_dead,
+                    attempts: row.attempts,
                     status: RecordStatus::from_sql(&row.status),
                     player: DatabasePlayer {
                         id: row.player_id,
@@ -72,9 +79,9 @@ impl FullRecord {
 
 pub async fn approved_records_by(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<MinimalRecordD>> {
     let mut stream = sqlx::query!(
-        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, demons.id AS demon_id, 
-         demons.name as "name: String", demons.position FROM records INNER JOIN demons ON records.demon = demons.id INNER JOIN players ON players.id 
-         = $1 WHERE status_ = 'APPROVED' AND records.player = $1"#,
+        r#"SELECT records.id, progress, progress_decimal, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, 
+         demons.id AS demon_id, demons.name as "name: String", demons.position FROM records INNER JOIN demons ON records.demon = demons.id 
+         INNER JOIN players ON players.id = $1 WHERE status_ = 'APPROVED' AND records.player = $1 AND demons.published"#,
         player.id
     )
     .fetch(connection);
@@ -86,7 +93,7 @@ pub async fn approved_records_by(player: &DatabasePlayer, connection: &mut PgCon
 
         records.push(MinimalRecordD {
             id: row.id,
-            progress: row.progress,
+            progress: Progress::new(row.progress, row.progress_decimal),
             video: row.video,
             status: RecordStatus::Approved,
             demon: MinimalDemon {
@@ -104,19 +111,26 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
     struct Fetched {
         id: i32,
         progress: i16,
+        progress_decimal: i16,
         video: Option<String>,
+        video_timestamp: Option<i32>,
         player_id: i32,
         name: String,
         banned: bool,
         nation: Option<String>,
+        continent: Option<String>,
         iso_country_code: Option<String>,
+        subdivision_code: Option<String>,
+        subdivision_name: Option<String>,
     }
 
     let mut stream = sqlx::query_as!(
         Fetched,
-        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
-         players.name AS "name: String", players.banned, nation::TEXT, iso_country_code::TEXT FROM records INNER JOIN players ON records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code WHERE status_ = 'APPROVED' AND 
-         records.demon = $1 ORDER BY progress DESC, id ASC"#,
+        r#"SELECT records.id, progress, progress_decimal, CASE WHEN players.link_banned THEN NULL ELSE video::text END, video_timestamp, players.id AS player_id, 
+         players.name AS "name: String", players.banned, nationalities.nation::TEXT, iso_country_code::TEXT, continent::TEXT, subdivisions.iso_code AS "subdivision_code?", 
+         subdivisions.name::TEXT AS "subdivision_name?" FROM records INNER JOIN players ON records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code 
+         LEFT OUTER JOIN subdivisions ON players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code WHERE status_ = 'APPROVED' AND 
+         records.demon = $1 ORDER BY progress DESC, progress_decimal DESC, id ASC"#,
         demon.id
     )
     .fetch(connection);
@@ -128,8 +142,9 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
 
         records.push(MinimalRecordP {
             id: row.id,
-            progress: row.progress,
+            progress: Progress::new(row.progress, row.progress_decimal),
             video: row.video,
+            video_timestamp: row.video_timestamp,
             status: RecordStatus::Approved,
             player: DatabasePlayer {
                 id: row.player_id,
@@ -141,6 +156,11 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
                     Some(Nationality {
                         iso_country_code: code,
                         nation: CiString(nation),
+                        continent: row.continent.as_deref().map(Continent::from_sql),
+                        subdivision: row
+                            .subdivision_code
+                            .zip(row.subdivision_name)
+                            .map(|(code, name)| Subdivision::new(code, CiString(name))),
                     }),
                 _ => None,
             },
@@ -149,3 +169,116 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
 
     Ok(records)
 }
+
+/// Gets the ID of the record of the first player to complete the given demon after its verifier,
+/// if anyone did
+///
+/// Records are ordered by the time they were approved, as reconstructed from the audit log by the
+/// `record_approved_at` SQL function. Ties are broken by record ID.
+pub async fn first_victor_on(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Option<i32>> {
+    Ok(sqlx::query!(r#"SELECT demon_first_victor($1) AS "record_id""#, demon.id)
+        .fetch_one(connection)
+        .await?
+        .record_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{approved_records_on, first_victor_on};
+    use crate::{cistring::CiStr, model::demonlist::demon::MinimalDemon, state::audit_connection};
+    use chrono::{Duration, Utc};
+
+    #[actix_rt::test]
+    async fn test_approved_records_include_nationality() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET nationality = 'US', subdivision = 'AL' WHERE name = 'stardust1971'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+        let records = approved_records_on(&demon, &mut connection).await.unwrap();
+
+        let holder = records.iter().find(|record| record.player.name.as_str() == "stardust1971").unwrap();
+        let nationality = holder.nationality.as_ref().unwrap();
+
+        assert_eq!(nationality.iso_country_code, "US");
+        assert_eq!(nationality.subdivision.as_ref().unwrap().iso_code, "AL");
+
+        // holders without a nationality are still listed
+        let holder = records.iter().find(|record| record.player.name.as_str() == "Aaron Ari").unwrap();
+
+        assert_eq!(holder.nationality, None);
+    }
+
+    #[actix_rt::test]
+    async fn test_first_victor() {
+        let mut connection = crate::test::test_setup().await;
+
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+        let aaron_ari = sqlx::query!(
+            "SELECT records.id FROM records INNER JOIN players ON players.id = player WHERE players.name = 'Aaron Ari' AND demon = $1",
+            trichotomy.id
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        // Aeon Air's record is no completion and the verifier's (stardust1971) record does not count,
+        // leaving Aaron Ari's
+        assert_eq!(first_victor_on(&trichotomy, &mut connection).await.unwrap(), Some(aaron_ari));
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        let samifying = sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT 100, 'APPROVED', players.id, submitter_id, $1 FROM \
+             players, submitters WHERE players.name = 'Samifying' LIMIT 1 RETURNING id",
+            trichotomy.id
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        // Records without audit data predate those with it
+        assert_eq!(first_victor_on(&trichotomy, &mut connection).await.unwrap(), Some(aaron_ari));
+
+        // Aaron Ari's record was actually only approved tomorrow
+        sqlx::query!(
+            "INSERT INTO record_modifications (time, userid, id, status_) VALUES ($1, 0, $2, 'SUBMITTED')",
+            Utc::now().naive_utc() + Duration::days(1),
+            aaron_ari
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        assert_eq!(first_victor_on(&trichotomy, &mut connection).await.unwrap(), Some(samifying));
+
+        // Approved at the same time as Samifying's record, which was added later
+        sqlx::query!(
+            "UPDATE record_modifications SET time = (SELECT time FROM record_additions WHERE id = $1) WHERE id = $2",
+            samifying,
+            aaron_ari
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        assert_eq!(first_victor_on(&trichotomy, &mut connection).await.unwrap(), Some(aaron_ari));
+    }
+
+    #[actix_rt::test]
+    async fn test_no_first_victor() {
+        let mut connection = crate::test::test_setup().await;
+
+        // only has a 90% record by its verifier
+        let demon = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection)
+            .await
+            .unwrap();
+
+        assert_eq!(first_victor_on(&demon, &mut connection).await.unwrap(), None);
+    }
+}
@@ -1,17 +1,18 @@
 pub use self::{get::notes_on, patch::PatchNote, post::NewNote};
-use serde::Serialize;
+use crate::{config, error::PointercrateError};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 use std::hash::{Hash, Hasher};
 
 mod delete;
 mod get;
+mod markdown;
 mod patch;
 mod post;
 
-#[derive(Serialize, Debug)]
+#[derive(Debug)]
 pub struct Note {
     pub id: i32,
 
-    #[serde(skip)]
     pub record: i32,
 
     pub content: String,
@@ -31,6 +32,41 @@ pub struct Note {
     pub editors: Vec<String>,
 }
 
+/// Strips all control characters except line breaks and tabs from the given note content, and makes
+/// sure that what remains is at most [`max_note_length`](config::max_note_length) characters long
+///
+/// Does not check whether the note is empty, as the places notes are created in handle that
+/// differently.
+pub fn sanitize_content(content: &str) -> crate::Result<String> {
+    let sanitized: String = content
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        .collect();
+    let max_length = config::max_note_length();
+
+    if sanitized.chars().count() > max_length {
+        return Err(PointercrateError::NoteTooLong { max_length })
+    }
+
+    Ok(sanitized)
+}
+
+// Implemented manually so that we can include the rendered version of the note's content, which is
+// never stored
+impl Serialize for Note {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut note = serializer.serialize_struct("Note", 6)?;
+
+        note.serialize_field("id", &self.id)?;
+        note.serialize_field("content", &self.content)?;
+        note.serialize_field("content_html", &markdown::render(&self.content))?;
+        note.serialize_field("transferred", &self.transferred)?;
+        note.serialize_field("author", &self.author)?;
+        note.serialize_field("editors", &self.editors)?;
+        note.end()
+    }
+}
+
 impl Hash for Note {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.content.hash(state)
@@ -0,0 +1,227 @@
+//! Rendering of record notes to HTML
+//!
+//! Notes are stored exactly as they were submitted. When displaying them, we support a small,
+//! safe subset of markdown (links, bold and italic text, line breaks and lists). Everything else
+//! is HTML-escaped, so that notes can never inject markup into the pages they are shown on.
+
+/// Renders the given note content to sanitized HTML
+pub fn render(content: &str) -> String {
+    let mut html = String::with_capacity(content.len());
+    let mut open_list: Option<&'static str> = None;
+    let mut needs_break = false;
+
+    for line in content.lines() {
+        match list_item(line) {
+            Some((list, item)) => {
+                if open_list != Some(list) {
+                    if let Some(open) = open_list {
+                        close_tag(open, &mut html);
+                    }
+
+                    open_tag(list, &mut html);
+                    open_list = Some(list);
+                }
+
+                html.push_str("<li>");
+                render_inline(item, &mut html);
+                html.push_str("</li>");
+
+                needs_break = false;
+            },
+            None => {
+                // Lists are block elements, so there is no need for an explicit line break after them
+                if let Some(open) = open_list.take() {
+                    close_tag(open, &mut html);
+                } else if needs_break {
+                    html.push_str("<br>");
+                }
+
+                render_inline(line, &mut html);
+
+                needs_break = true;
+            },
+        }
+    }
+
+    if let Some(open) = open_list {
+        close_tag(open, &mut html);
+    }
+
+    html
+}
+
+/// Checks whether the given line is an item of an unordered (`- item`, `* item`) or ordered (`1.
+/// item`) list, returning the kind of list and the item's text
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    let line = line.trim_start();
+
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return Some(("ul", &line[2..]))
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some(("ol", &line[digits + 2..]))
+    }
+
+    None
+}
+
+fn render_inline(text: &str, html: &mut String) {
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+
+    while let Some(next) = rest.chars().next() {
+        if rest.starts_with("**") {
+            if let Some(end) = rest[2..].find("**").filter(|&end| end > 0) {
+                html.push_str("<b>");
+                render_inline(&rest[2..2 + end], html);
+                html.push_str("</b>");
+
+                rest = &rest[4 + end..];
+                previous = Some('*');
+                continue
+            }
+        } else if next == '*' || next == '_' {
+            if let Some(end) = emphasis_end(rest, next, previous) {
+                html.push_str("<i>");
+                render_inline(&rest[1..1 + end], html);
+                html.push_str("</i>");
+
+                rest = &rest[2 + end..];
+                previous = Some(next);
+                continue
+            }
+        } else if next == '[' {
+            if let Some((label, url, length)) = link(rest) {
+                html.push_str("<a target=\"_blank\" rel=\"nofollow noopener\" href=\"");
+                escape(url, html);
+                html.push_str("\">");
+                render_inline(label, html);
+                html.push_str("</a>");
+
+                rest = &rest[length..];
+                previous = Some(')');
+                continue
+            }
+        }
+
+        escape(&rest[..next.len_utf8()], html);
+
+        rest = &rest[next.len_utf8()..];
+        previous = Some(next);
+    }
+}
+
+/// Finds the end of an emphasized section started by the given delimiter at the start of `text`,
+/// returning its offset relative to the first character after the opening delimiter
+///
+/// Underscores only delimit emphasis at word boundaries, as they commonly appear inside player
+/// names.
+fn emphasis_end(text: &str, delimiter: char, previous: Option<char>) -> Option<usize> {
+    let end = text[1..].find(delimiter).filter(|&end| end > 0)?;
+
+    if delimiter == '_' {
+        let after = text[2 + end..].chars().next();
+
+        if previous.map_or(false, char::is_alphanumeric) || after.map_or(false, char::is_alphanumeric) {
+            return None
+        }
+    }
+
+    Some(end)
+}
+
+/// Parses a `[label](url)` link at the start of `text`, returning the label, the url and the total
+/// length of the link syntax.
+///
+/// Only absolute `http` and `https` urls are accepted, to prevent `javascript:` links and the like.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+
+    if label.is_empty() || label.contains('[') {
+        return None
+    }
+
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    let url = &text[url_start..url_end];
+
+    if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace) {
+        return None
+    }
+
+    Some((label, url, url_end + 1))
+}
+
+fn open_tag(tag: &str, html: &mut String) {
+    html.push('<');
+    html.push_str(tag);
+    html.push('>');
+}
+
+fn close_tag(tag: &str, html: &mut String) {
+    html.push_str("</");
+    html.push_str(tag);
+    html.push('>');
+}
+
+fn escape(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn test_script_is_escaped() {
+        assert_eq!(
+            render("<script>alert('hi')</script>"),
+            "&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn test_link() {
+        assert_eq!(
+            render("see [this video](https://youtu.be/dQw4w9WgXcQ)"),
+            "see <a target=\"_blank\" rel=\"nofollow noopener\" href=\"https://youtu.be/dQw4w9WgXcQ\">this video</a>"
+        );
+
+        // Only http(s) links are allowed, and attributes cannot be broken out of
+        assert_eq!(render("[click](javascript:alert(1))"), "[click](javascript:alert(1))");
+        assert_eq!(
+            render("[x](https://a.com/\"onclick=\"alert(1))"),
+            "<a target=\"_blank\" rel=\"nofollow noopener\" href=\"https://a.com/&quot;onclick=&quot;alert(1\">x</a>)"
+        );
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(render("**bold** and *italic* and _also italic_"), "<b>bold</b> and <i>italic</i> and <i>also italic</i>");
+        assert_eq!(render("checked by some_player_name"), "checked by some_player_name");
+        assert_eq!(render("**<b>**"), "<b>&lt;b&gt;</b>");
+    }
+
+    #[test]
+    fn test_line_breaks_and_lists() {
+        assert_eq!(render("first\nsecond"), "first<br>second");
+        assert_eq!(
+            render("issues:\n- no clicks\n- spliced\nrejected"),
+            "issues:<ul><li>no clicks</li><li>spliced</li></ul>rejected"
+        );
+        assert_eq!(render("1. one\n2. two"), "<ol><li>one</li><li>two</li></ol>");
+    }
+}
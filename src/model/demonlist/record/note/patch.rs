@@ -1,4 +1,9 @@
-use crate::{error::PointercrateError, model::demonlist::record::note::Note, util::non_nullable, Result};
+use crate::{
+    error::PointercrateError,
+    model::demonlist::record::note::{sanitize_content, Note},
+    util::non_nullable,
+    Result,
+};
 use serde::Deserialize;
 use sqlx::PgConnection;
 
@@ -11,6 +16,8 @@ pub struct PatchNote {
 impl Note {
     pub async fn apply_patch(mut self, patch: PatchNote, connection: &mut PgConnection) -> Result<Note> {
         if let Some(content) = patch.content {
+            let content = sanitize_content(&content).map_err(|error| error.in_field("content"))?;
+
             if content.trim().is_empty() {
                 return Err(PointercrateError::NoteEmpty)
             }
@@ -1,6 +1,9 @@
 use crate::{
     error::PointercrateError,
-    model::demonlist::record::{note::Note, FullRecord},
+    model::demonlist::record::{
+        note::{sanitize_content, Note},
+        FullRecord,
+    },
     Result,
 };
 use serde::Deserialize;
@@ -17,14 +20,16 @@ impl Note {
     /// This does **not** insert the note into the records `notes` vector! Also doesn't set the
     /// `author` field!
     pub async fn create_on(record: &FullRecord, new_note: NewNote, connection: &mut PgConnection) -> Result<Note> {
-        if new_note.content.trim().is_empty() {
+        let content = sanitize_content(&new_note.content).map_err(|error| error.in_field("content"))?;
+
+        if content.trim().is_empty() {
             return Err(PointercrateError::NoteEmpty)
         }
 
         let note_id = sqlx::query!(
             "INSERT INTO record_notes (record, content) VALUES ($1, $2) RETURNING id",
             record.id,
-            new_note.content
+            content
         )
         .fetch_one(connection)
         .await?
@@ -33,7 +38,7 @@ impl Note {
         Ok(Note {
             id: note_id,
             record: record.id,
-            content: new_note.content,
+            content,
             transferred: false,
             author: None,
             editors: vec![],
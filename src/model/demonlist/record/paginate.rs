@@ -1,11 +1,15 @@
 use crate::{
     cistring::CiString,
     error::PointercrateError,
-    model::demonlist::{
-        demon::MinimalDemon,
-        player::DatabasePlayer,
-        record::{MinimalRecordPD, RecordStatus},
+    model::{
+        demonlist::{
+            demon::MinimalDemon,
+            player::DatabasePlayer,
+            record::{MinimalRecordPD, Progress, RecordStatus},
+        },
+        user::User,
     },
+    permissions::Permissions,
     util::{non_nullable, nullable},
     Result,
 };
@@ -63,9 +67,59 @@ pub struct RecordPagination {
 
     #[serde(default, deserialize_with = "non_nullable")]
     pub submitter: Option<i32>,
+
+    /// Only include records whose video the last [recheck](super::recheck) found to be (un)available
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub video_dead: Option<bool>,
+
+    /// Only settable by list moderators, everyone else only ever sees records on published demons
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub demon_published: Option<bool>,
 }
 
 impl RecordPagination {
+    /// Restricts this pagination to the records the given user (or an unauthenticated client, if
+    /// [`None`]) is allowed to see
+    ///
+    /// Approved records are public, and listings without a `status` filter are limited to them
+    /// unless the user is at least a [list helper](Permissions::ListHelper), who is also required
+    /// for filtering by any other status or by `video_dead`. Filtering by `submitter` requires
+    /// [`ListModerator`](Permissions::ListModerator) permissions, who are also the only ones to see
+    /// records on unpublished demons. Unauthenticated clients asking for something restricted get an
+    /// [`Unauthorized`](PointercrateError::Unauthorized) error.
+    pub fn restrict_for(&mut self, user: Option<&User>) -> Result<()> {
+        let require = |permissions: Permissions| {
+            match user {
+                Some(user) => user.require_permissions(permissions),
+                None => Err(PointercrateError::Unauthorized),
+            }
+        };
+
+        if self.submitter.is_some() {
+            require(Permissions::ListModerator)?;
+        }
+
+        // Dead videos are only surfaced to the list team, which is responsible for handling them
+        if self.video_dead.is_some() {
+            require(Permissions::ListHelper)?;
+        }
+
+        match self.status {
+            Some(RecordStatus::Approved) => (),
+            Some(_) => require(Permissions::ListHelper)?,
+            None =>
+                if !user.map(|user| user.has_permission(Permissions::ListHelper)).unwrap_or(false) {
+                    self.status = Some(RecordStatus::Approved)
+                },
+        }
+
+        if !user.map(|user| user.has_permission(Permissions::ListModerator)).unwrap_or(false) {
+            self.demon_published = Some(true)
+        }
+
+        Ok(())
+    }
+
     /// Retries the page of records matching the pagination data in here
     ///
     /// Note that this method returns _one more record than requested_. This is used as a quick and
@@ -113,6 +167,8 @@ impl RecordPagination {
             .bind(self.video == Some(None))
             .bind(self.player)
             .bind(self.submitter)
+            .bind(self.video_dead)
+            .bind(self.demon_published)
             .bind(limit + 1)
             .fetch(&mut *connection);
 
@@ -123,7 +179,7 @@ impl RecordPagination {
 
             records.push(MinimalRecordPD {
                 id: row.try_get("id")?,
-                progress: row.try_get("progress")?,
+                progress: Progress::new(row.try_get("progress")?, row.try_get("progress_decimal")?),
                 video: row.try_get("video")?,
                 status: RecordStatus::from_sql(&row.try_get::<String, _>("status")?),
                 player: DatabasePlayer {
@@ -142,3 +198,106 @@ impl RecordPagination {
         Ok(records)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::RecordPagination;
+    use crate::{
+        error::PointercrateError,
+        model::{demonlist::record::RecordStatus, user::User},
+        permissions::Permissions,
+    };
+
+    fn user(permissions: Permissions) -> User {
+        User {
+            id: 1,
+            name: "stadust_existing".to_string(),
+            permissions,
+            display_name: None,
+            youtube_channel: None,
+        }
+    }
+
+    fn pagination(query: &str) -> RecordPagination {
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    #[test]
+    fn test_status_filter_requires_list_helper() {
+        let helper = user(Permissions::ListHelper);
+
+        assert!(matches!(
+            pagination("status=submitted").restrict_for(None),
+            Err(PointercrateError::Unauthorized)
+        ));
+
+        for permissions in &[Permissions::empty(), Permissions::ExtendedAccess] {
+            assert!(matches!(
+                pagination("status=rejected").restrict_for(Some(&user(*permissions))),
+                Err(PointercrateError::MissingPermissions {
+                    required: Permissions::ListHelper
+                })
+            ));
+        }
+
+        let mut submitted = pagination("status=submitted");
+
+        submitted.restrict_for(Some(&helper)).unwrap();
+
+        assert_eq!(submitted.status, Some(RecordStatus::Submitted));
+
+        // The list team sees everything by default
+        let mut unfiltered = pagination("");
+
+        unfiltered.restrict_for(Some(&helper)).unwrap();
+
+        assert_eq!(unfiltered.status, None);
+        assert_eq!(unfiltered.demon_published, Some(true));
+
+        let mut moderator = pagination("demon_published=false");
+
+        moderator.restrict_for(Some(&user(Permissions::ListModerator))).unwrap();
+
+        assert_eq!(moderator.demon_published, Some(false));
+
+        assert!(matches!(
+            pagination("submitter=1").restrict_for(Some(&helper)),
+            Err(PointercrateError::MissingPermissions {
+                required: Permissions::ListModerator
+            })
+        ));
+        assert!(matches!(
+            pagination("video_dead=true").restrict_for(None),
+            Err(PointercrateError::Unauthorized)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_public_pagination_approved_only() {
+        let mut connection = crate::test::test_setup().await;
+
+        for viewer in &[None, Some(user(Permissions::empty())), Some(user(Permissions::ExtendedAccess))] {
+            let mut public = pagination("");
+
+            public.restrict_for(viewer.as_ref()).unwrap();
+
+            let records = public.page(&mut connection).await.unwrap();
+
+            // 5 of the 7 records in the test data are approved
+            assert_eq!(records.len(), 5);
+            assert!(records.iter().all(|record| record.status == RecordStatus::Approved));
+
+            // Ordered by submission
+            assert!(records.windows(2).all(|pair| pair[0].id < pair[1].id));
+        }
+
+        let mut completions = pagination("progress__gt=90&progress__lt=101");
+
+        completions.restrict_for(None).unwrap();
+
+        let records = completions.page(&mut connection).await.unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record.progress.percent == 100 && record.status == RecordStatus::Approved));
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{
     cistring::CiString,
+    config,
     error::PointercrateError,
     model::demonlist::{
         demon::MinimalDemon,
         player::DatabasePlayer,
-        record::{FullRecord, RecordStatus},
+        record::{validate_attempts, validate_configured_progress, FullRecord, Progress, RecordStatus},
     },
     util::{non_nullable, nullable},
     Result,
@@ -16,11 +17,14 @@ use sqlx::{Done, PgConnection};
 #[derive(Debug, Deserialize)]
 pub struct PatchRecord {
     #[serde(default, deserialize_with = "non_nullable")]
-    progress: Option<i16>,
+    progress: Option<f64>,
 
     #[serde(default, deserialize_with = "nullable")]
     video: Option<Option<String>>,
 
+    #[serde(default, deserialize_with = "nullable")]
+    attempts: Option<Option<i32>>,
+
     #[serde(default, deserialize_with = "non_nullable")]
     status: Option<RecordStatus>,
 
@@ -39,6 +43,10 @@ impl FullRecord {
     pub async fn apply_patch(mut self, data: PatchRecord, connection: &mut PgConnection) -> Result<Self> {
         info!("Applying patch {:?} for record {}", data, self);
 
+        // Whether a record may be approved depends on its final progress and demon, so this can only be
+        // validated once all fields have been applied
+        let affects_approval = data.status.is_some() || data.progress.is_some() || data.demon.is_some() || data.demon_id.is_some();
+
         if let Some(progress) = data.progress {
             self.set_progress(progress, connection).await?;
         }
@@ -50,11 +58,17 @@ impl FullRecord {
             }
         }
 
+        if let Some(attempts) = data.attempts {
+            self.set_attempts(attempts, connection).await?;
+        }
+
         if let Some(status) = data.status {
             self.set_status(status, connection).await?
         }
 
         if let Some(player) = data.player {
+            DatabasePlayer::validate_name(player.as_ref()).map_err(|error| error.in_field("player"))?;
+
             let player = DatabasePlayer::by_name_or_create(player.as_ref(), connection).await?;
 
             self.set_player(player, connection).await?;
@@ -69,9 +83,41 @@ impl FullRecord {
             _ => (),
         }
 
+        if affects_approval && self.status == RecordStatus::Approved {
+            self.validate_approval(connection).await?;
+        }
+
         Ok(self)
     }
 
+    /// Validates that this record may be moved into the given status
+    pub(super) async fn validate_status_change(&self, status: RecordStatus, connection: &mut PgConnection) -> Result<()> {
+        if status != RecordStatus::Approved || self.status == RecordStatus::Approved {
+            return Ok(())
+        }
+
+        self.validate_approval(connection).await
+    }
+
+    /// Validates that this record, with its current progress and demon, may be approved
+    ///
+    /// Non-100% records on demons outside the main list can only be approved if they were
+    /// grandfathered, that is, made before the demon left the main list.
+    async fn validate_approval(&self, connection: &mut PgConnection) -> Result<()> {
+        if self.progress < Progress::from(100) && self.demon.position > config::list_size() {
+            let grandfathered = sqlx::query!("SELECT grandfathered FROM records WHERE id = $1", self.id)
+                .fetch_one(connection)
+                .await?
+                .grandfathered;
+
+            if !grandfathered {
+                return Err(PointercrateError::Non100Extended)
+            }
+        }
+
+        Ok(())
+    }
+
     /// Prepared turning `self` into a (player, demon)-record (either player or demon will be
     /// changed)
     async fn ensure_invariants(&mut self, player: i32, demon: i32, connection: &mut PgConnection) -> Result<()> {
@@ -119,16 +165,19 @@ impl FullRecord {
                 struct _Existing {
                     id: i32,
                     progress: i16,
+                    progress_decimal: i16,
                     video: Option<String>,
+                    video_timestamp: Option<i32>,
                 }
 
                 let row = sqlx::query_as!(
                     _Existing,
-                    "SELECT id, progress, video::TEXT FROM records WHERE status_ = 'APPROVED' AND demon = $1 AND player = $2 AND progress \
-                     > $3",
+                    "SELECT id, progress, progress_decimal, video::TEXT, video_timestamp FROM records WHERE status_ = 'APPROVED' AND demon = $1 \
+                     AND player = $2 AND (progress, progress_decimal) > ($3, $4)",
                     demon,
                     player,
-                    self.progress
+                    self.progress.percent,
+                    self.progress.decimal
                 )
                 .fetch_optional(&mut *connection)
                 .await?;
@@ -137,33 +186,42 @@ impl FullRecord {
                     sqlx::query!("DELETE FROM records WHERE id = $1", row.id)
                         .execute(&mut *connection)
                         .await?;
-                    sqlx::query("UPDATE records SET video = $1::TEXT, progress = $2 WHERE id = $3")
-                        .bind(&row.video)
-                        .bind(row.progress)
-                        .bind(self.id)
-                        .execute(&mut *connection)
-                        .await?;
+                    sqlx::query(
+                        "UPDATE records SET video = $1::TEXT, video_timestamp = $2, video_dead = FALSE, progress = $3, progress_decimal = $4 \
+                         WHERE id = $5",
+                    )
+                    .bind(&row.video)
+                    .bind(row.video_timestamp)
+                    .bind(row.progress)
+                    .bind(row.progress_decimal)
+                    .bind(self.id)
+                    .execute(&mut *connection)
+                    .await?;
 
-                    self.progress = row.progress;
+                    self.progress = Progress::new(row.progress, row.progress_decimal);
                     self.video = row.video;
+                    self.video_timestamp = row.video_timestamp;
+                    self.video_dead = false;
                 }
 
                 let notes_transferred = sqlx::query!(
                     "UPDATE record_notes SET record = $1 FROM records WHERE record_notes.record = records.id AND records.demon = $2 AND \
-                     records.player = $3 AND (records.status_ = 'REJECTED' OR records.progress <= $4)",
+                     records.player = $3 AND (records.status_ = 'REJECTED' OR (records.progress, records.progress_decimal) <= ($4, $5))",
                     self.id,
                     demon,
                     player,
-                    self.progress
+                    self.progress.percent,
+                    self.progress.decimal
                 )
                 .execute(&mut *connection)
                 .await?;
 
                 let records_deleted = sqlx::query!(
-                    "DELETE FROM records WHERE demon = $1 AND player = $2 AND (status_ = 'REJECTED' OR progress <= $3)",
+                    "DELETE FROM records WHERE demon = $1 AND player = $2 AND (status_ = 'REJECTED' OR (progress, progress_decimal) <= ($3, $4))",
                     demon,
                     player,
-                    self.progress
+                    self.progress.percent,
+                    self.progress.decimal
                 )
                 .execute(connection)
                 .await?;
@@ -185,34 +243,63 @@ impl FullRecord {
     }
 
     pub async fn delete_video(&mut self, connection: &mut PgConnection) -> Result<()> {
-        sqlx::query!("UPDATE records SET video = NULL WHERE id = $1", self.id)
+        sqlx::query!("UPDATE records SET video = NULL, video_timestamp = NULL, video_dead = FALSE WHERE id = $1", self.id)
             .execute(connection)
             .await?;
 
         self.video = None;
+        self.video_timestamp = None;
+        self.video_dead = false;
 
         Ok(())
     }
 
     pub async fn set_video(&mut self, video: String, connection: &mut PgConnection) -> Result<()> {
-        let video = crate::video::validate(&video)?;
+        let timestamp = crate::video::timestamp(&video);
+        let video = crate::video::validate(&video).map_err(|error| error.in_field("video"))?;
 
-        if Some(&video) == self.video.as_ref() {
+        if Some(&video) == self.video.as_ref() && timestamp == self.video_timestamp {
             return Ok(())
         }
 
-        if let Some(row) = sqlx::query!(r#"SELECT id FROM records WHERE video = $1"#, video.to_string())
-            .fetch_optional(&mut *connection)
-            .await?
-        {
-            return Err(PointercrateError::DuplicateVideo { id: row.id })
+        // Only changing the timestamp cannot cause a duplicate
+        if Some(&video) != self.video.as_ref() {
+            if let Some(row) = sqlx::query!(r#"SELECT id FROM records WHERE video = $1"#, video.to_string())
+                .fetch_optional(&mut *connection)
+                .await?
+            {
+                return Err(PointercrateError::DuplicateVideo { id: row.id })
+            }
         }
 
-        sqlx::query!("UPDATE records SET video = $1::text WHERE id = $2", video, self.id)
+        sqlx::query!(
+            "UPDATE records SET video = $1::text, video_timestamp = $2, video_dead = FALSE WHERE id = $3",
+            video,
+            timestamp,
+            self.id
+        )
+        .execute(connection)
+        .await?;
+
+        self.video = Some(video);
+        self.video_timestamp = timestamp;
+        self.video_dead = false;
+
+        Ok(())
+    }
+
+    /// Updates the number of attempts logged for this record, or removes it if `None` is given
+    pub async fn set_attempts(&mut self, attempts: Option<i32>, connection: &mut PgConnection) -> Result<()> {
+        let attempts = attempts
+            .map(validate_attempts)
+            .transpose()
+            .map_err(|error| error.in_field("attempts"))?;
+
+        sqlx::query!("UPDATE records SET attempts = $1 WHERE id = $2", attempts, self.id)
             .execute(connection)
             .await?;
 
-        self.video = Some(video);
+        self.attempts = attempts;
 
         Ok(())
     }
@@ -220,8 +307,8 @@ impl FullRecord {
     pub async fn set_demon(&mut self, demon: MinimalDemon, connection: &mut PgConnection) -> Result<()> {
         let requirement = demon.requirement(connection).await?;
 
-        if self.progress < requirement {
-            return Err(PointercrateError::InvalidProgress { requirement })
+        if self.progress.percent < requirement {
+            return Err(PointercrateError::InvalidProgress { requirement }.in_field("demon"))
         }
 
         self.ensure_invariants(self.player.id, self.demon.id, connection).await?;
@@ -299,21 +386,24 @@ impl FullRecord {
 
                 sqlx::query!(
                     "UPDATE record_notes SET record = $1 FROM records WHERE record_notes.record = records.id AND records.player = $2 AND \
-                     records.demon = $3 AND progress <= $4",
+                     records.demon = $3 AND (progress, progress_decimal) <= ($4, $5)",
                     self.id,
                     self.player.id,
                     self.demon.id,
-                    self.progress
+                    self.progress.percent,
+                    self.progress.decimal
                 )
                 .execute(&mut *connection)
                 .await?;
 
                 sqlx::query!(
-                    "DELETE FROM records WHERE id <> $1 AND records.player = $2 AND records.demon = $3 AND progress <= $4",
+                    "DELETE FROM records WHERE id <> $1 AND records.player = $2 AND records.demon = $3 AND (progress, progress_decimal) <= \
+                     ($4, $5)",
                     self.id,
                     self.player.id,
                     self.demon.id,
-                    self.progress
+                    self.progress.percent,
+                    self.progress.decimal
                 )
                 .execute(&mut *connection)
                 .await?;
@@ -342,22 +432,20 @@ impl FullRecord {
     ///
     /// If this record is approved, all submissions with lower progress of the same (player,
     /// demon)-tuple are deleted and have their notes transferred to this record.
-    pub async fn set_progress(&mut self, progress: i16, connection: &mut PgConnection) -> Result<()> {
+    pub async fn set_progress(&mut self, progress: f64, connection: &mut PgConnection) -> Result<()> {
         let requirement = self.demon.requirement(&mut *connection).await?;
-
-        if progress > 100 || progress < requirement {
-            return Err(PointercrateError::InvalidProgress { requirement })
-        }
+        let progress = validate_configured_progress(progress, requirement).map_err(|error| error.in_field("progress"))?;
 
         if self.status == RecordStatus::Approved {
             // Transfer over all notes from the records deleted below
             sqlx::query!(
                 "UPDATE record_notes SET record = $1 FROM records WHERE record_notes.record = records.id AND player = $2 AND demon = $3 \
-                 AND progress < $4 AND status_='SUBMITTED'",
+                 AND (progress, progress_decimal) < ($4, $5) AND status_='SUBMITTED'",
                 self.id,
                 self.player.id,
                 self.demon.id,
-                progress
+                progress.percent,
+                progress.decimal
             )
             .execute(&mut *connection)
             .await?;
@@ -379,12 +467,161 @@ impl FullRecord {
             );
         }
 
-        sqlx::query!("UPDATE records SET progress = $1 WHERE id = $2", progress, self.id)
-            .execute(connection)
-            .await?;
+        sqlx::query!(
+            "UPDATE records SET progress = $1, progress_decimal = $2 WHERE id = $3",
+            progress.percent,
+            progress.decimal,
+            self.id
+        )
+        .execute(connection)
+        .await?;
 
         self.progress = progress;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cistring::{CiStr, CiString},
+        error::PointercrateError,
+        model::demonlist::{
+            demon::MinimalDemon,
+            record::{FullRecord, PatchRecord, Progress, RecordStatus, Submission},
+            submitter::Submitter,
+        },
+    };
+    use serde_json::json;
+    use sqlx::PgConnection;
+
+    async fn insert_submission(player: &str, progress: i16, demon: &MinimalDemon, connection: &mut PgConnection) -> i32 {
+        sqlx::query!(
+            "INSERT INTO records (progress, status_, player, submitter, demon) SELECT $1, 'SUBMITTED', id, (SELECT submitter_id FROM \
+             submitters LIMIT 1), $2 FROM players WHERE name = $3::TEXT RETURNING id",
+            progress,
+            demon.id,
+            player.to_string()
+        )
+        .fetch_one(connection)
+        .await
+        .unwrap()
+        .id
+    }
+
+    fn approve() -> PatchRecord {
+        serde_json::from_value(json!({"status": "approved"})).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_grandfathered_record_is_retained() {
+        let mut connection = crate::test::test_setup().await;
+
+        let trichotomy = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+        let pending = insert_submission("Samifying", 95, &trichotomy, &mut connection).await;
+
+        crate::test::move_trichotomy_to_extended(&mut connection).await;
+
+        // Aeon Air's approved 90% stays on the list, and the 95% submitted before the move can still be
+        // approved
+        let record = FullRecord::by_id(pending, &mut connection).await.unwrap();
+        let record = record.apply_patch(approve(), &mut connection).await.unwrap();
+
+        assert_eq!(record.status, RecordStatus::Approved);
+
+        let progress: Vec<i16> = sqlx::query!(
+            "SELECT progress FROM records WHERE demon = $1 AND status_ = 'APPROVED' AND grandfathered ORDER BY progress",
+            trichotomy.id
+        )
+        .fetch_all(&mut connection)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.progress)
+        .collect();
+
+        assert_eq!(progress, vec![90, 95]);
+    }
+
+    #[actix_rt::test]
+    async fn test_new_non100_record_on_extended_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        let trichotomy = crate::test::move_trichotomy_to_extended(&mut connection).await;
+        let submitter_id = sqlx::query!("SELECT submitter_id FROM submitters LIMIT 1")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .submitter_id;
+
+        let submission = Submission {
+            progress: 95.0,
+            player: CiString("Samifying".to_string()),
+            demon: trichotomy.id,
+            video: None,
+            status: RecordStatus::Submitted,
+            note: None,
+            attempts: None,
+        };
+        let submitter = Submitter {
+            id: submitter_id,
+            banned: false,
+        };
+
+        assert_eq!(
+            FullRecord::create_from(submitter, submission, &mut connection, None).await.unwrap_err(),
+            PointercrateError::Non100Extended
+        );
+
+        // records that somehow got onto the demon after the move cannot be approved either
+        let pending = insert_submission("Mullsy", 95, &trichotomy, &mut connection).await;
+        let record = FullRecord::by_id(pending, &mut connection).await.unwrap();
+
+        assert_eq!(
+            record.apply_patch(approve(), &mut connection).await.unwrap_err(),
+            PointercrateError::Non100Extended
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_approval_validated_against_final_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        let abstract_interpretation = MinimalDemon::by_name(CiStr::from_str("abstract interpretation"), &mut connection)
+            .await
+            .unwrap();
+        let pending = insert_submission("Samifying", 90, &abstract_interpretation, &mut connection).await;
+        let trichotomy = crate::test::move_trichotomy_to_extended(&mut connection).await;
+
+        let record = FullRecord::by_id(pending, &mut connection).await.unwrap();
+        let patch = serde_json::from_value(json!({"status": "approved", "demon_id": trichotomy.id})).unwrap();
+
+        assert_eq!(
+            record.apply_patch(patch, &mut connection).await.unwrap_err(),
+            PointercrateError::Non100Extended
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_lowering_progress_on_extended_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        crate::test::move_trichotomy_to_extended(&mut connection).await;
+
+        // Aaron Ari's approved 100% on Trichotomy (record 5) was not grandfathered in, record 4 (Aeon
+        // Air's 90%) was
+        let record = FullRecord::by_id(5, &mut connection).await.unwrap();
+        let patch = serde_json::from_value(json!({"progress": 95})).unwrap();
+
+        assert_eq!(
+            record.apply_patch(patch, &mut connection).await.unwrap_err(),
+            PointercrateError::Non100Extended
+        );
+
+        let record = FullRecord::by_id(4, &mut connection).await.unwrap();
+        let patch = serde_json::from_value(json!({"progress": 92})).unwrap();
+
+        assert_eq!(record.apply_patch(patch, &mut connection).await.unwrap().progress, Progress::from(92));
+    }
+}
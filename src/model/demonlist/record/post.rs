@@ -5,7 +5,10 @@ use crate::{
     model::demonlist::{
         demon::MinimalDemon,
         player::DatabasePlayer,
-        record::{note::Note, FullRecord, RecordStatus},
+        record::{
+            note::{self, Note},
+            validate_attempts, validate_configured_progress, FullRecord, RecordStatus,
+        },
         submitter::Submitter,
     },
     ratelimit::{PreparedRatelimits, RatelimitScope},
@@ -19,7 +22,9 @@ use sqlx::{PgConnection, Row};
 #[derive(Deserialize, Debug, Display)]
 #[display(fmt = "{}% on {} by {} [status: {}]", progress, demon, player, status)]
 pub struct Submission {
-    pub progress: i16,
+    /// The record's progress. Can only have decimal places if allowed via
+    /// [`config::progress_decimals`]
+    pub progress: f64,
     pub player: CiString,
     pub demon: i32,
     #[serde(default)]
@@ -30,6 +35,29 @@ pub struct Submission {
     /// An initial, submitter provided note for the demon.
     #[serde(default)]
     pub note: Option<String>,
+
+    /// The number of attempts the player needed for the record, if they want to log it
+    #[serde(default)]
+    pub attempts: Option<i32>,
+}
+
+impl Submission {
+    /// Checks that this submission comes with evidence of the record: Either a video, or, if videos
+    /// are not `video_required`, a note explaining how to obtain raw footage of the record
+    pub fn validate_evidence(&self, video_required: bool) -> Result<()> {
+        if self.video.is_some() {
+            return Ok(())
+        }
+
+        if video_required {
+            return Err(PointercrateError::VideoRequired.in_field("video"))
+        }
+
+        match self.note {
+            Some(ref note) if !note.trim().is_empty() => Ok(()),
+            _ => Err(PointercrateError::RawFootageNoteRequired.in_field("note")),
+        }
+    }
 }
 
 impl FullRecord {
@@ -43,9 +71,27 @@ impl FullRecord {
             return Err(PointercrateError::BannedFromSubmissions)
         }
 
-        // validate video
-        let video = match submission.video {
-            Some(ref video) => Some(crate::video::validate(video)?),
+        // validate video. Any timestamp in the URL is stripped during validation, so we extract it
+        // beforehand
+        let (video, video_timestamp) = match submission.video {
+            Some(ref video) =>
+                (
+                    Some(crate::video::validate(video).map_err(|error| error.in_field("video"))?),
+                    crate::video::timestamp(video),
+                ),
+            None => (None, None),
+        };
+
+        DatabasePlayer::validate_name(submission.player.as_ref()).map_err(|error| error.in_field("player"))?;
+
+        let attempts = submission
+            .attempts
+            .map(validate_attempts)
+            .transpose()
+            .map_err(|error| error.in_field("attempts"))?;
+
+        let note = match submission.note {
+            Some(ref note) => Some(note::sanitize_content(note).map_err(|error| error.in_field("note"))?),
             None => None,
         };
 
@@ -54,6 +100,15 @@ impl FullRecord {
         // TODO: handle the ambiguous case
         let demon = MinimalDemon::by_id(submission.demon, connection).await?;
 
+        // Unpublished demons do not exist as far as the public is concerned (it is possible to directly
+        // add records to them for list mods)
+        if submission.status == RecordStatus::Submitted && !demon.published(&mut *connection).await? {
+            return Err(PointercrateError::ModelNotFound {
+                model: "Demon",
+                identified_by: submission.demon.to_string(),
+            })
+        }
+
         // Banned player can't have records on the list
         if player.banned {
             return Err(PointercrateError::PlayerBanned)
@@ -66,16 +121,14 @@ impl FullRecord {
 
         // Can only submit 100% records for the extended list (it is possible to directly add them for list
         // mods)
-        if demon.position > config::list_size() && submission.progress != 100 && submission.status == RecordStatus::Submitted {
+        if demon.position > config::list_size() && submission.progress < 100.0 && submission.status == RecordStatus::Submitted {
             return Err(PointercrateError::Non100Extended)
         }
 
         let requirement = demon.requirement(&mut *connection).await?;
 
         // Check if the record meets the record requirement for this demon
-        if submission.progress > 100 || submission.progress < requirement {
-            return Err(PointercrateError::InvalidProgress { requirement })
-        }
+        let progress = validate_configured_progress(submission.progress, requirement).map_err(|error| error.in_field("progress"))?;
 
         debug!("Submission is valid, checking for duplicates!");
 
@@ -96,10 +149,11 @@ impl FullRecord {
 
         let existing = sqlx::query!(
             r#"SELECT id, status_::text as "status_!: String" FROM records WHERE demon = $1 AND player = $2 AND (status_ = 'REJECTED' OR status_ = 
-             'UNDER_CONSIDERATION' OR (status_ = 'APPROVED' AND progress >= $3)) LIMIT 1"#,
+             'UNDER_CONSIDERATION' OR (status_ = 'APPROVED' AND (progress, progress_decimal) >= ($3, $4))) LIMIT 1"#,
             demon.id,
             player.id,
-            submission.progress
+            progress.percent,
+            progress.decimal
         )
         .fetch_optional(&mut *connection)
         .await?;
@@ -116,14 +170,18 @@ impl FullRecord {
         if let Some(ratelimits) = ratelimits {
             ratelimits.check(RatelimitScope::RecordSubmissionGlobal)?;
             ratelimits.check(RatelimitScope::RecordSubmission)?;
+            ratelimits.check_player(player.name.as_ref())?;
         }
 
         let id = sqlx::query(
-            "INSERT INTO records (progress, video, status_, player, submitter, demon) VALUES ($1, $2::TEXT, 'SUBMITTED', $3, $4,$5) \
-             RETURNING id",
+            "INSERT INTO records (progress, progress_decimal, video, video_timestamp, attempts, status_, player, submitter, demon) VALUES \
+             ($1, $2, $3::TEXT, $4, $5, 'SUBMITTED', $6, $7, $8) RETURNING id",
         )
-        .bind(submission.progress)
+        .bind(progress.percent)
+        .bind(progress.decimal)
         .bind(&video)
+        .bind(video_timestamp)
+        .bind(attempts)
         .bind(player.id)
         .bind(submitter.id)
         .bind(demon.id)
@@ -133,8 +191,11 @@ impl FullRecord {
 
         let mut record = FullRecord {
             id,
-            progress: submission.progress,
+            progress,
             video,
+            video_timestamp,
+            video_dead: false,
+            attempts,
             status: RecordStatus::Submitted,
             player,
             demon,
@@ -148,7 +209,7 @@ impl FullRecord {
             record.set_status(submission.status, &mut *connection).await?;
         }
 
-        if let Some(note) = submission.note {
+        if let Some(note) = note {
             if !note.trim().is_empty() {
                 let note_id = sqlx::query!(
                     "INSERT INTO record_notes (record, content) VALUES ($1, $2) RETURNING id",
@@ -172,4 +233,182 @@ impl FullRecord {
 
         Ok(record)
     }
+
+    /// Like [`FullRecord::create_from`], but submissions that fail validation additionally count
+    /// towards their own ratelimit ([`RatelimitScope::FailedRecordSubmission`]), so that they don't
+    /// eat into the budget of whatever corrected submission follows them
+    ///
+    /// Since this is how everyone outside the list team submits records, this is also where the
+    /// [video policy](config::video_required) is enforced.
+    pub async fn create_ratelimited(
+        submitter: Submitter, submission: Submission, connection: &mut PgConnection, ratelimits: PreparedRatelimits<'_>,
+    ) -> Result<FullRecord> {
+        let result = match submission.validate_evidence(config::video_required()) {
+            Ok(()) => FullRecord::create_from(submitter, submission, connection, Some(ratelimits)).await,
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Err(error @ PointercrateError::Ratelimited { .. }) => Err(error),
+            Err(error) => {
+                ratelimits.check(RatelimitScope::FailedRecordSubmission)?;
+
+                Err(error)
+            },
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Submission;
+    use crate::{
+        cistring::{CiStr, CiString},
+        config,
+        error::PointercrateError,
+        model::demonlist::{
+            demon::MinimalDemon,
+            record::{FullRecord, RecordStatus},
+            submitter::Submitter,
+        },
+    };
+
+    async fn submit(note: String) -> Result<FullRecord, PointercrateError> {
+        let mut connection = crate::test::test_setup().await;
+
+        let submitter = Submitter::by_ip_or_create("127.0.0.1".parse().unwrap(), &mut connection, None)
+            .await
+            .unwrap();
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        let submission = Submission {
+            progress: 100.0,
+            player: CiString("Samifying".to_string()),
+            demon: demon.id,
+            video: None,
+            status: RecordStatus::Submitted,
+            note: Some(note),
+            attempts: None,
+        };
+
+        FullRecord::create_from(submitter, submission, &mut connection, None).await
+    }
+
+    #[actix_rt::test]
+    async fn test_submit_to_unpublished_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        let submitter = Submitter::by_ip_or_create("127.0.0.1".parse().unwrap(), &mut connection, None)
+            .await
+            .unwrap();
+        let demon = MinimalDemon::by_name(CiStr::from_str("taraturusus"), &mut connection).await.unwrap();
+
+        sqlx::query!("UPDATE demons SET published = FALSE WHERE id = $1", demon.id)
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let submission = |status| {
+            Submission {
+                progress: 100.0,
+                player: CiString("Samifying".to_string()),
+                demon: demon.id,
+                video: None,
+                status,
+                note: None,
+                attempts: None,
+            }
+        };
+
+        // indistinguishable from a demon that does not exist at all
+        assert_eq!(
+            FullRecord::create_from(submitter, submission(RecordStatus::Submitted), &mut connection, None)
+                .await
+                .unwrap_err(),
+            PointercrateError::ModelNotFound {
+                model: "Demon",
+                identified_by: demon.id.to_string(),
+            }
+        );
+
+        let record = FullRecord::create_from(submitter, submission(RecordStatus::Approved), &mut connection, None)
+            .await
+            .unwrap();
+
+        assert_eq!(record.demon.id, demon.id);
+    }
+
+    #[actix_rt::test]
+    async fn test_submit_overlong_note() {
+        let result = submit("a".repeat(config::max_note_length() + 1)).await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            PointercrateError::NoteTooLong {
+                max_length: config::max_note_length()
+            }
+            .in_field("note")
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_submit_note_with_null_bytes() {
+        let record = submit("Cool\0 run\u{7}!\nGG".to_string()).await.unwrap();
+
+        assert_eq!(record.notes.len(), 1);
+        assert_eq!(record.notes[0].content, "Cool run!\nGG");
+    }
+
+    fn evidence(video: Option<&str>, note: Option<&str>) -> Submission {
+        Submission {
+            progress: 100.0,
+            player: CiString("Samifying".to_string()),
+            demon: 1,
+            video: video.map(ToString::to_string),
+            status: RecordStatus::Submitted,
+            note: note.map(ToString::to_string),
+            attempts: None,
+        }
+    }
+
+    #[test]
+    fn test_video_required() {
+        let video = Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+
+        assert!(evidence(video, None).validate_evidence(true).is_ok());
+        assert_eq!(
+            evidence(None, Some("Raw footage is on my drive")).validate_evidence(true),
+            Err(PointercrateError::VideoRequired.in_field("video"))
+        );
+    }
+
+    #[test]
+    fn test_video_optional() {
+        let video = Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+
+        assert!(evidence(video, None).validate_evidence(false).is_ok());
+        assert!(evidence(None, Some("Raw footage is on my drive")).validate_evidence(false).is_ok());
+
+        for note in vec![None, Some(""), Some("  \n ")] {
+            assert_eq!(
+                evidence(None, note).validate_evidence(false),
+                Err(PointercrateError::RawFootageNoteRequired.in_field("note"))
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_submit_raw_footage_note() {
+        let note = "No video, but raw footage is available at https://drive.example/raw.mp4".to_string();
+
+        assert!(evidence(None, Some(note.as_str())).validate_evidence(false).is_ok());
+
+        let record = submit(note.clone()).await.unwrap();
+
+        assert_eq!(record.video, None);
+        assert_eq!(record.status, RecordStatus::Submitted);
+        assert_eq!(record.notes.len(), 1);
+        assert_eq!(record.notes[0].content, note);
+    }
 }
@@ -0,0 +1,142 @@
+//! Record progress with optional decimal precision
+//!
+//! Some lists track progress to one decimal place (e.g. 87.5%). The whole percentage is stored in
+//! the `progress` column as always, with the tenths kept separately in `progress_decimal`. This way
+//! requirement checks, 100% checks and score computations (which only ever care about whole
+//! percentages) work the same regardless of [`config::progress_decimals`].
+
+use crate::{config, error::PointercrateError, Result};
+use serde::{Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+
+/// A record's progress. Ordered by its numeric value
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Progress {
+    /// The whole percentage
+    pub percent: i16,
+
+    /// The tenths of a percent, always between 0 and 9 (inclusive)
+    pub decimal: i16,
+}
+
+impl Progress {
+    pub fn new(percent: i16, decimal: i16) -> Progress {
+        Progress { percent, decimal }
+    }
+}
+
+impl From<i16> for Progress {
+    fn from(percent: i16) -> Self {
+        Progress::new(percent, 0)
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.decimal == 0 {
+            write!(f, "{}", self.percent)
+        } else {
+            write!(f, "{}.{}", self.percent, self.decimal)
+        }
+    }
+}
+
+// Whole percentages are serialized as integers, so nothing changes for lists not using decimals
+impl Serialize for Progress {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.decimal == 0 {
+            serializer.serialize_i16(self.percent)
+        } else {
+            serializer.serialize_f64(f64::from(self.percent) + f64::from(self.decimal) / 10.0)
+        }
+    }
+}
+
+/// Validates progress given in a request against a demon's record requirement and the number of
+/// decimal places allowed via [`config::progress_decimals`]
+pub fn validate_progress(progress: f64, requirement: i16, decimals: u8) -> Result<Progress> {
+    // Only whole multiples of 10^(-decimals) are allowed. Rounding takes care of floating point
+    // imprecision, e.g. 87.3 * 10 = 872.9999999999999
+    let scale = 10f64.powi(i32::from(decimals));
+    let scaled = (progress * scale).round();
+
+    if !progress.is_finite() || (progress * scale - scaled).abs() > 1e-6 {
+        return Err(PointercrateError::InvalidProgressPrecision { decimals })
+    }
+
+    if !(0.0..=100.0 * scale).contains(&scaled) || scaled < f64::from(requirement) * scale {
+        return Err(PointercrateError::InvalidProgress { requirement })
+    }
+
+    // Scale up to tenths regardless of the configured precision
+    let tenths = (scaled * 10f64.powi(1 - i32::from(decimals))) as i16;
+
+    Ok(Progress::new(tenths / 10, tenths % 10))
+}
+
+/// [`validate_progress`] with the configured number of decimal places
+pub fn validate_configured_progress(progress: f64, requirement: i16) -> Result<Progress> {
+    validate_progress(progress, requirement, config::progress_decimals())
+}
+
+/// The value of the `step` attribute of progress inputs
+pub fn progress_step() -> &'static str {
+    match config::progress_decimals() {
+        0 => "1",
+        _ => "0.1",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_progress, Progress};
+    use crate::error::PointercrateError;
+
+    #[test]
+    fn test_decimal_progress_enabled() {
+        assert_eq!(validate_progress(87.5, 50, 1), Ok(Progress::new(87, 5)));
+        assert_eq!(validate_progress(87.3, 50, 1), Ok(Progress::new(87, 3)));
+        assert_eq!(validate_progress(100.0, 50, 1), Ok(Progress::from(100)));
+        assert_eq!(
+            validate_progress(87.55, 50, 1),
+            Err(PointercrateError::InvalidProgressPrecision { decimals: 1 })
+        );
+        assert_eq!(
+            validate_progress(49.9, 50, 1),
+            Err(PointercrateError::InvalidProgress { requirement: 50 })
+        );
+        assert_eq!(
+            validate_progress(100.1, 50, 1),
+            Err(PointercrateError::InvalidProgress { requirement: 50 })
+        );
+    }
+
+    #[test]
+    fn test_decimal_progress_disabled() {
+        assert_eq!(validate_progress(87.0, 50, 0), Ok(Progress::from(87)));
+        assert_eq!(
+            validate_progress(87.5, 50, 0),
+            Err(PointercrateError::InvalidProgressPrecision { decimals: 0 })
+        );
+        assert_eq!(
+            validate_progress(f64::NAN, 50, 0),
+            Err(PointercrateError::InvalidProgressPrecision { decimals: 0 })
+        );
+        assert_eq!(
+            validate_progress(40.0, 50, 0),
+            Err(PointercrateError::InvalidProgress { requirement: 50 })
+        );
+    }
+
+    #[test]
+    fn test_progress_serialization() {
+        assert_eq!(serde_json::to_string(&Progress::from(95)).unwrap(), "95");
+        assert_eq!(serde_json::to_string(&Progress::new(87, 5)).unwrap(), "87.5");
+        assert_eq!(Progress::new(87, 5).to_string(), "87.5");
+        assert!(Progress::new(87, 5) > Progress::from(87));
+        assert!(Progress::new(87, 5) < Progress::from(88));
+    }
+}
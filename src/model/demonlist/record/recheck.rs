@@ -0,0 +1,391 @@
+//! Detection of dead record videos
+//!
+//! Over time, the videos of approved records get deleted or set to private. A recheck goes through
+//! all approved records in batches and flags those whose video is no longer available via their
+//! `video_dead` column. Records are never rejected because of this, the flag is only surfaced to
+//! the list team, who have to decide what to do about it.
+//!
+//! YouTube videos are checked via YouTube's oEmbed API, as youtube.com itself happily serves a
+//! `200 OK` for deleted videos. All other videos are checked with a `HEAD` request.
+
+use crate::{config, error::PointercrateError, state::PointercrateState, Result};
+use actix_rt::time::delay_for;
+use log::{debug, error, info, warn};
+use reqwest::Client;
+use sqlx::PgConnection;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Whether a recheck is currently running. There is no point in running several at once
+static RECHECK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Marks a recheck as running for as long as it is alive
+///
+/// This way, the flag is reset even if the recheck panics.
+struct RecheckGuard;
+
+impl RecheckGuard {
+    /// Marks a recheck as running, or returns `None` if one already is
+    fn acquire() -> Option<RecheckGuard> {
+        RECHECK_RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| RecheckGuard)
+    }
+}
+
+impl Drop for RecheckGuard {
+    fn drop(&mut self) {
+        RECHECK_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VideoStatus {
+    Available,
+    Unavailable,
+
+    /// The video host could not be reached, or gave an answer we cannot interpret (e.g. because it
+    /// ratelimited us). Records whose video has this status are left as they are
+    Unknown,
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RecheckSummary {
+    /// The number of records whose video was checked
+    pub checked: usize,
+
+    /// The number of records newly flagged as having a dead video
+    pub flagged: usize,
+
+    /// The number of records whose video was previously flagged as dead, but is available again
+    pub unflagged: usize,
+}
+
+pub struct VideoChecker {
+    http_client: Client,
+    oembed_url: String,
+
+    /// The minimal time between two requests made by this checker
+    interval: Duration,
+    next_request: Mutex<Option<Instant>>,
+}
+
+impl VideoChecker {
+    pub fn new(http_client: Client, oembed_url: String, interval: Duration) -> Self {
+        VideoChecker {
+            http_client,
+            oembed_url,
+            interval,
+            next_request: Mutex::new(None),
+        }
+    }
+
+    /// Waits until [`VideoChecker::interval`] has passed since the previous request
+    async fn throttle(&self) {
+        let wait = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let now = Instant::now();
+            let wait = next_request.map(|next| next.saturating_duration_since(now)).unwrap_or_default();
+
+            *next_request = Some(now + wait + self.interval);
+            wait
+        };
+
+        if wait > Duration::default() {
+            delay_for(wait).await
+        }
+    }
+
+    pub async fn check(&self, video: &str) -> VideoStatus {
+        if video.starts_with("https://www.youtube.com/") {
+            self.check_oembed(video).await
+        } else {
+            self.check_head(video).await
+        }
+    }
+
+    async fn check_oembed(&self, video: &str) -> VideoStatus {
+        self.throttle().await;
+
+        match self.http_client.get(&self.oembed_url).query(&[("url", video), ("format", "json")]).send().await {
+            Ok(response) =>
+                match response.status().as_u16() {
+                    200..=299 => VideoStatus::Available,
+                    // Private videos yield a 401, deleted ones a 404
+                    401 | 404 => VideoStatus::Unavailable,
+                    status => {
+                        warn!("Unexpected oEmbed response {} for video {}", status, video);
+
+                        VideoStatus::Unknown
+                    },
+                },
+            Err(error) => {
+                warn!("oEmbed request for video {} failed: {:?}", video, error);
+
+                VideoStatus::Unknown
+            },
+        }
+    }
+
+    async fn check_head(&self, video: &str) -> VideoStatus {
+        self.throttle().await;
+
+        let status = match self.http_client.head(video).send().await {
+            Ok(response) => response.status().as_u16(),
+            Err(error) => {
+                warn!("HEAD request to video {} failed: {:?}", video, error);
+
+                return VideoStatus::Unknown
+            },
+        };
+
+        // Some websites (billibilli) respond unfavorably to HEAD requests, see FullRecord::validate
+        let status = if status == 401 || status == 403 || status == 405 {
+            self.throttle().await;
+
+            match self.http_client.get(video).send().await {
+                Ok(response) => response.status().as_u16(),
+                Err(error) => {
+                    warn!("GET request to video {} failed: {:?}", video, error);
+
+                    return VideoStatus::Unknown
+                },
+            }
+        } else {
+            status
+        };
+
+        match status {
+            200..=399 => VideoStatus::Available,
+            404 | 410 => VideoStatus::Unavailable,
+            _ => VideoStatus::Unknown,
+        }
+    }
+}
+
+/// Checks the videos of the next `batch_size` approved records with an ID larger than `after_id`,
+/// and updates their `video_dead` flags
+///
+/// Returns the ID of the last record checked, or `None` if there were no records left to check.
+pub async fn recheck_batch(
+    checker: &VideoChecker, after_id: i32, batch_size: i64, summary: &mut RecheckSummary, connection: &mut PgConnection,
+) -> Result<Option<i32>> {
+    let batch = sqlx::query!(
+        r#"SELECT id, video::TEXT AS "video!", video_dead FROM records WHERE status_ = 'APPROVED' AND video IS NOT NULL AND id > $1 ORDER BY 
+         id LIMIT $2"#,
+        after_id,
+        batch_size
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let last_id = batch.last().map(|row| row.id);
+
+    for row in batch {
+        let video_dead = match checker.check(&row.video).await {
+            VideoStatus::Available => false,
+            VideoStatus::Unavailable => true,
+            VideoStatus::Unknown => row.video_dead,
+        };
+
+        summary.checked += 1;
+
+        if video_dead != row.video_dead {
+            debug!("Video {} of record {} is now considered dead: {}", row.video, row.id, video_dead);
+
+            // The video might have been changed while we were checking it
+            let updated = sqlx::query!(
+                "UPDATE records SET video_dead = $1 WHERE id = $2 AND video = $3::TEXT",
+                video_dead,
+                row.id,
+                row.video
+            )
+            .execute(&mut *connection)
+            .await?
+            .rows_affected();
+
+            if updated > 0 {
+                if video_dead {
+                    summary.flagged += 1
+                } else {
+                    summary.unflagged += 1
+                }
+            }
+        }
+    }
+
+    Ok(last_id)
+}
+
+/// Rechecks the videos of all approved records in the background
+///
+/// Between two batches, the database connection is returned to the pool, as a recheck of the whole
+/// list takes a while. Fails if a recheck is already running.
+pub fn spawn_recheck(state: PointercrateState) -> Result<()> {
+    let guard = RecheckGuard::acquire().ok_or(PointercrateError::RecheckInProgress)?;
+
+    actix_rt::spawn(async move {
+        let _guard = guard;
+        let checker = VideoChecker::new(
+            state.http_client.clone(),
+            config::youtube_oembed_url(),
+            Duration::from_millis(config::video_recheck_interval()),
+        );
+        let mut summary = RecheckSummary::default();
+        let mut after_id = 0;
+
+        info!("Starting recheck of record videos");
+
+        loop {
+            let batch_size = config::video_recheck_batch_size();
+            let result = match state.connection().await {
+                Ok(mut connection) => recheck_batch(&checker, after_id, batch_size, &mut summary, &mut connection).await,
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Ok(Some(last_id)) => after_id = last_id,
+                Ok(None) => {
+                    info!("Finished recheck of record videos: {:?}", summary);
+
+                    break
+                },
+                Err(error) => {
+                    error!("Recheck of record videos aborted after record {}: {:?}", after_id, error);
+
+                    break
+                },
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recheck_batch, RecheckGuard, RecheckSummary, VideoChecker, VideoStatus};
+    use reqwest::Client;
+    use std::time::Duration;
+
+    /// Starts a mock server that answers every request whose request line contains "dead" with a
+    /// `404 NOT FOUND`, and all others with a `200 OK`
+    fn mock_server() -> String {
+        let (url, _) = crate::test::mock_server(|request| {
+            if request.request_line.contains("dead") {
+                ("404 Not Found", "")
+            } else {
+                ("200 OK", "")
+            }
+        });
+
+        url
+    }
+
+    fn checker(server: &str) -> VideoChecker {
+        VideoChecker::new(Client::new(), format!("{}/oembed", server), Duration::from_millis(0))
+    }
+
+    #[actix_rt::test]
+    async fn test_check_video() {
+        let server = mock_server();
+        let checker = checker(&server);
+
+        assert_eq!(checker.check(&format!("{}/live", server)).await, VideoStatus::Available);
+        assert_eq!(checker.check(&format!("{}/dead", server)).await, VideoStatus::Unavailable);
+        assert_eq!(checker.check("https://www.youtube.com/watch?v=dQw4w9WgXcQ").await, VideoStatus::Available);
+        assert_eq!(checker.check("https://www.youtube.com/watch?v=deadbeef123").await, VideoStatus::Unavailable);
+
+        // bind and immediately drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+        assert_eq!(checker.check(&format!("http://127.0.0.1:{}/dead", port)).await, VideoStatus::Unknown);
+    }
+
+    #[actix_rt::test]
+    async fn test_recheck_flags_dead_videos() {
+        let mut connection = crate::test::test_setup().await;
+        let server = mock_server();
+
+        // Aeon Air's and Aaron Ari's approved records on Trichotomy. The latter was flagged by an
+        // earlier recheck, but its video is available again
+        let aeon_air = sqlx::query!(
+            "UPDATE records SET video = $1::TEXT WHERE progress = 90 AND player = (SELECT id FROM players WHERE name = 'Aeon Air') \
+             RETURNING id",
+            format!("{}/dead", server)
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+        let aaron_ari = sqlx::query!(
+            "UPDATE records SET video = $1::TEXT, video_dead = TRUE WHERE demon = (SELECT id FROM demons WHERE name = 'Trichotomy') AND \
+             player = (SELECT id FROM players WHERE name = 'Aaron Ari') RETURNING id",
+            format!("{}/live", server)
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        // Submitted records are not rechecked
+        let submitted = sqlx::query!(
+            "UPDATE records SET video = $1::TEXT WHERE status_ = 'SUBMITTED' RETURNING id",
+            format!("{}/dead-submission", server)
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        let checker = checker(&server);
+        let mut summary = RecheckSummary::default();
+        let mut after_id = 0;
+
+        while let Some(last_id) = recheck_batch(&checker, after_id, 1, &mut summary, &mut connection).await.unwrap() {
+            after_id = last_id;
+        }
+
+        assert_eq!(summary, RecheckSummary {
+            checked: 2,
+            flagged: 1,
+            unflagged: 1,
+        });
+
+        let flags = sqlx::query!(
+            r#"SELECT id, video_dead, status_::TEXT AS "status!" FROM records WHERE id = ANY($1) ORDER BY id"#,
+            &[aeon_air, aaron_ari, submitted][..]
+        )
+        .fetch_all(&mut connection)
+        .await
+        .unwrap();
+
+        for row in flags {
+            if row.id == aeon_air {
+                assert!(row.video_dead);
+                // Dead videos are only flagged, never rejected
+                assert_eq!(row.status, "APPROVED");
+            } else {
+                assert!(!row.video_dead);
+            }
+        }
+    }
+
+    #[test]
+    fn test_recheck_guard() {
+        let guard = RecheckGuard::acquire();
+
+        assert!(guard.is_some());
+        assert!(RecheckGuard::acquire().is_none());
+
+        drop(guard);
+
+        assert!(RecheckGuard::acquire().is_some());
+    }
+}
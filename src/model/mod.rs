@@ -16,6 +16,17 @@
 //! Only the `Database` representation always exists. The others are occasionally not necessary
 //! distinct from each other
 
+use serde::Serialize;
+
 pub mod demonlist;
 pub mod nationality;
 pub mod user;
+
+/// The id of some object together with its name, if that object still exists
+///
+/// Used by the audit logs to refer to the objects an entry is about.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct NamedId {
+    pub id: i32,
+    pub name: Option<String>,
+}
@@ -1,12 +1,72 @@
 use crate::cistring::CiString;
 use derive_more::Constructor;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 mod get;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Hash, Constructor)]
+#[derive(Debug, PartialEq, Eq, Serialize, Hash, Clone, Constructor)]
 pub struct Nationality {
     #[serde(rename = "country_code")]
     pub iso_country_code: String,
     pub nation: CiString,
+    pub continent: Option<Continent>,
+    pub subdivision: Option<Subdivision>,
+}
+
+/// A first-level subdivision (state, province, ...) of some nation, as defined in ISO 3166-2
+#[derive(Debug, PartialEq, Eq, Serialize, Hash, Clone, Constructor)]
+pub struct Subdivision {
+    /// The part of the subdivision's ISO 3166-2 code after the hyphen (e.g. `CA` for `US-CA`)
+    pub iso_code: String,
+    pub name: CiString,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Continent {
+    Africa,
+    Antarctica,
+    Asia,
+    Europe,
+    #[serde(rename = "North America")]
+    NorthAmerica,
+    Oceania,
+    #[serde(rename = "South America")]
+    SouthAmerica,
+}
+
+impl Continent {
+    pub const ALL: [Continent; 7] = [
+        Continent::Africa,
+        Continent::Antarctica,
+        Continent::Asia,
+        Continent::Europe,
+        Continent::NorthAmerica,
+        Continent::Oceania,
+        Continent::SouthAmerica,
+    ];
+
+    pub fn to_sql(self) -> &'static str {
+        match self {
+            Continent::Africa => "Africa",
+            Continent::Antarctica => "Antarctica",
+            Continent::Asia => "Asia",
+            Continent::Europe => "Europe",
+            Continent::NorthAmerica => "North America",
+            Continent::Oceania => "Oceania",
+            Continent::SouthAmerica => "South America",
+        }
+    }
+
+    pub fn from_sql(sql: &str) -> Self {
+        match sql {
+            "Africa" => Continent::Africa,
+            "Antarctica" => Continent::Antarctica,
+            "Asia" => Continent::Asia,
+            "Europe" => Continent::Europe,
+            "North America" => Continent::NorthAmerica,
+            "Oceania" => Continent::Oceania,
+            "South America" => Continent::SouthAmerica,
+            _ => unreachable!(),
+        }
+    }
 }
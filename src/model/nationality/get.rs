@@ -1,7 +1,7 @@
 use crate::{
     cistring::{CiStr, CiString},
     error::PointercrateError,
-    model::nationality::Nationality,
+    model::nationality::{Continent, Nationality, Subdivision},
     Result,
 };
 use futures::stream::StreamExt;
@@ -10,7 +10,7 @@ use sqlx::{Error, PgConnection};
 impl Nationality {
     pub async fn by_country_code_or_name(code: &CiStr, connection: &mut PgConnection) -> Result<Nationality> {
         sqlx::query!(
-            r#"SELECT nation as "nation: String", iso_country_code as "iso_country_code: String" FROM nationalities WHERE iso_country_code = $1 or nation = $1"#,
+            r#"SELECT nation as "nation: String", iso_country_code as "iso_country_code: String", continent::TEXT FROM nationalities WHERE iso_country_code = $1 or nation = $1"#,
             code.to_string() /* FIXME(sqlx 0.3) */
         )
         .fetch_one(connection)
@@ -19,6 +19,8 @@ impl Nationality {
             Nationality {
                 nation: CiString::from(row.nation),
                 iso_country_code: row.iso_country_code,
+                continent: row.continent.as_deref().map(Continent::from_sql),
+                subdivision: None,
             }
         })
         .map_err(|sqlx_error| {
@@ -35,7 +37,7 @@ impl Nationality {
 
     pub async fn all(connection: &mut PgConnection) -> Result<Vec<Nationality>> {
         let mut stream =
-            sqlx::query!(r#"SELECT nation as "nation: String", iso_country_code as "iso_country_code: String" FROM nationalities"#)
+            sqlx::query!(r#"SELECT nation as "nation: String", iso_country_code as "iso_country_code: String", continent::TEXT FROM nationalities"#)
                 .fetch(connection);
         let mut nationalities = Vec::new();
 
@@ -45,9 +47,105 @@ impl Nationality {
             nationalities.push(Nationality {
                 nation: CiString::from(row.nation),
                 iso_country_code: row.iso_country_code,
+                continent: row.continent.as_deref().map(Continent::from_sql),
+                subdivision: None,
             })
         }
 
         Ok(nationalities)
     }
+
+    /// Gets all subdivisions of this nation, ordered by name. Empty for nations we do not know any
+    /// subdivisions of.
+    pub async fn subdivisions(&self, connection: &mut PgConnection) -> Result<Vec<Subdivision>> {
+        let mut stream = sqlx::query!(
+            r#"SELECT iso_code, name::TEXT AS "name!" FROM subdivisions WHERE nation = $1 ORDER BY name"#,
+            self.iso_country_code
+        )
+        .fetch(connection);
+        let mut subdivisions = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            subdivisions.push(Subdivision {
+                iso_code: row.iso_code,
+                name: CiString(row.name),
+            })
+        }
+
+        Ok(subdivisions)
+    }
+
+    /// Gets the subdivision of this nation with the given code
+    ///
+    /// The code can either be a full ISO 3166-2 code (e.g. `US-CA`) or only the part after the
+    /// hyphen (e.g. `CA`).
+    pub async fn subdivision_by_code(&self, code: &str, connection: &mut PgConnection) -> Result<Subdivision> {
+        let code = code.trim().to_uppercase();
+        let code = code
+            .strip_prefix(&format!("{}-", self.iso_country_code))
+            .unwrap_or(&code)
+            .to_string();
+
+        sqlx::query!(
+            r#"SELECT iso_code, name::TEXT AS "name!" FROM subdivisions WHERE nation = $1 AND iso_code = $2"#,
+            self.iso_country_code,
+            code
+        )
+        .fetch_one(connection)
+        .await
+        .map(|row| {
+            Subdivision {
+                iso_code: row.iso_code,
+                name: CiString(row.name),
+            }
+        })
+        .map_err(|sqlx_error| {
+            match sqlx_error {
+                Error::RowNotFound =>
+                    PointercrateError::ModelNotFound {
+                        model: "Subdivision",
+                        identified_by: format!("{}-{}", self.iso_country_code, code),
+                    },
+                _ => sqlx_error.into(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cistring::CiStr, error::PointercrateError, model::nationality::Nationality};
+
+    #[actix_rt::test]
+    async fn test_subdivision_lookup() {
+        let mut connection = crate::test::test_setup().await;
+
+        let united_states = Nationality::by_country_code_or_name(CiStr::from_str("US"), &mut connection)
+            .await
+            .unwrap();
+
+        let california = united_states.subdivision_by_code("US-CA", &mut connection).await.unwrap();
+
+        assert_eq!(california.iso_code, "CA");
+        assert_eq!(california, united_states.subdivision_by_code("ca", &mut connection).await.unwrap());
+        assert!(united_states.subdivisions(&mut connection).await.unwrap().contains(&california));
+    }
+
+    #[actix_rt::test]
+    async fn test_nation_without_subdivisions() {
+        let mut connection = crate::test::test_setup().await;
+
+        let germany = Nationality::by_country_code_or_name(CiStr::from_str("DE"), &mut connection)
+            .await
+            .unwrap();
+
+        assert!(germany.subdivisions(&mut connection).await.unwrap().is_empty());
+
+        match germany.subdivision_by_code("DE-BY", &mut connection).await {
+            Err(PointercrateError::ModelNotFound { model: "Subdivision", .. }) => (),
+            result => panic!("expected subdivision to not be found, got {:?}", result),
+        }
+    }
 }
@@ -7,6 +7,7 @@
 
 pub use self::{
     auth::{AuthenticatedUser, Authorization, PatchMe, Registration},
+    history::TeamHistoryEntry,
     paginate::UserPagination,
     patch::PatchUser,
 };
@@ -21,6 +22,7 @@ use std::{
 mod auth;
 mod delete;
 mod get;
+mod history;
 mod paginate;
 mod patch;
 
@@ -1,4 +1,4 @@
-use crate::{error::PointercrateError, model::user::User, permissions::Permissions, Result};
+use crate::{error::PointercrateError, model::user::User, permissions::Permissions, ratelimit::QuotaTier, Result};
 use futures::StreamExt;
 use sqlx::{Error, PgConnection};
 
@@ -81,4 +81,14 @@ impl User {
 
         Ok(users)
     }
+
+    /// Gets the tier of this [`User`]'s access tokens, which determines how many requests they can
+    /// make to the API
+    pub async fn quota_tier(&self, connection: &mut PgConnection) -> Result<QuotaTier> {
+        let row = sqlx::query!(r#"SELECT api_quota_tier::TEXT AS "tier!" FROM members WHERE member_id = $1"#, self.id)
+            .fetch_one(connection)
+            .await?;
+
+        Ok(QuotaTier::from_sql(&row.tier))
+    }
 }
@@ -0,0 +1,110 @@
+use crate::{
+    model::{user::User, NamedId},
+    permissions::Permissions,
+    Result,
+};
+use chrono::NaiveDateTime;
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// A single change to the demonlist team's composition
+#[derive(Debug, Serialize)]
+pub struct TeamHistoryEntry {
+    pub time: NaiveDateTime,
+    pub audit_id: i32,
+
+    /// The user whose permissions were changed
+    pub user: NamedId,
+
+    /// The user who performed the change
+    pub actor: NamedId,
+
+    /// The team permissions the user gained with this change
+    pub granted: Permissions,
+
+    /// The team permissions the user lost with this change
+    pub revoked: Permissions,
+}
+
+impl User {
+    /// The permissions that make a user part of the demonlist team
+    pub fn team_permissions() -> Permissions {
+        Permissions::ListHelper | Permissions::ListModerator | Permissions::ListAdministrator
+    }
+
+    /// Gets all changes to the demonlist team's composition, in chronological order
+    ///
+    /// Only changes that granted or revoked at least one of the [`User::team_permissions`] are
+    /// returned.
+    pub async fn team_history(connection: &mut PgConnection) -> Result<Vec<TeamHistoryEntry>> {
+        let mut stream = sqlx::query!(
+            r#"SELECT time, audit_id, permission_modifications.id AS "user_id!", users.name AS "user_name?", userid AS "actor_id!",
+                      actors.name AS "actor_name?", old_permissions::integer AS "old_permissions!", new_permissions::integer AS "new_permissions!"
+               FROM permission_modifications
+               LEFT OUTER JOIN members AS users ON users.member_id = permission_modifications.id
+               LEFT OUTER JOIN members AS actors ON actors.member_id = userid
+               WHERE (old_permissions # new_permissions) & CAST($1::INTEGER AS BIT(16)) <> B'0000000000000000'
+               ORDER BY time, audit_id"#,
+            Self::team_permissions().bits() as i32
+        )
+        .fetch(connection);
+
+        let mut entries = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            let old = Permissions::from_bits_truncate(row.old_permissions as u16) & Self::team_permissions();
+            let new = Permissions::from_bits_truncate(row.new_permissions as u16) & Self::team_permissions();
+
+            entries.push(TeamHistoryEntry {
+                time: row.time,
+                audit_id: row.audit_id,
+                user: NamedId {
+                    id: row.user_id,
+                    name: row.user_name,
+                },
+                actor: NamedId {
+                    id: row.actor_id,
+                    name: row.actor_name,
+                },
+                granted: new - old,
+                revoked: old - new,
+            })
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::user::User, permissions::Permissions, state::audit_connection};
+
+    #[actix_rt::test]
+    async fn test_granting_permission_records_history() {
+        let mut connection = crate::test::test_setup().await;
+
+        let actor_id = sqlx::query!("INSERT INTO members (name, password_hash) VALUES ('team_admin', 'x') RETURNING member_id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .member_id;
+
+        audit_connection(&mut connection, actor_id).await.unwrap();
+
+        let mut user = User::by_name("stadust_existing", &mut connection).await.unwrap();
+        user.set_permissions(Permissions::ListHelper, &mut connection).await.unwrap();
+
+        let history = User::team_history(&mut connection).await.unwrap();
+        let entry = history.last().unwrap();
+
+        assert_eq!(entry.user.id, user.id);
+        assert_eq!(entry.actor.id, actor_id);
+        assert_eq!(entry.actor.name.as_deref(), Some("team_admin"));
+        assert_eq!(entry.granted, Permissions::ListHelper);
+        assert_eq!(entry.revoked, Permissions::empty());
+        assert!(entry.time <= chrono::Utc::now().naive_utc());
+    }
+}
@@ -0,0 +1,442 @@
+//! Module for announcing events on the demonlist to external services via webhooks and WebSub
+//!
+//! All notifications are best-effort: they are sent in the background after the change they
+//! announce has been committed, and failures are only logged.
+
+use crate::{
+    config,
+    model::demonlist::{demon::Demon, record::FullRecord},
+    state::PointercrateState,
+    view::demonlist::CHANGELOG_FEED_URL,
+};
+use log::{debug, error};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_json::{json, Value};
+
+/// The maximal number of embeds discord accepts in a single webhook message
+const EMBEDS_PER_MESSAGE: usize = 10;
+
+/// Announces that the given record has been approved via the webhook configured by
+/// [`config::record_webhook_url`]. Does nothing if no webhook is configured.
+pub fn record_approved(state: &PointercrateState, record: &FullRecord) {
+    records_approved(state, &[record])
+}
+
+/// Announces that the given records have been approved, with as few webhook executions as discord
+/// allows (at most [`EMBEDS_PER_MESSAGE`] records are announced per message)
+pub fn records_approved(state: &PointercrateState, records: &[&FullRecord]) {
+    let webhook_url = match config::record_webhook_url() {
+        Some(webhook_url) => webhook_url,
+        None => return,
+    };
+
+    if records.is_empty() {
+        return
+    }
+
+    let payloads = approval_batches(records);
+    let http_client = state.http_client.clone();
+    let record_ids = records.iter().map(|record| record.id).collect::<Vec<_>>();
+
+    actix_rt::spawn(async move {
+        for payload in payloads {
+            if let Err(error) = execute_webhook(&http_client, &webhook_url, &payload).await {
+                error!("Failure to announce approval of records {:?} via webhook: {:?}", record_ids, error);
+
+                return
+            }
+        }
+
+        debug!("Successfully announced approval of records {:?}", record_ids)
+    });
+}
+
+/// Announces that the given demon newly took the #1 spot via the webhook configured by
+/// [`config::top_demon_webhook_url`]. Does nothing if no webhook is configured.
+///
+/// Callers are responsible for only calling this when the demon actually moved to #1, see
+/// [`Demon::new_top`].
+pub fn new_top_demon(state: &PointercrateState, demon: &Demon) {
+    let webhook_url = match config::top_demon_webhook_url() {
+        Some(webhook_url) => webhook_url,
+        None => return,
+    };
+
+    let payload = top_demon_embed(demon);
+    let http_client = state.http_client.clone();
+    let demon_name = demon.base.name.to_string();
+
+    actix_rt::spawn(async move {
+        match execute_webhook(&http_client, &webhook_url, &payload).await {
+            Ok(()) => debug!("Successfully announced {} as the new #1", demon_name),
+            Err(error) => error!("Failure to announce {} as the new #1 via webhook: {:?}", demon_name, error),
+        }
+    });
+}
+
+/// Notifies the WebSub hub configured by [`config::websub_hub_url`] that the changelog feed was
+/// updated, so that it can push the new entries to all subscribers. Does nothing if no hub is
+/// configured.
+///
+/// Should be called whenever a change to the placement of a demon was committed.
+pub fn placement_changed(state: &PointercrateState) {
+    let hub_url = match config::websub_hub_url() {
+        Some(hub_url) => hub_url,
+        None => return,
+    };
+
+    let http_client = state.http_client.clone();
+
+    actix_rt::spawn(async move {
+        match ping_hub(&http_client, &hub_url, CHANGELOG_FEED_URL).await {
+            Ok(()) => debug!("Successfully notified WebSub hub {} of changelog update", hub_url),
+            Err(error) => error!("Failure to notify WebSub hub {} of changelog update: {:?}", hub_url, error),
+        }
+    });
+}
+
+/// Sends a WebSub publish notification for the given topic to the given hub
+async fn ping_hub(http_client: &Client, hub_url: &str, topic: &str) -> Result<(), reqwest::Error> {
+    http_client
+        .post(hub_url)
+        .form(&[("hub.mode", "publish"), ("hub.url", topic)])
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+async fn execute_webhook(http_client: &Client, webhook_url: &str, payload: &Value) -> Result<(), reqwest::Error> {
+    http_client
+        .post(webhook_url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Combines the approval embeds of the given records into as few webhook payloads as possible
+fn approval_batches(records: &[&FullRecord]) -> Vec<Value> {
+    records
+        .chunks(EMBEDS_PER_MESSAGE)
+        .map(|chunk| {
+            let embeds = chunk
+                .iter()
+                .map(|record| approval_embed(record)["embeds"][0].take())
+                .collect::<Vec<_>>();
+
+            json!({ "embeds": embeds })
+        })
+        .collect()
+}
+
+fn approval_embed(record: &FullRecord) -> Value {
+    let mut payload = json!({
+        "embeds": [
+            {
+                "type": "rich",
+                "title": format!("{} - {}% on {}", record.player.name, record.progress, record.demon.name),
+                "description": format!("{}'s record of {}% on {} has been approved!", record.player.name, record.progress, record.demon.name),
+                "fields": [
+                    {
+                        "name": "Player",
+                        "value": format!("{} (ID: {})", record.player.name, record.player.id),
+                        "inline": true
+                    },
+                    {
+                        "name": "Demon",
+                        "value": format!("{} (#{})", record.demon.name, record.demon.position),
+                        "inline": true
+                    },
+                    {
+                        "name": "Progress",
+                        "value": format!("{}%", record.progress),
+                        "inline": true
+                    }
+                ],
+                "footer": {
+                    "text": format!("Record ID: {}", record.id)
+                }
+            }
+        ]
+    });
+
+    if let Some(ref video) = record.video {
+        payload["embeds"][0]["url"] = json!(video);
+        payload["embeds"][0]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"name": "Video Proof:", "value": video}));
+    }
+
+    payload
+}
+
+fn top_demon_embed(demon: &Demon) -> Value {
+    let mut payload = json!({
+        "embeds": [
+            {
+                "type": "rich",
+                "title": format!("{} is the new #1!", demon.base.name),
+                "description": format!("{} has been placed at the top of the list!", demon.base.name),
+                "fields": [
+                    {
+                        "name": "Publisher",
+                        "value": demon.publisher.name.to_string(),
+                        "inline": true
+                    },
+                    {
+                        "name": "Verifier",
+                        "value": demon.verifier.name.to_string(),
+                        "inline": true
+                    }
+                ],
+                "footer": {
+                    "text": format!("Demon ID: {}", demon.base.id)
+                }
+            }
+        ]
+    });
+
+    if let Some(ref video) = demon.video {
+        payload["embeds"][0]["url"] = json!(video);
+    }
+
+    if let Some(video) = demon.verification_video.as_ref().or_else(|| demon.video.as_ref()) {
+        payload["embeds"][0]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"name": "Verification:", "value": video}));
+    }
+
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{approval_batches, approval_embed, execute_webhook, ping_hub, top_demon_embed};
+    use crate::{
+        api::demonlist::demon::v2,
+        cistring::CiString,
+        model::demonlist::{
+            demon::{Demon, FullDemon, MinimalDemon, VerificationPlatform},
+            player::DatabasePlayer,
+            record::{FullRecord, Progress, RecordStatus},
+        },
+        state::audit_connection,
+        view::demonlist::CHANGELOG_FEED_URL,
+    };
+    use actix_rt::time::delay_for;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+    use reqwest::Client;
+    use serde_json::{json, Value};
+    use sqlx::PgConnection;
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        net::TcpListener,
+        time::Duration,
+    };
+
+    fn record() -> FullRecord {
+        FullRecord {
+            id: 12,
+            progress: Progress::from(100),
+            video: Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+            video_timestamp: None,
+            video_dead: false,
+            attempts: None,
+            status: RecordStatus::Approved,
+            player: DatabasePlayer {
+                id: 1,
+                name: CiString("stardust1971".to_string()),
+                banned: false,
+            },
+            demon: MinimalDemon {
+                id: 2,
+                position: 1,
+                name: CiString("Bloodbath".to_string()),
+            },
+            submitter: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_approval_embed_shape() {
+        let (url, receiver) = crate::test::mock_server(|_| ("200 OK", ""));
+
+        execute_webhook(&Client::new(), &url, &approval_embed(&record())).await.unwrap();
+
+        let payload: Value = serde_json::from_str(&receiver.recv().unwrap().body).unwrap();
+        let embed = &payload["embeds"][0];
+
+        assert_eq!(embed["title"], "stardust1971 - 100% on Bloodbath");
+        assert_eq!(embed["url"], "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!(embed["fields"][0]["value"], "stardust1971 (ID: 1)");
+        assert_eq!(embed["fields"][1]["value"], "Bloodbath (#1)");
+        assert_eq!(embed["fields"][2]["value"], "100%");
+        assert_eq!(embed["fields"][3]["value"], "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+    }
+
+    #[test]
+    fn test_approval_embed_without_video() {
+        let mut record = record();
+        record.video = None;
+
+        let payload = approval_embed(&record);
+
+        assert!(payload["embeds"][0].get("url").is_none());
+        assert_eq!(payload["embeds"][0]["fields"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_approval_batches() {
+        let records = (0..12)
+            .map(|id| {
+                let mut record = record();
+                record.id = id;
+                record
+            })
+            .collect::<Vec<_>>();
+
+        let payloads = approval_batches(&records.iter().collect::<Vec<_>>());
+
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0]["embeds"].as_array().unwrap().len(), 10);
+        assert_eq!(payloads[1]["embeds"].as_array().unwrap().len(), 2);
+        assert_eq!(payloads[1]["embeds"][1]["footer"]["text"], "Record ID: 11");
+    }
+
+    #[test]
+    fn test_top_demon_embed() {
+        let record = record();
+        let demon = Demon {
+            base: record.demon,
+            requirement: 54,
+            video: Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+            verification_video: None,
+            publisher: record.player.clone(),
+            verifier: record.player,
+            level_id: None,
+            verified_on: VerificationPlatform::Unknown,
+            published: true,
+            song_name: None,
+            song_artist: None,
+        };
+
+        let payload = top_demon_embed(&demon);
+        let embed = &payload["embeds"][0];
+
+        assert_eq!(embed["title"], "Bloodbath is the new #1!");
+        assert_eq!(embed["url"], "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!(embed["fields"][0]["value"], "stardust1971");
+        assert_eq!(embed["fields"][2]["value"], "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+    }
+
+    #[actix_rt::test]
+    async fn test_unreachable_webhook_is_an_error() {
+        // bind and immediately drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+        let result = execute_webhook(&Client::new(), &format!("http://127.0.0.1:{}/", port), &approval_embed(&record())).await;
+
+        assert!(result.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_hub_ping_contains_topic() {
+        let (url, receiver) = crate::test::mock_server(|_| ("200 OK", ""));
+
+        ping_hub(&Client::new(), &url, CHANGELOG_FEED_URL).await.unwrap();
+
+        let body = receiver.recv().unwrap().body;
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(&body).unwrap();
+
+        assert_eq!(params, vec![
+            ("hub.mode".to_string(), "publish".to_string()),
+            ("hub.url".to_string(), "https://pointercrate.com/demonlist/changelog/feed.xml".to_string())
+        ]);
+    }
+
+    /// Removes everything [`test_placement_change_pings_hub`] commits
+    async fn remove_committed_demons(connection: &mut PgConnection) {
+        audit_connection(connection, 0).await.unwrap();
+
+        sqlx::query!("DELETE FROM demons WHERE name IN ('Bloodbath', 'Sonic Wave')")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM players WHERE name = 'Riot'")
+            .execute(&mut *connection)
+            .await
+            .unwrap();
+        crate::test::remove_moderator(connection).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_placement_change_pings_hub() {
+        let _env = crate::test::lock_env();
+        let mut database = crate::test::lock_database().await;
+        let (url, receiver) = crate::test::mock_server(|_| ("200 OK", ""));
+
+        remove_committed_demons(&mut database).await;
+
+        let token = crate::test::commit_moderator(&mut database).await;
+        let demon_id = sqlx::query!(
+            "WITH riot AS (INSERT INTO players (name) VALUES ('Riot') RETURNING id) INSERT INTO demons (name, position, requirement, \
+             verifier, publisher) SELECT name, position, 50, id, id FROM riot, (VALUES ('Bloodbath', 1), ('Sonic Wave', 2)) AS demons \
+             (name, position) RETURNING id, position"
+        )
+        .fetch_all(&mut database)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|row| row.position == 2)
+        .unwrap()
+        .id;
+
+        let mut hasher = DefaultHasher::new();
+        FullDemon::by_id(demon_id, &mut database).await.unwrap().hash(&mut hasher);
+
+        std::env::set_var("WEBSUB_HUB_URL", &url);
+
+        let mut app = init_service(App::new().app_data(crate::test::test_state().await).service(v2::patch)).await;
+        let request = TestRequest::patch()
+            .uri(&format!("/{}/", demon_id))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("If-Match", hasher.finish().to_string())
+            .set_json(&json!({"position": 1}))
+            .to_request();
+        let status = call_service(&mut app, request).await.status();
+
+        // the hub is notified in the background, after the response has been sent
+        let mut body = None;
+
+        for _ in 0..50 {
+            if let Ok(received) = receiver.try_recv() {
+                body = Some(received.body);
+                break
+            }
+
+            delay_for(Duration::from_millis(100)).await;
+        }
+
+        std::env::remove_var("WEBSUB_HUB_URL");
+        remove_committed_demons(&mut database).await;
+
+        assert_eq!(status, StatusCode::OK);
+
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(&body.expect("hub was not pinged")).unwrap();
+
+        assert_eq!(params[1], ("hub.url".to_string(), CHANGELOG_FEED_URL.to_string()));
+    }
+}
@@ -1,9 +1,14 @@
-use crate::{error::PointercrateError, Result};
+use crate::{cistring::CiStr, config, error::PointercrateError, Result};
+use actix_web::http::Method;
 use derive_more::Display;
 use nonzero_ext::nonzero;
 use ratelimit_meter::{DirectRateLimiter, KeyedRateLimiter, NonConformance};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, VecDeque},
     net::IpAddr,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
@@ -15,6 +20,9 @@ pub enum RatelimitScope {
     #[display(fmt = "Too many records are being submitted right now!")]
     RecordSubmissionGlobal,
 
+    #[display(fmt = "Too many invalid record submissions!")]
+    FailedRecordSubmission,
+
     #[display(fmt = "Well that's pretty unfortunate!")]
     NewSubmitter,
 
@@ -26,16 +34,42 @@ pub enum RatelimitScope {
 
     #[display(fmt = "Too many login attempts!")]
     Login,
+
+    #[display(fmt = "You're checking too many videos!")]
+    VideoPreflight,
+}
+
+/// A ratelimit that was exceeded
+///
+/// Most ratelimits are checked via a [`RatelimitScope`]. Those keyed by something other than the
+/// client's IP address, as well as the API quotas, have their own variants here, as they are
+/// checked separately.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ExceededRatelimit {
+    #[display(fmt = "{}", _0)]
+    Scope(RatelimitScope),
+
+    #[display(fmt = "Too many records are being submitted for this player!")]
+    RecordSubmissionPlayer,
+
+    #[display(fmt = "You're making too many requests to the API!")]
+    ApiRead,
+
+    #[display(fmt = "You're making too many modifying requests to the API!")]
+    ApiWrite,
 }
 
 #[derive(Debug, Clone)]
 pub struct Ratelimits {
     record_submission: KeyedRateLimiter<IpAddr>,
     record_submission_global: DirectRateLimiter,
+    record_submission_player: KeyedRateLimiter<String>,
+    failed_record_submissions: KeyedRateLimiter<IpAddr>,
     new_submitters: DirectRateLimiter,
     registrations: KeyedRateLimiter<IpAddr>,
     soft_registrations: KeyedRateLimiter<IpAddr>,
     login_attempts: KeyedRateLimiter<IpAddr>,
+    video_preflights: KeyedRateLimiter<IpAddr>,
 }
 
 #[derive(Copy, Clone)]
@@ -48,15 +82,32 @@ impl PreparedRatelimits<'_> {
     pub fn check(&self, scope: RatelimitScope) -> Result<()> {
         self.ratelimits.check(scope, self.ip)
     }
+
+    pub fn check_player(&self, player: &CiStr) -> Result<()> {
+        self.ratelimits.check_player(player)
+    }
 }
 
 impl Ratelimits {
     pub fn initialize() -> Self {
+        let submissions = NonZeroU32::new(config::submission_ratelimit()).expect("SUBMISSION_RATELIMIT must be positive");
+
+        Ratelimits::with_submission_limit(submissions, Duration::from_secs(config::submission_ratelimit_window()))
+    }
+
+    /// Initializes the ratelimits, allowing `submissions` accepted record submissions per `window`
+    /// for each IP address and each player
+    fn with_submission_limit(submissions: NonZeroU32, window: Duration) -> Self {
         Ratelimits {
-            // 3 per 20  minutes
-            record_submission: KeyedRateLimiter::new(nonzero!(3u32), Duration::from_secs(20 * 60)),
+            // 3 per 20 minutes by default
+            record_submission: KeyedRateLimiter::new(submissions, window),
             // 20 per hour
             record_submission_global: DirectRateLimiter::new(nonzero!(20u32), Duration::from_secs(3600)),
+            // 3 per 20 minutes by default
+            record_submission_player: KeyedRateLimiter::new(submissions, window),
+            // 10 per 20 minutes. Submissions that fail validation are tracked separately, so that
+            // people fixing up a rejected submission aren't locked out by their own mistakes
+            failed_record_submissions: KeyedRateLimiter::new(nonzero!(10u32), Duration::from_secs(20 * 60)),
             // 5 per hour
             new_submitters: DirectRateLimiter::new(nonzero!(5u32), Duration::from_secs(3600)),
             // 1 per day
@@ -65,6 +116,8 @@ impl Ratelimits {
             soft_registrations: KeyedRateLimiter::new(nonzero!(5u32), Duration::from_secs(3600 * 6)),
             // 3 per 30 minutes
             login_attempts: KeyedRateLimiter::new(nonzero!(3u32), Duration::from_secs(1800)),
+            // 30 per 10 minutes
+            video_preflights: KeyedRateLimiter::new(nonzero!(30u32), Duration::from_secs(600)),
         }
     }
 
@@ -78,16 +131,396 @@ impl Ratelimits {
         match scope {
             RatelimitScope::RecordSubmission => self.record_submission.clone().check_at(ip, now),
             RatelimitScope::RecordSubmissionGlobal => self.record_submission_global.clone().check_at(now),
+            RatelimitScope::FailedRecordSubmission => self.failed_record_submissions.clone().check_at(ip, now),
             RatelimitScope::NewSubmitter => self.new_submitters.clone().check_at(now),
             RatelimitScope::Registration => self.registrations.clone().check_at(ip, now),
             RatelimitScope::SoftRegistration => self.soft_registrations.clone().check_at(ip, now),
             RatelimitScope::Login => self.login_attempts.clone().check_at(ip, now),
+            RatelimitScope::VideoPreflight => self.video_preflights.clone().check_at(ip, now),
+        }
+        .map_err(|too_early| ratelimited(ExceededRatelimit::Scope(scope), too_early, now))
+    }
+
+    pub fn check_player(&self, player: &CiStr) -> Result<()> {
+        let now = Instant::now();
+
+        self.record_submission_player
+            .clone()
+            .check_at(player.as_ref().to_lowercase(), now)
+            .map_err(|too_early| ratelimited(ExceededRatelimit::RecordSubmissionPlayer, too_early, now))
+    }
+}
+
+/// The tier of a user's access tokens, determining how many API requests can be made with them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuotaTier {
+    Standard,
+    Elevated,
+    Unlimited,
+}
+
+impl QuotaTier {
+    pub fn from_sql(sql: &str) -> Self {
+        match sql {
+            "STANDARD" => QuotaTier::Standard,
+            "ELEVATED" => QuotaTier::Elevated,
+            "UNLIMITED" => QuotaTier::Unlimited,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Whether an API request only reads data, or might modify it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiAccess {
+    Read,
+    Write,
+}
+
+impl ApiAccess {
+    pub fn of(method: &Method) -> ApiAccess {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => ApiAccess::Read,
+            _ => ApiAccess::Write,
+        }
+    }
+}
+
+/// The party an API request counts towards the quota of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiClient {
+    /// A client without a (valid) access token, identified by its IP address
+    Anonymous(IpAddr),
+
+    /// A client using an access token of the user with the given ID
+    Token { user: i32, tier: QuotaTier },
+}
+
+impl ApiClient {
+    /// The number of requests of the given kind this client can make within the ratelimit window, or
+    /// `None` if it is not limited at all
+    pub fn quota(self, access: ApiAccess) -> Option<u32> {
+        let (read, write) = match self {
+            ApiClient::Anonymous(_) => (300, 30),
+            ApiClient::Token {
+                tier: QuotaTier::Standard, ..
+            } => (600, 60),
+            ApiClient::Token {
+                tier: QuotaTier::Elevated, ..
+            } => (3000, 300),
+            ApiClient::Token {
+                tier: QuotaTier::Unlimited, ..
+            } => return None,
+        };
+
+        match access {
+            ApiAccess::Read => Some(read),
+            ApiAccess::Write => Some(write),
+        }
+    }
+}
+
+/// The state of a client's quota after a request was counted towards it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiQuota {
+    pub limit: u32,
+    pub remaining: u32,
+}
+
+/// Per-client quotas for requests to the API
+///
+/// Unlike the other ratelimits, these are enforced over a sliding window: We remember when each
+/// request within the last window was made, so that we can tell clients exactly how many requests
+/// they have left.
+#[derive(Debug, Clone)]
+pub struct ApiQuotas {
+    window: Duration,
+    requests: Arc<Mutex<ApiRequests>>,
+    clients: Arc<Mutex<HashMap<String, (ApiClient, Instant)>>>,
+}
+
+/// How long the client an access token belongs to is remembered, so that we do not have to hit the
+/// database for every API request. Changes to a user's quota tier, or invalidated access tokens,
+/// take at most this long to be picked up.
+const CLIENT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct ApiRequests {
+    /// Number of checks since clients without requests in the current window were last forgotten
+    checks: usize,
+    made_at: HashMap<(ApiClient, ApiAccess), VecDeque<Instant>>,
+}
+
+impl ApiQuotas {
+    pub fn initialize() -> Self {
+        ApiQuotas::with_window(Duration::from_secs(config::api_ratelimit_window()))
+    }
+
+    fn with_window(window: Duration) -> Self {
+        ApiQuotas {
+            window,
+            requests: Arc::new(Mutex::new(ApiRequests::default())),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Looks up the client the given access token was recently resolved to
+    pub fn cached_client(&self, access_token: &str) -> Option<ApiClient> {
+        self.cached_client_at(access_token, Instant::now())
+    }
+
+    fn cached_client_at(&self, access_token: &str, now: Instant) -> Option<ApiClient> {
+        match self.clients.lock().unwrap().get(access_token) {
+            Some(&(client, cached_at)) if now.saturating_duration_since(cached_at) < CLIENT_CACHE_TTL => Some(client),
+            _ => None,
+        }
+    }
+
+    /// Remembers the client the given (valid) access token belongs to
+    pub fn cache_client(&self, access_token: String, client: ApiClient) {
+        self.cache_client_at(access_token, client, Instant::now())
+    }
+
+    fn cache_client_at(&self, access_token: String, client: ApiClient, now: Instant) {
+        let mut clients = self.clients.lock().unwrap();
+
+        if clients.len() >= 1024 {
+            clients.retain(|_, &mut (_, cached_at)| now.saturating_duration_since(cached_at) < CLIENT_CACHE_TTL);
+        }
+
+        clients.insert(access_token, (client, now));
+    }
+
+    /// Counts a request of the given kind towards the given client's quota
+    ///
+    /// Returns `None` for clients that are not ratelimited. Fails with
+    /// [`PointercrateError::Ratelimited`] if the quota is exhausted, in which case the request
+    /// is not counted.
+    pub fn check(&self, client: ApiClient, access: ApiAccess) -> Result<Option<ApiQuota>> {
+        self.check_at(client, access, Instant::now())
+    }
+
+    fn check_at(&self, client: ApiClient, access: ApiAccess, now: Instant) -> Result<Option<ApiQuota>> {
+        let limit = match client.quota(access) {
+            Some(limit) => limit,
+            None => return Ok(None),
+        };
+
+        let window = self.window;
+        let mut requests = self.requests.lock().unwrap();
+
+        requests.checks += 1;
+
+        if requests.checks >= 1024 {
+            requests.checks = 0;
+            requests
+                .made_at
+                .retain(|_, made_at| made_at.back().map(|&at| now.saturating_duration_since(at) < window).unwrap_or(false));
         }
-        .map_err(|too_early| {
-            PointercrateError::Ratelimited {
+
+        let made_at = requests.made_at.entry((client, access)).or_default();
+
+        while made_at.front().map(|&at| now.saturating_duration_since(at) >= window).unwrap_or(false) {
+            made_at.pop_front();
+        }
+
+        if made_at.len() >= limit as usize {
+            let scope = match access {
+                ApiAccess::Read => ExceededRatelimit::ApiRead,
+                ApiAccess::Write => ExceededRatelimit::ApiWrite,
+            };
+
+            return Err(PointercrateError::Ratelimited {
                 scope,
-                remaining: too_early.earliest_possible() - now,
-            }
-        }) // TODO: add jitter
+                remaining: (made_at[0] + window).saturating_duration_since(now),
+            })
+        }
+
+        made_at.push_back(now);
+
+        Ok(Some(ApiQuota {
+            limit,
+            remaining: limit - made_at.len() as u32,
+        }))
+    }
+}
+
+fn ratelimited(scope: ExceededRatelimit, too_early: NonConformance, now: Instant) -> PointercrateError {
+    // TODO: add jitter
+    PointercrateError::Ratelimited {
+        scope,
+        remaining: too_early.earliest_possible() - now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cistring::CiStr,
+        error::{JsonError, PointercrateError},
+        ratelimit::{ApiAccess, ApiClient, ApiQuota, ApiQuotas, ExceededRatelimit, QuotaTier, RatelimitScope, Ratelimits},
+    };
+    use actix_web::{http::StatusCode, ResponseError};
+    use nonzero_ext::nonzero;
+    use std::{
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    fn ratelimits() -> Ratelimits {
+        Ratelimits::with_submission_limit(nonzero!(2u32), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_submission_ratelimit_per_ip() {
+        let ratelimits = ratelimits();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        assert!(ratelimits.check(RatelimitScope::RecordSubmission, ip).is_ok());
+        assert!(ratelimits.check(RatelimitScope::RecordSubmission, ip).is_ok());
+
+        let error = ratelimits.check(RatelimitScope::RecordSubmission, ip).unwrap_err();
+
+        assert_eq!(error.status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.error_code(), 42900);
+
+        // other IPs are unaffected
+        assert!(ratelimits.check(RatelimitScope::RecordSubmission, "127.0.0.2".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_submission_ratelimit_per_player() {
+        let ratelimits = ratelimits();
+
+        assert!(ratelimits.check_player(CiStr::from_str("stardust1971")).is_ok());
+        assert!(ratelimits.check_player(CiStr::from_str("Stardust1971")).is_ok());
+
+        match ratelimits.check_player(CiStr::from_str("STARDUST1971")) {
+            Err(PointercrateError::Ratelimited {
+                scope: ExceededRatelimit::RecordSubmissionPlayer,
+                remaining,
+            }) => assert!(remaining <= Duration::from_secs(60)),
+            result => panic!("expected ratelimit, got {:?}", result),
+        }
+
+        assert!(ratelimits.check_player(CiStr::from_str("Aquatias")).is_ok());
+    }
+
+    #[test]
+    fn test_failed_submissions_dont_count_towards_submission_ratelimit() {
+        let ratelimits = ratelimits();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        while ratelimits.check(RatelimitScope::FailedRecordSubmission, ip).is_ok() {}
+
+        assert!(ratelimits.check(RatelimitScope::RecordSubmission, ip).is_ok());
+    }
+
+    #[test]
+    fn test_ratelimited_response_has_retry_after() {
+        let ratelimits = ratelimits();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        ratelimits.check(RatelimitScope::RecordSubmission, ip).unwrap();
+        ratelimits.check(RatelimitScope::RecordSubmission, ip).unwrap();
+
+        let response = JsonError(ratelimits.check(RatelimitScope::RecordSubmission, ip).unwrap_err()).error_response();
+        let retry_after: u64 = response.headers().get("Retry-After").unwrap().to_str().unwrap().parse().unwrap();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(retry_after > 0 && retry_after <= 60);
+    }
+
+    #[test]
+    fn test_anonymous_api_quota() {
+        let quotas = ApiQuotas::with_window(Duration::from_secs(60));
+        let client = ApiClient::Anonymous("127.0.0.1".parse().unwrap());
+        let start = Instant::now();
+
+        for made in 1..=300 {
+            assert_eq!(
+                quotas.check_at(client, ApiAccess::Read, start).unwrap(),
+                Some(ApiQuota {
+                    limit: 300,
+                    remaining: 300 - made,
+                })
+            );
+        }
+
+        let later = start + Duration::from_secs(20);
+
+        match quotas.check_at(client, ApiAccess::Read, later) {
+            Err(PointercrateError::Ratelimited {
+                scope: ExceededRatelimit::ApiRead,
+                remaining,
+            }) => assert_eq!(remaining, Duration::from_secs(40)),
+            result => panic!("expected ratelimit, got {:?}", result),
+        }
+
+        // writes have their own (stricter) quota, and other clients are unaffected
+        assert_eq!(quotas.check_at(client, ApiAccess::Write, later).unwrap().unwrap().remaining, 29);
+        assert!(quotas
+            .check_at(ApiClient::Anonymous("127.0.0.2".parse().unwrap()), ApiAccess::Read, later)
+            .is_ok());
+
+        // once the window has slid past the earlier requests, they no longer count
+        assert_eq!(
+            quotas
+                .check_at(client, ApiAccess::Read, start + Duration::from_secs(60))
+                .unwrap()
+                .unwrap()
+                .remaining,
+            299
+        );
+    }
+
+    #[test]
+    fn test_token_api_quota() {
+        let quotas = ApiQuotas::with_window(Duration::from_secs(60));
+        let now = Instant::now();
+        let standard = ApiClient::Token {
+            user: 1,
+            tier: QuotaTier::Standard,
+        };
+
+        for _ in 0..60 {
+            assert!(quotas.check_at(standard, ApiAccess::Write, now).is_ok());
+        }
+
+        let error = quotas.check_at(standard, ApiAccess::Write, now).unwrap_err();
+
+        assert_eq!(error.status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.error_code(), 42900);
+
+        // the token's tier is more generous than the anonymous quota
+        assert_eq!(quotas.check_at(standard, ApiAccess::Read, now).unwrap().unwrap().limit, 600);
+
+        let unlimited = ApiClient::Token {
+            user: 2,
+            tier: QuotaTier::Unlimited,
+        };
+
+        for _ in 0..1000 {
+            assert_eq!(quotas.check_at(unlimited, ApiAccess::Write, now).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_client_cache_expires() {
+        let quotas = ApiQuotas::with_window(Duration::from_secs(60));
+        let now = Instant::now();
+        let client = ApiClient::Token {
+            user: 1,
+            tier: QuotaTier::Elevated,
+        };
+
+        assert_eq!(quotas.cached_client_at("token", now), None);
+
+        quotas.cache_client_at("token".to_string(), client, now);
+
+        assert_eq!(quotas.cached_client_at("token", now + Duration::from_secs(59)), Some(client));
+        assert_eq!(quotas.cached_client_at("other token", now), None);
+        assert_eq!(quotas.cached_client_at("token", now + Duration::from_secs(60)), None);
     }
 }
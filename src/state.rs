@@ -1,4 +1,12 @@
-use crate::{config, documentation, gd::PgCache, model::user::AuthenticatedUser, ratelimit::Ratelimits, Result};
+use crate::{
+    config,
+    documentation,
+    gd::PgCache,
+    model::{demonlist::player::HardestDemonCache, user::AuthenticatedUser},
+    ratelimit::{ApiQuotas, Ratelimits},
+    view::demonlist::OverviewCache,
+    Result,
+};
 use chrono::Duration;
 use log::{info, trace};
 use reqwest::Client;
@@ -16,10 +24,13 @@ pub struct PointercrateState {
     pub secret: Arc<Vec<u8>>,
     pub connection_pool: Pool<Postgres>,
     pub ratelimits: Ratelimits,
+    pub api_quotas: ApiQuotas,
 
     pub http_client: Client,
     pub webhook_url: Option<Arc<String>>,
     pub gd_integration: PgCache,
+    pub overview_cache: OverviewCache,
+    pub hardest_demons: HardestDemonCache,
 }
 
 impl PointercrateState {
@@ -53,8 +64,11 @@ impl PointercrateState {
             connection_pool,
             secret: Arc::new(config::secret()),
             ratelimits: Ratelimits::initialize(),
+            api_quotas: ApiQuotas::initialize(),
             http_client: Client::builder().build().expect("Failed to create reqwest client"),
             webhook_url: std::env::var("DISCORD_WEBHOOK").ok().map(Arc::new),
+            overview_cache: OverviewCache::new(std::time::Duration::from_secs(config::overview_cache_ttl())),
+            hardest_demons: HardestDemonCache::new(std::time::Duration::from_secs(config::overview_cache_ttl())),
         }
     }
 
@@ -1,7 +1,11 @@
 //! Some utils for pagination and patch
 
 use crate::error::PointercrateError;
-use actix_web::{dev::HttpResponseBuilder, http::HeaderMap, HttpResponse};
+use actix_web::{
+    dev::HttpResponseBuilder,
+    http::{header::IF_NONE_MATCH, HeaderMap},
+    HttpResponse,
+};
 use log::warn;
 use mime::Mime;
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
@@ -12,6 +16,26 @@ use std::{
 };
 
 macro_rules! pagination_response {
+    // Additionally sets the `X-Total-Count` header to the given total amount of objects matching the request's filters
+    (total = $total:expr; $($rest:tt)*) => {
+        pagination_response!($($rest)*).map(|mut response: HttpResponse| {
+            response.headers_mut().insert(
+                actix_web::http::header::HeaderName::from_static("x-total-count"),
+                actix_web::http::header::HeaderValue::from($total),
+            );
+            response
+        })
+    };
+    // Additionally sets the `ETag` header to the given value
+    (etag = $etag:expr; $($rest:tt)*) => {
+        pagination_response!($($rest)*).map(|mut response: HttpResponse| {
+            response.headers_mut().insert(
+                actix_web::http::header::ETAG,
+                actix_web::http::header::HeaderValue::from_str(&$etag).expect("ETag is not a valid header value"),
+            );
+            response
+        })
+    };
     ($endpoint: expr, $objects:expr, $pagination:expr, $min_id:expr, $max_id:expr, $before_field:ident, $after_field:ident, $($id_field:tt)*) => {{
         log::debug!("Received pagination request {:?}", $pagination);
 
@@ -110,7 +134,8 @@ macro_rules! pagination_response {
 
         log::debug!("Links headers has value '{}'", links);
 
-        Ok(HttpResponse::Ok().header("Links", links).json(&$objects))
+        // 'Links' is what pointercrate has always used, 'Link' is the header name RFC 5988 specifies
+        Ok(HttpResponse::Ok().header("Links", links.clone()).header("Link", links).json(&$objects))
     }};
 }
 
@@ -125,6 +150,15 @@ pub fn header<'a>(request: &'a HeaderMap, header: &'static str) -> Result<Option
     }
 }
 
+/// Checks whether any of the values of the request's `If-None-Match` header equals the given ETag
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == etag || candidate.trim() == "*")
+}
+
 pub fn parse_list_of_header_values<T: FromStr>(request: &HeaderMap, header_: &'static str) -> Result<Vec<T>, PointercrateError>
 where
     T::Err: std::error::Error,
@@ -0,0 +1,217 @@
+//! Thumbnails of twitch clips and VODs
+//!
+//! Twitch hands out thumbnail URLs as templates containing `%{width}` and `%{height}`
+//! placeholders. For clips, the template can be derived from the clip's slug, but for VODs it
+//! contains a server-generated path, which we have to request from twitch's API. Since that
+//! requires an API call, resolved VOD thumbnails are stored in the `video_thumbnails` table.
+//! Failed lookups are stored there as well, and only retried once they are older than an hour.
+
+use crate::{config, Result};
+use log::{debug, error, warn};
+use reqwest::{header::AUTHORIZATION, Client};
+use serde_json::Value;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+/// Thumbnails are requested in the same size as the youtube thumbnails we use (`mqdefault`)
+const THUMBNAIL_WIDTH: u32 = 320;
+const THUMBNAIL_HEIGHT: u32 = 180;
+
+const CLIP_THUMBNAIL_TEMPLATE: &str = "https://clips-media-assets2.twitch.tv/{slug}-preview-%{width}x%{height}.jpg";
+
+/// Replaces the size placeholders in one of twitch's thumbnail URL templates
+fn fill_template(template: &str) -> String {
+    template
+        .replace("%{width}", &THUMBNAIL_WIDTH.to_string())
+        .replace("%{height}", &THUMBNAIL_HEIGHT.to_string())
+}
+
+pub(super) fn clip_thumbnail(slug: &str) -> String {
+    fill_template(&CLIP_THUMBNAIL_TEMPLATE.replace("{slug}", slug))
+}
+
+/// Extracts the ID of a twitch VOD from its (validated) URL
+fn vod_id(video: &str) -> Option<&str> {
+    match video.strip_prefix("https://www.twitch.tv/videos/") {
+        Some(video_id) if !video_id.is_empty() => Some(video_id),
+        _ => None,
+    }
+}
+
+/// Gets the thumbnails of the given videos that had to be resolved via twitch's API, keyed by
+/// video URL
+///
+/// Thumbnails that are not yet known are resolved (if twitch credentials are configured). Videos
+/// whose thumbnail cannot be resolved do not have an entry in the returned map, and should fall
+/// back to [`super::thumbnail`].
+pub async fn vod_thumbnails(videos: &[&str], http_client: &Client, connection: &mut PgConnection) -> Result<HashMap<String, String>> {
+    let vods: Vec<String> = videos
+        .iter()
+        .filter(|video| vod_id(video).is_some())
+        .map(|video| video.to_string())
+        .collect();
+
+    let mut cached: HashMap<String, Option<String>> = sqlx::query!(
+        r#"SELECT video::TEXT AS "video!: String", thumbnail FROM video_thumbnails WHERE video = ANY($1::TEXT[]) AND (thumbnail IS NOT NULL OR 
+         resolved_at > (NOW() AT TIME ZONE 'utc') - INTERVAL '1 hour')"#,
+        &vods[..]
+    )
+    .fetch_all(&mut *connection)
+    .await?
+    .into_iter()
+    .map(|row| (row.video, row.thumbnail))
+    .collect();
+
+    let mut thumbnails = HashMap::new();
+
+    for video in vods {
+        let thumbnail = match cached.remove(&video) {
+            Some(thumbnail) => thumbnail,
+            None => vod_thumbnail(&video, http_client, connection).await?,
+        };
+
+        if let Some(thumbnail) = thumbnail {
+            thumbnails.insert(video, thumbnail);
+        }
+    }
+
+    Ok(thumbnails)
+}
+
+/// Gets the thumbnail of the given twitch VOD, asking twitch's API if it isn't cached yet
+///
+/// Returns `None` if the given video is not a twitch VOD or its thumbnail could not be resolved
+pub async fn vod_thumbnail(video: &str, http_client: &Client, connection: &mut PgConnection) -> Result<Option<String>> {
+    let video_id = match vod_id(video) {
+        Some(video_id) => video_id,
+        None => return Ok(None),
+    };
+
+    // Failed lookups are only retried after an hour, as otherwise every request showing the video would cause another one
+    let cached = sqlx::query!(
+        "SELECT thumbnail FROM video_thumbnails WHERE video = $1::TEXT AND (thumbnail IS NOT NULL OR resolved_at > (NOW() AT TIME ZONE \
+         'utc') - INTERVAL '1 hour')",
+        video.to_string()
+    )
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    if let Some(row) = cached {
+        return Ok(row.thumbnail)
+    }
+
+    let (client_id, access_token) = match config::twitch_credentials() {
+        Some(credentials) => credentials,
+        None => return Ok(None),
+    };
+
+    let thumbnail = match request_thumbnail_template(http_client, video_id, &client_id, &access_token).await {
+        Ok(Some(template)) => {
+            let thumbnail = fill_template(&template);
+
+            debug!("Resolved thumbnail of twitch VOD {} to {}", video, thumbnail);
+
+            Some(thumbnail)
+        },
+        Ok(None) => {
+            warn!("Twitch did not provide a thumbnail for VOD {}", video);
+
+            None
+        },
+        Err(error) => {
+            error!("Failure to resolve the thumbnail of twitch VOD {}: {:?}", video, error);
+
+            None
+        },
+    };
+
+    sqlx::query!(
+        "INSERT INTO video_thumbnails (video, thumbnail) VALUES ($1::TEXT, $2) ON CONFLICT (video) DO UPDATE SET thumbnail = EXCLUDED.thumbnail, \
+         resolved_at = EXCLUDED.resolved_at",
+        video.to_string(),
+        thumbnail
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(thumbnail)
+}
+
+async fn request_thumbnail_template(
+    http_client: &Client, video_id: &str, client_id: &str, access_token: &str,
+) -> std::result::Result<Option<String>, reqwest::Error> {
+    let response = http_client
+        .get("https://api.twitch.tv/helix/videos")
+        .query(&[("id", video_id)])
+        .header("Client-Id", client_id)
+        .header(AUTHORIZATION, format!("Bearer {}", access_token))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    // VODs that are still being processed have an empty thumbnail URL
+    Ok(serde_json::from_str::<Value>(&response)
+        .ok()
+        .and_then(|response| response["data"][0]["thumbnail_url"].as_str().map(ToString::to_string))
+        .filter(|template| !template.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clip_thumbnail, vod_thumbnail, vod_thumbnails};
+    use reqwest::Client;
+
+    #[test]
+    fn test_clip_thumbnail() {
+        assert_eq!(
+            clip_thumbnail("AwkwardHelplessSalamanderSwiftRage"),
+            "https://clips-media-assets2.twitch.tv/AwkwardHelplessSalamanderSwiftRage-preview-320x180.jpg"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_cached_vod_thumbnail() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!(
+            "INSERT INTO video_thumbnails (video, thumbnail) VALUES ('https://www.twitch.tv/videos/123456789', \
+             'https://static-cdn.jtvnw.net/cf_vods/thumb0-320x180.jpg')"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // Cached thumbnails are returned without asking twitch, so this works without credentials
+        assert_eq!(
+            vod_thumbnail("https://www.twitch.tv/videos/123456789", &Client::new(), &mut connection)
+                .await
+                .unwrap(),
+            Some("https://static-cdn.jtvnw.net/cf_vods/thumb0-320x180.jpg".to_owned())
+        );
+        assert_eq!(
+            vod_thumbnail("https://www.youtube.com/watch?v=dQw4w9WgXcQ", &Client::new(), &mut connection)
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Failed lookups are remembered as well
+        sqlx::query!("INSERT INTO video_thumbnails (video, thumbnail) VALUES ('https://www.twitch.tv/videos/987654321', NULL)")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let thumbnails = vod_thumbnails(
+            &["https://www.twitch.tv/videos/123456789", "https://www.twitch.tv/videos/987654321"],
+            &Client::new(),
+            &mut connection,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(thumbnails.len(), 1);
+        assert!(!thumbnails.contains_key("https://www.twitch.tv/videos/987654321"));
+    }
+}
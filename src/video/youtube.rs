@@ -0,0 +1,133 @@
+//! Lookups of YouTube channel details via the YouTube Data API
+//!
+//! Channels are stored as the canonical URLs produced by [`super::validate_channel`]. The API can
+//! look up channels by ID, handle and legacy username, but not by custom (`/c/`) URL, so the
+//! latter cannot be resolved.
+
+use crate::config;
+use reqwest::Client;
+use serde_json::Value;
+
+pub struct YouTubeApi {
+    http_client: Client,
+    url: String,
+    key: String,
+}
+
+impl YouTubeApi {
+    pub fn new(http_client: Client, url: String, key: String) -> Self {
+        YouTubeApi { http_client, url, key }
+    }
+
+    /// The API as configured via [`config::youtube_api_url`] and [`config::youtube_api_key`], or
+    /// `None` if no key is configured
+    pub fn from_config(http_client: Client) -> Option<Self> {
+        Some(YouTubeApi::new(http_client, config::youtube_api_url(), config::youtube_api_key()?))
+    }
+
+    /// Gets the country the given channel has set in its settings
+    ///
+    /// Returns `None` if the channel has no country set, does not exist, or cannot be looked up via
+    /// the API at all (custom URLs). Errors if the request to the API fails.
+    ///
+    /// The key is sent as a header rather than a query parameter so that it does not end up in the
+    /// URLs attached to errors.
+    pub async fn channel_country(&self, channel: &str) -> Result<Option<String>, reqwest::Error> {
+        let (parameter, value) = match channel_filter(channel) {
+            Some(filter) => filter,
+            None => return Ok(None),
+        };
+
+        let response = self
+            .http_client
+            .get(&format!("{}/channels", self.url))
+            .query(&[("part", "snippet"), (parameter, value.as_str())])
+            .header("X-Goog-Api-Key", &self.key)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(serde_json::from_str::<Value>(&response)
+            .ok()
+            .and_then(|response| response["items"][0]["snippet"]["country"].as_str().map(ToString::to_string))
+            .filter(|country| !country.is_empty()))
+    }
+}
+
+/// Turns a canonical channel URL into the query parameter identifying the channel in the API
+fn channel_filter(channel: &str) -> Option<(&'static str, String)> {
+    let path = channel.strip_prefix("https://www.youtube.com/")?;
+
+    if path.starts_with('@') {
+        return Some(("forHandle", path.to_string()))
+    }
+
+    let segments: Vec<&str> = path.split('/').collect();
+
+    match &segments[..] {
+        ["channel", id] => Some(("id", id.to_string())),
+        ["user", name] => Some(("forUsername", super::percent_decode(name).ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{channel_filter, YouTubeApi};
+    use reqwest::Client;
+
+    /// Starts a mock server standing in for the `channels` endpoint of the YouTube Data API
+    ///
+    /// The channel `UCgermangermangermangerm` has its country set to Germany, the handle
+    /// `@nocountry` belongs to a channel without country, and requests for the username `broken`
+    /// fail. All other channels do not exist. Requests without the key `key` are rejected.
+    pub(crate) fn mock_api() -> String {
+        let (url, _) = crate::test::mock_server(|request| {
+            if request.headers.get("x-goog-api-key").map(String::as_str) != Some("key") || request.request_line.contains("key=") {
+                ("400 Bad Request", r#"{"error": {"code": 400, "message": "API key not valid"}}"#)
+            } else if request.request_line.contains("id=UCgermangermangermangerm") {
+                ("200 OK", r#"{"items": [{"id": "UCgermangermangermangerm", "snippet": {"title": "Aeon Air", "country": "DE"}}]}"#)
+            } else if request.request_line.contains("forHandle=%40nocountry") {
+                ("200 OK", r#"{"items": [{"id": "UCnocountrynocountrynoco", "snippet": {"title": "No Country"}}]}"#)
+            } else if request.request_line.contains("forUsername=broken") {
+                ("403 Forbidden", r#"{"error": {"code": 403, "message": "quotaExceeded"}}"#)
+            } else {
+                ("200 OK", r#"{"pageInfo": {"totalResults": 0}}"#)
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn test_channel_filter() {
+        assert_eq!(
+            channel_filter("https://www.youtube.com/channel/UCgermangermangermangerm"),
+            Some(("id", "UCgermangermangermangerm".to_string()))
+        );
+        assert_eq!(
+            channel_filter("https://www.youtube.com/@stardust1971"),
+            Some(("forHandle", "@stardust1971".to_string()))
+        );
+        assert_eq!(
+            channel_filter("https://www.youtube.com/user/%C3%A4on"),
+            Some(("forUsername", "äon".to_string()))
+        );
+        assert_eq!(channel_filter("https://www.youtube.com/c/stardust1971"), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_channel_country() {
+        let api = YouTubeApi::new(Client::new(), mock_api(), "key".to_string());
+
+        assert_eq!(
+            api.channel_country("https://www.youtube.com/channel/UCgermangermangermangerm").await.unwrap(),
+            Some("DE".to_string())
+        );
+        assert_eq!(api.channel_country("https://www.youtube.com/@nocountry").await.unwrap(), None);
+        assert_eq!(api.channel_country("https://www.youtube.com/@unknown").await.unwrap(), None);
+        assert!(api.channel_country("https://www.youtube.com/user/broken").await.is_err());
+    }
+}
@@ -37,7 +37,9 @@ pub trait Page {
                         (self.title())
                     }
 
-                    script data-ad-client=(config::adsense_publisher_id()) async="" src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js" {}
+                    @if let Some(publisher) = config::adsense_publisher() {
+                        script data-ad-client=(publisher) async="" src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js" {}
+                    }
 
                     (PreEscaped(format!(r#"
                     <!-- Global site tag (gtag.js) - Google Analytics -->
@@ -197,9 +199,11 @@ pub fn footer() -> Markup {
                     a.link href="/demonlist/1/" title = "Hardest demon" {
                         "Current top demon"
                     }
-                    br;
-                    a.link href = {"/demonlist/" (first_extended) "/"} title="Extended list" {
-                        "Extended list"
+                    @if !config::main_list_only() {
+                        br;
+                        a.link href = {"/demonlist/" (first_extended) "/"} title="Extended list" {
+                            "Extended list"
+                        }
                     }
                     br;
                     a.link href = {"/demonlist/" (first_legacy) "/"} title="Legacy list" {
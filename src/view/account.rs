@@ -40,11 +40,7 @@ pub async fn index(user: ApiResult<TokenAuth>, state: PointercrateState) -> View
                 let mut connection = state.connection().await?;
                 (
                     overview_demons(&mut connection, None).await?,
-                    if user.inner().has_permission(Permissions::ListModerator) {
-                        Nationality::all(&mut connection).await?
-                    } else {
-                        Vec::new()
-                    },
+                    Nationality::all(&mut connection).await?,
                 )
             } else {
                 (Vec::new(), Vec::new())
@@ -155,11 +151,11 @@ impl Page for AccountPage {
                     (users::page(self.user.has_permission(Permissions::Administrator)))
                 }
                 @if self.user.has_permission(Permissions::ListHelper) {
-                    (records::page(&self.demons))
+                    (records::page(&self.demons, &self.nations))
                 }
                 @if self.user.has_permission(Permissions::ListModerator) {
                     (players::page(&self.nations))
-                    (demons::page())
+                    (demons::page(&self.nations))
                     (submitters::page())
                 }
             }
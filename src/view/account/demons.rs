@@ -1,11 +1,14 @@
-use crate::view::{demonlist, filtered_paginator};
+use crate::{
+    model::nationality::Nationality,
+    view::{demonlist, filtered_paginator},
+};
 use maud::{html, Markup, PreEscaped};
 
-pub(super) fn page() -> Markup {
+pub(super) fn page(nations: &[Nationality]) -> Markup {
     html! {
         div.m-center.flex.tab-content.container data-tab-id = "5"{
             div.left {
-                (demon_submitter())
+                (demon_submitter(nations))
                 div.panel.fade {
                     h2.underlined.pad {
                         "Demon Manager"
@@ -92,9 +95,9 @@ pub(super) fn page() -> Markup {
             (change_position_dialog())
             (change_requirement_dialog())
             (change_video_dialog())
-            (change_verifier_dialog())
-            (change_publisher_dialog())
-            (add_creator_dialog())
+            (change_verifier_dialog(nations))
+            (change_publisher_dialog(nations))
+            (add_creator_dialog(nations))
         }
     }
 }
@@ -218,7 +221,7 @@ fn change_video_dialog() -> Markup {
     }
 }
 
-fn change_verifier_dialog() -> Markup {
+fn change_verifier_dialog(nations: &[Nationality]) -> Markup {
     demonlist::player_selection_dialog(
         "demon-verifier-dialog",
         "Change demon verifier:",
@@ -226,10 +229,11 @@ fn change_verifier_dialog() -> Markup {
          and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server to \
          create a new player.",
         "Edit",
+        nations,
     )
 }
 
-fn change_publisher_dialog() -> Markup {
+fn change_publisher_dialog(nations: &[Nationality]) -> Markup {
     demonlist::player_selection_dialog(
         "demon-publisher-dialog",
         "Change demon publisher:",
@@ -237,10 +241,11 @@ fn change_publisher_dialog() -> Markup {
          and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server to \
          create a new player.",
         "Edit",
+        nations,
     )
 }
 
-fn add_creator_dialog() -> Markup {
+fn add_creator_dialog(nations: &[Nationality]) -> Markup {
     demonlist::player_selection_dialog(
         "demon-add-creator-dialog",
         "Add creator:",
@@ -248,10 +253,11 @@ fn add_creator_dialog() -> Markup {
          left and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server \
          to create a new player.",
         "Add Creator",
+        nations,
     )
 }
 
-fn demon_submitter() -> Markup {
+fn demon_submitter(nations: &[Nationality]) -> Markup {
     html! {
         section.panel.fade.closable#demon-submitter style = "display: none" {
             span.plus.cross.hover {}
@@ -331,6 +337,7 @@ fn demon_submitter() -> Markup {
              and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server to \
              create a new player.",
             "Select",
+            nations,
         ))
         (demonlist::player_selection_dialog(
             "demon-add-publisher-dialog",
@@ -339,6 +346,7 @@ fn demon_submitter() -> Markup {
              and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server to \
              create a new player.",
             "Select",
+            nations,
         ))
     }
 }
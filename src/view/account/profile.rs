@@ -172,7 +172,7 @@ fn edit_youtube_link_dialog() -> Markup {
                     p.info-green.output {}
                     span.form-input#edit-yt {
                         label for = "youtube_channel" {"New YouTube link:"}
-                        input type = "url" name = "youtube_channel";
+                        input type = "text" name = "youtube_channel" placeholder = "https://www.youtube.com/@yourchannel";
                         p.error {}
                     }
                     span.overlined.pad.form-input#auth-yt {
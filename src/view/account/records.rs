@@ -1,6 +1,11 @@
-use crate::view::{
-    demonlist::{self, OverviewDemon},
-    dropdown, paginator,
+use crate::{
+    config,
+    i18n::Locale,
+    model::{demonlist::record::progress_step, nationality::Nationality},
+    view::{
+        demonlist::{self, OverviewDemon},
+        dropdown, paginator,
+    },
 };
 use maud::{html, Markup};
 
@@ -42,6 +47,9 @@ fn record_manager(demons: &[OverviewDemon]) -> Markup {
                         iframe."ratio-16-9"#record-video style="width:90%; margin: 15px 5%" allowfullscreen="" {"Video"}
                         p.info-red.output style = "margin: 10px" {}
                         p.info-green.output style = "margin: 10px" {}
+                        p.info-yellow#record-video-dead style = "margin: 10px; display: none" {
+                            "The last video recheck found this record's video to no longer be available!"
+                        }
                         div.stats-container.flex.space  {
                             span {
                                 b {
@@ -232,11 +240,11 @@ fn note_adder() -> Markup {
     }
 }
 
-pub(super) fn page(demons: &[OverviewDemon]) -> Markup {
+pub(super) fn page(demons: &[OverviewDemon], nations: &[Nationality]) -> Markup {
     html! {
         div.m-center.flex.tab-content.container data-tab-id = "3" {
             div.left {
-                (crate::view::demonlist::submission_panel(demons, false))
+                (crate::view::demonlist::submission_panel(demons, nations, false, config::video_required(), Locale::default(), None))
                 (record_manager(demons))
                 (note_adder())
                 div.panel.fade#record-notes-container style = "display:none" {
@@ -255,7 +263,7 @@ pub(super) fn page(demons: &[OverviewDemon]) -> Markup {
             }
             (change_progress_dialog())
             (change_video_dialog())
-            (change_holder_dialog())
+            (change_holder_dialog(nations))
             (change_demon_dialog(demons))
         }
     }
@@ -277,7 +285,7 @@ fn change_progress_dialog() -> Markup {
                     p.info-green.output {}
                     span.form-input#record-progress-edit {
                         label for = "progress" {"Progress:"}
-                        input name = "progress" type = "number" min = "0" max="100" required = "";
+                        input name = "progress" type = "number" min = "0" max="100" step = (progress_step()) required = "";
                         p.error {}
                     }
                     input.button.blue.hover type = "submit" style = "margin: 15px auto 0px;" value = "Edit";
@@ -313,13 +321,14 @@ fn change_video_dialog() -> Markup {
     }
 }
 
-fn change_holder_dialog() -> Markup {
+fn change_holder_dialog(nations: &[Nationality]) -> Markup {
     html! {
         (demonlist::player_selection_dialog(
             "record-holder-dialog",
             "Change record holder:",
             "Change the player associated with this record. If the player you want to change this record to already exists, search them up on the left and click them. In case the player does not exist, fill out only the text field on the right. This will prompt the server to create a new player.",
-            "Edit"
+            "Edit",
+            nations
         ))
     }
 }
@@ -1,75 +1,126 @@
 pub use self::{
+    cache::OverviewCache,
+    changelog::{additions_calendar, changelog_feed, list_changelog, FEED_URL as CHANGELOG_FEED_URL},
     demon_page::{demon_permalink, page},
-    overview::{index, overview_demons, OverviewDemon},
+    diff::list_diff,
+    history::{demon_position_history, position_history},
+    overview::{at, index, overview_demons, OverviewDemon},
+    statistics::list_statistics,
     statsviewer::stats_viewer as stats_viewer2,
+    submit::{form_opened, submit},
 };
 use crate::{
-    config,
-    model::{demonlist::demon::Demon, nationality::Nationality},
+    antibot, config,
+    error::PointercrateError,
+    i18n::{t, Locale},
+    model::{
+        demonlist::{
+            demon::Demon,
+            player::{HardestDemonCache, PlayerStats},
+            record::progress_step,
+        },
+        nationality::{Continent, Nationality},
+    },
+    Result,
 };
 use maud::{html, Markup, PreEscaped, Render};
+use sqlx::PgConnection;
+use submit::SubmissionFlash;
 
+mod cache;
+mod changelog;
 mod demon_page;
+mod diff;
+mod disambiguation;
+mod history;
 mod overview;
+mod statistics;
 mod statsviewer;
+mod submit;
+mod unknown_demon;
 
 struct ListSection {
-    name: &'static str,
+    name: String,
     description: &'static str,
-    id: &'static str,
+    id: String,
     numbered: bool,
+
+    /// The position of the last demon in this section, or `None` if this section contains all
+    /// remaining demons
+    end: Option<i16>,
 }
 
-static MAIN_SECTION: ListSection = ListSection {
-    name: "Main List",
-    description: "The main section of the Demonlist. These demons are the hardest rated levels in the game. Records are accepted above a \
-                  given threshold and award a large amount of points!",
-    id: "mainlist",
-    numbered: true,
-};
+impl ListSection {
+    fn new(name: String, end: Option<i16>, locale: Locale) -> ListSection {
+        // Keep the ids of the default sections stable, they are what the dropdowns are toggled by
+        let (id, description) = match name.as_str() {
+            "Main List" => ("mainlist".to_owned(), t(locale, "section.main.description")),
+            "Extended List" => ("extended".to_owned(), t(locale, "section.extended.description")),
+            "Legacy List" => ("legacy".to_owned(), t(locale, "section.legacy.description")),
+            _ =>
+                (
+                    name.to_lowercase().chars().filter(char::is_ascii_alphanumeric).collect(),
+                    "",
+                ),
+        };
 
-static EXTENDED_SECTION: ListSection = ListSection {
-    name: "Extended List",
-    description: "These are demons that dont qualify for the main section of the list, but are still of high relevance. Only 100% records \
-                  are accepted for these demons! Note that non-100% that were submitted/approved before a demon fell off the main list \
-                  will be retained",
-    id: "extended",
-    numbered: true,
-};
+        ListSection {
+            name,
+            description,
+            id,
+            // The final section is where demons end up once they drop off the list, so their order
+            // doesn't matter anymore
+            numbered: end.is_some(),
+            end,
+        }
+    }
+}
 
-static LEGACY_SECTION: ListSection = ListSection {
-    name: "Legacy List",
-    description: "These are demons that used to be on the list, but got pushed off as new demons were added. They are here for nostalgic \
-                  reasons. This list is in no order whatsoever and will not be maintained any longer at all. This means no new records \
-                  will be added for these demons.",
-    id: "legacy",
-    numbered: false,
-};
+fn list_sections(locale: Locale) -> Vec<ListSection> {
+    config::list_sections()
+        .into_iter()
+        .map(|(name, end)| ListSection::new(name, end, locale))
+        .collect()
+}
 
-fn dropdowns(all_demons: &[OverviewDemon], current: Option<&Demon>) -> Markup {
-    let (main, extended, legacy) = if all_demons.len() < config::list_size() as usize {
-        (&all_demons[..], Default::default(), Default::default())
-    } else {
-        let (extended, legacy) = if all_demons.len() < config::extended_list_size() as usize {
-            (&all_demons[config::list_size() as usize..], Default::default())
-        } else {
-            (
-                &all_demons[config::list_size() as usize..config::extended_list_size() as usize],
-                &all_demons[config::extended_list_size() as usize..],
-            )
-        };
+/// Splits the given demons (ordered by position) into the given sections
+///
+/// If there are fewer demons than a section's boundary, that section contains all remaining demons
+/// and all sections after it are empty.
+fn split_sections<'a, T>(demons: &'a [T], sections: &[ListSection]) -> Vec<&'a [T]> {
+    let mut start = 0;
 
-        (&all_demons[..config::list_size() as usize], extended, legacy)
-    };
+    sections
+        .iter()
+        .map(|section| {
+            let end = match section.end {
+                Some(end) => (end.max(0) as usize).min(demons.len()).max(start),
+                None => demons.len(),
+            };
+            let slice = &demons[start..end];
+
+            start = end;
+            slice
+        })
+        .collect()
+}
+
+fn dropdowns(all_demons: &[OverviewDemon], current: Option<&Demon>, locale: Locale) -> Markup {
+    section_dropdowns(&list_sections(locale), all_demons, current)
+}
+
+/// Renders a dropdown for each of the given sections, leaving out empty ones (e.g. the legacy list
+/// of a list that never had any demons drop off it)
+fn section_dropdowns(sections: &[ListSection], all_demons: &[OverviewDemon], current: Option<&Demon>) -> Markup {
+    let demons = split_sections(all_demons, sections);
 
     html! {
         nav.flex.wrap.m-center.fade#lists style="text-align: center;" {
-            // The drop down for the main list:
-            (dropdown(&MAIN_SECTION, main, current))
-            // The drop down for the extended list:
-            (dropdown(&EXTENDED_SECTION, extended, current))
-            // The drop down for the legacy list:
-            (dropdown(&LEGACY_SECTION, legacy, current))
+            @for (section, demons) in sections.iter().zip(demons) {
+                @if !demons.is_empty() {
+                    (dropdown(section, demons, current))
+                }
+            }
         }
     }
 }
@@ -106,8 +157,10 @@ fn dropdown(section: &ListSection, demons: &[OverviewDemon], current: Option<&De
                 div.search.js-search.seperated style = "margin: 10px" {
                     input placeholder = "Filter..." type = "text" {}
                 }
-                p style = "margin: 10px" {
-                    (section.description)
+                @if !section.description.is_empty() {
+                    p style = "margin: 10px" {
+                        (section.description)
+                    }
                 }
                 ul.flex.wrap.space {
                     @for demon in demons {
@@ -128,6 +181,10 @@ fn dropdown(section: &ListSection, demons: &[OverviewDemon], current: Option<&De
     }
 }
 
+/// Dropdown for selecting one of the given demons
+///
+/// Each entry carries the demon's effective requirement as `data-requirement`, which the submission
+/// form uses as the minimum of its progress input.
 pub fn demon_dropdown<'a>(dropdown_id: &str, demons: impl Iterator<Item = &'a OverviewDemon>) -> Markup {
     html! {
         div.dropdown-menu.js-search#(dropdown_id) {
@@ -135,7 +192,7 @@ pub fn demon_dropdown<'a>(dropdown_id: &str, demons: impl Iterator<Item = &'a Ov
             div.menu {
                ul {
                     @for demon in demons {
-                        li.white.hover data-value = (demon.id) data-display = (demon.name) {b{"#"(demon.position) " - " (demon.name)} br; {"by "(demon.publisher)}}
+                        li.white.hover data-value = (demon.id) data-requirement = (Demon::effective_requirement(demon.requirement, demon.position)) data-display = (demon.name) {b{"#"(demon.position) " - " (demon.name)} br; {"by "(demon.publisher)}}
                     }
                 }
             }
@@ -143,7 +200,11 @@ pub fn demon_dropdown<'a>(dropdown_id: &str, demons: impl Iterator<Item = &'a Ov
     }
 }
 
-pub fn player_selection_dialog(dialog_id: &str, headline: &str, description: &str, button_text: &str) -> Markup {
+/// Dialog for selecting a player, either by searching for an existing one or by entering a new name
+///
+/// If `nations` is non-empty, the search can additionally be narrowed down to players of a single
+/// nation.
+pub fn player_selection_dialog(dialog_id: &str, headline: &str, description: &str, button_text: &str, nations: &[Nationality]) -> Markup {
     html! {
         div.overlay.closable {
             div.dialog#(dialog_id) {
@@ -154,6 +215,14 @@ pub fn player_selection_dialog(dialog_id: &str, headline: &str, description: &st
                 div.flex.viewer {
                     (crate::view::filtered_paginator(&format!("{}-pagination", dialog_id), "/api/v1/players/"))
                     div {
+                        @if !nations.is_empty() {
+                            div {
+                                p {
+                                    "Only show players from:"
+                                }
+                                (nation_dropdown(nations, None, false))
+                            }
+                        }
                         p {
                             (description)
                         }
@@ -174,207 +243,389 @@ pub fn player_selection_dialog(dialog_id: &str, headline: &str, description: &st
     }
 }
 
-pub(super) fn submission_panel(demons: &[OverviewDemon], visible: bool) -> Markup {
+/// The record submission form
+///
+/// Without JavaScript, the form is posted to [`submit::submit`], which redirects back to the overview
+/// with the given `flash` message describing the outcome. Whether a video is required should be
+/// [`config::video_required`].
+pub(super) fn submission_panel(
+    demons: &[OverviewDemon], nations: &[Nationality], visible: bool, video_required: bool, locale: Locale, flash: Option<&SubmissionFlash>,
+) -> Markup {
     html! {
         section.panel.fade.closable#submitter style=(if !visible {"display:none"} else {""}) {
             span.plus.cross.hover {}
-            form#submission-form novalidate = "" {
+            form#submission-form method = "post" action = "/demonlist/submit/" novalidate = "" {
                 div.underlined {
-                    h2 {"Record Submission"}
+                    h2 {(t(locale, "submission.title"))}
+                }
+                @match flash {
+                    Some(SubmissionFlash::Error(message)) => {
+                        p.info-red.output style = "display: block" {(message)}
+                        p.info-green.output {}
+                    },
+                    Some(SubmissionFlash::Success) => {
+                        p.info-red.output {}
+                        p.info-green.output style = "display: block" {(t(locale, "submission.success"))}
+                    },
+                    None => {
+                        p.info-red.output {}
+                        p.info-green.output {}
+                    },
                 }
-                p.info-red.output {}
-                p.info-green.output {}
                 h3 {
-                    "Demon:"
+                    (t(locale, "submission.demon"))
                 }
                 p {
-                    "The demon the record was made on. Only demons in the top " (config::extended_list_size()) " are accepted. This excludes legacy demons!"
+                    (config::submission_demon_text())
                 }
                 span.form-input data-type = "dropdown" {
                     (demon_dropdown("id_demon", demons.iter().filter(|demon| demon.position <= config::extended_list_size())))
                     p.error {}
                 }
                 h3 {
-                    "Holder:"
+                    (t(locale, "submission.holder"))
                 }
                 p {
-                    "The holder of the record. Please enter the holders Geometry Dash name here, even if their YouTube name differs! Click the pencil to select a player!"
+                    (t(locale, "submission.holder.description"))
                 }
-                span.form-input.flex.col#id_player data-type = "html" data-target-id = "selected-holder" data-default = "None Selected" {
+                span.form-input.flex.col#id_player data-type = "html" data-target-id = "selected-holder" data-default = (t(locale, "submission.holder.none")) {
                     span {
                         b {
                             i.fa.fa-pencil-alt.clickable#record-submitter-holder-pen aria-hidden = "true" {}
                             " "
                         }
-                        i#selected-holder data-name = "player" {"None Selected"}
+                        i#selected-holder data-name = "player" {(t(locale, "submission.holder.none"))}
+                    }
+                    // The player selection dialog requires JavaScript
+                    noscript {
+                        input type = "text" name = "player" required = "" placeholder = "e.g. 'stardust1971'";
                     }
                     p.error {}
                 }
                 h3 {
-                    "Progress:"
+                    (t(locale, "submission.progress"))
                 }
                 p {
-                    "The progress made as percentage. Only values greater than or equal to the demons record requirement and smaller than or equal to 100 are accepted!"
+                    (t(locale, "submission.progress.description"))
                 }
                 span.form-input.flex.col#id_progress {
-                    input type = "number" name = "progress" required="" placeholder = "e. g. '50', '98'" min="0" max="100";
+                    input type = "number" name = "progress" required="" placeholder = "e. g. '50', '98'" min="0" max="100" step = (progress_step());
                     p.error {}
                 }
                 h3 {
-                    "Video: "
+                    (t(locale, "submission.attempts"))
                 }
                 p {
-                    "A proof video of the legitimacy of the given record. If the record was achieved on stream, but wasn't uploaded anywhere else, please provide a twitch link to that stream."
+                    (t(locale, "submission.attempts.description"))
+                }
+                span.form-input.flex.col#id_attempts {
+                    input type = "number" name = "attempts" placeholder = "e. g. '12345'" min = "0" step = "1";
+                    p.error {}
+                }
+                h3 {
+                    (t(locale, "submission.video"))
+                }
+                p {
+                    (t(locale, "submission.video.description"))
                     br {}
 
-                    i { "Note: " }
-                    "Please pay attention to only submit well-formed URLs!"
+                    i { (t(locale, "submission.note")) }
+                    (t(locale, "submission.video.note"))
+                    @if !video_required {
+                        br {}
+                        (t(locale, "submission.video.optional"))
+                    }
                 }
                 span.form-input.flex.col#id_video {
-                    input type = "url" name = "video" required = "" placeholder = "e.g. 'https://youtu.be/cHEGAqOgddA'" ;
+                    @if video_required {
+                        input type = "url" name = "video" required = "" placeholder = "e.g. 'https://youtu.be/cHEGAqOgddA'" ;
+                    } @else {
+                        input type = "url" name = "video" placeholder = "e.g. 'https://youtu.be/cHEGAqOgddA'" ;
+                    }
                     p.error {}
                 }
                 h3 {
-                    "Notes or comments: "
+                    (t(locale, "submission.notes"))
                 }
                 p {
-                    "Provide any additional notes you'd like to pass on to the list moderator receiving your submission. In particular, any required " b { "raw footage"} " goes here."
+                    (t(locale, "submission.notes.description.before")) b { (t(locale, "submission.notes.description.footage")) } (t(locale, "submission.notes.description.after"))
                 }
                 span.form-input.flex.col#submit-note {
-                    textarea name = "note" placeholder = "Your dreams and hopes for this record... or something like that" {}
+                    textarea name = "note" placeholder = (t(locale, "submission.notes.placeholder")) {}
                     p.error {}
                 }
+                // Only bots fill this in, see the antibot module
+                span.form-input style = "display: none" aria-hidden = "true" {
+                    input type = "text" name = (antibot::HONEYPOT_FIELD) tabindex = "-1" autocomplete = "off";
+                }
+                // Without JavaScript, this is how we learn when the form was opened, see the antibot module
+                noscript {
+                    img src = "/demonlist/submit/opened/" alt = "" width = "0" height = "0" style = "display: none";
+                }
                 p {
-                    "By submitting the record you acknowledge the " a.link href = "/guidelines" {"submission guidelines"} "."
+                    (t(locale, "submission.guidelines.before")) a.link href = (config::guidelines_url()) {(t(locale, "submission.guidelines.link"))} (t(locale, "submission.guidelines.after"))
                 }
-                input.button.blue.hover type = "submit" style = "margin: 15px auto 0px;" value="Submit record";
+                input.button.blue.hover type = "submit" style = "margin: 15px auto 0px;" value = (t(locale, "submission.submit"));
             }
         }
         (player_selection_dialog(
             "submission-holder-dialog",
-            "Select player:",
-            "To select the player holding this record, search them up on the left to see if they already have records on the list and click them. In case the player does not exist, fill out only the text field on the right.",
-            "Select"
+            t(locale, "submission.holder_dialog.title"),
+            t(locale, "submission.holder_dialog.description"),
+            t(locale, "submission.holder_dialog.button"),
+            nations
         ))
     }
 }
 
-fn stats_viewer(nations: &[Nationality], visible: bool) -> Markup {
+/// Describes the progress records on a demon with the given (effective) requirement need to reach
+fn requirement_description(effective_requirement: i16) -> String {
+    if effective_requirement >= 100 {
+        "100% required to qualify".to_string()
+    } else {
+        format!("{}% or better required to qualify", effective_requirement)
+    }
+}
+
+/// Determines the nation whose ranking the stats viewer should initially display, based on the
+/// country code from the request's geolocation header.
+///
+/// Returns `None` (meaning "International") if no country code is available or it does not belong
+/// to any nation we know about.
+fn geolocated_nation<'a>(country_code: Option<&str>, nations: &'a [Nationality]) -> Option<&'a Nationality> {
+    let country_code = country_code?.trim();
+
+    nations
+        .iter()
+        .find(|nation| nation.iso_country_code.eq_ignore_ascii_case(country_code))
+}
+
+/// Groups the given nations by continent, in the order of [`Continent::ALL`]
+///
+/// Nations whose continent is unknown are collected in a trailing group without continent. Empty
+/// groups are omitted.
+fn nations_by_continent(nations: &[Nationality]) -> Vec<(Option<Continent>, Vec<&Nationality>)> {
+    let mut groups: Vec<(Option<Continent>, Vec<&Nationality>)> = Continent::ALL
+        .iter()
+        .map(|&continent| (Some(continent), Vec::new()))
+        .chain(std::iter::once((None, Vec::new())))
+        .collect();
+
+    for nation in nations {
+        let group = Continent::ALL
+            .iter()
+            .position(|&continent| Some(continent) == nation.continent)
+            .unwrap_or(Continent::ALL.len());
+
+        groups[group].1.push(nation);
+    }
+
+    groups.retain(|(_, nations)| !nations.is_empty());
+    groups
+}
+
+/// Dropdown for selecting a nation (or "International", meaning no nation), with the nations grouped
+/// by continent. If `continents` is set, whole continents can be selected as well, with values of
+/// the form `continent:<name>`
+fn nation_dropdown(nations: &[Nationality], preselected: Option<&Nationality>, continents: bool) -> Markup {
+    super::dropdown(
+        preselected.map(|nation| nation.iso_country_code.as_str()).unwrap_or("International"),
+        html! {
+            li.white.hover.underlined data-value = "International" data-display = "International" {
+                span.em.em-world_map {}
+                (PreEscaped("&nbsp;"))
+                b {"WORLD"}
+                br;
+                span style = "font-size: 90%; font-style: italic" { "International" }
+            }
+        },
+        nations_by_continent(nations).into_iter().map(move |(continent, nations)| {
+            html! {
+                @if let Some(continent) = continent.filter(|_| continents) {
+                    li.white.hover.underlined data-value = {"continent:" (continent.to_sql())} data-display = (continent.to_sql()) {
+                        b {(continent.to_sql().to_uppercase())}
+                    }
+                }
+                @for nation in nations {
+                    li.white.hover data-value = {(nation.iso_country_code)} data-display = {(nation.nation)} {
+                        span class = {"flag-icon flag-icon-" (nation.iso_country_code.to_lowercase())} {}
+                        (PreEscaped("&nbsp;"))
+                        b {(nation.iso_country_code)}
+                        br;
+                        span style = "font-size: 90%; font-style: italic" {(nation.nation)}
+                    }
+                }
+            }
+        }),
+    )
+}
+
+/// The orders the stats viewer's player list can be sorted in, as pairs of (value of the `sort`
+/// parameter, display name)
+static RANKING_SORTS: [(&str, &str); 5] = [
+    ("score", "Score"),
+    ("name", "Name"),
+    ("record_count", "Record count"),
+    ("last_active", "Last active"),
+    ("attempts", "Attempts"),
+];
+
+/// Dropdown selecting the order of the stats viewer's player list
+fn ranking_sort_dropdown() -> Markup {
     html! {
-        section.panel.fade.closable#statsviewer  style=(if !visible {"display:none"} else {""}) {
-            span.plus.cross.hover {}
-            h2.underlined.pad {
-                "Stats Viewer - "
-                (super::dropdown("International",
-                    html! {
-                        li.white.hover.underlined data-value = "International" data-display = "International" {
-                            span.em.em-world_map {}
-                            (PreEscaped("&nbsp;"))
-                            b {"WORLD"}
-                            br;
-                            span style = "font-size: 90%; font-style: italic" { "International" }
+        p style = "margin: 0 0 10px" {
+            i { "Sort by: " }
+            div.dropdown-menu.js-search.no-stretch#ranking-sort style = "display: inline-block" {
+                input type="text" data-default="score" autocomplete="off" style = "color: #444446; font-weight: bold;";
+                div.menu {
+                    ul {
+                        @for (value, display) in RANKING_SORTS.iter() {
+                            li.white.hover data-value = (value) data-display = (display) {
+                                b { (display) }
+                            }
                         }
-                    },
-                    nations.iter().map(|nation| html! {
-                        li.white.hover data-value = {(nation.iso_country_code)} data-display = {(nation.nation)} {
-                            span class = {"flag-icon flag-icon-" (nation.iso_country_code.to_lowercase())} {}
-                            (PreEscaped("&nbsp;"))
-                            b {(nation.iso_country_code)}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The rows of metrics shown in the stats viewer, as (metric name, id of the element the stats
+/// viewer's JavaScript fills in, display name) triples. Metrics can be hidden by name via
+/// [`config::hidden_stats`], rows whose metrics are all hidden are left out entirely
+static STATS_VIEWER_ROWS: [&[(&str, &str, &str)]; 5] = [
+    &[
+        ("completed", "amount-beaten", "List demons completed"),
+        ("legacy", "amount-legacy", "Legacy demons completed"),
+        ("score", "score", "Demonlist score"),
+        ("attempts", "attempts", "Attempts logged"),
+    ],
+    &[("rank", "rank", "Demonlist rank"), ("hardest", "hardest", "Hardest demon")],
+    &[("beaten", "beaten", "Demons completed")],
+    &[
+        ("created", "created", "List demons created"),
+        ("published", "published", "List demons published"),
+        ("verified", "verified", "List demons verified"),
+    ],
+    &[("progress", "progress", "Progress on")],
+];
+
+/// Renders the rows of [`STATS_VIEWER_ROWS`] that are not entirely hidden, filling each metric's
+/// element with whatever `content` renders for it
+fn stats_viewer_rows(hidden: &[String], content: impl Fn(&str) -> Markup) -> Markup {
+    html! {
+        @for row in STATS_VIEWER_ROWS.iter() {
+            @let shown = row.iter().filter(|(metric, ..)| !hidden.iter().any(|hidden| hidden == metric)).collect::<Vec<_>>();
+
+            @if !shown.is_empty() {
+                div.stats-container.flex.space {
+                    @for (metric, id, display) in shown {
+                        span {
+                            b {
+                                (display) ":"
+                            }
                             br;
-                            span style = "font-size: 90%; font-style: italic" {(nation.nation)}
+                            span id = (id) {
+                                (content(metric))
+                            }
                         }
-                    })
-                ))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Loads the stats of the player a stats viewer was linked to via `?player=<id>`. Links to players
+/// that do not exist (anymore) are treated as if no player was linked to
+async fn linked_player(player_id: Option<i32>, cache: &HardestDemonCache, connection: &mut PgConnection) -> Result<Option<PlayerStats>> {
+    let player_id = match player_id {
+        Some(player_id) => player_id,
+        None => return Ok(None),
+    };
+
+    match PlayerStats::by_id(player_id, cache, connection).await {
+        Ok(stats) => Ok(Some(stats)),
+        Err(PointercrateError::ModelNotFound { .. }) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Renders the hardest demon a player has beaten the same way the stats viewer's JavaScript does:
+/// bold for main list demons, italic and faded for legacy demons
+fn hardest_demon(stats: &PlayerStats) -> Markup {
+    match stats.hardest {
+        None => html! { "None" },
+        Some(ref demon) => {
+            let link = html! {
+                a href = {"/demonlist/permalink/" (demon.id) "/"} { (demon.name) }
+            };
+
+            html! {
+                @if demon.position <= config::list_size() {
+                    b { (link) }
+                } @else if demon.position <= config::extended_list_size() {
+                    span { (link) }
+                } @else {
+                    i style = "opacity: .5" { (link) }
+                }
+            }
+        },
+    }
+}
+
+/// Renders the value of the given metric for a preselected player. Only metrics contained in
+/// [`PlayerStats`] are rendered, the stats viewer's JavaScript fills in the rest
+fn preselected_stat(metric: &str, selected: Option<&PlayerStats>) -> Markup {
+    match (metric, selected) {
+        ("score", Some(stats)) => html! { (format!("{:.2}", stats.score)) },
+        ("rank", Some(stats)) => html! { (stats.rank.map(|rank| rank.to_string()).unwrap_or_else(|| "-".to_string())) },
+        ("hardest", Some(stats)) => hardest_demon(stats),
+        _ => html! {},
+    }
+}
+
+/// The heading of the stats viewer, naming the selected player (if one was linked to)
+fn selected_player_name(selected: Option<&PlayerStats>) -> Markup {
+    html! {
+        h3#player-name style = "font-size:1.4em; overflow: hidden" {
+            @if let Some(stats) = selected {
+                (stats.player.base.name) " "
+                @if let Some(ref nationality) = stats.player.nationality {
+                    span.flag-icon.{"flag-icon-"(nationality.iso_country_code.to_lowercase())} title = (nationality.nation) {}
+                }
             }
+        }
+    }
+}
+
+/// The stats viewer panel, leaving out the metrics in `hidden` (see [`config::hidden_stats`]). If a
+/// player is given, their stats are rendered right away
+fn stats_viewer(
+    nations: &[Nationality], preselected: Option<&Nationality>, selected: Option<&PlayerStats>, visible: bool, hidden: &[String],
+) -> Markup {
+    html! {
+        section.panel.fade.closable#statsviewer  style=(if !visible {"display:none"} else {""}) data-nation=(preselected.map(|nation| nation.iso_country_code.as_str()).unwrap_or("")) data-player = (selected.map(|stats| stats.player.base.id.to_string()).unwrap_or_default()) {
+            span.plus.cross.hover {}
+            h2.underlined.pad {
+                "Stats Viewer - "
+                (nation_dropdown(nations, preselected, true))
+                " "
+                // populated by the stats viewer once a nation with known subdivisions is selected
+                span#subdivision-dropdown style = "display: none" {}
+            }
+            (ranking_sort_dropdown())
             div.flex.viewer {
                 (super::filtered_paginator("stats-viewer-pagination", "/api/v1/players/ranking/"))
-                p.viewer-welcome {
+                p.viewer-welcome style = (if selected.is_some() { "display: none" } else { "" }) {
                     "Click on a player's name on the left to get started!"
                 }
-                div.viewer-content {
+                div.viewer-content style = (if selected.is_some() { "display: block" } else { "" }) {
                     div {
                         div.flex.col {
-                            h3#player-name style = "font-size:1.4em; overflow: hidden" {}
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "List demons completed:"
-                                    }
-                                    br;
-                                    span#amount-beaten {}
-                                }
-                                span {
-                                    b {
-                                        "Legacy demons completed:"
-                                    }
-                                    br;
-                                    span#amount-legacy {}
-                                }
-                                span {
-                                    b {
-                                        "Demonlist score:"
-                                    }
-                                    br;
-                                    span#score {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Demonlist rank:"
-                                    }
-                                    br;
-                                    span#rank {}
-                                }
-                                span {
-                                    b {
-                                        "Hardest demon:"
-                                    }
-                                    br;
-                                    span#hardest {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Demons completed:"
-                                    }
-                                    br;
-                                    span#beaten {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "List demons created:"
-                                    }
-                                    br;
-                                    span#created {}
-                                }
-                                span {
-                                    b {
-                                        "List demons published:"
-                                    }
-                                    br;
-                                    span#published {}
-                                }
-                                span {
-                                    b {
-                                        "List demons verified:"
-                                    }
-                                    br;
-                                    span#verified {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Progress on:"
-                                    }
-                                    br;
-                                    span#progress {}
-                                }
-                            }
+                            (selected_player_name(selected))
+                            (stats_viewer_rows(hidden, |metric| preselected_stat(metric, selected)))
                         }
                     }
                 }
@@ -383,7 +634,12 @@ fn stats_viewer(nations: &[Nationality], visible: bool) -> Markup {
     }
 }
 
-fn sidebar_ad() -> Markup {
+fn sidebar_ad(publisher: Option<&str>) -> Markup {
+    let publisher = match publisher {
+        Some(publisher) => publisher,
+        None => return html! {},
+    };
+
     html! {
         section.panel.fade.js-scroll-anim data-anim = "fade" style = "order: 1; padding: 0px; border: 0" {
             (PreEscaped(format!(r#"
@@ -398,12 +654,17 @@ fn sidebar_ad() -> Markup {
             <script>
                  (adsbygoogle = window.adsbygoogle || []).push({{}});
             </script>
-            "#, config::adsense_publisher_id())))
+            "#, publisher)))
         }
     }
 }
 
-fn besides_sidebar_ad() -> Markup {
+fn besides_sidebar_ad(publisher: Option<&str>) -> Markup {
+    let publisher = match publisher {
+        Some(publisher) => publisher,
+        None => return html! {},
+    };
+
     html! {
         div#outofboundsad style="margin-left: calc(45% + 1072px/2);position: fixed;padding-left: 15px;padding-top: 15px; max-width: 200px" {
             (PreEscaped(format!(r#"
@@ -418,7 +679,93 @@ fn besides_sidebar_ad() -> Markup {
                 <script>
                      (adsbygoogle = window.adsbygoogle || []).push({{}});
                 </script>
-            "#, config::adsense_publisher_id())))
+            "#, publisher)))
+        }
+    }
+}
+
+/// The banner ad shown below the #1 demon on the overview
+fn overview_banner_ad(publisher: Option<&str>) -> Markup {
+    let publisher = match publisher {
+        Some(publisher) => publisher,
+        None => return html! {},
+    };
+
+    html! {
+        section.panel.fade style = "padding: 0px; height: 90px"{
+        (PreEscaped(format!(r#"
+            <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
+            <!-- Demonlist Responsive Feed Ad -->
+            <ins class="adsbygoogle"
+                 style="display:inline-block;width:728px;height:90px"
+                 data-ad-client="{}"
+                 data-ad-slot="2819150519"></ins>
+            <script>
+                 (adsbygoogle = window.adsbygoogle || []).push({{}});
+            </script>
+            "#, publisher)))
+        }
+    }
+}
+
+/// The in-feed ad shown after every 20th demon on the overview
+fn in_feed_ad(publisher: Option<&str>) -> Markup {
+    let publisher = match publisher {
+        Some(publisher) => publisher,
+        None => return html! {},
+    };
+
+    html! {
+        section.panel.fade {
+        (PreEscaped(format!(r#"
+            <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
+            <ins class="adsbygoogle"
+                 style="display:block"
+                 data-ad-format="fluid"
+                 data-ad-layout-key="-h1+40+4u-93+n"
+                 data-ad-client="{}"
+                 data-ad-slot="5157884729"></ins>
+            <script>
+                 (adsbygoogle = window.adsbygoogle || []).push({{}});
+            </script>
+            "#, publisher)))
+        }
+    }
+}
+
+/// The ads shown after the demon at the given position on the demonlist overview: the banner ad
+/// after the demon at `first_position`, and an in-feed ad after every `interval`-th demon
+fn overview_ad(position: i16, first_position: i16, interval: i16, publisher: Option<&str>) -> Markup {
+    html! {
+        @if position == first_position {
+            (overview_banner_ad(publisher))
+        }
+        @if interval > 0 && position % interval == 0 {
+            (in_feed_ad(publisher))
+        }
+    }
+}
+
+/// The banner ad shown at the top of a demon's page
+fn demon_page_banner_ad(publisher: Option<&str>) -> Markup {
+    let publisher = match publisher {
+        Some(publisher) => publisher,
+        None => return html! {},
+    };
+
+    html! {
+        div.panel.fade style = "padding: 0px; height: 90px" {
+            (PreEscaped(format!(r#"
+            <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
+            <!-- Demonpage Banner ad -->
+            <ins class="adsbygoogle"
+                 style="display:inline-block;width:728px;height:90px"
+                 data-ad-client="{}"
+                 data-ad-slot="4829214686"></ins>
+            <script>
+                 (adsbygoogle = window.adsbygoogle || []).push({{}});
+            </script>
+            "#, publisher)))
         }
     }
 }
@@ -430,9 +777,9 @@ fn rules_panel() -> Markup {
                 "Guidelines:"
             }
             p {
-                "All demonlist operations are carried out in accordance to our guidelines. Be sure to check them before submitting a record to ensure a flawless experience!"
+                (config::guidelines_text())
             }
-            a.blue.hover.button href = "/guidelines/" {
+            a.blue.hover.button href = (config::guidelines_url()) {
                 "Read the guidelines!"
             }
         }
@@ -448,7 +795,7 @@ pub(super) fn submit_panel() -> Markup {
                 }
             }
             p {
-                "Note: Please do not submit nonsense, it only makes it harder for us all and will get you banned. Also note that the form rejects duplicate submissions."
+                (config::submit_text())
             }
             a.blue.hover.button.js-scroll data-destination = "submitter" data-reveal = "true" {
                 "Submit a record!"
@@ -475,12 +822,15 @@ fn stats_viewer_panel() -> Markup {
     }
 }
 
+/// The embedded discord widget, unless [disabled](config::discord_widget_enabled)
 fn discord_panel() -> Markup {
     html! {
-        section.panel.fade.js-scroll-anim#discord data-anim = "fade" {
-            iframe.js-delay-attr style = "width: 100%; height: 400px;" allowtransparency="true" frameborder = "0" data-attr = "src" data-attr-value = "https://discordapp.com/widget?id=395654171422097420&theme=light" {}
-            p {
-                "Join the official Demonlist discord server, where you can get in touch with the demonlist team!"
+        @if config::discord_widget_enabled() {
+            section.panel.fade.js-scroll-anim#discord data-anim = "fade" {
+                iframe.js-delay-attr style = "width: 100%; height: 400px;" allowtransparency="true" frameborder = "0" data-attr = "src" data-attr-value = "https://discordapp.com/widget?id=395654171422097420&theme=light" {}
+                p {
+                    "Join the official Demonlist discord server, where you can get in touch with the demonlist team!"
+                }
             }
         }
     }
@@ -490,6 +840,367 @@ impl Render for Nationality {
     fn render(&self) -> Markup {
         html! {
             span.flag-icon.{"flag-icon-"(self.iso_country_code.to_lowercase())} title = (self.nation) {}
+            @if let Some(ref subdivision) = self.subdivision {
+                (PreEscaped("&nbsp;"))
+                small title = (subdivision.name) {
+                    (self.iso_country_code) "-" (subdivision.iso_code)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        besides_sidebar_ad, demon_dropdown, demon_page_banner_ad, dropdowns, geolocated_nation, in_feed_ad, linked_player, list_sections,
+        nations_by_continent, overview_ad, overview_banner_ad, rules_panel, section_dropdowns, sidebar_ad, split_sections, stats_viewer,
+        submission_panel, submit_panel, ListSection, OverviewDemon,
+    };
+    use crate::{
+        cistring::CiString,
+        config,
+        i18n::Locale,
+        model::{
+            demonlist::player::HardestDemonCache,
+            nationality::{Continent, Nationality, Subdivision},
+        },
+    };
+    use maud::Render;
+    use std::time::Duration;
+
+    fn nations() -> Vec<Nationality> {
+        vec![
+            Nationality::new("DE".to_string(), CiString("Germany".to_string()), Some(Continent::Europe), None),
+            Nationality::new("GB".to_string(), CiString("United Kingdom".to_string()), Some(Continent::Europe), None),
+        ]
+    }
+
+    #[test]
+    fn test_known_country_preselects_nation() {
+        let nations = nations();
+        let preselected = geolocated_nation(Some("de"), &nations);
+
+        assert_eq!(preselected, Some(&nations[0]));
+
+        let markup = stats_viewer(&nations, preselected, None, true, &[]).into_string();
+
+        assert!(markup.contains(r#"data-nation="DE""#));
+        assert!(markup.contains(r#"data-default="DE""#));
+    }
+
+    #[test]
+    fn test_unknown_country_stays_international() {
+        let nations = nations();
+
+        // cloudflare uses "XX" for clients without country information
+        let preselected = geolocated_nation(Some("XX"), &nations);
+
+        assert_eq!(preselected, None);
+        assert_eq!(geolocated_nation(None, &nations), None);
+
+        let markup = stats_viewer(&nations, preselected, None, true, &[]).into_string();
+
+        assert!(markup.contains(r#"data-nation="""#));
+        assert!(markup.contains(r#"data-default="International""#));
+    }
+
+    #[actix_rt::test]
+    async fn test_linked_player() {
+        let mut connection = crate::test::test_setup().await;
+        let cache = HardestDemonCache::new(Duration::from_secs(60));
+
+        let player_id = sqlx::query!("SELECT id FROM players WHERE name = 'Aeon Air'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+        let stats = linked_player(Some(player_id), &cache, &mut connection).await.unwrap();
+        let markup = stats_viewer(&nations(), None, stats.as_ref(), false, &[]).into_string();
+
+        assert!(markup.contains(&format!(r#"data-player="{}""#, player_id)));
+        assert!(markup.contains("Aeon Air"));
+        assert!(markup.contains(r#"class="viewer-content" style="display: block""#));
+
+        // links to players that do not exist do not break the page the stats viewer is on
+        assert!(linked_player(Some(-1), &cache, &mut connection).await.unwrap().is_none());
+        assert!(linked_player(None, &cache, &mut connection).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_hidden_stats() {
+        let nations = nations();
+
+        let hidden = vec!["created".to_string(), "published".to_string(), "verified".to_string()];
+        let markup = stats_viewer(&nations, None, None, true, &hidden).into_string();
+
+        for id in &["created", "published", "verified"] {
+            assert!(!markup.contains(&format!(r#"id="{}""#, id)));
         }
+
+        assert!(!markup.contains("List demons created:"));
+        assert_eq!(markup.matches("stats-container").count(), 4);
+        assert!(markup.contains(r#"id="progress""#));
+        assert!(markup.contains("Hardest demon:"));
+    }
+
+    #[test]
+    fn test_nations_grouped_by_continent() {
+        let mut nations = nations();
+        nations.push(Nationality::new("JP".to_string(), CiString("Japan".to_string()), Some(Continent::Asia), None));
+        nations.push(Nationality::new("XK".to_string(), CiString("Kosovo".to_string()), None, None));
+
+        let groups: Vec<(Option<Continent>, usize)> = nations_by_continent(&nations)
+            .iter()
+            .map(|(continent, nations)| (*continent, nations.len()))
+            .collect();
+
+        assert_eq!(groups, vec![(Some(Continent::Asia), 1), (Some(Continent::Europe), 2), (None, 1)]);
+
+        let markup = stats_viewer(&nations, None, None, true, &[]).into_string();
+        let international = markup.find(r#"data-value="International""#).unwrap();
+        let asia = markup.find(r#"data-value="continent:Asia""#).unwrap();
+        let europe = markup.find(r#"data-value="continent:Europe""#).unwrap();
+
+        assert!(international < asia && asia < europe);
+    }
+
+    #[test]
+    fn test_render_subdivision() {
+        let mut nationality = Nationality::new(
+            "US".to_string(),
+            CiString("United States".to_string()),
+            Some(Continent::NorthAmerica),
+            None,
+        );
+
+        assert!(!nationality.render().into_string().contains("<small"));
+
+        nationality.subdivision = Some(Subdivision::new("CA".to_string(), CiString("California".to_string())));
+
+        let markup = nationality.render().into_string();
+
+        assert!(markup.contains(r#"title="United States""#));
+        assert!(markup.contains(r#"<small title="California">US-CA</small>"#));
+    }
+
+    fn section_lengths(demons: usize, sections: &[ListSection]) -> Vec<usize> {
+        let demons: Vec<usize> = (0..demons).collect();
+
+        split_sections(&demons, sections).iter().map(|section| section.len()).collect()
+    }
+
+    #[test]
+    fn test_two_sections() {
+        let sections = vec![
+            ListSection::new("Main List".to_string(), Some(75), Locale::English),
+            ListSection::new("Legacy List".to_string(), None, Locale::English),
+        ];
+
+        assert_eq!(section_lengths(200, &sections), vec![75, 125]);
+        assert_eq!(section_lengths(75, &sections), vec![75, 0]);
+        assert_eq!(section_lengths(10, &sections), vec![10, 0]);
+        assert!(sections[0].numbered);
+        assert!(!sections[1].numbered);
+    }
+
+    #[test]
+    fn test_four_sections() {
+        let sections = vec![
+            ListSection::new("Main List".to_string(), Some(25), Locale::English),
+            ListSection::new("Extended List".to_string(), Some(75), Locale::English),
+            ListSection::new("Hall of Fame".to_string(), Some(150), Locale::English),
+            ListSection::new("Legacy List".to_string(), None, Locale::English),
+        ];
+
+        assert_eq!(sections[2].id, "halloffame");
+        assert_eq!(section_lengths(200, &sections), vec![25, 50, 75, 50]);
+        assert_eq!(section_lengths(100, &sections), vec![25, 50, 25, 0]);
+        assert_eq!(section_lengths(20, &sections), vec![20, 0, 0, 0]);
+        assert_eq!(section_lengths(0, &sections), vec![0, 0, 0, 0]);
+    }
+
+    fn demon(position: i16, requirement: i16) -> OverviewDemon {
+        OverviewDemon {
+            id: position as i32,
+            position,
+            name: format!("Demon {}", position),
+            requirement,
+            publisher: "Riot".to_string(),
+            publisher_nationality: None,
+            video: None,
+            current_position: None,
+            mirrors: Vec::new(),
+            legacy_since: None,
+            tags: Vec::new(),
+            record_count: None,
+        }
+    }
+
+    #[test]
+    fn test_lists_shorter_than_sections() {
+        let sections = vec![
+            ListSection::new("Main List".to_string(), Some(75), Locale::English),
+            ListSection::new("Extended List".to_string(), Some(150), Locale::English),
+            ListSection::new("Legacy List".to_string(), None, Locale::English),
+        ];
+
+        assert_eq!(section_lengths(0, &sections), vec![0, 0, 0]);
+        assert_eq!(section_lengths(1, &sections), vec![1, 0, 0]);
+        assert_eq!(section_lengths(100, &sections), vec![75, 25, 0]);
+        assert_eq!(section_lengths(200, &sections), vec![75, 75, 50]);
+
+        for length in &[0, 1, 100, 200] {
+            let demons: Vec<OverviewDemon> = (1..=*length).map(|position| demon(position, 50)).collect();
+
+            let markup = dropdowns(&demons, None, Locale::English).into_string();
+
+            assert_eq!(markup.matches("<li").count(), *length as usize);
+        }
+    }
+
+    #[test]
+    fn test_main_list_only() {
+        let sections: Vec<ListSection> = config::default_list_sections(75, 75)
+            .into_iter()
+            .map(|(name, end)| ListSection::new(name, end, Locale::English))
+            .collect();
+
+        assert_eq!(
+            sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>(),
+            vec!["Main List", "Legacy List"]
+        );
+
+        let demons: Vec<OverviewDemon> = (1..=75).map(|position| demon(position, 50)).collect();
+        let markup = section_dropdowns(&sections, &demons, None).into_string();
+
+        assert!(markup.contains(r#"id="mainlist""#));
+        assert!(!markup.contains(r#"id="extended""#));
+        assert!(!markup.contains(r#"id="legacy""#));
+        assert_eq!(markup.matches("<li").count(), 75);
+
+        // Demons dropping off the list still end up on the legacy list
+        let demons: Vec<OverviewDemon> = (1..=80).map(|position| demon(position, 50)).collect();
+        let markup = section_dropdowns(&sections, &demons, None).into_string();
+
+        assert!(!markup.contains(r#"id="extended""#));
+        assert!(markup.contains(r#"id="legacy""#));
+
+        // Neither does the footer link to an extended list
+        let _env = crate::test::lock_env();
+
+        assert!(crate::view::footer().into_string().contains(r#"title="Extended list""#));
+
+        std::env::set_var("EXTENDED_LIST_SIZE", config::list_size().to_string());
+
+        let footer = crate::view::footer().into_string();
+
+        std::env::remove_var("EXTENDED_LIST_SIZE");
+
+        assert!(!footer.contains(r#"title="Extended list""#));
+        assert!(footer.contains(&format!(r#"href="/demonlist/{}/" title="Legacy list""#, config::list_size() + 1)));
+    }
+
+    #[test]
+    fn test_dropdown_requirements() {
+        let list_size = config::list_size();
+        let demons = vec![demon(1, 54), demon(list_size, 60), demon(list_size + 1, 50)];
+        let markup = demon_dropdown("id_demon", demons.iter()).into_string();
+
+        assert!(markup.contains(r#"data-value="1" data-requirement="54""#));
+        assert!(markup.contains(&format!(r#"data-value="{}" data-requirement="60""#, list_size)));
+
+        // Extended demons only accept 100%, regardless of their requirement
+        assert!(markup.contains(&format!(r#"data-value="{}" data-requirement="100""#, list_size + 1)));
+    }
+
+    #[test]
+    fn test_configurable_submission_copy() {
+        let _env = crate::test::lock_env();
+        let default = rules_panel().into_string();
+
+        assert!(default.contains(r#"href="/guidelines/""#));
+        assert!(submission_panel(&[], &[], true, true, Locale::English, None).into_string().contains("Only demons in the top 100 are accepted"));
+
+        std::env::set_var("GUIDELINES_URL", "https://example.com/rules");
+        std::env::set_var("GUIDELINES_TEXT", "Read the rules");
+        std::env::set_var("SUBMIT_TEXT", "Submit responsibly");
+        std::env::set_var("SUBMISSION_DEMON_TEXT", "Top {extended_list_size} only");
+
+        let rules = rules_panel().into_string();
+        let submit = submit_panel().into_string();
+        let submission = submission_panel(&[], &[], true, true, Locale::English, None).into_string();
+
+        for key in &["GUIDELINES_URL", "GUIDELINES_TEXT", "SUBMIT_TEXT", "SUBMISSION_DEMON_TEXT"] {
+            std::env::remove_var(key);
+        }
+
+        assert!(rules.contains(r#"href="https://example.com/rules""#));
+        assert!(rules.contains("Read the rules"));
+        assert!(submit.contains("Submit responsibly"));
+        assert!(submission.contains("Top 100 only"));
+        assert!(submission.contains(r#"href="https://example.com/rules""#));
+    }
+
+    #[test]
+    fn test_video_optional_submission_panel() {
+        let required = submission_panel(&[], &[], true, true, Locale::English, None).into_string();
+        let optional = submission_panel(&[], &[], true, false, Locale::English, None).into_string();
+
+        assert!(required.contains(r#"name="video" required="""#));
+        assert!(!required.contains("Videos are optional"));
+        assert!(!optional.contains(r#"name="video" required="""#));
+        assert!(optional.contains("explain how to obtain raw footage"));
+    }
+
+    #[test]
+    fn test_translated_submission_panel() {
+        let submission = submission_panel(&[], &[], true, true, Locale::German, None).into_string();
+
+        assert!(submission.contains("Rekord einreichen"));
+        assert!(!submission.contains("Record Submission"));
+        assert!(submission.contains("benötigtes <b>Rohmaterial</b> hierher"));
+
+        let sections = list_sections(Locale::German);
+
+        assert!(sections[0].description.starts_with("Der Hauptteil der Demonlist"));
+    }
+
+    #[test]
+    fn test_ads_toggle() {
+        let ads = |publisher| {
+            vec![
+                sidebar_ad(publisher).into_string(),
+                besides_sidebar_ad(publisher).into_string(),
+                overview_banner_ad(publisher).into_string(),
+                in_feed_ad(publisher).into_string(),
+                demon_page_banner_ad(publisher).into_string(),
+            ]
+        };
+
+        // Positions of the demons on the overview followed by ads, with an in-feed ad every 10 demons
+        let ad_positions = |publisher| {
+            (1..=35)
+                .filter(|&position| !overview_ad(position, 1, 10, publisher).into_string().is_empty())
+                .collect::<Vec<i16>>()
+        };
+
+        let disabled = ads(None);
+        let disabled_positions = ad_positions(None);
+
+        let enabled = ads(Some("ca-pub-0000000000000000"));
+        let enabled_positions = ad_positions(Some("ca-pub-0000000000000000"));
+
+        for markup in disabled {
+            assert!(markup.is_empty());
+        }
+
+        for markup in enabled {
+            assert!(markup.contains("adsbygoogle"));
+            assert!(markup.contains(r#"data-ad-client="ca-pub-0000000000000000""#));
+        }
+
+        assert!(disabled_positions.is_empty());
+        assert_eq!(enabled_positions, vec![1, 10, 20, 30]);
     }
 }
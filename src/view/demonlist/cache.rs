@@ -0,0 +1,210 @@
+//! In-process cache for the demons shown on the demonlist overview
+//!
+//! Loading the overview requires joining the entire list against the players table (twice), which
+//! makes it by far the most expensive page we serve. Since the list changes rarely compared to how
+//! often it is viewed, we memoize the result for a (configurable) number of seconds. The present
+//! list is cached separately from time machine views, as it is requested far more often and should
+//! never be evicted by a burst of time machine requests.
+
+use crate::{view::demonlist::OverviewDemon, Result};
+use chrono::{DateTime, FixedOffset};
+use log::debug;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// The maximal number of time machine views cached at once
+const MAX_TIME_MACHINE_ENTRIES: usize = 64;
+
+struct CacheEntry {
+    loaded_at: Instant,
+    demons: Vec<OverviewDemon>,
+}
+
+#[derive(Clone)]
+pub struct OverviewCache {
+    ttl: Duration,
+
+    /// Incremented on every invalidation. Loads that started before an invalidation do not get
+    /// cached, as they might have read data from before the write
+    generation: Arc<AtomicU64>,
+
+    present: Arc<Mutex<Option<CacheEntry>>>,
+    time_machine: Arc<Mutex<HashMap<DateTime<FixedOffset>, CacheEntry>>>,
+}
+
+impl CacheEntry {
+    fn fresh(&self, ttl: Duration) -> Option<Vec<OverviewDemon>> {
+        if self.loaded_at.elapsed() < ttl {
+            Some(self.demons.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl OverviewCache {
+    pub fn new(ttl: Duration) -> Self {
+        OverviewCache {
+            ttl,
+            generation: Arc::new(AtomicU64::new(0)),
+            present: Arc::new(Mutex::new(None)),
+            time_machine: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Gets the overview demons at the given point in time (`None` meaning the current list) from
+    /// the cache, awaiting the given future to load them if they aren't cached (or the cached
+    /// value expired).
+    ///
+    /// Note that the future is simply dropped if the value was cached, meaning no database queries
+    /// are made in that case.
+    pub async fn get_or_load<F>(&self, at: Option<DateTime<FixedOffset>>, load: F) -> Result<Vec<OverviewDemon>>
+    where
+        F: Future<Output = Result<Vec<OverviewDemon>>>,
+    {
+        let cached = match at {
+            None => self.present.lock().unwrap().as_ref().and_then(|entry| entry.fresh(self.ttl)),
+            Some(at) => self.time_machine.lock().unwrap().get(&at).and_then(|entry| entry.fresh(self.ttl)),
+        };
+
+        if let Some(demons) = cached {
+            return Ok(demons)
+        }
+
+        debug!("Overview at {:?} not cached, loading from database", at);
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let demons = load.await?;
+
+        let entry = CacheEntry {
+            loaded_at: Instant::now(),
+            demons: demons.clone(),
+        };
+
+        match at {
+            None => {
+                let mut present = self.present.lock().unwrap();
+
+                if generation == self.generation.load(Ordering::SeqCst) {
+                    *present = Some(entry);
+                }
+            },
+            Some(at) => {
+                let mut time_machine = self.time_machine.lock().unwrap();
+
+                if generation == self.generation.load(Ordering::SeqCst) {
+                    let ttl = self.ttl;
+
+                    time_machine.retain(|_, entry| entry.loaded_at.elapsed() < ttl);
+
+                    if time_machine.len() < MAX_TIME_MACHINE_ENTRIES {
+                        time_machine.insert(at, entry);
+                    }
+                }
+            },
+        }
+
+        Ok(demons)
+    }
+
+    /// Drops all cached values. Needs to be called whenever a demon is modified
+    pub fn invalidate(&self) {
+        // Lock both caches so that no load that read stale data can slip in between incrementing
+        // the generation and clearing
+        let mut present = self.present.lock().unwrap();
+        let mut time_machine = self.time_machine.lock().unwrap();
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        *present = None;
+        time_machine.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OverviewCache;
+    use crate::view::demonlist::OverviewDemon;
+    use chrono::{DateTime, FixedOffset};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    fn demon() -> OverviewDemon {
+        OverviewDemon {
+            id: 1,
+            position: 1,
+            name: "Bloodbath".to_string(),
+            requirement: 50,
+            publisher: "Riot".to_string(),
+            publisher_nationality: None,
+            video: None,
+            current_position: None,
+            mirrors: Vec::new(),
+            legacy_since: None,
+            tags: Vec::new(),
+            record_count: None,
+        }
+    }
+
+    async fn load(cache: &OverviewCache, at: Option<DateTime<FixedOffset>>, queries: &AtomicUsize) -> usize {
+        cache
+            .get_or_load(at, async {
+                queries.fetch_add(1, Ordering::SeqCst);
+
+                Ok(vec![demon()])
+            })
+            .await
+            .unwrap()
+            .len()
+    }
+
+    #[actix_rt::test]
+    async fn test_cached_within_ttl() {
+        let cache = OverviewCache::new(Duration::from_secs(60));
+        let queries = AtomicUsize::new(0);
+
+        assert_eq!(load(&cache, None, &queries).await, 1);
+        assert_eq!(load(&cache, None, &queries).await, 1);
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+
+        // time machine views are cached separately
+        let at = DateTime::parse_from_rfc3339("2020-01-01T00:00:00+00:00").unwrap();
+
+        load(&cache, Some(at), &queries).await;
+        load(&cache, Some(at), &queries).await;
+
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_write_invalidates() {
+        let cache = OverviewCache::new(Duration::from_secs(60));
+        let queries = AtomicUsize::new(0);
+
+        load(&cache, None, &queries).await;
+        cache.invalidate();
+        load(&cache, None, &queries).await;
+
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_expiry() {
+        let cache = OverviewCache::new(Duration::from_secs(0));
+        let queries = AtomicUsize::new(0);
+
+        load(&cache, None, &queries).await;
+        load(&cache, None, &queries).await;
+
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,549 @@
+//! Public log of all changes to the placement of demons on the list
+//!
+//! The events are derived from the same audit data the time machine uses (see the
+//! `demon_movements` SQL function). Since moving or adding a demon shifts every demon below it by
+//! one, we only list demons that were explicitly moved, plus those that got shifted onto or off of
+//! the legacy list. Demons cannot be deleted from the list, so there are no "removed" events.
+//!
+//! The changelog is also available as an Atom feed, which advertises the configured WebSub hub (if
+//! any) so that aggregators can subscribe to it instead of polling. The hub is pinged from
+//! [`notify::placement_changed`](crate::notify::placement_changed).
+//!
+//! Demon additions are additionally available as an iCalendar feed, for community calendars.
+
+use crate::{config, error::PointercrateError, state::PointercrateState, view::Page, Result, ViewResult};
+use actix_web::{web::Query, HttpResponse};
+use actix_web_codegen::get;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use maud::{html, Markup};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use std::fmt::Write;
+
+/// The URL of the changelog's Atom feed. Also the topic URL under which the feed is published to
+/// the WebSub hub
+pub const FEED_URL: &str = "https://pointercrate.com/demonlist/changelog/feed.xml";
+
+/// The number of most recent entries included in the Atom feed
+const FEED_SIZE: i64 = 50;
+
+/// The URL of the list's permalinks, to be suffixed with a demon's ID
+const PERMALINK_URL: &str = "https://pointercrate.com/demonlist/permalink/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Raised,
+    Lowered,
+    FellToLegacy,
+    ReturnedFromLegacy,
+}
+
+#[derive(Debug)]
+pub struct ChangelogEntry {
+    pub demon_id: i32,
+    pub demon_name: String,
+    pub time: NaiveDateTime,
+    pub old_position: i16,
+    pub new_position: i16,
+    pub kind: ChangeKind,
+}
+
+impl ChangelogEntry {
+    /// Describes this change, to be prefixed with the demon's name
+    pub fn summary(&self) -> String {
+        match self.kind {
+            ChangeKind::Added => format!("was added to the list at #{}", self.new_position),
+            ChangeKind::Raised => format!("was moved up from #{} to #{}", self.old_position, self.new_position),
+            ChangeKind::Lowered => format!("was moved down from #{} to #{}", self.old_position, self.new_position),
+            ChangeKind::FellToLegacy => "fell to the legacy list".to_string(),
+            ChangeKind::ReturnedFromLegacy => format!("returned from the legacy list to #{}", self.new_position),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangelogQuery {
+    limit: Option<i64>,
+
+    /// Only show changes made on or after this day
+    from: Option<NaiveDate>,
+
+    /// Only show changes made on or before this day
+    to: Option<NaiveDate>,
+}
+
+impl ChangelogQuery {
+    fn limit(&self) -> Result<i64> {
+        let limit = self.limit.unwrap_or(50);
+
+        if limit < 1 || limit > 100 {
+            return Err(PointercrateError::InvalidPaginationLimit)
+        }
+
+        Ok(limit)
+    }
+
+    fn range(&self) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        let from = self.from.map(|from| from.and_hms(0, 0, 0));
+        // The range is inclusive, so go up to the start of the next day
+        let to = self.to.map(|to| to.and_hms(0, 0, 0) + Duration::days(1));
+
+        (from, to)
+    }
+}
+
+#[derive(Debug)]
+struct Changelog {
+    entries: Vec<ChangelogEntry>,
+}
+
+/// Classifies a change of a demon's position
+///
+/// `old_position` is `0` if the demon was newly added. `moved` indicates whether the demon was
+/// explicitly moved (as opposed to getting shifted because of another demon's movement). Changes
+/// that do not need to show up in the changelog are classified as `None`.
+pub fn classify(old_position: i16, new_position: i16, moved: bool, legacy_threshold: i16) -> Option<ChangeKind> {
+    if old_position == 0 {
+        Some(ChangeKind::Added)
+    } else if old_position <= legacy_threshold && new_position > legacy_threshold {
+        Some(ChangeKind::FellToLegacy)
+    } else if old_position > legacy_threshold && new_position <= legacy_threshold {
+        Some(ChangeKind::ReturnedFromLegacy)
+    } else if !moved || old_position == new_position {
+        None
+    } else if new_position < old_position {
+        Some(ChangeKind::Raised)
+    } else {
+        Some(ChangeKind::Lowered)
+    }
+}
+
+/// Loads the `limit` most recent changelog entries in the given time range, most recent first
+pub async fn changelog_entries(
+    connection: &mut PgConnection, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>, limit: i64, legacy_threshold: i16,
+) -> Result<Vec<ChangelogEntry>> {
+    // Prefilter in the database, so that the limit is not used up by the demons shifted around by
+    // a single addition
+    let movements = sqlx::query!(
+        r#"SELECT id AS "id!", name::TEXT AS "name!: String", time_ AS "time!", old_position AS "old_position!", new_position AS "new_position!",
+         moved AS "moved!" FROM demon_movements(COALESCE($1, '-infinity'::TIMESTAMP), COALESCE($2, 'infinity'::TIMESTAMP))
+         WHERE moved OR (old_position <= $3) != (new_position <= $3) ORDER BY time_ DESC, audit_id DESC LIMIT $4"#,
+        from,
+        to,
+        legacy_threshold,
+        limit
+    )
+    .fetch_all(connection)
+    .await?;
+
+    Ok(movements
+        .into_iter()
+        .filter_map(|row| {
+            classify(row.old_position, row.new_position, row.moved, legacy_threshold).map(|kind| {
+                ChangelogEntry {
+                    demon_id: row.id,
+                    demon_name: row.name,
+                    time: row.time,
+                    old_position: row.old_position,
+                    new_position: row.new_position,
+                    kind,
+                }
+            })
+        })
+        .collect())
+}
+
+/// Loads the `limit` most recent additions of demons in the given time range, most recent first
+pub async fn addition_entries(
+    connection: &mut PgConnection, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>, limit: i64,
+) -> Result<Vec<ChangelogEntry>> {
+    let additions = sqlx::query!(
+        r#"SELECT id AS "id!", name::TEXT AS "name!: String", time_ AS "time!", new_position AS "new_position!" FROM
+         demon_movements(COALESCE($1, '-infinity'::TIMESTAMP), COALESCE($2, 'infinity'::TIMESTAMP)) WHERE old_position = 0
+         ORDER BY time_ DESC, audit_id DESC LIMIT $3"#,
+        from,
+        to,
+        limit
+    )
+    .fetch_all(connection)
+    .await?;
+
+    Ok(additions
+        .into_iter()
+        .map(|row| {
+            ChangelogEntry {
+                demon_id: row.id,
+                demon_name: row.name,
+                time: row.time,
+                old_position: 0,
+                new_position: row.new_position,
+                kind: ChangeKind::Added,
+            }
+        })
+        .collect())
+}
+
+#[get("/demonlist/changelog/")]
+pub async fn list_changelog(state: PointercrateState, query: Query<ChangelogQuery>) -> ViewResult<HttpResponse> {
+    let limit = query.limit()?;
+    let (from, to) = query.range();
+
+    let mut connection = state.connection().await?;
+
+    let entries = changelog_entries(&mut connection, from, to, limit, config::extended_list_size()).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(Changelog { entries }.render().0))
+}
+
+#[get("/demonlist/changelog/feed.xml")]
+pub async fn changelog_feed(state: PointercrateState) -> ViewResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let entries = changelog_entries(&mut connection, None, None, FEED_SIZE, config::extended_list_size()).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/atom+xml; charset=utf-8")
+        .body(atom_feed(&entries, config::websub_hub_url().as_deref())))
+}
+
+#[get("/demonlist/additions.ics")]
+pub async fn additions_calendar(state: PointercrateState, query: Query<ChangelogQuery>) -> ViewResult<HttpResponse> {
+    let limit = query.limit()?;
+    let (from, to) = query.range();
+
+    let mut connection = state.connection().await?;
+
+    let entries = addition_entries(&mut connection, from, to, limit).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(ics_calendar(&entries)))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn atom_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Renders the given entries (most recent first) as an Atom feed, advertising the given WebSub hub
+fn atom_feed(entries: &[ChangelogEntry], hub: Option<&str>) -> String {
+    let updated = entries.first().map(|entry| entry.time).unwrap_or_else(|| Utc::now().naive_utc());
+    let mut feed = String::from(r#"<?xml version="1.0" encoding="utf-8"?><feed xmlns="http://www.w3.org/2005/Atom">"#);
+
+    // Writing to a String cannot fail, so the results of the write! calls below are ignored
+    let _ = write!(feed, "<title>Geometry Dash Demonlist Changelog</title><id>{}</id>", FEED_URL);
+    let _ = write!(feed, r#"<link rel="self" href="{}"/>"#, FEED_URL);
+    feed.push_str(r#"<link rel="alternate" href="https://pointercrate.com/demonlist/changelog/"/>"#);
+
+    if let Some(hub) = hub {
+        let _ = write!(feed, r#"<link rel="hub" href="{}"/>"#, escape_xml(hub));
+    }
+
+    let _ = write!(feed, "<updated>{}</updated><author><name>pointercrate</name></author>", atom_time(updated));
+
+    for entry in entries {
+        let _ = write!(
+            feed,
+            "<entry><title>{} {}</title><id>tag:pointercrate.com,2021:changelog/{}/{}</id>",
+            escape_xml(&entry.demon_name),
+            entry.summary(),
+            entry.demon_id,
+            entry.time.timestamp()
+        );
+        let _ = write!(
+            feed,
+            r#"<link href="{}{}/"/><updated>{}</updated></entry>"#,
+            PERMALINK_URL,
+            entry.demon_id,
+            atom_time(entry.time)
+        );
+    }
+
+    feed.push_str("</feed>");
+    feed
+}
+
+/// Escapes a value of the iCalendar TEXT type, as per RFC 5545, section 3.3.11
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn ics_time(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Appends a content line to an iCalendar object, folding it so that no line is longer than 75
+/// octets (RFC 5545, section 3.1)
+fn push_ics_line(calendar: &mut String, line: &str) {
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            calendar.push_str("\r\n ");
+            length = 1;
+        }
+
+        calendar.push(c);
+        length += c.len_utf8();
+    }
+
+    calendar.push_str("\r\n");
+}
+
+/// Renders the given addition entries as an iCalendar object with one event per addition
+///
+/// The UID of each event is derived from the ID of the added demon, which only ever gets added
+/// once, so that calendars recognize events they have already seen on refetch.
+fn ics_calendar(entries: &[ChangelogEntry]) -> String {
+    let mut calendar = String::new();
+
+    push_ics_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_ics_line(&mut calendar, "VERSION:2.0");
+    push_ics_line(&mut calendar, "PRODID:-//pointercrate//Demonlist Additions//EN");
+    push_ics_line(&mut calendar, "X-WR-CALNAME:Geometry Dash Demonlist Additions");
+
+    for entry in entries {
+        push_ics_line(&mut calendar, "BEGIN:VEVENT");
+        push_ics_line(&mut calendar, &format!("UID:demon-{}-added@pointercrate.com", entry.demon_id));
+        push_ics_line(&mut calendar, &format!("DTSTAMP:{}", ics_time(entry.time)));
+        push_ics_line(&mut calendar, &format!("DTSTART:{}", ics_time(entry.time)));
+        push_ics_line(&mut calendar, &format!("SUMMARY:{}", escape_ics(&entry.demon_name)));
+        push_ics_line(
+            &mut calendar,
+            &format!("DESCRIPTION:{}", escape_ics(&format!("{} {}", entry.demon_name, entry.summary()))),
+        );
+        push_ics_line(&mut calendar, &format!("URL:{}{}/", PERMALINK_URL, entry.demon_id));
+        push_ics_line(&mut calendar, "END:VEVENT");
+    }
+
+    push_ics_line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+impl Changelog {
+    /// Splits the entries into the runs of entries made on the same day
+    fn days(&self) -> Vec<&[ChangelogEntry]> {
+        let mut days = Vec::new();
+        let mut start = 0;
+
+        for idx in 1..=self.entries.len() {
+            if idx == self.entries.len() || self.entries[idx].time.date() != self.entries[start].time.date() {
+                days.push(&self.entries[start..idx]);
+                start = idx;
+            }
+        }
+
+        days
+    }
+
+    fn entry(entry: &ChangelogEntry) -> Markup {
+        html! {
+            li {
+                a href = {"/demonlist/permalink/" (entry.demon_id) "/"} {
+                    (entry.demon_name)
+                }
+                " "
+                (entry.summary())
+            }
+        }
+    }
+}
+
+impl Page for Changelog {
+    fn title(&self) -> String {
+        "Changelog - Geometry Dash Demonlist".to_string()
+    }
+
+    fn description(&self) -> String {
+        "All changes to the placement of demons on the Geometry Dash Demonlist".to_string()
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                "Changelog"
+                            }
+                        }
+                        @if self.entries.is_empty() {
+                            p {
+                                "No changes to the list were made in the selected time frame"
+                            }
+                        }
+                        @for day in self.days() {
+                            h3 {
+                                (day[0].time.format("%B %-d, %Y"))
+                            }
+                            ul {
+                                @for entry in day {
+                                    (Changelog::entry(entry))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![html! {
+            link rel = "alternate" type = "application/atom+xml" title = "Demonlist Changelog" href = "/demonlist/changelog/feed.xml";
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        addition_entries, atom_feed, changelog_entries, classify, ics_calendar, push_ics_line, ChangeKind, ChangelogEntry, FEED_URL,
+    };
+    use crate::{model::demonlist::demon::Demon, state::audit_connection};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(0, 5, true, 150), Some(ChangeKind::Added));
+        assert_eq!(classify(0, 160, true, 150), Some(ChangeKind::Added));
+        assert_eq!(classify(10, 5, true, 150), Some(ChangeKind::Raised));
+        assert_eq!(classify(5, 10, true, 150), Some(ChangeKind::Lowered));
+        assert_eq!(classify(150, 151, false, 150), Some(ChangeKind::FellToLegacy));
+        assert_eq!(classify(160, 20, true, 150), Some(ChangeKind::ReturnedFromLegacy));
+
+        // shifted by one because of some other demon's movement
+        assert_eq!(classify(5, 6, false, 150), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_changelog_from_audit_log() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        sqlx::query!(
+            "INSERT INTO demons (name, position, requirement, verifier, publisher) SELECT 'Bloodbath', 5, 50, id, id FROM players WHERE name \
+             = 'Mullsy'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // With a legacy threshold of 3, this moves taraturusus out of legacy, and pushes terminal void
+        // into it. Trichotomy is shifted by one and should not show up
+        let mut demon = Demon::by_position(4, &mut connection).await.unwrap().base;
+
+        demon.mv(2, &mut connection).await.unwrap();
+
+        let entries = changelog_entries(&mut connection, None, None, 50, 3).await.unwrap();
+        let events: Vec<(&str, ChangeKind)> = entries.iter().map(|entry| (entry.demon_name.as_str(), entry.kind)).collect();
+
+        assert_eq!(events, vec![
+            ("terminal void", ChangeKind::FellToLegacy),
+            ("taraturusus", ChangeKind::ReturnedFromLegacy),
+            ("Bloodbath", ChangeKind::Added)
+        ]);
+
+        let entries = changelog_entries(&mut connection, None, None, 1, 3).await.unwrap();
+
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_atom_feed() {
+        let entries = vec![ChangelogEntry {
+            demon_id: 7,
+            demon_name: "Sonic Wave & Co".to_string(),
+            time: NaiveDate::from_ymd(2021, 6, 3).and_hms(12, 30, 0),
+            old_position: 10,
+            new_position: 4,
+            kind: ChangeKind::Raised,
+        }];
+
+        let feed = atom_feed(&entries, Some("https://pubsubhubbub.appspot.com/"));
+
+        assert!(feed.contains(&format!(r#"<link rel="self" href="{}"/>"#, FEED_URL)));
+        assert!(feed.contains(r#"<link rel="hub" href="https://pubsubhubbub.appspot.com/"/>"#));
+        assert!(feed.contains("<updated>2021-06-03T12:30:00Z</updated>"));
+        assert!(feed.contains("<title>Sonic Wave &amp; Co was moved up from #10 to #4</title>"));
+        assert!(!atom_feed(&entries, None).contains(r#"rel="hub""#));
+    }
+
+    #[actix_rt::test]
+    async fn test_additions_calendar() {
+        let mut connection = crate::test::test_setup().await;
+
+        audit_connection(&mut connection, 0).await.unwrap();
+
+        sqlx::query!(
+            "INSERT INTO demons (name, position, requirement, verifier, publisher) SELECT 'Bloodbath', 5, 50, id, id FROM players WHERE name \
+             = 'Mullsy'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO demons (name, position, requirement, verifier, publisher) SELECT 'Sonic Wave, Infinity', 6, 50, id, id FROM players \
+             WHERE name = 'Aquatias'"
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+
+        // Moving a demon is not an addition
+        let mut demon = Demon::by_position(4, &mut connection).await.unwrap().base;
+
+        demon.mv(2, &mut connection).await.unwrap();
+
+        let entries = addition_entries(&mut connection, None, None, 50).await.unwrap();
+        let calendar = ics_calendar(&entries);
+        let lines: Vec<&str> = calendar.split_terminator("\r\n").collect();
+
+        assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+        assert_eq!(lines.last(), Some(&"END:VCALENDAR"));
+        assert_eq!(lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(), 2);
+        assert_eq!(lines.iter().filter(|line| **line == "END:VEVENT").count(), 2);
+        assert!(lines.contains(&"SUMMARY:Sonic Wave\\, Infinity"));
+        assert!(lines.contains(&"SUMMARY:Bloodbath"));
+        assert!(lines.contains(&format!("UID:demon-{}-added@pointercrate.com", entries[1].demon_id).as_str()));
+
+        // refetching yields the exact same calendar
+        assert_eq!(ics_calendar(&addition_entries(&mut connection, None, None, 50).await.unwrap()), calendar);
+        assert_eq!(addition_entries(&mut connection, None, None, 1).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ics_line_folding() {
+        let mut calendar = String::new();
+
+        push_ics_line(&mut calendar, &format!("SUMMARY:{}", "a".repeat(100)));
+
+        let lines: Vec<&str> = calendar.split_terminator("\r\n").collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1], format!(" {}", "a".repeat(33)));
+    }
+}
@@ -1,16 +1,23 @@
 use crate::{
+    cistring::CiStr,
     config,
+    error::PointercrateError,
     gd::GDIntegrationResult,
-    model::demonlist::demon::{FullDemon, MinimalDemon},
+    i18n::Locale,
+    model::demonlist::demon::{Demon, FullDemon, Longevity, MinimalDemon, VerificationPlatform},
     state::PointercrateState,
-    video,
+    video::{self, ThumbnailQuality},
     view::{
-        demonlist::overview::{DemonlistOverview, OverviewQueryData},
+        demonlist::{
+            disambiguation::disambiguation,
+            overview::{DemonlistOverview, OverviewQueryData},
+            unknown_demon::unknown_demon,
+        },
         Page,
     },
-    ViewResult,
+    Result, ViewResult,
 };
-use actix_web::{web::Path, HttpResponse};
+use actix_web::{http::header::LOCATION, web::Path, HttpRequest, HttpResponse};
 use actix_web_codegen::get;
 use chrono::NaiveDateTime;
 use dash_rs::{
@@ -19,6 +26,10 @@ use dash_rs::{
 };
 use log::error;
 use maud::{html, Markup, PreEscaped, Render};
+use sqlx::PgConnection;
+
+/// Image shown when sharing a demon whose video thumbnail cannot be derived
+const DEFAULT_CARD_IMAGE: &str = "https://pointercrate.com/static2/images/pointercrate2.png";
 
 #[derive(Debug)]
 pub struct DemonMovement {
@@ -31,25 +42,93 @@ pub struct Demonlist {
     overview: DemonlistOverview,
     data: FullDemon,
     movements: Vec<DemonMovement>,
+    legacy_since: Option<NaiveDateTime>,
+    longevity: Option<Longevity>,
     link_banned: bool,
     integration: GDIntegrationResult,
 }
 
 #[get("/demonlist/permalink/{id}/")]
-pub async fn demon_permalink(state: PointercrateState, id: Path<i32>) -> ViewResult<HttpResponse> {
+pub async fn demon_permalink(state: PointercrateState, id: Path<String>) -> ViewResult<HttpResponse> {
     let mut connection = state.connection().await?;
-    let demon = MinimalDemon::by_id(id.into_inner(), &mut connection).await?;
 
-    Ok(actix_web::HttpResponse::Found()
-        .header(actix_web::http::header::LOCATION, format!("/demonlist/{}/", demon.position))
-        .finish())
+    Ok(permalink(&id.into_inner(), &mut connection).await?)
+}
+
+/// Redirects to the target of a permalink, or renders a `404 NOT FOUND` page with suggestions if
+/// the permalink does not resolve to any demon
+async fn permalink(identifier: &str, connection: &mut PgConnection) -> Result<HttpResponse> {
+    match permalink_redirect(identifier, connection).await {
+        Err(PointercrateError::ModelNotFound { .. }) => unknown_demon(identifier, connection).await,
+        result => result,
+    }
+}
+
+/// Resolves the target of a permalink, which is either a demon's ID or one of its aliases.
+///
+/// IDs redirect to the demon's current page, while aliases permanently redirect to the ID-based
+/// permalink
+async fn permalink_redirect(identifier: &str, connection: &mut PgConnection) -> Result<HttpResponse> {
+    match identifier.parse() {
+        Ok(id) => {
+            let demon = MinimalDemon::by_id(id, connection).await?;
+
+            Ok(HttpResponse::Found()
+                .header(LOCATION, format!("/demonlist/{}/", demon.position))
+                .finish())
+        },
+        Err(_) => {
+            let demon = MinimalDemon::by_alias(CiStr::from_str(identifier), connection).await?;
+
+            Ok(HttpResponse::MovedPermanently()
+                .header(LOCATION, format!("/demonlist/permalink/{}/", demon.id))
+                .finish())
+        },
+    }
+}
+
+/// Resolves a demon's name (or one of its aliases) case-insensitively and permanently redirects
+/// to its permalink. Old and external links sometimes point to e.g. `/demonlist/Bloodbath/`.
+///
+/// If multiple demons share the name, a disambiguation page listing all of them is rendered.
+async fn name_redirect(name: &str, connection: &mut PgConnection) -> Result<HttpResponse> {
+    match MinimalDemon::by_name(CiStr::from_str(name), connection).await {
+        Ok(demon) =>
+            Ok(HttpResponse::MovedPermanently()
+                .header(LOCATION, format!("/demonlist/permalink/{}/", demon.id))
+                .finish()),
+        Err(PointercrateError::DemonNameNotUnique { demons }) => Ok(disambiguation(name, demons)),
+        Err(PointercrateError::ModelNotFound { .. }) => unknown_demon(name, connection).await,
+        Err(error) => Err(error),
+    }
 }
 
 #[get("/demonlist/{position}/")]
-pub async fn page(state: PointercrateState, position: Path<i16>) -> ViewResult<HttpResponse> {
+pub async fn page(request: HttpRequest, state: PointercrateState, position: Path<String>) -> ViewResult<HttpResponse> {
     let mut connection = state.connection().await?;
-    let overview = DemonlistOverview::load(&mut connection, None, OverviewQueryData::default()).await?;
-    let demon = FullDemon::by_position(position.into_inner(), &mut connection).await?;
+
+    let position = match position.parse() {
+        Ok(position) => position,
+        Err(_) => return Ok(name_redirect(&position, &mut connection).await?),
+    };
+    let overview = DemonlistOverview::load(
+        &mut connection,
+        &state.overview_cache,
+        None,
+        OverviewQueryData::default(),
+        Locale::of_request(&request),
+    )
+    .await?;
+    let demon = FullDemon::by_position(position, &mut connection).await?;
+
+    if !demon.demon.published {
+        return Err(PointercrateError::ModelNotFound {
+            model: "Demon",
+            identified_by: position.to_string(),
+        }
+        .into())
+    }
+
     let link_banned = sqlx::query!(
         r#"SELECT link_banned AS "link_banned!: bool" FROM players WHERE id = $1"#,
         demon.demon.verifier.id
@@ -84,6 +163,9 @@ pub async fn page(state: PointercrateState, position: Path<i16>) -> ViewResult<H
         None => error!("No addition logged for demon {}!", demon),
     }
 
+    let legacy_since = demon.demon.base.legacy_since(&mut connection).await?;
+    let longevity = demon.demon.base.longevity(&mut connection).await?;
+
     let integration = state.gd_integration.data_for_demon(state.http_client.clone(), &demon.demon).await?;
 
     Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(
@@ -91,6 +173,8 @@ pub async fn page(state: PointercrateState, position: Path<i16>) -> ViewResult<H
             overview,
             data: demon,
             movements,
+            legacy_since,
+            longevity,
             link_banned,
             integration,
         }
@@ -143,6 +227,11 @@ impl Demonlist {
                             (self.data.headline())
                         }
                     }
+                    @if let Some(since) = self.legacy_since {
+                        h4 {
+                            "Legacy since " (since.format("%B %-d, %Y"))
+                        }
+                    }
                 }
                 @if let GDIntegrationResult::Success(ref level, ..) = self.integration {
                     @if let Some(Thunk::Processed(ref description)) = level.description {
@@ -161,7 +250,26 @@ impl Demonlist {
                 @else {
                     @if let Some(ref video) = self.data.demon.video {
                         @if let Some(embedded_video) = video::embed(video) {
-                            iframe."ratio-16-9"."js-delay-attr" style="width:90%; margin: 15px 5%" allowfullscreen="" data-attr = "src" data-attr-value = (embedded_video) {"Verification Video"}
+                            iframe."ratio-16-9"."js-delay-attr" style="width:90%; margin: 15px 5%" allowfullscreen="" data-attr = "src" data-attr-value = (embedded_video) {"Showcase Video"}
+                        }
+                    }
+                    @if let Some(ref verification) = self.data.demon.verification_video {
+                        @if self.data.demon.video.as_ref() != Some(verification) {
+                            div.underlined.pad#verification-video {
+                                h3 {
+                                    "Verification"
+                                }
+                                @match video::embed(verification) {
+                                    Some(embedded_video) => {
+                                        iframe."ratio-16-9"."js-delay-attr" style="width:90%; margin: 15px 5%" allowfullscreen="" data-attr = "src" data-attr-value = (embedded_video) {"Verification Video"}
+                                    }
+                                    None => {
+                                        a.link href = (verification) target = "_blank" {
+                                            "Watch on " (video::host(verification).unwrap_or("external site"))
+                                        }
+                                    }
+                                }
+                            }
                         }
                     }
                 }
@@ -283,6 +391,43 @@ impl Demonlist {
                             (format!("{:.2}", score_requirement))
                         }
                     }
+                    @if let Some(longevity) = self.longevity {
+                        span#days-on-list {
+                            b {
+                                "Days on list: "
+                            }
+                            br;
+                            (longevity.days_on_list)
+                        }
+                        span#peak-position {
+                            b {
+                                "Peak position: "
+                            }
+                            br;
+                            "#" (longevity.peak_position)
+                        }
+                    }
+                    @if let Some(ref song_name) = self.data.demon.song_name {
+                        span#song {
+                            b {
+                                "Song: "
+                            }
+                            br;
+                            (song_name)
+                            @if let Some(ref song_artist) = self.data.demon.song_artist {
+                                " by " (song_artist)
+                            }
+                        }
+                    }
+                    @if self.data.demon.verified_on != VerificationPlatform::Unknown {
+                        span#verified-on {
+                            b {
+                                "Verified on: "
+                            }
+                            br;
+                            (self.data.demon.verified_on)
+                        }
+                    }
                 }
             }
         }
@@ -299,19 +444,14 @@ impl Demonlist {
                         h2 {
                             "Records"
                         }
-                        @if position <= config::list_size() {
+                        @if position <= config::extended_list_size() {
                             h3 {
-                                (self.data.demon.requirement) "% or better required to qualify"
-                            }
-                        }
-                        @else if position <= config::extended_list_size() {
-                            h3 {
-                                "100% required to qualify"
+                                (super::requirement_description(Demon::effective_requirement(self.data.demon.requirement, position)))
                             }
                         }
                         @if !self.data.records.is_empty() {
                             h4 {
-                                @let records_registered_100_count = self.data.records.iter().filter(|record| record.progress == 100).count();
+                                @let records_registered_100_count = self.data.records.iter().filter(|record| record.progress.percent == 100).count();
                                 (self.data.records.len())
                                 " records registered, out of which "
                                 (records_registered_100_count)
@@ -319,6 +459,12 @@ impl Demonlist {
                                 " 100%"
                             }
                         }
+                        @if let Some(ref first_victor) = self.data.first_victor {
+                            h4 {
+                                "First victor: "
+                                (first_victor.player.name)
+                            }
+                        }
                     }
                     @if self.data.records.is_empty() {
                         h3 {
@@ -346,7 +492,7 @@ impl Demonlist {
                                     }
                                 }
                                 @for record in &self.data.records {
-                                    tr style = { @if record.progress == 100 {"font-weight: bold"} @else {""} } {
+                                    tr style = { @if record.progress.percent == 100 {"font-weight: bold"} @else {""} } {
                                         td {
                                             @if let Some(ref nationality) = record.nationality {
                                                 (nationality)
@@ -367,8 +513,12 @@ impl Demonlist {
                                         }
                                         td.video-link {
                                             @if let Some(ref video) = record.video {
-                                                 a.link href = (video) target = "_blank"{
-                                                     (video::host(video))
+                                                 @let link = match record.video_timestamp {
+                                                     Some(seconds) => video::timestamped(video, seconds),
+                                                     None => video.clone(),
+                                                 };
+                                                 a.link href = (link) target = "_blank"{
+                                                     (video::host(video).unwrap_or("Video"))
                                                  }
                                             }
                                         }
@@ -410,7 +560,7 @@ impl Page for Demonlist {
     }
 
     fn body(&self) -> Markup {
-        let dropdowns = super::dropdowns(&self.overview.demon_overview, Some(&self.data.demon));
+        let dropdowns = super::dropdowns(&self.overview.demon_overview, Some(&self.data.demon), self.overview.locale);
 
         let mut labels = Vec::new();
 
@@ -437,34 +587,38 @@ impl Page for Demonlist {
             }
         }
 
+        let publisher = config::adsense_publisher();
+
         html! {
-            (super::besides_sidebar_ad())
+            (super::besides_sidebar_ad(publisher.as_deref()))
             (dropdowns)
 
             div.flex.m-center.container {
                 main.left {
-                    div.panel.fade style = "padding: 0px; height: 90px" {
-                        (PreEscaped(format!(r#"
-                        <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
-                        <!-- Demonpage Banner ad -->
-                        <ins class="adsbygoogle"
-                             style="display:inline-block;width:728px;height:90px"
-                             data-ad-client="{}"
-                             data-ad-slot="4829214686"></ins>
-                        <script>
-                             (adsbygoogle = window.adsbygoogle || []).push({{}});
-                        </script>
-                        "#, config::adsense_publisher_id())))
-                    }
-                    (super::submission_panel(&self.overview.demon_overview, false))
-                    (super::stats_viewer(&self.overview.nations, false))
+                    (super::demon_page_banner_ad(publisher.as_deref()))
+                    (super::submission_panel(
+                        &self.overview.demon_overview,
+                        &self.overview.nations,
+                        false,
+                        config::video_required(),
+                        self.overview.locale,
+                        None
+                    ))
+                    (super::stats_viewer(&self.overview.nations, None, None, false, &config::hidden_stats()))
                     (self.demon_panel())
                     div.panel.fade.js-scroll-anim.js-collapse data-anim = "fade" {
                         h2.underlined.pad {
                             "Position History"
                             span.arrow.hover {}
                         }
-                        div.ct-chart.ct-perfect-fourth.js-collapse-content#position-chart style="display:none" {}
+                        div.js-collapse-content style="display:none" {
+                            div.ct-chart.ct-perfect-fourth#position-chart {}
+                            p style = "text-align: center" {
+                                a.link href = {"/demonlist/permalink/" (self.data.demon.base.id) "/history/"} {
+                                    "Detailed placement history"
+                                }
+                            }
+                        }
                     }
                     (self.records_panel())
                     (PreEscaped(format!("
@@ -478,7 +632,7 @@ impl Page for Demonlist {
                 }
                 aside.right {
                     (self.overview.team_panel())
-                    (super::sidebar_ad())
+                    (super::sidebar_ad(publisher.as_deref()))
                     (super::rules_panel())
                     (super::submit_panel())
                     (super::stats_viewer_panel())
@@ -490,6 +644,7 @@ impl Page for Demonlist {
 
     fn head(&self) -> Vec<Markup> {
         vec![
+            social_card(&self.data.demon, &self.title(), &self.description()),
             html! {
                 (PreEscaped(format!(r##"
                     <link href="https://cdnjs.cloudflare.com/ajax/libs/flag-icon-css/3.4.3/css/flag-icon.min.css" rel="stylesheet">
@@ -545,3 +700,169 @@ impl Page for Demonlist {
         ]
     }
 }
+
+/// The image shown in social media embeds of the given demon's page, which is the thumbnail of its
+/// video if one can be derived
+fn card_image(demon: &Demon) -> String {
+    demon
+        .video
+        .as_deref()
+        .map(|url| video::thumbnail(url, ThumbnailQuality::HiRes))
+        .filter(|thumbnail| thumbnail != video::PLACEHOLDER_THUMBNAIL)
+        .unwrap_or_else(|| DEFAULT_CARD_IMAGE.to_string())
+}
+
+/// Open Graph and Twitter card tags for the given demon's page. `og:title` and `og:description`
+/// are already set for every page from the page's title and description.
+fn social_card(demon: &Demon, title: &str, description: &str) -> Markup {
+    let image = card_image(demon);
+
+    html! {
+        meta property="og:url" content=(format!("https://pointercrate.com/demonlist/permalink/{}/", demon.base.id));
+        meta property="og:image" content=(image);
+        meta name="twitter:card" content="summary_large_image";
+        meta name="twitter:site" content="@demonlistgd";
+        meta name="twitter:title" content=(title);
+        meta name="twitter:description" content=(description);
+        meta name="twitter:image" content=(image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{card_image, name_redirect, permalink, permalink_redirect, social_card, DEFAULT_CARD_IMAGE};
+    use crate::{
+        cistring::{CiStr, CiString},
+        model::demonlist::{
+            demon::{Demon, DemonAlias, MinimalDemon, VerificationPlatform},
+            player::DatabasePlayer,
+        },
+    };
+    use actix_web::{
+        body::{Body, ResponseBody},
+        http::{header::LOCATION, StatusCode},
+    };
+
+    #[actix_rt::test]
+    async fn test_alias_permalink_redirects_permanently() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        DemonAlias::insert(&demon, CiStr::from_str("tricho"), &mut connection).await.unwrap();
+
+        let response = permalink_redirect("Tricho", &mut connection).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[LOCATION], format!("/demonlist/permalink/{}/", demon.id));
+
+        let response = permalink_redirect(&demon.id.to_string(), &mut connection).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()[LOCATION], "/demonlist/2/");
+
+        assert!(permalink_redirect("not an alias", &mut connection).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_permalink() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        assert_eq!(permalink("trichotom", &mut connection).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(permalink("0", &mut connection).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        // known demons are still redirected to
+        assert_eq!(permalink(&demon.id.to_string(), &mut connection).await.unwrap().status(), StatusCode::FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_name_redirects_to_permalink() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demon = MinimalDemon::by_name(CiStr::from_str("Trichotomy"), &mut connection).await.unwrap();
+
+        DemonAlias::insert(&demon, CiStr::from_str("tricho"), &mut connection).await.unwrap();
+
+        for name in &["trichotomy", "TRICHO"] {
+            let response = name_redirect(name, &mut connection).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(response.headers()[LOCATION], format!("/demonlist/permalink/{}/", demon.id));
+        }
+
+        assert_eq!(name_redirect("trichotom", &mut connection).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_ambiguous_name_disambiguation() {
+        let mut connection = crate::test::test_setup().await;
+
+        let original = MinimalDemon::by_name(CiStr::from_str("terminal void"), &mut connection).await.unwrap();
+        let duplicate = sqlx::query!(
+            "INSERT INTO demons (name, position, requirement, verifier, publisher) SELECT 'Terminal Void', 5, 50, id, id FROM players \
+             WHERE name = 'Mullsy' RETURNING id"
+        )
+        .fetch_one(&mut connection)
+        .await
+        .unwrap()
+        .id;
+
+        let response = name_redirect("terminal void", &mut connection).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(LOCATION));
+
+        let body = match response.body() {
+            ResponseBody::Body(Body::Bytes(bytes)) => String::from_utf8(bytes.to_vec()).unwrap(),
+            _ => panic!("Unexpected response body"),
+        };
+        let first = body.find(&format!("/demonlist/permalink/{}/", original.id)).unwrap();
+        let second = body.find(&format!("/demonlist/permalink/{}/", duplicate)).unwrap();
+
+        // ordered by position
+        assert!(first < second);
+    }
+
+    fn demon(video: Option<&str>) -> Demon {
+        let riot = DatabasePlayer {
+            id: 1,
+            name: CiString("Riot".to_string()),
+            banned: false,
+        };
+
+        Demon {
+            base: MinimalDemon {
+                id: 1,
+                name: CiString("Bloodbath".to_string()),
+                position: 1,
+            },
+            requirement: 50,
+            video: video.map(ToString::to_string),
+            verification_video: None,
+            publisher: riot.clone(),
+            verifier: riot,
+            level_id: None,
+            verified_on: VerificationPlatform::Unknown,
+            published: true,
+            song_name: None,
+            song_artist: None,
+        }
+    }
+
+    #[test]
+    fn test_social_card_image() {
+        let youtube = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        let markup = social_card(&demon(Some(youtube)), "#1 - Bloodbath", "Bloodbath").into_string();
+
+        // permalinks get the larger thumbnail, as cards are displayed much bigger than the overview's
+        assert_eq!(card_image(&demon(Some(youtube))), "https://i.ytimg.com/vi/dQw4w9WgXcQ/sddefault.jpg");
+        assert!(markup.contains(r#"<meta property="og:image" content="https://i.ytimg.com/vi/dQw4w9WgXcQ/sddefault.jpg">"#));
+        assert!(markup.contains(r#"<meta name="twitter:title" content="#1 - Bloodbath">"#));
+
+        // neither a missing video nor one without derivable thumbnail leads to the placeholder being used
+        assert_eq!(card_image(&demon(None)), DEFAULT_CARD_IMAGE);
+        assert_eq!(card_image(&demon(Some("https://medal.tv/clips/abc"))), DEFAULT_CARD_IMAGE);
+    }
+}
@@ -0,0 +1,366 @@
+//! Side-by-side comparison of the list at two points in time
+//!
+//! Both snapshots are produced by the time machine's `list_at` SQL function (or read straight from
+//! the `demons` table, if a date lies in the future). Position changes are classified the same way
+//! the [changelog](super::changelog) does it, except that every demon whose position differs
+//! between the two snapshots counts as moved, as we cannot tell explicit moves from shifts here.
+
+use crate::{
+    config,
+    state::PointercrateState,
+    view::{
+        demonlist::{
+            changelog::{classify, ChangeKind},
+            overview::{clamp_when, parse_time_machine_date},
+        },
+        Page,
+    },
+    Result, ViewResult,
+};
+use actix_web::{web::Query, HttpResponse};
+use actix_web_codegen::get;
+use chrono::{DateTime, FixedOffset, Utc};
+use maud::{html, Markup};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotDemon {
+    pub id: i32,
+    pub name: String,
+    pub position: i16,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MovedDemon {
+    /// The demon as it was placed in the later snapshot
+    pub demon: SnapshotDemon,
+    pub old_position: i16,
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ListDiff {
+    /// Demons only present in the later snapshot
+    pub added: Vec<SnapshotDemon>,
+
+    /// Demons only present in the earlier snapshot
+    pub removed: Vec<SnapshotDemon>,
+
+    pub moved: Vec<MovedDemon>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    from: String,
+    to: String,
+}
+
+/// Partitions the demons of two list snapshots into added, removed and moved demons
+///
+/// Demons whose position did not change are not part of the result. Each partition is ordered by
+/// the position of its demons in the snapshot they are taken from.
+pub fn diff_lists(before: &[SnapshotDemon], after: &[SnapshotDemon], legacy_threshold: i16) -> ListDiff {
+    let old_positions: HashMap<i32, i16> = before.iter().map(|demon| (demon.id, demon.position)).collect();
+    let mut diff = ListDiff::default();
+
+    for demon in after {
+        let old_position = old_positions.get(&demon.id).copied().unwrap_or(0);
+
+        match classify(old_position, demon.position, old_position != demon.position, legacy_threshold) {
+            Some(ChangeKind::Added) => diff.added.push(demon.clone()),
+            Some(kind) =>
+                diff.moved.push(MovedDemon {
+                    demon: demon.clone(),
+                    old_position,
+                    kind,
+                }),
+            None => (),
+        }
+    }
+
+    diff.removed = before
+        .iter()
+        .filter(|demon| !after.iter().any(|other| other.id == demon.id))
+        .cloned()
+        .collect();
+
+    diff.added.sort_by_key(|demon| demon.position);
+    diff.removed.sort_by_key(|demon| demon.position);
+    diff.moved.sort_by_key(|moved| moved.demon.position);
+    diff
+}
+
+/// Loads the list as it was at the given point in time, or the current list if `at` is `None`
+async fn snapshot(connection: &mut PgConnection, at: Option<DateTime<FixedOffset>>) -> Result<Vec<SnapshotDemon>> {
+    let demons = match at {
+        None =>
+            sqlx::query!(
+                r#"SELECT id, name AS "name: String", position AS "position!" FROM demons WHERE position IS NOT NULL AND published ORDER BY position"#
+            )
+            .fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(|row| {
+                SnapshotDemon {
+                    id: row.id,
+                    name: row.name,
+                    position: row.position,
+                }
+            })
+            .collect(),
+        Some(time) =>
+            sqlx::query!(
+                r#"SELECT id AS "id!", name::TEXT AS "name!: String", position_ AS "position!" FROM list_at($1) ORDER BY position_"#,
+                time.naive_utc()
+            )
+            .fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(|row| {
+                SnapshotDemon {
+                    id: row.id,
+                    name: row.name,
+                    position: row.position,
+                }
+            })
+            .collect(),
+    };
+
+    Ok(demons)
+}
+
+#[derive(Debug)]
+struct DemonlistDiff {
+    from: Option<DateTime<FixedOffset>>,
+    to: Option<DateTime<FixedOffset>>,
+    before: Vec<SnapshotDemon>,
+    after: Vec<SnapshotDemon>,
+    diff: ListDiff,
+}
+
+#[get("/demonlist/diff/")]
+pub async fn list_diff(state: PointercrateState, query: Query<DiffQuery>) -> ViewResult<HttpResponse> {
+    let mut from = parse_time_machine_date(&query.from)?;
+    let mut to = parse_time_machine_date(&query.to)?;
+
+    if from > to {
+        std::mem::swap(&mut from, &mut to);
+    }
+
+    let now = Utc::now();
+    let earliest = config::time_machine_earliest_date();
+    let (from, to) = (clamp_when(from, earliest, now), clamp_when(to, earliest, now));
+
+    let mut connection = state.connection().await?;
+
+    let before = snapshot(&mut connection, from).await?;
+    let after = snapshot(&mut connection, to).await?;
+    let diff = diff_lists(&before, &after, config::extended_list_size());
+
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(
+        DemonlistDiff {
+            from,
+            to,
+            before,
+            after,
+            diff,
+        }
+        .render()
+        .0,
+    ))
+}
+
+fn format_date(date: Option<DateTime<FixedOffset>>) -> String {
+    match date {
+        Some(date) => date.format("%B %-d, %Y").to_string(),
+        None => "today".to_string(),
+    }
+}
+
+/// Formats a date as the value of a date input, leaving it empty for the current list
+fn date_value(date: Option<DateTime<FixedOffset>>) -> String {
+    date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+fn arrow(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "+",
+        ChangeKind::Raised | ChangeKind::ReturnedFromLegacy => "↑",
+        ChangeKind::Lowered | ChangeKind::FellToLegacy => "↓",
+    }
+}
+
+impl DemonlistDiff {
+    fn moved(&self, demon: &SnapshotDemon) -> Option<&MovedDemon> {
+        self.diff.moved.iter().find(|moved| moved.demon.id == demon.id)
+    }
+
+    fn before_column(&self) -> Markup {
+        html! {
+            ul {
+                @for demon in &self.before {
+                    li {
+                        "#" (demon.position) " " (demon.name)
+                        @if self.diff.removed.contains(demon) {
+                            b { " (removed)" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn after_column(&self) -> Markup {
+        html! {
+            ul {
+                @for demon in &self.after {
+                    li {
+                        "#" (demon.position) " "
+                        a href = {"/demonlist/permalink/" (demon.id) "/"} {
+                            (demon.name)
+                        }
+                        @if self.diff.added.contains(demon) {
+                            b { " " (arrow(ChangeKind::Added)) " new" }
+                        }
+                        @if let Some(moved) = self.moved(demon) {
+                            b { " " (arrow(moved.kind)) " from #" (moved.old_position) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Page for DemonlistDiff {
+    fn title(&self) -> String {
+        "Comparison - Geometry Dash Demonlist".to_string()
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Changes to the Geometry Dash Demonlist between {} and {}",
+            format_date(self.from),
+            format_date(self.to)
+        )
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                "Compare"
+                            }
+                        }
+                        form.flex method = "get" action = "/demonlist/diff/" {
+                            input type = "date" name = "from" value = (date_value(self.from));
+                            input type = "date" name = "to" value = (date_value(self.to));
+                            input.button.blue.hover type = "submit" value = "Compare";
+                        }
+                        p {
+                            (self.diff.added.len()) " added, " (self.diff.removed.len()) " removed and " (self.diff.moved.len()) " moved demons between "
+                            (format_date(self.from)) " and " (format_date(self.to))
+                        }
+                        div.flex style = "align-items: flex-start" {
+                            div style = "flex-basis: 50%" {
+                                h3 {
+                                    (format_date(self.from))
+                                }
+                                (self.before_column())
+                            }
+                            div style = "flex-basis: 50%" {
+                                h3 {
+                                    (format_date(self.to))
+                                }
+                                (self.after_column())
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_lists, MovedDemon, SnapshotDemon};
+    use crate::view::demonlist::changelog::ChangeKind;
+
+    fn demon(id: i32, name: &str, position: i16) -> SnapshotDemon {
+        SnapshotDemon {
+            id,
+            name: name.to_string(),
+            position,
+        }
+    }
+
+    #[test]
+    fn test_diff_partition() {
+        let before = vec![
+            demon(1, "abstract interpretation", 1),
+            demon(2, "Trichotomy", 2),
+            demon(3, "terminal void", 3),
+            demon(4, "taraturusus", 4),
+            demon(5, "Deleted", 5),
+        ];
+        let after = vec![
+            demon(2, "Trichotomy", 1),
+            demon(6, "Bloodbath", 2),
+            demon(1, "abstract interpretation", 3),
+            demon(3, "terminal void", 4),
+            demon(4, "taraturusus", 5),
+        ];
+
+        let diff = diff_lists(&before, &after, 4);
+
+        assert_eq!(diff.added, vec![demon(6, "Bloodbath", 2)]);
+        assert_eq!(diff.removed, vec![demon(5, "Deleted", 5)]);
+        assert_eq!(diff.moved, vec![
+            MovedDemon {
+                demon: demon(2, "Trichotomy", 1),
+                old_position: 2,
+                kind: ChangeKind::Raised,
+            },
+            MovedDemon {
+                demon: demon(1, "abstract interpretation", 3),
+                old_position: 1,
+                kind: ChangeKind::Lowered,
+            },
+            MovedDemon {
+                demon: demon(3, "terminal void", 4),
+                old_position: 3,
+                kind: ChangeKind::Lowered,
+            },
+            MovedDemon {
+                demon: demon(4, "taraturusus", 5),
+                old_position: 4,
+                kind: ChangeKind::FellToLegacy,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_diff_identical_snapshots() {
+        let snapshot = vec![demon(1, "abstract interpretation", 1), demon(2, "Trichotomy", 2)];
+
+        assert_eq!(diff_lists(&snapshot, &snapshot, 150), Default::default());
+    }
+}
@@ -0,0 +1,79 @@
+//! Page shown when a name-based demon link matches more than one demon
+
+use crate::{model::demonlist::demon::MinimalDemon, view::Page};
+use actix_web::HttpResponse;
+use maud::{html, Markup};
+
+#[derive(Debug)]
+struct Disambiguation {
+    /// The name the link was requested for
+    name: String,
+
+    /// All demons with that name, ordered by position
+    demons: Vec<MinimalDemon>,
+}
+
+/// Renders a page letting the user pick which of the demons sharing the given name they meant
+pub(super) fn disambiguation(name: &str, mut demons: Vec<MinimalDemon>) -> HttpResponse {
+    demons.sort_by_key(|demon| demon.position);
+
+    let page = Disambiguation {
+        name: name.to_string(),
+        demons,
+    };
+
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(page.render().0)
+}
+
+impl Page for Disambiguation {
+    fn title(&self) -> String {
+        format!("{} (disambiguation) - Geometry Dash Demonlist", self.name)
+    }
+
+    fn description(&self) -> String {
+        format!("There are multiple demons called '{}' on the Geometry Dash Demonlist", self.name)
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                (self.name)
+                            }
+                        }
+                        p {
+                            "There are multiple demons called '" (self.name) "'. Which one did you mean?"
+                        }
+                        ul#disambiguation {
+                            @for demon in &self.demons {
+                                li {
+                                    a href = {"/demonlist/permalink/" (demon.id) "/"} {
+                                        "#" (demon.position) " - " (demon.name)
+                                    }
+                                }
+                            }
+                        }
+                        p {
+                            a.link href = "/demonlist/" { "Back to the demonlist" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![]
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    config,
+    model::demonlist::demon::MinimalDemon,
+    state::PointercrateState,
+    view::Page,
+    Result, ViewResult,
+};
+use actix_web::{web::Path, HttpResponse};
+use actix_web_codegen::get;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use maud::{html, Markup};
+use sqlx::PgConnection;
+
+const SPARKLINE_WIDTH: i64 = 600;
+const SPARKLINE_HEIGHT: i64 = 120;
+
+#[derive(Debug)]
+struct DemonHistory {
+    demon: MinimalDemon,
+    history: Vec<(DateTime<FixedOffset>, i16)>,
+}
+
+/// Reconstructs all positions the given demon has held, in chronological order
+///
+/// Each entry is the time at which the demon was moved, together with the position it was moved
+/// to. The first entry is the demon's addition to the list. Since `demon_modifications` only ever
+/// stores the position a demon had _before_ a change, the position after a change is the one
+/// logged by the next modification (or the demon's current position, for the last one).
+///
+/// The temporary `-1` position used while moving demons around is skipped.
+pub async fn demon_position_history(connection: &mut PgConnection, demon_id: i32) -> Result<Vec<(DateTime<FixedOffset>, i16)>> {
+    let current_position = MinimalDemon::by_id(demon_id, connection).await?.position;
+
+    let modifications = sqlx::query!(
+        r#"SELECT time, position AS "position!" FROM demon_modifications WHERE id = $1 AND position IS NOT NULL ORDER BY time, audit_id"#,
+        demon_id
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+
+    let addition = sqlx::query!("SELECT time FROM demon_additions WHERE id = $1", demon_id)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    let to_utc = |time: NaiveDateTime| FixedOffset::east(0).from_utc_datetime(&time);
+
+    let mut history: Vec<(DateTime<FixedOffset>, i16)> = Vec::new();
+
+    if let Some(addition) = addition {
+        let initial_position = modifications.first().map(|m| m.position).unwrap_or(current_position);
+
+        history.push((to_utc(addition.time), initial_position));
+    }
+
+    for (idx, modification) in modifications.iter().enumerate() {
+        let new_position = modifications.get(idx + 1).map(|m| m.position).unwrap_or(current_position);
+
+        if new_position < 1 || history.last().map(|&(_, position)| position) == Some(new_position) {
+            continue
+        }
+
+        history.push((to_utc(modification.time), new_position));
+    }
+
+    Ok(history)
+}
+
+#[get("/demonlist/permalink/{id}/history/")]
+pub async fn position_history(state: PointercrateState, id: Path<i32>) -> ViewResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let demon = MinimalDemon::by_id(id.into_inner(), &mut connection).await?;
+    let history = demon_position_history(&mut connection, demon.id).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(DemonHistory { demon, history }.render().0))
+}
+
+impl DemonHistory {
+    /// Renders the position history as a polyline, with better positions further up. The line
+    /// extends to the present to show the demon's current position.
+    fn sparkline(&self) -> Markup {
+        let (first, last) = match (self.history.first(), self.history.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return html! {},
+        };
+
+        let now = FixedOffset::east(0).from_utc_datetime(&Utc::now().naive_utc());
+
+        let best = self.history.iter().map(|&(_, position)| position).min().unwrap_or(1) as i64;
+        let worst = self.history.iter().map(|&(_, position)| position).max().unwrap_or(1) as i64;
+        let start = first.0.timestamp();
+        let span = (now.timestamp() - start).max(1);
+
+        let x = |time: &DateTime<FixedOffset>| (time.timestamp() - start) * SPARKLINE_WIDTH / span;
+        let y = |position: i16| {
+            if worst == best {
+                SPARKLINE_HEIGHT / 2
+            } else {
+                (position as i64 - best) * SPARKLINE_HEIGHT / (worst - best)
+            }
+        };
+
+        // Step function: a demon keeps its position until the next movement
+        let mut points = Vec::new();
+        let mut previous: Option<i16> = None;
+
+        for (time, position) in &self.history {
+            if let Some(previous) = previous {
+                points.push(format!("{},{}", x(time), y(previous)));
+            }
+            points.push(format!("{},{}", x(time), y(*position)));
+            previous = Some(*position);
+        }
+        points.push(format!("{},{}", SPARKLINE_WIDTH, y(last.1)));
+
+        html! {
+            svg viewBox = (format!("-2 -2 {} {}", SPARKLINE_WIDTH + 4, SPARKLINE_HEIGHT + 4)) style = "width: 100%; height: 120px" preserveAspectRatio = "none" {
+                polyline points = (points.join(" ")) fill = "none" stroke = "#0881c6" stroke-width = "2" {}
+            }
+        }
+    }
+}
+
+impl Page for DemonHistory {
+    fn title(&self) -> String {
+        format!("{} - Placement History - Geometry Dash Demonlist", self.demon.name)
+    }
+
+    fn description(&self) -> String {
+        format!("All positions {} has held on the Geometry Dash Demonlist", self.demon.name)
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                a href = {"/demonlist/permalink/" (self.demon.id) "/"} {
+                                    (self.demon.name)
+                                }
+                            }
+                            h3 {
+                                "Placement History"
+                            }
+                        }
+                        (self.sparkline())
+                        @if self.history.is_empty() {
+                            p {
+                                "No placement history is available for this demon"
+                            }
+                        }
+                        @else {
+                            table {
+                                tbody {
+                                    tr {
+                                        th.blue {
+                                            "Date"
+                                        }
+                                        th.blue {
+                                            "Position"
+                                        }
+                                        th.blue {
+                                            "Change"
+                                        }
+                                    }
+                                    @for (idx, (time, position)) in self.history.iter().enumerate() {
+                                        @let previous = if idx == 0 { None } else { Some(self.history[idx - 1].1) };
+
+                                        tr {
+                                            td {
+                                                (time.format("%b %d, %Y"))
+                                            }
+                                            td {
+                                                (position)
+                                            }
+                                            td {
+                                                @match previous {
+                                                    None => "Added to list",
+                                                    Some(previous) if previous <= config::extended_list_size() && *position > config::extended_list_size() => "Fell to legacy",
+                                                    Some(previous) if previous > config::extended_list_size() && *position <= config::extended_list_size() => "Returned from legacy",
+                                                    Some(previous) if previous > *position => {(format!("+{}", previous - position))},
+                                                    Some(previous) => {(format!("-{}", position - previous))},
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demon_position_history;
+    use crate::model::demonlist::demon::Demon;
+
+    #[actix_rt::test]
+    async fn test_two_position_changes() {
+        let mut connection = crate::test::test_setup().await;
+
+        let mut demon = Demon::by_position(4, &mut connection).await.unwrap().base;
+
+        demon.mv(2, &mut connection).await.unwrap();
+        demon.mv(3, &mut connection).await.unwrap();
+
+        let history = demon_position_history(&mut connection, demon.id).await.unwrap();
+        let positions: Vec<i16> = history.iter().map(|&(_, position)| position).collect();
+
+        assert_eq!(positions, vec![4, 2, 3]);
+        assert!(history.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+}
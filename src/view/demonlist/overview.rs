@@ -1,27 +1,118 @@
 use crate::{
+    cistring::CiString,
     config,
-    model::{nationality::Nationality, user::User},
+    error::PointercrateError,
+    i18n::Locale,
+    model::{
+        demonlist::{demon::effective_requirement, last_modified, player::PlayerStats},
+        nationality::{Continent, Nationality, Subdivision},
+        user::User,
+    },
     permissions::Permissions,
     state::PointercrateState,
-    video,
-    view::Page,
+    util::etag_matches,
+    video::{self, ThumbnailQuality},
+    view::{
+        demonlist::{
+            submit::{SubmissionFlash, FLASH_COOKIE},
+            OverviewCache,
+        },
+        Page,
+    },
     Result, ViewResult,
 };
-use actix_web::{web::Query, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{
+    http::header::{CACHE_CONTROL, ETAG, LAST_MODIFIED, VARY},
+    web::{Path, Query},
+    HttpMessage, HttpRequest, HttpResponse,
+};
 use actix_web_codegen::get;
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use maud::{html, Markup, PreEscaped};
 use serde::Deserialize;
 use sqlx::PgConnection;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OverviewDemon {
     pub id: i32,
     pub position: i16,
     pub name: String,
+
+    /// The record requirement as set by the list moderators. Use
+    /// [`effective_requirement`](crate::model::demonlist::demon::effective_requirement) to get the
+    /// requirement that actually applies at the demon's position
+    pub requirement: i16,
     pub publisher: String,
+    pub publisher_nationality: Option<Nationality>,
     pub video: Option<String>,
     pub current_position: Option<i16>,
+    pub mirrors: Vec<String>,
+
+    /// The most recent time this demon fell onto the legacy list, if it is currently on it
+    pub legacy_since: Option<NaiveDateTime>,
+
+    pub tags: Vec<String>,
+
+    /// The number of players with an approved record on this demon. Not available in time machine
+    /// views, as we do not keep track of historical counts
+    pub record_count: Option<i32>,
+}
+
+struct FetchedOverviewDemon {
+    id: i32,
+    position: i16,
+    name: String,
+    requirement: i16,
+    publisher: String,
+    publisher_nation: Option<String>,
+    publisher_country_code: Option<String>,
+    publisher_continent: Option<String>,
+    publisher_subdivision_code: Option<String>,
+    publisher_subdivision_name: Option<String>,
+    video: Option<String>,
+    current_position: Option<i16>,
+    mirrors: Vec<String>,
+    legacy_since: Option<NaiveDateTime>,
+    tags: Vec<String>,
+    record_count: Option<i32>,
+}
+
+impl From<FetchedOverviewDemon> for OverviewDemon {
+    fn from(row: FetchedOverviewDemon) -> Self {
+        let publisher_nationality = if let (Some(nation), Some(iso_country_code)) = (row.publisher_nation, row.publisher_country_code) {
+            Some(Nationality {
+                iso_country_code,
+                nation: CiString(nation),
+                continent: row.publisher_continent.as_deref().map(Continent::from_sql),
+                subdivision: row
+                    .publisher_subdivision_code
+                    .zip(row.publisher_subdivision_name)
+                    .map(|(code, name)| Subdivision::new(code, CiString(name))),
+            })
+        } else {
+            None
+        };
+
+        OverviewDemon {
+            id: row.id,
+            position: row.position,
+            name: row.name,
+            requirement: row.requirement,
+            publisher: row.publisher,
+            publisher_nationality,
+            video: row.video,
+            current_position: row.current_position,
+            mirrors: row.mirrors,
+            legacy_since: row.legacy_since,
+            tags: row.tags,
+            record_count: row.record_count,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -34,31 +125,157 @@ pub struct DemonlistOverview {
 
     pub when: Option<DateTime<FixedOffset>>,
     pub query_data: OverviewQueryData,
+
+    /// The client's country code, as given by the configured geolocation header
+    pub geolocation: Option<String>,
+
+    /// Set if the client requested a time machine date that we could not make sense of, in which
+    /// case the current list is shown instead
+    pub time_machine_error: Option<PointercrateError>,
+
+    /// Thumbnails of videos whose thumbnail cannot be derived from the URL alone, keyed by video URL
+    pub thumbnails: HashMap<String, String>,
+
+    pub locale: Locale,
+
+    /// The outcome of a record submission made without JavaScript, see [`super::submit`]
+    pub submission_flash: Option<SubmissionFlash>,
+
+    /// The player linked to via `?player=<id>`, whose stats are shown in the stats viewer
+    pub linked_player: Option<PlayerStats>,
 }
 
 pub async fn overview_demons(connection: &mut PgConnection, at: Option<DateTime<FixedOffset>>) -> Result<Vec<OverviewDemon>> {
-    match at {
-        None => Ok(sqlx::query_as!(
-                OverviewDemon,
-                r#"SELECT demons.id, position, demons.name as "name: String", CASE WHEN verifiers.link_banned THEN NULL ELSE video::TEXT END, 
-                 players.name as "publisher: String", null::smallint as current_position FROM demons INNER JOIN players ON demons.publisher = players.id INNER JOIN players AS verifiers 
-                 ON demons.verifier = verifiers.id WHERE position IS NOT NULL ORDER BY position"#
+    let rows = match at {
+        None => sqlx::query_as!(
+                FetchedOverviewDemon,
+                r#"SELECT demons.id, published_positions.position AS "position!", demons.name as "name: String", demons.requirement, CASE WHEN verifiers.link_banned THEN NULL ELSE video::TEXT END, 
+                 players.name as "publisher: String", nationalities.nation::TEXT AS "publisher_nation?", nationalities.iso_country_code::TEXT AS 
+                 "publisher_country_code?", nationalities.continent::TEXT AS "publisher_continent?", subdivisions.iso_code::TEXT AS "publisher_subdivision_code?", 
+                 subdivisions.name::TEXT AS "publisher_subdivision_name?", null::smallint as current_position, ARRAY(SELECT mirrors.video::TEXT FROM 
+                 demon_video_mirrors AS mirrors WHERE mirrors.demon = demons.id AND verifiers.link_banned IS NOT TRUE ORDER BY mirrors.position) AS "mirrors!: Vec<String>", legacy.since AS legacy_since, 
+                 ARRAY(SELECT tag::TEXT FROM demon_tags WHERE demon_tags.demon = demons.id ORDER BY tag) AS "tags!: Vec<String>", demons.record_count AS "record_count?" FROM demons 
+                 INNER JOIN players ON demons.publisher = players.id LEFT OUTER JOIN nationalities ON players.nationality = nationalities.iso_country_code LEFT OUTER JOIN subdivisions ON 
+                 players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code INNER JOIN players AS verifiers ON demons.verifier = 
+                 verifiers.id LEFT OUTER JOIN demon_legacy_since($1) AS legacy ON legacy.id = demons.id INNER JOIN published_positions ON 
+                 published_positions.id = demons.id ORDER BY published_positions.position"#, 
+                 config::extended_list_size()
             )
             .fetch_all(connection)
-            .await?),
-        Some(time) => Ok(sqlx::query_as!(
-                OverviewDemon,
-                r#"SELECT demons.id as "id!", position_ as "position!", demons.name as "name!: String", CASE WHEN verifiers.link_banned THEN NULL ELSE video::TEXT END, 
-                 players.name as "publisher: String", current_position FROM list_at($1) AS demons INNER JOIN players ON demons.publisher = players.id INNER JOIN players AS verifiers 
-                 ON demons.verifier = verifiers.id ORDER BY position_"#, time.naive_utc()
+            .await?,
+        Some(time) => sqlx::query_as!(
+                FetchedOverviewDemon,
+                r#"SELECT demons.id as "id!", position_ as "position!", demons.name as "name!: String", demons.requirement AS "requirement!", CASE WHEN verifiers.link_banned THEN NULL ELSE video::TEXT END, 
+                 players.name as "publisher: String", nationalities.nation::TEXT AS "publisher_nation?", nationalities.iso_country_code::TEXT AS 
+                 "publisher_country_code?", nationalities.continent::TEXT AS "publisher_continent?", subdivisions.iso_code::TEXT AS "publisher_subdivision_code?", 
+                 subdivisions.name::TEXT AS "publisher_subdivision_name?", current_position, ARRAY(SELECT mirrors.video::TEXT FROM demon_video_mirrors AS mirrors 
+                 WHERE mirrors.demon = demons.id AND verifiers.link_banned IS NOT TRUE ORDER BY mirrors.position) AS "mirrors!: Vec<String>", legacy.since AS legacy_since, ARRAY(SELECT tag::TEXT FROM 
+                 demon_tags WHERE demon_tags.demon = demons.id ORDER BY tag) AS "tags!: Vec<String>", null::INTEGER AS record_count FROM 
+                 list_at($1) AS demons INNER JOIN players ON demons.publisher = players.id LEFT OUTER JOIN nationalities ON players.nationality = nationalities.iso_country_code LEFT OUTER JOIN subdivisions ON 
+                 players.nationality = subdivisions.nation AND players.subdivision = subdivisions.iso_code INNER JOIN players AS verifiers ON demons.verifier = 
+                 verifiers.id LEFT OUTER JOIN demon_legacy_since($2) AS legacy ON legacy.id = demons.id ORDER BY position_"#,
+                 time.naive_utc(), config::extended_list_size()
             )
             .fetch_all(connection)
-            .await?)
+            .await?,
+    };
 
+    Ok(rows.into_iter().map(OverviewDemon::from).collect())
+}
+
+/// Renders the progress records on a demon need to reach to be accepted, given the size of the main
+/// list
+fn requirement(demon: &OverviewDemon, list_size: i16) -> Markup {
+    html! {
+        br;
+        small {
+            (super::requirement_description(effective_requirement(demon.requirement, demon.position, list_size)))
+        }
+    }
+}
+
+/// Renders how many players have beaten (or made progress on) a demon
+fn record_count(demon: &OverviewDemon) -> Markup {
+    html! {
+        @if let Some(record_count) = demon.record_count {
+            br;
+            small {
+                @match record_count {
+                    0 => { "Nobody has a record on this yet" },
+                    1 => { "1 player has a record on this" },
+                    _ => { (record_count) " players have a record on this" },
+                }
+            }
+        }
+    }
+}
+
+/// Renders where a demon shown in a time machine view is placed on the current list
+fn current_position(demon: &OverviewDemon) -> Markup {
+    html! {
+        @if let Some(current_position) = demon.current_position {
+            br;
+            @if current_position > config::extended_list_size() {
+                @match demon.legacy_since {
+                    Some(since) => { "Legacy since " (since.format("%B %-d, %Y")) },
+                    None => { "Currently Legacy" },
+                }
+            }
+            @else {
+                "Currently #"(current_position)
+            }
+        }
+    }
+}
+
+/// Renders the tags of a demon as small pills
+fn tags(tags: &[String]) -> Markup {
+    html! {
+        @if !tags.is_empty() {
+            div.tags {
+                @for tag in tags {
+                    span.tag {
+                        (tag)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the list of video mirrors of a demon, collapsed behind a small "mirrors" toggle
+fn mirrors(mirrors: &[String]) -> Markup {
+    html! {
+        @if !mirrors.is_empty() {
+            details.mirrors style = "font-size: 80%" {
+                summary {
+                    "mirrors"
+                }
+                ul style = "margin: 0px" {
+                    @for (idx, mirror) in mirrors.iter().enumerate() {
+                        li {
+                            a target = "_blank" href = (mirror) {
+                                "#" (idx + 1)
+                                @if let Some(host) = video::host(mirror) {
+                                    " (" (host) ")"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
 impl DemonlistOverview {
+    fn thumbnail(&self, video: &str) -> String {
+        match self.thumbnails.get(video) {
+            Some(thumbnail) => thumbnail.clone(),
+            None => video::thumbnail(video, ThumbnailQuality::Compact),
+        }
+    }
+
     pub(super) fn team_panel(&self) -> Markup {
         let maybe_link = |user: &User| -> Markup {
             html! {
@@ -111,14 +328,15 @@ impl DemonlistOverview {
     }
 
     pub(super) async fn load(
-        connection: &mut PgConnection, when: Option<DateTime<FixedOffset>>, query_data: OverviewQueryData,
+        connection: &mut PgConnection, cache: &OverviewCache, when: Option<DateTime<FixedOffset>>, query_data: OverviewQueryData,
+        locale: Locale,
     ) -> Result<DemonlistOverview> {
         let admins = User::by_permission(Permissions::ListAdministrator, connection).await?;
         let mods = User::by_permission(Permissions::ListModerator, connection).await?;
         let helpers = User::by_permission(Permissions::ListHelper, connection).await?;
 
         let nations = Nationality::all(connection).await?;
-        let demon_overview = overview_demons(connection, when).await?;
+        let demon_overview = cache.get_or_load(when, overview_demons(connection, when)).await?;
 
         Ok(DemonlistOverview {
             admins,
@@ -128,11 +346,17 @@ impl DemonlistOverview {
             demon_overview,
             when,
             query_data,
+            geolocation: None,
+            time_machine_error: None,
+            thumbnails: HashMap::new(),
+            locale,
+            submission_flash: None,
+            linked_player: None,
         })
     }
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Hash)]
 pub struct OverviewQueryData {
     #[serde(rename = "timemachine", default)]
     time_machine_shown: bool,
@@ -142,36 +366,178 @@ pub struct OverviewQueryData {
 
     #[serde(rename = "submitter", default)]
     record_submitter_shown: bool,
+
+    /// The ID of the player to preselect in the stats viewer, which is then shown right away
+    #[serde(default)]
+    player: Option<i32>,
 }
 
-#[get("/demonlist/")]
-pub async fn index(request: HttpRequest, state: PointercrateState, query_data: Query<OverviewQueryData>) -> ViewResult<HttpResponse> {
-    /* static */
-    let EARLIEST_DATE: DateTime<FixedOffset> = FixedOffset::east(0).from_utc_datetime(&NaiveDate::from_ymd(2017, 1, 4).and_hms(0, 0, 0));
+/// Midnight (UTC) of the given earliest time machine date
+fn earliest_time(earliest_date: NaiveDate) -> DateTime<FixedOffset> {
+    FixedOffset::east(0).from_utc_datetime(&earliest_date.and_hms(0, 0, 0))
+}
+
+/// Clamps a time machine date into the range the time machine supports
+///
+/// Dates before `earliest_date` (usually [`config::time_machine_earliest_date`]) are moved up to it,
+/// while dates at or after `now` yield `None`, meaning the current list should be shown.
+pub(super) fn clamp_when(when: DateTime<FixedOffset>, earliest_date: NaiveDate, now: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+    let earliest = earliest_time(earliest_date);
+
+    if when < earliest {
+        Some(earliest)
+    } else if when >= now {
+        None
+    } else {
+        Some(when)
+    }
+}
+
+/// Parses a time machine date, as given either by the `when` cookie set by the time machine form
+/// or by the date part of a `/demonlist/at/{date}/` URL
+///
+/// Accepts either a full RFC3339 timestamp or a plain `YYYY-MM-DD` date, the latter being
+/// interpreted as midnight UTC. Dates that do not exist in the calendar (such as February 30th) are
+/// rejected.
+pub(super) fn parse_time_machine_date(value: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::<FixedOffset>::parse_from_rfc3339(value)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| FixedOffset::east(0).from_utc_datetime(&date.and_hms(0, 0, 0)))
+        })
+        .ok_or_else(|| {
+            PointercrateError::InvalidTimeMachineDate {
+                date: value.to_string(),
+            }
+        })
+}
+
+/// Computes the (weak) ETag of the overview page rendered with the given parameters
+///
+/// Time machine views get different ETags from the present list (and from each other), as they are
+/// all served under the same URL.
+fn overview_etag(
+    last_modified: NaiveDateTime, when: Option<DateTime<FixedOffset>>, query_data: &OverviewQueryData, geolocation: Option<&str>,
+    time_machine_error: Option<&PointercrateError>, locale: Locale, submission_flash: Option<&SubmissionFlash>,
+) -> String {
+    let mut hasher = DefaultHasher::new();
+
+    last_modified.hash(&mut hasher);
+    when.hash(&mut hasher);
+    query_data.hash(&mut hasher);
+    geolocation.hash(&mut hasher);
+    time_machine_error.map(ToString::to_string).hash(&mut hasher);
+    locale.hash(&mut hasher);
+    submission_flash.hash(&mut hasher);
+
+    format!("W/\"{:x}\"", hasher.finish())
+}
+
+/// The request headers the overview depends on, for the `Vary` header. The stats viewer preselects
+/// the client's nation based on the configured [geolocation header](config::geolocation_header)
+fn overview_vary(geolocation_header: Option<&str>) -> String {
+    match geolocation_header {
+        Some(header) => format!("Accept-Language, Cookie, {}", header),
+        None => "Accept-Language, Cookie".to_string(),
+    }
+}
 
+async fn render_overview(
+    request: HttpRequest, state: PointercrateState, when: Option<DateTime<FixedOffset>>, query_data: OverviewQueryData,
+    time_machine_error: Option<PointercrateError>, submission_flash: Option<SubmissionFlash>,
+) -> ViewResult<HttpResponse> {
     let mut connection = state.connection().await?;
 
-    let specified_when = request
-        .cookie("when")
-        .map(|cookie| DateTime::<FixedOffset>::parse_from_rfc3339(cookie.value()));
+    let geolocation = config::geolocation_header().and_then(|header| {
+        request
+            .headers()
+            .get(header.as_str())
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string)
+    });
 
-    let when = if let Some(when) = specified_when {
-        match when {
-            Ok(when) if when < EARLIEST_DATE => Some(EARLIEST_DATE),
-            Ok(when) if when >= Utc::now() => None,
-            Ok(when) => Some(when),
-            _ => None,
-        }
-    } else {
-        None
+    let locale = Locale::of_request(&request);
+
+    let last_modified = last_modified(&mut connection).await?;
+    let etag = overview_etag(
+        last_modified,
+        when,
+        &query_data,
+        geolocation.as_deref(),
+        time_machine_error.as_ref(),
+        locale,
+        submission_flash.as_ref(),
+    );
+
+    if etag_matches(request.headers(), &etag) {
+        return Ok(HttpResponse::NotModified().header(ETAG, etag).finish())
+    }
+
+    let linked_player = super::linked_player(query_data.player, &state.hardest_demons, &mut connection).await?;
+    let mut overview = DemonlistOverview::load(&mut connection, &state.overview_cache, when, query_data, locale).await?;
+
+    let videos: Vec<&str> = overview
+        .demon_overview
+        .iter()
+        .filter(|demon| demon.position <= config::extended_list_size())
+        .filter_map(|demon| demon.video.as_deref())
+        .collect();
+
+    overview.thumbnails = video::twitch::vod_thumbnails(&videos, &state.http_client, &mut connection).await?;
+    overview.geolocation = geolocation;
+    overview.time_machine_error = time_machine_error;
+    overview.linked_player = linked_player;
+
+    let mut response = HttpResponse::Ok();
+
+    // flash messages are only shown once, and only to the client that made the submission
+    if submission_flash.is_some() {
+        response.del_cookie(&SubmissionFlash::removal_cookie()).header(CACHE_CONTROL, "private");
+    }
+
+    overview.submission_flash = submission_flash;
+
+    Ok(response
+        .content_type("text/html; charset=utf-8")
+        .header(ETAG, etag)
+        .header(VARY, overview_vary(config::geolocation_header().as_deref()))
+        .header(LAST_MODIFIED, last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+        .body(overview.render().0))
+}
+
+#[get("/demonlist/")]
+pub async fn index(request: HttpRequest, state: PointercrateState, query_data: Query<OverviewQueryData>) -> ViewResult<HttpResponse> {
+    // The "Go to present" button resets the cookie to the empty string
+    let cookie = request.cookie("when").filter(|cookie| !cookie.value().is_empty());
+
+    // We cannot simply return an error page here, as the cookie would then lock the client out of
+    // the overview until it expires
+    let (when, time_machine_error) = match cookie.map(|cookie| parse_time_machine_date(cookie.value())) {
+        None => (None, None),
+        Some(Ok(when)) => (clamp_when(when, config::time_machine_earliest_date(), Utc::now()), None),
+        Some(Err(error)) => (None, Some(error)),
     };
 
-    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(
-        DemonlistOverview::load(&mut connection, when, query_data.into_inner())
-            .await?
-            .render()
-            .0,
-    ))
+    let submission_flash = request
+        .cookie(FLASH_COOKIE)
+        .and_then(|cookie| SubmissionFlash::from_cookie_value(cookie.value()));
+
+    render_overview(request, state, when, query_data.into_inner(), time_machine_error, submission_flash).await
+}
+
+/// Shareable variant of the time machine. The date given in the path takes precedence over any
+/// `when` cookie set via the time machine form.
+#[get("/demonlist/at/{date}/")]
+pub async fn at(
+    request: HttpRequest, state: PointercrateState, date: Path<String>, query_data: Query<OverviewQueryData>,
+) -> ViewResult<HttpResponse> {
+    let when = parse_time_machine_date(&date.into_inner())?;
+
+    let when = clamp_when(when, config::time_machine_earliest_date(), Utc::now());
+
+    render_overview(request, state, when, query_data.into_inner(), None, None).await
 }
 
 impl Page for DemonlistOverview {
@@ -192,17 +558,41 @@ impl Page for DemonlistOverview {
     }
 
     fn body(&self) -> Markup {
-        let dropdowns = super::dropdowns(&self.demon_overview, None);
+        let dropdowns = super::dropdowns(&self.demon_overview, None, self.locale);
+        let first_ad_position = config::first_ad_position();
+        let ad_interval = config::ad_interval();
+        let publisher = config::adsense_publisher();
 
         html! {
-            (super::besides_sidebar_ad())
+            (super::besides_sidebar_ad(publisher.as_deref()))
             (dropdowns)
 
             div.flex.m-center.container {
                 main.left {
-                    (time_machine(self.query_data.time_machine_shown))
-                    (super::submission_panel(&self.demon_overview, self.query_data.record_submitter_shown))
-                    (super::stats_viewer(&self.nations, self.query_data.stats_viewer_shown))
+                    (time_machine(self.query_data.time_machine_shown, config::time_machine_earliest_date()))
+                    (super::submission_panel(
+                        &self.demon_overview,
+                        &self.nations,
+                        self.query_data.record_submitter_shown,
+                        config::video_required(),
+                        self.locale,
+                        self.submission_flash.as_ref()
+                    ))
+                    (super::stats_viewer(
+                        &self.nations,
+                        super::geolocated_nation(self.geolocation.as_deref(), &self.nations),
+                        self.linked_player.as_ref(),
+                        self.query_data.stats_viewer_shown || self.linked_player.is_some(),
+                        &config::hidden_stats()
+                    ))
+                    @if let Some(ref error) = self.time_machine_error {
+                        div.panel.fade.flex style="align-items: center;" {
+                            p.info-red style = "text-align: end" {
+                                "The time machine could not be used: " (error) ". You are looking at the current demonlist instead."
+                            }
+                            a.white.button href = "/demonlist/" onclick=r#"document.cookie = "when=""# style = "margin-left: 15px"{ b{"Reset time machine" }}
+                        }
+                    }
                     @if let Some(when) = self.when {
                         div.panel.fade.blue.flex style="align-items: center;" {
                              span style = "text-align: end"{
@@ -224,7 +614,7 @@ impl Page for DemonlistOverview {
                             section.panel.fade style="overflow:hidden" {
                                 @if let Some(ref video) = demon.video {
                                     div.flex style = "align-items: center" {
-                                        div.thumb."ratio-16-9"."js-delay-css" style = "position: relative" data-property = "background-image" data-property-value = {"url('" (video::thumbnail(video)) "')"} {
+                                        div.thumb."ratio-16-9"."js-delay-css" style = "position: relative" data-property = "background-image" data-property-value = {"url('" (self.thumbnail(video)) "')"} {
                                             a.play href = (video) {}
                                         }
                                         div style = "padding-left: 15px" {
@@ -233,20 +623,20 @@ impl Page for DemonlistOverview {
                                                     "#" (demon.position) (PreEscaped(" &#8211; ")) (demon.name)
                                                 }
                                             }
+                                            (tags(&demon.tags))
                                             h3 style = "text-align: left" {
+                                                @if let Some(ref nationality) = demon.publisher_nationality {
+                                                    (nationality)
+                                                    (PreEscaped("&nbsp;"))
+                                                }
                                                 i {
                                                     (demon.publisher)
                                                 }
-                                                @if let Some(current_position) = demon.current_position {
-                                                    br;
-                                                    @if current_position > config::extended_list_size() {
-                                                        "Currently Legacy"
-                                                    }
-                                                    @else {
-                                                        "Currently #"(current_position)
-                                                    }
-                                                }
+                                                (current_position(demon))
+                                                (requirement(demon, config::list_size()))
+                                                (record_count(demon))
                                             }
+                                            (mirrors(&demon.mirrors))
                                         }
                                     }
                                 }
@@ -257,62 +647,30 @@ impl Page for DemonlistOverview {
                                                 "#" (demon.position) (PreEscaped(" &#8211; ")) (demon.name)
                                             }
                                         }
+                                        (tags(&demon.tags))
                                         h3 {
+                                            @if let Some(ref nationality) = demon.publisher_nationality {
+                                                (nationality)
+                                                (PreEscaped("&nbsp;"))
+                                            }
                                             i {
                                                 (demon.publisher)
                                             }
-                                            @if let Some(current_position) = demon.current_position {
-                                                br;
-                                                @if current_position > config::extended_list_size() {
-                                                    "Currently Legacy"
-                                                }
-                                                @else {
-                                                    "Currently #"(current_position)
-                                                }
-                                            }
+                                            (current_position(demon))
+                                            (requirement(demon, config::list_size()))
+                                            (record_count(demon))
                                         }
                                     }
                                 }
                             }
-                            @if demon.position == 1 {
-                                section.panel.fade style = "padding: 0px; height: 90px"{
-                                (PreEscaped(format!(r#"
-                                    <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
-                                    <!-- Demonlist Responsive Feed Ad -->
-                                    <ins class="adsbygoogle"
-                                         style="display:inline-block;width:728px;height:90px"
-                                         data-ad-client="{}"
-                                         data-ad-slot="2819150519"></ins>
-                                    <script>
-                                         (adsbygoogle = window.adsbygoogle || []).push({{}});
-                                    </script>
-                                    "#, config::adsense_publisher_id())))
-                                }
-                            }
-                            // Place ad every 20th demon
-                            @if demon.position % 20 == 0 {
-                                section.panel.fade {
-                                (PreEscaped(format!(r#"
-                                    <script async src="https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"></script>
-                                    <ins class="adsbygoogle"
-                                         style="display:block"
-                                         data-ad-format="fluid"
-                                         data-ad-layout-key="-h1+40+4u-93+n"
-                                         data-ad-client="{}"
-                                         data-ad-slot="5157884729"></ins>
-                                    <script>
-                                         (adsbygoogle = window.adsbygoogle || []).push({{}});
-                                    </script>
-                                    "#, config::adsense_publisher_id())))
-                                }
-                            }
+                            (super::overview_ad(demon.position, first_ad_position, ad_interval, publisher.as_deref()))
                         }
                     }
                 }
 
                 aside.right {
                     (self.team_panel())
-                    (super::sidebar_ad())
+                    (super::sidebar_ad(publisher.as_deref()))
                     (super::rules_panel())
                     (super::submit_panel())
                     (super::stats_viewer_panel())
@@ -375,11 +733,15 @@ impl Page for DemonlistOverview {
     }
 }
 
-fn time_machine(visible: bool) -> Markup {
-    let current_year = FixedOffset::east(3600 * 23 + 3599)
-        .from_utc_datetime(&Utc::now().naive_utc())
-        .year();
+/// The years selectable in the time machine form, from the year of the earliest date up to the
+/// current year in the timezone furthest ahead of UTC
+fn time_machine_years(earliest_date: NaiveDate, now: DateTime<Utc>) -> RangeInclusive<i32> {
+    let current_year = FixedOffset::east(3600 * 23 + 3599).from_utc_datetime(&now.naive_utc()).year();
+
+    earliest_date.year()..=current_year
+}
 
+fn time_machine(visible: bool, earliest_date: NaiveDate) -> Markup {
     let months = [
         "January",
         "February",
@@ -403,12 +765,12 @@ fn time_machine(visible: bool) -> Markup {
                     h2 {"Time Machine"}
                 }
                 p {
-                    "Enter the date you want to view the demonlist at below. For technical reasons, the earliest possible date is January 4th 2017. Note however that data before August 4th 2017 is only provided on a best-effort basis and not guaranteed to be 100% accurate. Particularly data from before April 4th 2017 contains significant errors!"
+                    "Enter the date you want to view the demonlist at below. For technical reasons, the earliest possible date is " (earliest_date.format("%B %-d, %Y")) ". " (config::time_machine_caveat())
                 }
                 div.flex {
                     span.form-input data-type = "dropdown" style = "max-width:33%" {
                         h3 {"Year:"}
-                        (crate::view::simple_dropdown("time-machine-year", None, 2017..=current_year))
+                        (crate::view::simple_dropdown("time-machine-year", None, time_machine_years(earliest_date, Utc::now())))
                         p.error {}
                     }
                     span.form-input data-type = "dropdown" style = "max-width:33%"  {
@@ -444,3 +806,235 @@ fn time_machine(visible: bool) -> Markup {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clamp_when, earliest_time, last_modified, overview_demons, overview_etag, overview_vary, parse_time_machine_date, requirement,
+        time_machine_years, OverviewQueryData,
+    };
+    use crate::{error::PointercrateError, i18n::Locale, util::etag_matches};
+    use actix_web::http::{header::IF_NONE_MATCH, HeaderMap, HeaderValue};
+    use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2021-04-01T12:00:00+00:00").unwrap().with_timezone(&Utc)
+    }
+
+    fn earliest_date() -> NaiveDate {
+        NaiveDate::from_ymd(2017, 1, 4)
+    }
+
+    #[test]
+    fn test_clamp_before_earliest_date() {
+        let when = parse_time_machine_date("2016-05-12").unwrap();
+
+        assert_eq!(clamp_when(when, earliest_date(), now()), Some(earliest_time(earliest_date())));
+    }
+
+    #[test]
+    fn test_clamp_future_date() {
+        let when = parse_time_machine_date("2021-04-01T12:00:01+00:00").unwrap();
+
+        assert_eq!(clamp_when(when, earliest_date(), now()), None);
+    }
+
+    #[test]
+    fn test_clamp_mid_range_date() {
+        let when = parse_time_machine_date("2019-08-04T15:30:00+02:00").unwrap();
+
+        assert_eq!(
+            clamp_when(when, earliest_date(), now()),
+            Some(DateTime::<FixedOffset>::parse_from_rfc3339("2019-08-04T13:30:00+00:00").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_later_earliest_date() {
+        let earliest = NaiveDate::from_ymd(2019, 6, 1);
+        let when = parse_time_machine_date("2018-03-15").unwrap();
+
+        assert_eq!(
+            clamp_when(when, earliest, now()),
+            Some(DateTime::<FixedOffset>::parse_from_rfc3339("2019-06-01T00:00:00+00:00").unwrap())
+        );
+        assert_eq!(time_machine_years(earliest, now()), 2019..=2021);
+        assert_eq!(time_machine_years(earliest_date(), now()), 2017..=2021);
+    }
+
+    #[test]
+    fn test_parse_time_machine_date() {
+        assert_eq!(
+            parse_time_machine_date("2019-08-04"),
+            parse_time_machine_date("2019-08-04T00:00:00Z")
+        );
+        assert!(parse_time_machine_date("2019-08-04T00:00:00").is_err());
+        assert!(parse_time_machine_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_impossible_dates() {
+        assert_eq!(
+            parse_time_machine_date("2019-02-30T12:00:00+00:00"),
+            Err(PointercrateError::InvalidTimeMachineDate {
+                date: "2019-02-30T12:00:00+00:00".to_string()
+            })
+        );
+        assert_eq!(
+            parse_time_machine_date("2019-02-30"),
+            Err(PointercrateError::InvalidTimeMachineDate {
+                date: "2019-02-30".to_string()
+            })
+        );
+        assert_eq!(
+            parse_time_machine_date("2019-13-01T00:00:00Z"),
+            Err(PointercrateError::InvalidTimeMachineDate {
+                date: "2019-13-01T00:00:00Z".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_leap_day() {
+        assert_eq!(
+            parse_time_machine_date("2020-02-29T18:30:00+01:00"),
+            Ok(DateTime::<FixedOffset>::parse_from_rfc3339("2020-02-29T17:30:00+00:00").unwrap())
+        );
+        assert!(parse_time_machine_date("2019-02-29").is_err());
+    }
+
+    #[test]
+    fn test_overview_vary() {
+        assert_eq!(overview_vary(None), "Accept-Language, Cookie");
+        assert_eq!(overview_vary(Some("CF-IPCountry")), "Accept-Language, Cookie, CF-IPCountry");
+    }
+
+    #[actix_rt::test]
+    async fn test_overview_etag() {
+        let mut connection = crate::test::test_setup().await;
+
+        // list_last_modified is only touched when a transaction commits, which test transactions never do
+        sqlx::query!("SET CONSTRAINTS ALL IMMEDIATE").execute(&mut connection).await.unwrap();
+
+        let query_data = OverviewQueryData::default();
+        let before = last_modified(&mut connection).await.unwrap();
+        let etag = overview_etag(before, None, &query_data, None, None, Locale::English, None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(&format!("W/\"0\", {}", etag)).unwrap());
+
+        // a client that has the current version gets a 304
+        assert!(etag_matches(&headers, &etag));
+
+        // time machine views must not be confused with the current list
+        let when = parse_time_machine_date("2019-08-04").unwrap();
+
+        assert!(!etag_matches(&headers, &overview_etag(before, Some(when), &query_data, None, None, Locale::English, None)));
+
+        sqlx::query!("UPDATE demons SET requirement = 60 WHERE name = 'Trichotomy'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let after = last_modified(&mut connection).await.unwrap();
+
+        assert!(after > before);
+        assert!(!etag_matches(&headers, &overview_etag(after, None, &query_data, None, None, Locale::English, None)));
+    }
+
+    #[actix_rt::test]
+    async fn test_overview_publisher_nationality() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE players SET nationality = 'US', subdivision = 'AL' WHERE name = 'stardust1971'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let now = Utc::now().with_timezone(&FixedOffset::east(0));
+
+        for at in vec![None, Some(now)] {
+            let demons = overview_demons(&mut connection, at).await.unwrap();
+
+            let published_by_stardust = demons.iter().find(|demon| demon.name == "Trichotomy").unwrap();
+            let nationality = published_by_stardust.publisher_nationality.as_ref().unwrap();
+
+            assert_eq!(nationality.iso_country_code, "US");
+            assert_eq!(nationality.subdivision.as_ref().unwrap().iso_code, "AL");
+
+            let published_by_aquatias = demons.iter().find(|demon| demon.name == "terminal void").unwrap();
+
+            assert!(published_by_aquatias.publisher_nationality.is_none());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_overview_requirement() {
+        let mut connection = crate::test::test_setup().await;
+
+        let demons = overview_demons(&mut connection, None).await.unwrap();
+        let trichotomy = demons.iter().find(|demon| demon.name == "Trichotomy").unwrap();
+
+        assert_eq!(trichotomy.requirement, 84);
+
+        // Trichotomy is at #2, so on the main list if it has at least two demons, and on the extended
+        // list otherwise
+        assert!(requirement(trichotomy, 2)
+            .into_string()
+            .contains("84% or better required to qualify"));
+        assert!(requirement(trichotomy, 1).into_string().contains("100% required to qualify"));
+    }
+
+    #[actix_rt::test]
+    async fn test_overview_hides_unpublished() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("UPDATE demons SET published = FALSE WHERE name = 'terminal void'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let now = Utc::now().with_timezone(&FixedOffset::east(0));
+
+        for at in vec![None, Some(now)] {
+            let demons = overview_demons(&mut connection, at).await.unwrap();
+            let positions = demons.iter().map(|demon| demon.position).collect::<Vec<_>>();
+
+            assert!(!demons.iter().any(|demon| demon.name == "terminal void"));
+
+            // taraturusus moves up into the spot terminal void holds until it gets published
+            assert_eq!(demons.iter().find(|demon| demon.name == "taraturusus").unwrap().position, 3);
+            assert_eq!(positions, (1..=demons.len() as i16).collect::<Vec<_>>());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_time_machine_publication_state() {
+        let mut connection = crate::test::test_setup().await;
+
+        // everything in the test setup happens inside a single transaction, so backdate the history
+        sqlx::query!("UPDATE demon_additions SET time = time - INTERVAL '2 days'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demons SET published = FALSE WHERE name = 'terminal void'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE demon_modifications SET time = time - INTERVAL '1 day' WHERE published IS NOT NULL")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        let now = Utc::now().with_timezone(&FixedOffset::east(0));
+
+        // published before it was hidden
+        let demons = overview_demons(&mut connection, Some(now - Duration::hours(36))).await.unwrap();
+
+        assert_eq!(demons.iter().find(|demon| demon.name == "terminal void").unwrap().position, 3);
+
+        let demons = overview_demons(&mut connection, Some(now - Duration::hours(12))).await.unwrap();
+
+        assert!(!demons.iter().any(|demon| demon.name == "terminal void"));
+    }
+}
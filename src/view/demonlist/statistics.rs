@@ -0,0 +1,113 @@
+//! Page with miscellaneous statistics about the list as a whole
+
+use crate::{config, model::demonlist::demon::Demon, state::PointercrateState, view::Page, ViewResult};
+use actix_web::{web::Query, HttpResponse};
+use actix_web_codegen::get;
+use maud::{html, Markup};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct StatisticsQuery {
+    /// Whether demons on the legacy list should be considered
+    #[serde(default)]
+    include_legacy: bool,
+}
+
+#[derive(Debug)]
+struct Statistics {
+    include_legacy: bool,
+
+    /// Demons that were verified, but never beaten by anyone else
+    unbeaten: Vec<Demon>,
+}
+
+#[get("/demonlist/statistics/")]
+pub async fn list_statistics(state: PointercrateState, query: Query<StatisticsQuery>) -> ViewResult<HttpResponse> {
+    let mut connection = state.connection().await?;
+
+    let unbeaten = Demon::unbeaten(query.include_legacy, config::extended_list_size(), &mut connection).await?;
+
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(
+        Statistics {
+            include_legacy: query.include_legacy,
+            unbeaten,
+        }
+        .render()
+        .0,
+    ))
+}
+
+impl Statistics {
+    fn unbeaten_panel(&self) -> Markup {
+        html! {
+            section.panel.fade {
+                div.underlined {
+                    h2 {
+                        "Verified, but never beaten"
+                    }
+                }
+                p {
+                    "Demons that nobody but their verifier has completed yet. "
+                    @if self.include_legacy {
+                        a.link href = "/demonlist/statistics/" { "Hide legacy demons" }
+                    }
+                    @else {
+                        a.link href = "/demonlist/statistics/?include_legacy=true" { "Include legacy demons" }
+                    }
+                }
+                ul {
+                    @for demon in &self.unbeaten {
+                        li {
+                            a href = {"/demonlist/permalink/" (demon.base.id) "/"} {
+                                "#" (demon.base.position) " - " (demon.base.name)
+                            }
+                            " (verified by " (demon.verifier.name) ")"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Page for Statistics {
+    fn title(&self) -> String {
+        "Statistics - Geometry Dash Demonlist".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Miscellaneous statistics about the Geometry Dash Demonlist".to_string()
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                "Statistics"
+                            }
+                        }
+                    }
+                    // Only shown if there is something to show, a list of no demons is not much of a statistic
+                    @if !self.unbeaten.is_empty() {
+                        (self.unbeaten_panel())
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![]
+    }
+}
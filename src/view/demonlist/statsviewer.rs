@@ -1,20 +1,35 @@
 use crate::{
+    config,
     extractor::auth::TokenAuth,
+    model::demonlist::player::PlayerStats,
     permissions::Permissions,
     state::PointercrateState,
     view::{filtered_paginator, Page},
     ViewResult,
 };
-use actix_web::HttpResponse;
+use actix_web::{web::Query, HttpResponse};
 use actix_web_codegen::get;
 use futures::StreamExt;
 use maud::{html, Markup, PreEscaped};
+use serde::Deserialize;
 use sqlx::query;
 use std::collections::HashMap;
 
 #[derive(Debug)]
 struct StatsViewer {
     heatmap: HeatMap,
+
+    /// The player whose stats should be displayed right away, if one was linked to
+    selected: Option<PlayerStats>,
+
+    /// The metrics left out of the stats viewer and the comparison, see [`config::hidden_stats`]
+    hidden_stats: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsViewerQuery {
+    /// The ID of the player to preselect
+    player: Option<i32>,
 }
 
 #[derive(Debug)]
@@ -84,7 +99,9 @@ impl HeatMap {
 }
 
 #[get("/demonlist/statsviewer/")]
-pub async fn stats_viewer(TokenAuth(user): TokenAuth, state: PointercrateState) -> ViewResult<HttpResponse> {
+pub async fn stats_viewer(
+    TokenAuth(user): TokenAuth, state: PointercrateState, query: Query<StatsViewerQuery>,
+) -> ViewResult<HttpResponse> {
     if !user.inner().has_permission(Permissions::Administrator) {
         user.inner().require_permissions(Permissions::ListHelper)?;
     }
@@ -105,9 +122,16 @@ pub async fn stats_viewer(TokenAuth(user): TokenAuth, state: PointercrateState)
         heatmap.insert(row.iso_country_code, row.cnt as i64);
     }
 
+    drop(stream);
+
+    // Fetching the stats here means the panel is already populated on first paint for shared links
+    let selected = super::linked_player(query.player, &state.hardest_demons, &mut connection).await?;
+
     Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(
         StatsViewer {
             heatmap: HeatMap { map: heatmap },
+            selected,
+            hidden_stats: config::hidden_stats(),
         }
         .render()
         .0,
@@ -156,7 +180,7 @@ impl Page for StatsViewer {
             }
             div.flex.m-center.container {
                 main.left {
-                    (stats_viewer2())
+                    (stats_viewer2(self.selected.as_ref(), &self.hidden_stats))
                 }
                 aside.right {
                     div.panel.fade {
@@ -180,105 +204,131 @@ impl Page for StatsViewer {
     }
 }
 
-fn stats_viewer2() -> Markup {
+/// The metrics shown in the head-to-head comparison, as pairs of (field in the comparison
+/// response, display name)
+static COMPARED_METRICS: [(&str, &str); 7] = [
+    ("score", "Demonlist score"),
+    ("rank", "Demonlist rank"),
+    ("beaten", "Demons completed"),
+    ("hardest", "Hardest demon"),
+    ("created", "List demons created"),
+    ("published", "List demons published"),
+    ("verified", "List demons verified"),
+];
+
+fn player_comparison(hidden: &[String]) -> Markup {
+    html! {
+        div#player-comparison style = "display: none" {
+            table style = "width: 100%; text-align: center" {
+                tbody {
+                    tr {
+                        th.blue {}
+                        th.blue#compare-a-name {}
+                        th.blue#compare-b-name {}
+                    }
+                    @for (field, display) in COMPARED_METRICS.iter().filter(|(field, _)| !hidden.iter().any(|hidden| hidden == field)) {
+                        tr data-metric = (field) {
+                            td {
+                                b { (display) }
+                            }
+                            td.compare-a {}
+                            td.compare-b {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The stats viewer panel. If a player is given, their stats are rendered right away (the remaining
+/// details are loaded by the stats viewer's JavaScript), otherwise the panel waits for a player to
+/// be selected
+fn stats_viewer2(selected: Option<&PlayerStats>, hidden: &[String]) -> Markup {
     html! {
-        section.panel.fade#statsviewer {
+        section.panel.fade#statsviewer data-player = (selected.map(|stats| stats.player.base.id.to_string()).unwrap_or_default()) {
             h2.underlined.pad {
                 "Stats Viewer - " span#current-nation {"International"}
             }
+            label.cb-container for = "compare-toggle" style = "margin-bottom: 10px" {
+                i { "Compare two players" }
+                input#compare-toggle type = "checkbox";
+                span.checkmark {}
+            }
+            label.cb-container for = "improved-toggle" style = "margin-bottom: 10px" {
+                i { "Most improved players (last 30 days)" }
+                input#improved-toggle type = "checkbox";
+                span.checkmark {}
+            }
+            // populated by the stats viewer once the toggle above is checked
+            div#most-improved style = "display: none" {
+                ul.selection-list {}
+            }
+            (super::ranking_sort_dropdown())
             div.flex.viewer {
                 (filtered_paginator("stats-viewer-pagination", "/api/v1/players/ranking/"))
-                p.viewer-welcome {
+                div#stats-viewer-compare style = "display: none" {
+                    (filtered_paginator("stats-viewer-compare-pagination", "/api/v1/players/ranking/"))
+                }
+                p.viewer-welcome style = (if selected.is_some() { "display: none" } else { "" }) {
                     "Click on a player's name on the left to get started!"
                 }
-                div.viewer-content {
+                div.viewer-content style = (if selected.is_some() { "display: block" } else { "" }) {
                     div {
                         div.flex.col {
-                            h3#player-name style = "font-size:1.4em; overflow: hidden" {}
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "List demons completed:"
-                                    }
-                                    br;
-                                    span#amount-beaten {}
-                                }
-                                span {
-                                    b {
-                                        "Legacy demons completed:"
-                                    }
-                                    br;
-                                    span#amount-legacy {}
-                                }
-                                span {
-                                    b {
-                                        "Demonlist score:"
-                                    }
-                                    br;
-                                    span#score {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Demonlist rank:"
-                                    }
-                                    br;
-                                    span#rank {}
-                                }
-                                span {
-                                    b {
-                                        "Hardest demon:"
-                                    }
-                                    br;
-                                    span#hardest {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Demons completed:"
-                                    }
-                                    br;
-                                    span#beaten {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "List demons created:"
-                                    }
-                                    br;
-                                    span#created {}
-                                }
-                                span {
-                                    b {
-                                        "List demons published:"
-                                    }
-                                    br;
-                                    span#published {}
-                                }
-                                span {
-                                    b {
-                                        "List demons verified:"
-                                    }
-                                    br;
-                                    span#verified {}
-                                }
-                            }
-                            div.stats-container.flex.space {
-                                span {
-                                    b {
-                                        "Progress on:"
-                                    }
-                                    br;
-                                    span#progress {}
-                                }
-                            }
+                            (super::selected_player_name(selected))
+                            (super::stats_viewer_rows(hidden, |metric| super::preselected_stat(metric, selected)))
                         }
                     }
                 }
             }
+            (player_comparison(hidden))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::stats_viewer2;
+    use crate::model::demonlist::player::{HardestDemonCache, PlayerStats};
+    use std::time::Duration;
+
+    #[actix_rt::test]
+    async fn test_preselected_player() {
+        let mut connection = crate::test::test_setup().await;
+
+        let player_id = sqlx::query!("SELECT id FROM players WHERE name = 'Aeon Air'")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap()
+            .id;
+        let stats = PlayerStats::by_id(player_id, &HardestDemonCache::new(Duration::from_secs(60)), &mut connection)
+            .await
+            .unwrap();
+
+        let markup = stats_viewer2(Some(&stats), &[]).into_string();
+
+        assert!(markup.contains(&format!(r#"data-player="{}""#, player_id)));
+        assert!(markup.contains("Aeon Air"));
+        assert!(markup.contains(r#"href="/demonlist/permalink/"#));
+        assert!(markup.contains(r#"class="viewer-content" style="display: block""#));
+
+        // without a preselected player, nothing is rendered until one is clicked
+        let markup = stats_viewer2(None, &[]).into_string();
+
+        assert!(markup.contains(r#"data-player="""#));
+        assert!(!markup.contains("Aeon Air"));
+    }
+
+    #[test]
+    fn test_hidden_stats() {
+        let hidden = vec!["score".to_string(), "hardest".to_string()];
+        let markup = stats_viewer2(None, &hidden).into_string();
+
+        assert!(!markup.contains(r#"id="score""#));
+        assert!(!markup.contains(r#"data-metric="score""#));
+        assert!(!markup.contains(r#"data-metric="hardest""#));
+        assert!(markup.contains(r#"id="rank""#));
+        assert!(markup.contains(r#"data-metric="rank""#));
+    }
+}
@@ -0,0 +1,501 @@
+//! Record submission for clients without JavaScript
+//!
+//! Normally, the submission form on the overview is submitted via the API by `demonlist.v2.2.js`.
+//! Without JavaScript, the browser instead posts the plain form here. After processing it, we
+//! redirect back to the overview, passing along the outcome as a flash message stored in a
+//! short-lived cookie, which the overview displays (and clears) on its next render.
+//!
+//! Since the form is submitted without any credentials, cross-site request forgery cannot be
+//! prevented via the usual CSRF tokens. Instead, we only accept forms posted from our own pages, as
+//! indicated by the `Origin` (or, for older browsers, `Referer`) header.
+//!
+//! The form loads [`form_opened`] as an image, which tells us when it was opened, so that forms
+//! submitted implausibly fast can be discarded (see the [antibot](crate::antibot) module).
+
+use crate::{
+    antibot::{self, BotCheck, FORM_OPENED_COOKIE},
+    cistring::{CiStr, CiString},
+    config,
+    error::PointercrateError,
+    extractor::ip::Ip,
+    metrics,
+    model::demonlist::{
+        demon::MinimalDemon,
+        record::{FullRecord, RecordStatus, Submission},
+        submitter::Submitter,
+    },
+    ratelimit::Ratelimits,
+    state::PointercrateState,
+    Result, ViewResult,
+};
+use actix_web::{
+    http::{
+        header::{CACHE_CONTROL, LOCATION, ORIGIN, REFERER},
+        Cookie,
+    },
+    web::Form,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use actix_web_codegen::{get, post};
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use std::net::IpAddr;
+use url::Url;
+
+/// Name of the cookie the outcome of a submission is passed back to the overview in
+pub const FLASH_COOKIE: &str = "submission_flash";
+
+/// The fields of the submission form. Everything is optional and kept as text, so that malformed
+/// input results in a flash message instead of a bare error response
+#[derive(Debug, Deserialize)]
+pub struct SubmissionForm {
+    /// The demon's ID (as set by the JavaScript dropdown) or its name (as typed in without it)
+    #[serde(default)]
+    demon: String,
+
+    #[serde(default)]
+    player: String,
+
+    #[serde(default)]
+    progress: String,
+
+    /// Optional, left empty if the holder does not want to log their attempts
+    #[serde(default)]
+    attempts: String,
+
+    #[serde(default)]
+    video: String,
+
+    #[serde(default)]
+    note: String,
+
+    /// The [honeypot](crate::antibot::HONEYPOT_FIELD), hidden from humans
+    #[serde(default)]
+    homepage: String,
+
+    /// The number of seconds since the form was opened, as derived from the [`FORM_OPENED_COOKIE`].
+    /// Not part of the form itself
+    #[serde(skip)]
+    fill_time: Option<i64>,
+}
+
+/// The outcome of a record submission made via the fallback form
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub enum SubmissionFlash {
+    Success,
+    Error(String),
+}
+
+impl SubmissionFlash {
+    // Error messages can contain all kinds of characters that are not allowed in cookies, so they
+    // are base64 encoded
+    fn to_cookie_value(&self) -> String {
+        match self {
+            SubmissionFlash::Success => "success".to_string(),
+            SubmissionFlash::Error(message) => format!("error:{}", base64::encode_config(message, base64::URL_SAFE_NO_PAD)),
+        }
+    }
+
+    pub fn from_cookie_value(value: &str) -> Option<SubmissionFlash> {
+        if value == "success" {
+            return Some(SubmissionFlash::Success)
+        }
+
+        let message = base64::decode_config(value.strip_prefix("error:")?, base64::URL_SAFE_NO_PAD).ok()?;
+
+        String::from_utf8(message).ok().map(SubmissionFlash::Error)
+    }
+
+    /// The cookie the overview uses to clear the flash message once it has been displayed
+    pub fn removal_cookie() -> Cookie<'static> {
+        Cookie::build(FLASH_COOKIE, "").path("/").finish()
+    }
+
+    /// Redirects back to the overview (with the submission panel open), setting this flash message
+    fn redirect(&self) -> HttpResponse {
+        let cookie = Cookie::build(FLASH_COOKIE, self.to_cookie_value())
+            .http_only(true)
+            .path("/")
+            .finish();
+
+        // the flash message is specific to this client, and must not end up in any shared cache
+        HttpResponse::SeeOther()
+            .header(LOCATION, "/demonlist/?submitter=true")
+            .header(CACHE_CONTROL, "private")
+            .cookie(cookie)
+            .finish()
+    }
+}
+
+impl SubmissionForm {
+    fn bot_check(&self) -> BotCheck {
+        BotCheck {
+            homepage: Some(self.homepage.clone()),
+            fill_time: self.fill_time,
+        }
+    }
+
+    /// Validates the form and turns it into a [`Submission`]. Further validation happens in
+    /// [`FullRecord::create_ratelimited`], exactly as it does for submissions made via the API
+    async fn into_submission(self, connection: &mut PgConnection) -> Result<Submission> {
+        let demon = self.demon.trim();
+        let player = self.player.trim();
+        let video = self.video.trim();
+
+        if demon.is_empty() {
+            return Err(PointercrateError::BadRequest {
+                message: "Please select the demon the record was made on".to_string(),
+            })
+        }
+
+        if player.is_empty() {
+            return Err(PointercrateError::BadRequest {
+                message: "Please enter the name of the record's holder".to_string(),
+            })
+        }
+
+        // only members of the list team can submit records without video (unless the list does not
+        // require videos), and they use the regular form
+        if video.is_empty() && config::video_required() {
+            return Err(PointercrateError::BadRequest {
+                message: "Please provide a video of the record".to_string(),
+            })
+        }
+
+        let progress = self.progress.trim().parse().map_err(|_| {
+            PointercrateError::BadRequest {
+                message: "The progress must be a number between 0 and 100".to_string(),
+            }
+        })?;
+
+        let attempts = match self.attempts.trim() {
+            "" => None,
+            attempts =>
+                Some(attempts.parse().map_err(|_| {
+                    PointercrateError::BadRequest {
+                        message: "The number of attempts must be a whole number".to_string(),
+                    }
+                })?),
+        };
+
+        let demon = match demon.parse() {
+            Ok(id) => id,
+            Err(_) => MinimalDemon::by_name(CiStr::from_str(demon), connection).await?.id,
+        };
+
+        Ok(Submission {
+            progress,
+            player: CiString(player.to_string()),
+            demon,
+            video: Some(video.to_string()).filter(|video| !video.is_empty()),
+            status: RecordStatus::Submitted,
+            note: Some(self.note),
+            attempts,
+        })
+    }
+
+    /// Processes the submission, returning `None` if it was silently discarded because it
+    /// appears to come from a bot
+    async fn submit(self, ip: IpAddr, ratelimits: &Ratelimits, connection: &mut PgConnection) -> Result<Option<FullRecord>> {
+        if self.bot_check().is_bot() {
+            info!("Discarding record submission from {} that appears to come from a bot", ip);
+
+            return Ok(None)
+        }
+
+        let ratelimiter = ratelimits.prepare(ip);
+        let submitter = Submitter::by_ip_or_create(ip, connection, Some(ratelimiter)).await?;
+        let submission = self.into_submission(connection).await?;
+
+        FullRecord::create_ratelimited(submitter, submission, connection, ratelimiter)
+            .await
+            .map(Some)
+    }
+}
+
+/// Checks whether the given request was made from one of our own pages, by comparing the host the
+/// request was made to with the one given in its `Origin` header (or, if there is none, its `Referer`
+/// header). Requests without either are rejected, as browsers send at least one of them for form
+/// submissions
+fn is_same_origin(request: &HttpRequest) -> bool {
+    let source = match request.headers().get(ORIGIN).or_else(|| request.headers().get(REFERER)) {
+        Some(source) => source,
+        None => return false,
+    };
+
+    let source = match source.to_str().ok().and_then(|source| Url::parse(source).ok()) {
+        Some(source) => source,
+        None => return false,
+    };
+
+    let source_host = match (source.host_str(), source.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return false,
+    };
+
+    source_host.eq_ignore_ascii_case(request.connection_info().host())
+}
+
+/// Loaded as an (invisible) image by the submission form, records the time at which the form was
+/// opened in a cookie sent along with the form once it is submitted
+#[get("/demonlist/submit/opened/")]
+pub async fn form_opened(state: PointercrateState) -> HttpResponse {
+    let cookie = Cookie::build(FORM_OPENED_COOKIE, antibot::form_opened_token(&state.secret))
+        .http_only(true)
+        .path("/demonlist/submit/")
+        .finish();
+
+    HttpResponse::NoContent().header(CACHE_CONTROL, "no-store").cookie(cookie).finish()
+}
+
+#[post("/demonlist/submit/")]
+pub async fn submit(
+    request: HttpRequest, Ip(ip): Ip, state: PointercrateState, form: Form<SubmissionForm>,
+) -> ViewResult<HttpResponse> {
+    if !is_same_origin(&request) {
+        warn!("Rejecting record submission form from {} posted from a foreign site", ip);
+
+        return Err(PointercrateError::Forbidden.into())
+    }
+
+    let mut form = form.into_inner();
+
+    form.fill_time = request
+        .cookie(FORM_OPENED_COOKIE)
+        .and_then(|cookie| antibot::fill_time_since(cookie.value(), &state.secret));
+
+    let mut connection = state.transaction().await?;
+
+    let result = match form.submit(ip, &state.ratelimits, &mut connection).await {
+        // bots are told everything went fine
+        Ok(None) => {
+            metrics::observe_discarded_submission();
+
+            return Ok(SubmissionFlash::Success.redirect())
+        },
+        Ok(Some(record)) => Ok(record),
+        Err(error) => Err(error),
+    };
+
+    metrics::observe_submission(&result);
+
+    let flash = match result {
+        Ok(record) => {
+            connection.commit().await?;
+
+            actix_rt::spawn(record.validate(state));
+
+            SubmissionFlash::Success
+        },
+        // Errors in the 5xx range indicate that something went wrong on our end, the submitter
+        // cannot do anything about those
+        Err(error) if error.error_code() >= 50000 => return Err(error.into()),
+        Err(error) => SubmissionFlash::Error(error.to_string()),
+    };
+
+    Ok(flash.redirect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{form_opened, submit, SubmissionFlash, SubmissionForm, FLASH_COOKIE, FORM_OPENED_COOKIE};
+    use crate::{
+        model::demonlist::record::{FullRecord, Progress, RecordStatus},
+        ratelimit::Ratelimits,
+    };
+    use actix_web::{
+        dev::ServiceResponse,
+        http::{
+            header::{CACHE_CONTROL, CONTENT_TYPE, HOST, LOCATION, ORIGIN, REFERER, SET_COOKIE},
+            Cookie, StatusCode,
+        },
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+    use sqlx::PgConnection;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn form(demon: &str, progress: &str) -> SubmissionForm {
+        SubmissionForm {
+            demon: demon.to_string(),
+            player: "Samifying".to_string(),
+            progress: progress.to_string(),
+            attempts: String::new(),
+            video: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            note: String::new(),
+            homepage: String::new(),
+            fill_time: None,
+        }
+    }
+
+    #[test]
+    fn test_flash_cookie_roundtrip() {
+        let flash = SubmissionFlash::Error("Invalid progress; 50% & more required!".to_string());
+
+        assert_eq!(SubmissionFlash::from_cookie_value(&flash.to_cookie_value()), Some(flash));
+        assert_eq!(SubmissionFlash::from_cookie_value("success"), Some(SubmissionFlash::Success));
+        assert_eq!(SubmissionFlash::from_cookie_value("garbage"), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_form_submission() {
+        let mut connection = crate::test::test_setup().await;
+        let ratelimits = Ratelimits::initialize();
+        let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+        // demons can be given by name, as without JavaScript there is nothing to turn them into IDs
+        let record = form("taraturusus", "95")
+            .submit(ip, &ratelimits, &mut connection)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(record.progress, Progress::from(95));
+        assert_eq!(record.status, RecordStatus::Submitted);
+        assert_eq!(record.demon.name.as_str(), "taraturusus");
+        assert_eq!(
+            FullRecord::by_id(record.id, &mut connection).await.unwrap().player.name.as_str(),
+            "Samifying"
+        );
+
+        assert_eq!(record.attempts, None);
+
+        // the same server-side validation as for the API applies
+        assert!(form("taraturusus", "20").submit(ip, &ratelimits, &mut connection).await.is_err());
+        assert!(form("taraturusus", "lots").submit(ip, &ratelimits, &mut connection).await.is_err());
+
+        let mut with_attempts = form("terminal void", "80");
+        with_attempts.attempts = "4321".to_string();
+
+        let record = with_attempts.submit(ip, &ratelimits, &mut connection).await.unwrap().unwrap();
+
+        assert_eq!(record.attempts, Some(4321));
+
+        for attempts in &["-1", "many"] {
+            let mut invalid = form("terminal void", "90");
+            invalid.attempts = attempts.to_string();
+
+            assert!(invalid.submit(ip, &ratelimits, &mut connection).await.is_err());
+        }
+    }
+
+    /// Posts a submission form for a demon that does not exist (so that nothing is ever committed)
+    /// to the handler, with the given header indicating where the form was posted from
+    async fn post_form(source: Option<(&str, &str)>) -> ServiceResponse {
+        post_form_with_cookie(source, None).await
+    }
+
+    async fn post_form_with_cookie(source: Option<(&str, &str)>, cookie: Option<Cookie<'static>>) -> ServiceResponse {
+        let mut app = init_service(App::new().app_data(crate::test::test_state().await).service(submit)).await;
+        let body = "demon=Bloodbath&player=Samifying&progress=95&video=https%3A%2F%2Fyoutu.be%2FdQw4w9WgXcQ";
+
+        let mut request = TestRequest::post()
+            .uri("/demonlist/submit/")
+            .peer_addr("10.0.0.4:443".parse().unwrap())
+            .header(HOST, "pointercrate.test")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(body);
+
+        if let Some((header, value)) = source {
+            request = request.header(header, value);
+        }
+
+        if let Some(cookie) = cookie {
+            request = request.cookie(cookie);
+        }
+
+        call_service(&mut app, request.to_request()).await
+    }
+
+    #[actix_rt::test]
+    async fn test_cross_site_submission_rejected() {
+        for source in &[None, Some((ORIGIN.as_str(), "https://evil.example")), Some((REFERER.as_str(), "https://evil.example/"))] {
+            let response = post_form(*source).await;
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            assert!(response.headers().get(SET_COOKIE).is_none());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_submission_flash() {
+        for source in &[(ORIGIN.as_str(), "https://pointercrate.test"), (REFERER.as_str(), "https://pointercrate.test/demonlist/")] {
+            let response = post_form(Some(*source)).await;
+
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+            assert_eq!(response.headers()[LOCATION], "/demonlist/?submitter=true");
+            assert_eq!(response.headers()[CACHE_CONTROL], "private");
+
+            let flash = response
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == FLASH_COOKIE)
+                .and_then(|cookie| SubmissionFlash::from_cookie_value(cookie.value()));
+
+            assert!(matches!(flash, Some(SubmissionFlash::Error(_))));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_form_opened_cookie() {
+        let mut app = init_service(App::new().app_data(crate::test::test_state().await).service(form_opened)).await;
+        let response = call_service(&mut app, TestRequest::get().uri("/demonlist/submit/opened/").to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-store");
+
+        let cookie = response
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == FORM_OPENED_COOKIE)
+            .unwrap()
+            .into_owned();
+
+        assert_eq!(cookie.path(), Some("/demonlist/submit/"));
+
+        // Posting the form right after opening it is too fast for a human, so instead of being told that
+        // the demon does not exist, the bot is told everything went fine
+        let response = post_form_with_cookie(Some((ORIGIN.as_str(), "https://pointercrate.test")), Some(cookie)).await;
+        let flash = response
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == FLASH_COOKIE)
+            .and_then(|cookie| SubmissionFlash::from_cookie_value(cookie.value()));
+
+        assert_eq!(flash, Some(SubmissionFlash::Success));
+    }
+
+    async fn record_count(connection: &mut PgConnection) -> i64 {
+        sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM records"#)
+            .fetch_one(connection)
+            .await
+            .unwrap()
+            .count
+    }
+
+    #[actix_rt::test]
+    async fn test_bot_submissions_discarded() {
+        let mut connection = crate::test::test_setup().await;
+        let ratelimits = Ratelimits::initialize();
+        let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3));
+
+        let before = record_count(&mut connection).await;
+
+        let mut honeypot = form("taraturusus", "95");
+        honeypot.homepage = "https://cheap-pills.example".to_string();
+
+        assert!(honeypot.submit(ip, &ratelimits, &mut connection).await.unwrap().is_none());
+
+        // nothing was stored, and it does not count towards the ratelimit
+        assert_eq!(record_count(&mut connection).await, before);
+
+        assert!(form("taraturusus", "95")
+            .submit(ip, &ratelimits, &mut connection)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(record_count(&mut connection).await, before + 1);
+    }
+}
@@ -0,0 +1,160 @@
+//! Page shown when a demon permalink does not resolve to any demon
+
+use crate::{
+    cistring::CiString,
+    model::demonlist::demon::{DemonSearch, SearchResult},
+    view::Page,
+    Result,
+};
+use actix_web::HttpResponse;
+use maud::{html, Markup};
+use sqlx::PgConnection;
+
+#[derive(Debug)]
+struct UnknownDemon {
+    /// The ID or alias the permalink was requested for
+    identifier: String,
+
+    /// Whether a demon with this ID existed at some point, but has since been deleted
+    deleted: bool,
+
+    /// Existing demons whose name (or an alias thereof) resembles the identifier
+    suggestions: Vec<SearchResult>,
+}
+
+impl UnknownDemon {
+    /// Gathers what we know about the target of a permalink that did not resolve to any demon
+    ///
+    /// Demons that were deleted can be told apart from ones that never existed via the audit log,
+    /// as the ID of every demon ever added is recorded in `demon_additions`.
+    async fn load(identifier: &str, connection: &mut PgConnection) -> Result<UnknownDemon> {
+        let (deleted, suggestions) = match identifier.parse::<i32>() {
+            Ok(id) => {
+                let deleted = sqlx::query!(r#"SELECT EXISTS (SELECT 1 FROM demon_additions WHERE id = $1) AS "deleted!""#, id)
+                    .fetch_one(connection)
+                    .await?
+                    .deleted;
+
+                // A numeric ID says nothing about which demon was meant, so there is nothing to search for
+                (deleted, Vec::new())
+            },
+            Err(_) => {
+                let search = DemonSearch {
+                    q: Some(CiString(identifier.to_string())),
+                    tag: None,
+                    verified_on: None,
+                };
+
+                (false, search.search(connection).await?)
+            },
+        };
+
+        Ok(UnknownDemon {
+            identifier: identifier.to_string(),
+            deleted,
+            suggestions,
+        })
+    }
+}
+
+/// Renders the `404 NOT FOUND` page for a permalink whose target does not exist
+pub(super) async fn unknown_demon(identifier: &str, connection: &mut PgConnection) -> Result<HttpResponse> {
+    let page = UnknownDemon::load(identifier, connection).await?;
+
+    Ok(HttpResponse::NotFound().content_type("text/html; charset=utf-8").body(page.render().0))
+}
+
+impl Page for UnknownDemon {
+    fn title(&self) -> String {
+        "Demon not found - Geometry Dash Demonlist".to_string()
+    }
+
+    fn description(&self) -> String {
+        format!("No demon could be found for the permalink '{}'", self.identifier)
+    }
+
+    fn scripts(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn stylesheets(&self) -> Vec<&str> {
+        vec!["css/demonlist.v2.1.css", "css/sidebar.css"]
+    }
+
+    fn body(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        div.underlined {
+                            h1 {
+                                "Demon not found"
+                            }
+                        }
+                        p {
+                            @if self.deleted {
+                                "The demon this link pointed to has been removed from the list."
+                            }
+                            @else {
+                                "There is no demon with the ID or alias '" (self.identifier) "'."
+                            }
+                        }
+                        @if !self.suggestions.is_empty() {
+                            p {
+                                "Maybe you meant one of these:"
+                            }
+                            ul#suggestions {
+                                @for demon in &self.suggestions {
+                                    li {
+                                        a href = {"/demonlist/permalink/" (demon.id) "/"} {
+                                            "#" (demon.position) " - " (demon.name)
+                                        }
+                                        " by " (demon.publisher)
+                                    }
+                                }
+                            }
+                        }
+                        p {
+                            a.link href = "/demonlist/" { "Back to the demonlist" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn head(&self) -> Vec<Markup> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnknownDemon;
+
+    #[actix_rt::test]
+    async fn test_suggestions() {
+        let mut connection = crate::test::test_setup().await;
+
+        let unknown = UnknownDemon::load("trichotom", &mut connection).await.unwrap();
+
+        assert!(!unknown.deleted);
+        assert_eq!(
+            unknown.suggestions.iter().map(|demon| demon.name.as_str()).collect::<Vec<_>>(),
+            vec!["Trichotomy"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_deleted_demon() {
+        let mut connection = crate::test::test_setup().await;
+
+        sqlx::query!("INSERT INTO demon_additions (userid, id) VALUES (0, 123456)")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        assert!(UnknownDemon::load("123456", &mut connection).await.unwrap().deleted);
+        assert!(!UnknownDemon::load("123457", &mut connection).await.unwrap().deleted);
+    }
+}
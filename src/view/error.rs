@@ -1,15 +1,21 @@
 use super::Page;
-use crate::error::PointercrateError;
+use crate::{error::PointercrateError, middleware::request_id::current_request_id};
 use maud::{html, Markup, PreEscaped};
 
 #[derive(Debug)]
 pub struct ErrorPage<'a> {
     error: &'a PointercrateError,
+
+    /// The ID of the request that caused the error, to be quoted in bug reports
+    request_id: Option<String>,
 }
 
 impl ErrorPage<'_> {
     pub fn new(error: &PointercrateError) -> ErrorPage {
-        ErrorPage { error }
+        ErrorPage {
+            error,
+            request_id: current_request_id(),
+        }
     }
 }
 
@@ -63,6 +69,11 @@ impl Page for ErrorPage<'_> {
                         "Contact us!"
                     }
                 }
+                @if let Some(ref request_id) = self.request_id {
+                    p style="text-align: center; font-size: .7em" {
+                        "Please include the request ID " code { (request_id) } " in your report."
+                    }
+                }
             }
         }
     }
@@ -6,7 +6,6 @@ import {
   tooShort,
   post,
   Output,
-  typeMismatch,
   del,
   displayError,
 } from "../modules/form.mjs";
@@ -127,9 +126,6 @@ function setupEditAccount() {
   );
 
   editYoutubeForm.addValidators({
-    "edit-yt": {
-      "Please enter a valid URL": typeMismatch,
-    },
     "auth-yt": {
       "Password required": valueMissing,
       "Password too short. It needs to be at least 10 characters long.": tooShort,
@@ -138,6 +134,7 @@ function setupEditAccount() {
 
   editYoutubeForm.addErrorOverride(40100, "auth-yt");
   editYoutubeForm.addErrorOverride(42225, "edit-yt");
+  editYoutubeForm.addErrorOverride(42226, "edit-yt");
 
   let changePasswordForm = setupFormDialogEditor(
     new ProfileEditorBackend(document.querySelector("#auth-pw input")), // not pretty, but oh well
@@ -21,7 +21,7 @@ import {
 import {
   initializeRecordSubmitter,
   generateRecord,
-  embedVideo, PlayerSelectionDialog,
+  embedVideo, timestampedVideo, PlayerSelectionDialog,
 } from "../modules/demonlist.mjs";
 
 export let recordManager;
@@ -39,6 +39,7 @@ class RecordManager extends Paginator {
 
     this._video = document.getElementById("record-video");
     this._video_link = document.getElementById("record-video-link");
+    this._video_dead = document.getElementById("record-video-dead");
     this._id = document.getElementById("record-id");
     this._demon = document.getElementById("record-demon");
     this._holder = document.getElementById("record-holder");
@@ -96,13 +97,14 @@ class RecordManager extends Paginator {
       "record-progress-edit": {
         "Record progress cannot be negative": rangeUnderflow,
         "Record progress cannot be larger than 100%": rangeOverflow,
-        "Record progress must be a valid integer": badInput,
-        "Record progress mustn't be a decimal": stepMismatch,
+        "Record progress must be a valid number": badInput,
+        "Record progress has too many decimal places": stepMismatch,
         "Please enter a progress value": valueMissing,
       },
     });
 
     form.addErrorOverride(42215, "record-progress-edit");
+    form.addErrorOverride(42243, "record-progress-edit");
   }
 
   initVideoDialog() {
@@ -151,13 +153,16 @@ class RecordManager extends Paginator {
     }
 
     if(this.currentObject.video !== undefined) {
-      this._video_link.href = this.currentObject.video;
+      // Deep-link to where the completion starts for stream VODs and the like
+      this._video_link.href = timestampedVideo(this.currentObject.video, this.currentObject.video_timestamp);
       this._video_link.innerHTML = this.currentObject.video;
       this._video_link.style.display = "initial";
     } else {
       this._video_link.style.display = "none";
     }
 
+    this._video_dead.style.display = this.currentObject.video_dead ? "block" : "none";
+
     this._id.innerHTML = this.currentObject.id;
     this._demon.innerHTML =
       this.currentObject.demon.name + " (" + this.currentObject.demon.id + ")";
@@ -219,7 +224,7 @@ function createNoteHtml(note, csrfToken) {
   b.innerHTML = "Record Note #" + note.id;
 
   let i = document.createElement("i");
-  i.innerHTML = note.content;
+  i.innerHTML = note.content_html; // rendered and sanitized server-side
 
   let furtherInfo = document.createElement("i");
   furtherInfo.style.fontSize = "80%";
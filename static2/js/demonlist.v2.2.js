@@ -2,6 +2,7 @@ import {
   initializeRecordSubmitter, initializeTimeMachine,
   StatsViewer,
 } from "./modules/demonlist.mjs";
+import {displayError} from "./modules/form.mjs";
 
 $(document).ready(function () {
   initializePositionChart();
@@ -10,7 +11,12 @@ $(document).ready(function () {
 
   window.statsViewer = new StatsViewer(document.getElementById("statsviewer"));
 
-  if(window.location.toString().includes("statsviewer=true"))
+  // Players can be linked to via `?player=<id>`, in which case the server already rendered their stats
+  let linkedPlayer = window.statsViewer.html.dataset.player;
+
+  if(linkedPlayer)
+    window.statsViewer.initialize().then(() => window.statsViewer.selectArbitrary(linkedPlayer).catch(displayError(window.statsViewer)));
+  else if(window.location.toString().includes("statsviewer=true"))
     window.statsViewer.initialize();
   else
     document
@@ -32,6 +32,25 @@ export function embedVideo(video) {
   }
 }
 
+/**
+ * Creates a link to the given video that starts playback at the given point in time (in seconds).
+ *
+ * See pointercrate::video::timestamped for the server side version of this
+ */
+export function timestampedVideo(video, seconds) {
+  if (!video || !seconds) return video;
+
+  if (video.startsWith("https://www.youtube")) {
+    return video + "&t=" + seconds + "s";
+  }
+
+  if (video.startsWith("https://www.twitch")) {
+    return video + "?t=" + Math.floor(seconds / 3600) + "h" + Math.floor((seconds % 3600) / 60) + "m" + (seconds % 60) + "s";
+  }
+
+  return video;
+}
+
 export function initializeTimeMachine() {
   let formHtml = document.getElementById("time-machine-form");
   
@@ -86,10 +105,26 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
   var demon = submissionForm.input("id_demon");
   var player = submissionForm.input("id_player");
   var progress = submissionForm.input("id_progress");
+  var attempts = submissionForm.input("id_attempts");
   var video = submissionForm.input("id_video");
+  var note = submissionForm.input("submit-note");
+
+  // Submitting within seconds of the form being shown gives away bots, see the antibot module.
+  // Measured here instead of being embedded into the form, as the overview page is cached
+  var shownAt = Date.now();
 
   demon.addValidator(input => input.dropdown.selected !== undefined, "Please specify a demon");
 
+  // Submissions need to meet the selected demon's requirement (which is 100% for extended demons).
+  // List mods adding approved records directly are exempt from the latter, so leave it to the server
+  if (!submitApproved) {
+    demon.dropdown.addEventListener(selected => {
+      let entry = demon.dropdown.html.querySelector("li[data-value='" + selected + "']");
+
+      progress.input.min = entry.dataset.requirement;
+    });
+  }
+
   let holderSelector = new PlayerSelectionDialog("submission-holder-dialog");
   document.getElementById("record-submitter-holder-pen").addEventListener('click', () => holderSelector.open().then(data => player.value = data.player));
 
@@ -100,13 +135,19 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
   );
 
   progress.addValidator(valueMissing, "Please specify the record's progress");
-  progress.addValidator(rangeUnderflow, "Record progress cannot be negative");
+  progress.addValidator(rangeUnderflow, () =>
+    progress.input.min > 0 ? "Record progress needs to be at least " + progress.input.min + "% for this demon" : "Record progress cannot be negative"
+  );
   progress.addValidator(
     rangeOverflow,
     "Record progress cannot be larger than 100%"
   );
-  progress.addValidator(badInput, "Record progress must be a valid integer");
-  progress.addValidator(stepMismatch, "Record progress mustn't be a decimal");
+  progress.addValidator(badInput, "Record progress must be a valid number");
+  progress.addValidator(stepMismatch, "Record progress has too many decimal places");
+
+  attempts.addValidator(rangeUnderflow, "The number of attempts cannot be negative");
+  attempts.addValidator(badInput, "The number of attempts must be a valid number");
+  attempts.addValidator(stepMismatch, "The number of attempts must be a whole number");
 
   video.addValidator(
     valueMissing,
@@ -119,6 +160,8 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
     let data = submissionForm.serialize();
     let headers = {};
 
+    data.fill_time = Math.floor((Date.now() - shownAt) / 1000);
+
     if (submitApproved) {
       data.status = "approved";
       headers["X-CSRF-TOKEN"] = csrf;
@@ -127,6 +170,7 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
       .then(() => {
         submissionForm.setSuccess("Record successfully submitted");
         submissionForm.clear();
+        shownAt = Date.now();
         gtag('event', 'record-submit-success', {'event-category': 'demonlist'});
       })
       .catch((response) =>  {
@@ -139,14 +183,22 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
             break;
           case 42215:
           case 42220:
+          case 42243:
             progress.errorText = response.data.message;
             break;
           case 42222:
           case 42223:
           case 42224:
           case 42225:
+          case 42244:
             video.errorText = response.data.message;
             break;
+          case 42245:
+            note.errorText = response.data.message;
+            break;
+          case 42247:
+            attempts.errorText = response.data.message;
+            break;
           default:
             submissionForm.setError(response.data.message)
         }
@@ -155,6 +207,28 @@ export function initializeRecordSubmitter(csrf = null, submitApproved = false) {
   });
 }
 
+/**
+ * Looks up the element displaying a metric in the stats viewer. Metrics can be hidden via the
+ * `HIDDEN_STATS` configuration, in which case a detached element is returned, so that filling it in
+ * is a no-op
+ *
+ * @param {String} id The id of the element
+ */
+function statsViewerElement(id) {
+  return document.getElementById(id) || document.createElement("span");
+}
+
+function createSubdivisionItem(isoCode, name) {
+  let li = document.createElement("li");
+
+  li.classList.add("white", "hover");
+  li.dataset.value = isoCode;
+  li.dataset.display = name;
+  li.textContent = name;
+
+  return li;
+}
+
 export class StatsViewer extends FilteredPaginator {
   /**
    * Constructs a new StatsViewer
@@ -165,7 +239,9 @@ export class StatsViewer extends FilteredPaginator {
     super(
       "stats-viewer-pagination",
       generateStatsViewerPlayer,
-      "name_contains"
+      "name_contains",
+      // nation preselected by the server (e.g. based on geolocation)
+      html.dataset.nation ? { nation: html.dataset.nation } : {}
     );
 
     // different from pagination endpoint here!
@@ -178,33 +254,109 @@ export class StatsViewer extends FilteredPaginator {
     );
 
     this._name = document.getElementById("player-name");
-    this._created = document.getElementById("created");
-    this._beaten = document.getElementById("beaten");
-    this._verified = document.getElementById("verified");
-    this._published = document.getElementById("published");
-    this._hardest = document.getElementById("hardest");
-    this._score = document.getElementById("score");
-    this._rank = document.getElementById("rank");
-    this._amountBeaten = document.getElementById("amount-beaten");
-    this._amountLegacy = document.getElementById("amount-legacy");
+    this._created = statsViewerElement("created");
+    this._beaten = statsViewerElement("beaten");
+    this._verified = statsViewerElement("verified");
+    this._published = statsViewerElement("published");
+    this._hardest = statsViewerElement("hardest");
+    this._score = statsViewerElement("score");
+    this._rank = statsViewerElement("rank");
+    this._amountBeaten = statsViewerElement("amount-beaten");
+    this._amountLegacy = statsViewerElement("amount-legacy");
+    this._attempts = statsViewerElement("attempts");
     this._welcome = html.getElementsByClassName("viewer-welcome")[0];
-    this._progress = document.getElementById("progress");
+    this._progress = statsViewerElement("progress");
     this._content = html.getElementsByClassName("viewer-content")[0];
 
     try {
       this.dropdown = new Dropdown(
-          html.getElementsByClassName("dropdown-menu")[0]
+          html.querySelector(".dropdown-menu:not(#ranking-sort)")
       );
       this.dropdown.addEventListener((selected) => {
-        if (selected == "International") {
-          this.updateQueryData("nation", undefined);
-        } else {
-          this.updateQueryData("nation", selected);
+        // nation and continent filters are mutually exclusive, so both need to be updated at once
+        let queryData = Object.assign({}, this.queryData);
+
+        delete queryData.nation;
+        delete queryData.continent;
+        delete queryData.subdivision;
+
+        if (selected.startsWith("continent:")) {
+          queryData.continent = selected.substring("continent:".length);
+        } else if (selected != "International") {
+          queryData.nation = selected;
         }
+
+        this.setQueryData(queryData);
+        this.updateSubdivisions(queryData.nation);
       });
     }catch (e) {
       
     }
+
+    this._subdivisions = document.getElementById("subdivision-dropdown");
+
+    let sortDropdown = document.getElementById("ranking-sort");
+
+    if (sortDropdown) {
+      new Dropdown(sortDropdown).addEventListener((selected) =>
+        this.updateQueryData("sort", selected === "score" ? undefined : selected)
+      );
+    }
+
+    if (html.dataset.nation) this.updateSubdivisions(html.dataset.nation);
+  }
+
+  /**
+   * Populates the subdivision dropdown with the subdivisions of the given nation, hiding it if
+   * the nation has no known subdivisions (or no nation is selected)
+   *
+   * @param {String} nation The ISO country code of the selected nation, or undefined
+   */
+  updateSubdivisions(nation) {
+    if (!this._subdivisions) return;
+
+    this._subdivisions.style.display = "none";
+
+    while (this._subdivisions.lastChild) {
+      this._subdivisions.removeChild(this._subdivisions.lastChild);
+    }
+
+    if (nation === undefined) return;
+
+    get("/api/v1/nationalities/" + nation + "/subdivisions/").then((response) => {
+      // The selected nation might have changed while we were waiting for the response
+      if (this.queryData.nation !== nation || response.data.length === 0) return;
+
+      let menu = document.createElement("div");
+      menu.classList.add("dropdown-menu", "no-stretch");
+
+      let input = document.createElement("input");
+      input.type = "text";
+      input.autocomplete = "off";
+      input.dataset.default = "All";
+      input.style = "color: #444446; font-weight: bold;";
+
+      let list = document.createElement("ul");
+      list.appendChild(createSubdivisionItem("All", "All"));
+
+      for (let subdivision of response.data) {
+        list.appendChild(createSubdivisionItem(subdivision.iso_code, subdivision.name));
+      }
+
+      let listContainer = document.createElement("div");
+      listContainer.classList.add("menu");
+      listContainer.appendChild(list);
+
+      menu.appendChild(input);
+      menu.appendChild(listContainer);
+
+      this._subdivisions.appendChild(menu);
+      this._subdivisions.style.display = "";
+
+      new Dropdown(menu).addEventListener((selected) =>
+        this.updateQueryData("subdivision", selected === "All" ? undefined : selected)
+      );
+    });
   }
 
   initialize() {
@@ -219,14 +371,25 @@ export class StatsViewer extends FilteredPaginator {
   onReceive(response) {
     super.onReceive(response);
 
-    // Using currentlySelected is O.K. here, as selection via clicking li-elements is the only possibility!
-    this._rank.innerHTML = this.currentlySelected.dataset.rank;
-    this._score.innerHTML = this.currentlySelected.getElementsByTagName(
-      "i"
-    )[0].innerHTML;
-
     var playerData = response.data.data;
 
+    if (this.currentlySelected !== null) {
+      this._rank.innerHTML = this.currentlySelected.dataset.rank;
+      this._score.innerHTML = this.currentlySelected.getElementsByTagName(
+        "i"
+      )[0].innerHTML;
+    } else {
+      // Selected via `selectArbitrary` (e.g. a linked player), so we need to look up rank and score ourselves
+      get("/api/v1/players/ranking/?name_contains=" + encodeURIComponent(playerData.name)).then((response) => {
+        if (this.currentObject.id !== playerData.id) return;
+
+        let ranked = response.data.find((player) => player.id === playerData.id);
+
+        this._rank.textContent = ranked === undefined ? "-" : ranked.rank;
+        this._score.textContent = ranked === undefined ? "0.00" : ranked.score.toFixed(2);
+      });
+    }
+
     if (playerData.nationality == null) {
       this._name.textContent = playerData.name;
     } else {
@@ -268,8 +431,14 @@ export class StatsViewer extends FilteredPaginator {
     this.formatRecordsInto(this._beaten, beaten);
 
     this._amountBeaten.textContent =
-      (beaten.length - legacy - extended + playerData.verified.length - verifiedExtended - verifiedLegacy) + " ( + " + (extended + verifiedExtended) + " )";
+      beaten.length - legacy - extended + playerData.verified.length - verifiedExtended - verifiedLegacy;
+
+    // Lists without an extended list have no extended demons to count
+    if (this.extended_list_size > this.list_size) {
+      this._amountBeaten.textContent += " ( + " + (extended + verifiedExtended) + " )";
+    }
     this._amountLegacy.textContent = legacy + verifiedLegacy;
+    this._attempts.textContent = playerData.total_attempts === null ? "-" : playerData.total_attempts;
 
     let hardest = playerData.verified
       .concat(beaten.map((record) => record.demon))
@@ -279,11 +448,60 @@ export class StatsViewer extends FilteredPaginator {
       this._hardest.removeChild(this._hardest.lastChild);
     this._hardest.appendChild(hardest.name === "None" ? document.createTextNode("None") : this.formatDemon(hardest, "/demonlist/permalink/" + hardest.id + "/"));
 
-    let non100Records = playerData.records
-      .filter((record) => record.progress != 100)
-      .sort((r1, r2) => r1.progress - r2.progress);
+    get("/api/v1/players/" + playerData.id + "/progress/").then((response) => {
+      // Another player might have been selected while we were waiting for the response
+      if (this.currentObject.id !== playerData.id) return;
 
-    this.formatRecordsInto(this._progress, non100Records);
+      this.formatProgressInto(this._progress, response.data);
+    });
+  }
+
+  /**
+   * Renders the given progress (as returned by `/api/v1/players/{id}/progress/`) as a collapsible
+   * list, with progress on legacy demons listed separately
+   */
+  formatProgressInto(element, progress) {
+    while (element.lastChild) {
+      element.removeChild(element.lastChild);
+    }
+
+    if (!progress.length) {
+      element.appendChild(document.createTextNode("None"));
+      return;
+    }
+
+    let listed = progress.filter((entry) => entry.demon.position <= this.extended_list_size);
+    let legacy = progress.filter((entry) => entry.demon.position > this.extended_list_size);
+
+    let details = document.createElement("details");
+    let summary = document.createElement("summary");
+
+    summary.textContent = progress.length + (progress.length == 1 ? " demon" : " demons");
+    details.appendChild(summary);
+
+    for (let [title, entries] of [[null, listed], ["Legacy", legacy]]) {
+      if (!entries.length) continue;
+
+      if (title) {
+        let heading = document.createElement("b");
+        heading.textContent = title + ":";
+        details.appendChild(heading);
+      }
+
+      let list = document.createElement("ul");
+
+      for (let entry of entries) {
+        let li = document.createElement("li");
+
+        li.appendChild(this.formatDemon(entry.demon, "/demonlist/permalink/" + entry.demon.id + "/"));
+        li.appendChild(document.createTextNode(" (" + entry.progress.join("%, ") + "%)"));
+        list.appendChild(li);
+      }
+
+      details.appendChild(list);
+    }
+
+    element.appendChild(details);
   }
 
   formatDemon(demon, link) {
@@ -372,6 +590,19 @@ export class PlayerSelectionDialog extends FormDialog {
       playerName.value = selected.name;
       this.form.html.requestSubmit();
     });
+
+    // only rendered if the server provided a list of nations
+    let nationDropdown = this.dialog.getElementsByClassName("dropdown-menu")[0];
+
+    if (nationDropdown) {
+      new Dropdown(nationDropdown).addEventListener((selected) => {
+        // combined with the name filter, so that both have to match
+        paginator.updateQueryData(
+          "nation",
+          selected === "International" ? undefined : selected
+        );
+      });
+    }
   }
 }
 
@@ -468,7 +699,7 @@ export function generateRecord(record) {
   return li;
 }
 
-function generateStatsViewerPlayer(player) {
+export function generateStatsViewerPlayer(player) {
   var li = document.createElement("li");
   var b = document.createElement("b");
   var i = document.createElement("i");
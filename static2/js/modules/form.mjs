@@ -754,6 +754,8 @@ export class HtmlFormInput extends FormInput {
         return this.input.checked;
       case "number":
         if (this.input.value === "" || this.input.value === null) return null;
+        // inputs allowing decimals (such as progress, if enabled) have a fractional step
+        if (this.input.step.includes(".")) return parseFloat(this.input.value);
         return parseInt(this.input.value);
       case "text": // also handles the text area case
       default:
@@ -983,21 +985,33 @@ export class Form extends Output {
     return data;
   }
 
-  setError(message, errorCode) {
+  /**
+   * Displays the given error message, either in the form's error output or at the input it belongs to
+   *
+   * @param message The error message
+   * @param errorCode The error code. Inputs registered via `addErrorOverride` take precedence
+   * @param field The request field the server attributed the error to (e.g. `creators[2]`), if any
+   */
+  setError(message, errorCode, field) {
     if (this.successOutput) this.successOutput.style.display = "none";
 
     if (this.errorOutput) {
       if (message === null || message === undefined) {
         this.errorOutput.style.display = "none";
       } else {
+        let input = undefined;
+
         if (errorCode in this._errorRedirects) {
-          let input = this.input(this._errorRedirects[errorCode]);
-          if (input) {
-            input.errorText = message;
-          } else {
-            this.errorOutput.style.display = "block";
-            this.errorOutput.innerHTML = message;
-          }
+          input = this.input(this._errorRedirects[errorCode]);
+        } else if (field) {
+          // list fields such as "creators[2]" are edited through a single input
+          let name = field.split("[")[0];
+
+          input = this.inputs.find((input) => input.name === name);
+        }
+
+        if (input) {
+          input.errorText = message;
         } else {
           this.errorOutput.style.display = "block";
           this.errorOutput.innerHTML = message;
@@ -1084,7 +1098,7 @@ export function displayError(output, specialCodes = {}) {
       if (response.data.code in specialCodes) {
         specialCodes[response.data.code](response.data);
       } else {
-        output.setError(response.data.message, response.data.code);
+        output.setError(response.data.message, response.data.code, response.data.field);
       }
     } else {
       output.setError(
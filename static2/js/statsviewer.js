@@ -1,4 +1,159 @@
-import {StatsViewer} from "./modules/demonlist.mjs";
+import {StatsViewer, generateStatsViewerPlayer} from "./modules/demonlist.mjs";
+import {FilteredPaginator, displayError, get} from "./modules/form.mjs";
+
+// Metrics for which the player with the lower value is ahead
+const LOWER_IS_BETTER = ["rank", "hardest"];
+
+function formatMetric(stats, metric) {
+    switch (metric) {
+        case "score":
+            return stats.score.toFixed(2);
+        case "rank":
+            return stats.rank === null ? "-" : stats.rank;
+        case "hardest":
+            return stats.hardest === null ? "None" : stats.hardest.name + " (#" + stats.hardest.position + ")";
+        default:
+            return stats[metric];
+    }
+}
+
+function renderComparison(html, comparison) {
+    document.getElementById("compare-a-name").textContent = comparison.a.name;
+    document.getElementById("compare-b-name").textContent = comparison.b.name;
+
+    for (let row of html.querySelectorAll("tr[data-metric]")) {
+        let metric = row.dataset.metric;
+        let diff = comparison.diff[metric];
+        let cellA = row.getElementsByClassName("compare-a")[0];
+        let cellB = row.getElementsByClassName("compare-b")[0];
+
+        cellA.textContent = formatMetric(comparison.a, metric);
+        cellB.textContent = formatMetric(comparison.b, metric);
+        cellA.style.fontWeight = cellB.style.fontWeight = "normal";
+
+        // diff is null if the metric cannot be compared (e.g. one of the players is unranked)
+        if (diff === null || diff === 0)
+            continue;
+
+        let aIsAhead = LOWER_IS_BETTER.includes(metric) ? diff < 0 : diff > 0;
+
+        (aIsAhead ? cellA : cellB).style.fontWeight = "bold";
+    }
+
+    html.style.display = "block";
+}
+
+function initializeComparison(statsViewer) {
+    let toggle = document.getElementById("compare-toggle");
+    let compareColumn = document.getElementById("stats-viewer-compare");
+    let comparison = document.getElementById("player-comparison");
+    let comparePaginator = new FilteredPaginator("stats-viewer-compare-pagination", generateStatsViewerPlayer, "name_contains");
+
+    let playerA = undefined;
+    let playerB = undefined;
+
+    function refresh() {
+        if (!toggle.checked || playerA === undefined || playerB === undefined) {
+            comparison.style.display = "none";
+            return;
+        }
+
+        get("/api/v1/players/" + playerA + "/compare/" + playerB + "/")
+            .then(response => renderComparison(comparison, response.data))
+            .catch(displayError(comparePaginator));
+    }
+
+    // The second column only needs the selected player's ID, all data comes from the comparison endpoint
+    comparePaginator.onSelect = selected => {
+        comparePaginator.currentlySelected = selected;
+        playerB = selected.dataset.id;
+        refresh();
+    };
+
+    statsViewer.addSelectionListener(player => {
+        playerA = player.id;
+        refresh();
+    });
+
+    toggle.addEventListener("change", () => {
+        compareColumn.style.display = toggle.checked ? "block" : "none";
+
+        if (toggle.checked)
+            comparePaginator.initialize();
+
+        refresh();
+    });
+}
+
+function generateImprovedPlayer(player) {
+    let li = document.createElement("li");
+    let b = document.createElement("b");
+    let i = document.createElement("i");
+
+    li.className = "white hover";
+    li.dataset.id = player.id;
+
+    b.appendChild(document.createTextNode("+" + player.improvement.toFixed(2) + " "));
+    i.appendChild(document.createTextNode(" " + player.score.toFixed(2)));
+
+    li.appendChild(b);
+    li.appendChild(document.createTextNode(player.name));
+    li.appendChild(i);
+
+    return li;
+}
+
+function initializeMostImproved(statsViewer) {
+    let toggle = document.getElementById("improved-toggle");
+    let container = document.getElementById("most-improved");
+    let list = container.getElementsByTagName("ul")[0];
+
+    list.addEventListener("click", event => {
+        let li = event.target.closest("li");
+
+        if (li === null)
+            return;
+
+        statsViewer.currentlySelected = null;
+        statsViewer.selectArbitrary(li.dataset.id).catch(displayError(statsViewer));
+    });
+
+    toggle.addEventListener("change", () => {
+        container.style.display = toggle.checked ? "block" : "none";
+
+        if (!toggle.checked)
+            return;
+
+        get("/api/v1/players/ranking/improved/?days=30")
+            .then(response => {
+                list.textContent = "";
+
+                for (let player of response.data)
+                    list.appendChild(generateImprovedPlayer(player));
+            })
+            .catch(displayError(statsViewer));
+    });
+}
+
+// Players can be linked to via `?player=<id>` (in which case the server already rendered their
+// stats) or via a `#player=<id>` fragment
+function initializeDeepLink(statsViewer) {
+    let fragment = window.location.hash.match(/^#player=(\d+)$/);
+    let player = statsViewer.html.dataset.player || (fragment && fragment[1]);
+
+    if (player)
+        statsViewer.selectArbitrary(player).catch(displayError(statsViewer));
+
+    // Keep the address bar pointing to the selected player, so that it can be shared
+    statsViewer.addSelectionListener(selected => {
+        let url = new URL(window.location.href);
+
+        url.searchParams.set("player", selected.id);
+        url.hash = "";
+
+        window.history.replaceState(null, "", url.toString());
+    });
+}
 
 $(window).on("load", function () {
     let worldMapWrapper = document.getElementById("world-map-wrapper");
@@ -6,7 +161,11 @@ $(window).on("load", function () {
     let svg = worldMap.contentDocument.children[0];
 
     window.statsViewer = new StatsViewer(document.getElementById("statsviewer"));
-    window.statsViewer.initialize();
+    // list sizes need to be known before any player can be displayed
+    window.statsViewer.initialize().then(() => initializeDeepLink(window.statsViewer));
+
+    initializeComparison(window.statsViewer);
+    initializeMostImproved(window.statsViewer);
 
     document.addEventListener('scroll', () => {
         let scrollRatio = window.scrollY / worldMapWrapper.clientHeight;